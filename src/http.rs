@@ -2,6 +2,7 @@ pub mod client;
 pub mod server;
 
 pub mod status {
+    use core::fmt;
     use core::ops::Range;
 
     pub const INFO: Range<u16> = 100..200;
@@ -9,8 +10,207 @@ pub mod status {
     pub const REDIRECT: Range<u16> = 300..400;
     pub const CLIENT_ERROR: Range<u16> = 400..500;
     pub const SERVER_ERROR: Range<u16> = 500..600;
+
+    /// An HTTP status code, i.e. a number between 100 and 999.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct StatusCode(u16);
+
+    impl StatusCode {
+        /// Returns `None` if `code` is not a three-digit number.
+        pub const fn from_u16(code: u16) -> Option<Self> {
+            if code >= 100 && code < 1000 {
+                Some(Self(code))
+            } else {
+                None
+            }
+        }
+
+        pub const fn as_u16(&self) -> u16 {
+            self.0
+        }
+
+        pub const fn is_informational(&self) -> bool {
+            self.0 >= INFO.start && self.0 < INFO.end
+        }
+
+        pub const fn is_success(&self) -> bool {
+            self.0 >= OK.start && self.0 < OK.end
+        }
+
+        pub const fn is_redirection(&self) -> bool {
+            self.0 >= REDIRECT.start && self.0 < REDIRECT.end
+        }
+
+        pub const fn is_client_error(&self) -> bool {
+            self.0 >= CLIENT_ERROR.start && self.0 < CLIENT_ERROR.end
+        }
+
+        pub const fn is_server_error(&self) -> bool {
+            self.0 >= SERVER_ERROR.start && self.0 < SERVER_ERROR.end
+        }
+    }
+
+    macro_rules! status_codes {
+        ($(($code:expr, $name:ident, $reason:expr);)+) => {
+            impl StatusCode {
+                $(
+                    #[doc = concat!("`", stringify!($code), " ", $reason, "`")]
+                    pub const $name: Self = Self($code);
+                )+
+
+                /// Returns the reason phrase registered with IANA for the status code, if any.
+                pub const fn canonical_reason(&self) -> Option<&'static str> {
+                    match self.0 {
+                        $($code => Some($reason),)+
+                        _ => None,
+                    }
+                }
+            }
+        };
+    }
+
+    status_codes! {
+        (100, CONTINUE, "Continue");
+        (101, SWITCHING_PROTOCOLS, "Switching Protocols");
+        (102, PROCESSING, "Processing");
+        (103, EARLY_HINTS, "Early Hints");
+        (200, OK, "OK");
+        (201, CREATED, "Created");
+        (202, ACCEPTED, "Accepted");
+        (203, NON_AUTHORITATIVE_INFORMATION, "Non-Authoritative Information");
+        (204, NO_CONTENT, "No Content");
+        (205, RESET_CONTENT, "Reset Content");
+        (206, PARTIAL_CONTENT, "Partial Content");
+        (207, MULTI_STATUS, "Multi-Status");
+        (208, ALREADY_REPORTED, "Already Reported");
+        (226, IM_USED, "IM Used");
+        (300, MULTIPLE_CHOICES, "Multiple Choices");
+        (301, MOVED_PERMANENTLY, "Moved Permanently");
+        (302, FOUND, "Found");
+        (303, SEE_OTHER, "See Other");
+        (304, NOT_MODIFIED, "Not Modified");
+        (305, USE_PROXY, "Use Proxy");
+        (307, TEMPORARY_REDIRECT, "Temporary Redirect");
+        (308, PERMANENT_REDIRECT, "Permanent Redirect");
+        (400, BAD_REQUEST, "Bad Request");
+        (401, UNAUTHORIZED, "Unauthorized");
+        (402, PAYMENT_REQUIRED, "Payment Required");
+        (403, FORBIDDEN, "Forbidden");
+        (404, NOT_FOUND, "Not Found");
+        (405, METHOD_NOT_ALLOWED, "Method Not Allowed");
+        (406, NOT_ACCEPTABLE, "Not Acceptable");
+        (407, PROXY_AUTHENTICATION_REQUIRED, "Proxy Authentication Required");
+        (408, REQUEST_TIMEOUT, "Request Timeout");
+        (409, CONFLICT, "Conflict");
+        (410, GONE, "Gone");
+        (411, LENGTH_REQUIRED, "Length Required");
+        (412, PRECONDITION_FAILED, "Precondition Failed");
+        (413, CONTENT_TOO_LARGE, "Content Too Large");
+        (414, URI_TOO_LONG, "URI Too Long");
+        (415, UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type");
+        (416, RANGE_NOT_SATISFIABLE, "Range Not Satisfiable");
+        (417, EXPECTATION_FAILED, "Expectation Failed");
+        (421, MISDIRECTED_REQUEST, "Misdirected Request");
+        (422, UNPROCESSABLE_CONTENT, "Unprocessable Content");
+        (423, LOCKED, "Locked");
+        (424, FAILED_DEPENDENCY, "Failed Dependency");
+        (425, TOO_EARLY, "Too Early");
+        (426, UPGRADE_REQUIRED, "Upgrade Required");
+        (428, PRECONDITION_REQUIRED, "Precondition Required");
+        (429, TOO_MANY_REQUESTS, "Too Many Requests");
+        (431, REQUEST_HEADER_FIELDS_TOO_LARGE, "Request Header Fields Too Large");
+        (451, UNAVAILABLE_FOR_LEGAL_REASONS, "Unavailable For Legal Reasons");
+        (500, INTERNAL_SERVER_ERROR, "Internal Server Error");
+        (501, NOT_IMPLEMENTED, "Not Implemented");
+        (502, BAD_GATEWAY, "Bad Gateway");
+        (503, SERVICE_UNAVAILABLE, "Service Unavailable");
+        (504, GATEWAY_TIMEOUT, "Gateway Timeout");
+        (505, HTTP_VERSION_NOT_SUPPORTED, "HTTP Version Not Supported");
+        (506, VARIANT_ALSO_NEGOTIATES, "Variant Also Negotiates");
+        (507, INSUFFICIENT_STORAGE, "Insufficient Storage");
+        (508, LOOP_DETECTED, "Loop Detected");
+        (510, NOT_EXTENDED, "Not Extended");
+        (511, NETWORK_AUTHENTICATION_REQUIRED, "Network Authentication Required");
+    }
+
+    impl From<StatusCode> for u16 {
+        fn from(status: StatusCode) -> Self {
+            status.0
+        }
+    }
+
+    impl TryFrom<u16> for StatusCode {
+        type Error = InvalidStatusCode;
+
+        fn try_from(code: u16) -> Result<Self, Self::Error> {
+            Self::from_u16(code).ok_or(InvalidStatusCode)
+        }
+    }
+
+    impl PartialEq<u16> for StatusCode {
+        fn eq(&self, other: &u16) -> bool {
+            self.0 == *other
+        }
+    }
+
+    impl PartialEq<StatusCode> for u16 {
+        fn eq(&self, other: &StatusCode) -> bool {
+            *self == other.0
+        }
+    }
+
+    /// Formats the status code followed by its canonical reason phrase, if any, e.g. `404 Not Found`.
+    impl fmt::Display for StatusCode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.canonical_reason() {
+                Some(reason) => write!(f, "{} {reason}", self.0),
+                None => write!(f, "{}", self.0),
+            }
+        }
+    }
+
+    /// A status for a response, i.e. a [`StatusCode`] or a plain `u16`.
+    ///
+    /// Sealed, so that an integer literal passed as a status is always inferred as a `u16`.
+    pub trait IntoStatus: sealed::Sealed {
+        fn into_status(self) -> u16;
+    }
+
+    mod sealed {
+        pub trait Sealed {}
+
+        impl Sealed for u16 {}
+        impl Sealed for super::StatusCode {}
+    }
+
+    impl IntoStatus for u16 {
+        fn into_status(self) -> u16 {
+            self
+        }
+    }
+
+    impl IntoStatus for StatusCode {
+        fn into_status(self) -> u16 {
+            self.0
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct InvalidStatusCode;
+
+    impl fmt::Display for InvalidStatusCode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Invalid status code")
+        }
+    }
+
+    impl core::error::Error for InvalidStatusCode {}
 }
 
+pub use status::{IntoStatus, StatusCode};
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg_attr(feature = "std", derive(Hash))]
@@ -50,6 +250,107 @@ pub enum Method {
     Unlink,
 }
 
+impl Method {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Delete => "DELETE",
+            Self::Get => "GET",
+            Self::Head => "HEAD",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Connect => "CONNECT",
+            Self::Options => "OPTIONS",
+            Self::Trace => "TRACE",
+            Self::Copy => "COPY",
+            Self::Lock => "LOCK",
+            Self::MkCol => "MKCOL",
+            Self::Move => "MOVE",
+            Self::Propfind => "PROPFIND",
+            Self::Proppatch => "PROPPATCH",
+            Self::Search => "SEARCH",
+            Self::Unlock => "UNLOCK",
+            Self::Bind => "BIND",
+            Self::Rebind => "REBIND",
+            Self::Unbind => "UNBIND",
+            Self::Acl => "ACL",
+            Self::Report => "REPORT",
+            Self::MkActivity => "MKACTIVITY",
+            Self::Checkout => "CHECKOUT",
+            Self::Merge => "MERGE",
+            Self::MSearch => "M-SEARCH",
+            Self::Notify => "NOTIFY",
+            Self::Subscribe => "SUBSCRIBE",
+            Self::Unsubscribe => "UNSUBSCRIBE",
+            Self::Patch => "PATCH",
+            Self::Purge => "PURGE",
+            Self::MkCalendar => "MKCALENDAR",
+            Self::Link => "LINK",
+            Self::Unlink => "UNLINK",
+        }
+    }
+}
+
+impl core::str::FromStr for Method {
+    type Err = UnknownMethod;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "DELETE" => Ok(Self::Delete),
+            "GET" => Ok(Self::Get),
+            "HEAD" => Ok(Self::Head),
+            "POST" => Ok(Self::Post),
+            "PUT" => Ok(Self::Put),
+            "CONNECT" => Ok(Self::Connect),
+            "OPTIONS" => Ok(Self::Options),
+            "TRACE" => Ok(Self::Trace),
+            "COPY" => Ok(Self::Copy),
+            "LOCK" => Ok(Self::Lock),
+            "MKCOL" => Ok(Self::MkCol),
+            "MOVE" => Ok(Self::Move),
+            "PROPFIND" => Ok(Self::Propfind),
+            "PROPPATCH" => Ok(Self::Proppatch),
+            "SEARCH" => Ok(Self::Search),
+            "UNLOCK" => Ok(Self::Unlock),
+            "BIND" => Ok(Self::Bind),
+            "REBIND" => Ok(Self::Rebind),
+            "UNBIND" => Ok(Self::Unbind),
+            "ACL" => Ok(Self::Acl),
+            "REPORT" => Ok(Self::Report),
+            "MKACTIVITY" => Ok(Self::MkActivity),
+            "CHECKOUT" => Ok(Self::Checkout),
+            "MERGE" => Ok(Self::Merge),
+            "M-SEARCH" => Ok(Self::MSearch),
+            "NOTIFY" => Ok(Self::Notify),
+            "SUBSCRIBE" => Ok(Self::Subscribe),
+            "UNSUBSCRIBE" => Ok(Self::Unsubscribe),
+            "PATCH" => Ok(Self::Patch),
+            "PURGE" => Ok(Self::Purge),
+            "MKCALENDAR" => Ok(Self::MkCalendar),
+            "LINK" => Ok(Self::Link),
+            "UNLINK" => Ok(Self::Unlink),
+            _ => Err(UnknownMethod),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnknownMethod;
+
+impl core::fmt::Display for UnknownMethod {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Unknown HTTP method")
+    }
+}
+
+impl core::error::Error for UnknownMethod {}
+
+impl core::fmt::Display for Method {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 pub trait Headers {
     fn header(&self, name: &str) -> Option<&'_ str>;
 
@@ -109,6 +410,12 @@ pub trait Status {
     fn status(&self) -> u16;
 
     fn status_message(&self) -> Option<&'_ str>;
+
+    /// The status as a [`StatusCode`], e.g. for matching against its named constants, or `None`
+    /// if it is not a three-digit number.
+    fn status_code(&self) -> Option<StatusCode> {
+        StatusCode::from_u16(self.status())
+    }
 }
 
 impl<S> Status for &S
@@ -238,3 +545,32 @@ pub mod headers {
 pub mod asynch {
     pub use super::*;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Status, StatusCode};
+
+    struct Response(u16);
+
+    impl Status for Response {
+        fn status(&self) -> u16 {
+            self.0
+        }
+
+        fn status_message(&self) -> Option<&'_ str> {
+            None
+        }
+    }
+
+    #[test]
+    fn status_code() {
+        assert_eq!(Response(404).status_code(), Some(StatusCode::NOT_FOUND));
+        assert_eq!(
+            Response(999).status_code().map(|code| code.as_u16()),
+            Some(999)
+        );
+        assert_eq!(Response(99).status_code(), None);
+        assert_eq!(Response(1000).status_code(), None);
+        assert_eq!(Response(0).status_code(), None);
+    }
+}
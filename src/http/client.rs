@@ -1,6 +1,6 @@
 use crate::io::{Error, ErrorType, Read, Write};
 
-pub use super::{Headers, Method, Status};
+pub use super::{Headers, Method, Status, StatusCode};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -164,6 +164,10 @@ where
         self.0.status()
     }
 
+    pub fn status_code(&self) -> Option<StatusCode> {
+        self.0.status_code()
+    }
+
     pub fn status_message(&self) -> Option<&'_ str> {
         self.0.status_message()
     }
@@ -289,7 +293,7 @@ pub mod asynch {
     use crate::io::{asynch::Read, asynch::Write, Error, ErrorType};
 
     pub use crate::http::asynch::*;
-    pub use crate::http::{Headers, Method, Status};
+    pub use crate::http::{Headers, Method, Status, StatusCode};
 
     #[derive(Debug)]
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -456,6 +460,10 @@ pub mod asynch {
             self.0.status()
         }
 
+        pub fn status_code(&self) -> Option<StatusCode> {
+            self.0.status_code()
+        }
+
         pub fn status_message(&self) -> Option<&'_ str> {
             self.0.status_message()
         }
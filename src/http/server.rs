@@ -2,7 +2,7 @@ use core::fmt::Debug;
 
 use crate::io::{Error, Read, Write};
 
-pub use super::{Headers, Method, Query, Status};
+pub use super::{Headers, IntoStatus, Method, Query, Status, StatusCode};
 pub use crate::io::ErrorType;
 
 #[derive(Debug)]
@@ -25,13 +25,15 @@ where
         self.0.split()
     }
 
+    /// Initiates the response with `status`, either a [`StatusCode`] or a `u16`.
     pub fn into_response<'b>(
         mut self,
-        status: u16,
+        status: impl IntoStatus,
         message: Option<&'b str>,
         headers: &'b [(&'b str, &'b str)],
     ) -> Result<Response<C>, C::Error> {
-        self.0.initiate_response(status, message, headers)?;
+        self.0
+            .initiate_response(status.into_status(), message, headers)?;
 
         Ok(Response(self.0))
     }
@@ -314,7 +316,7 @@ pub mod asynch {
 
     use crate::io::{asynch::Read, asynch::Write};
 
-    pub use super::{Headers, Method, Query, Status};
+    pub use super::{Headers, IntoStatus, Method, Query, Status, StatusCode};
     pub use crate::io::{Error, ErrorType};
 
     #[derive(Debug)]
@@ -337,13 +339,16 @@ pub mod asynch {
             self.0.split()
         }
 
+        /// Initiates the response with `status`, either a [`StatusCode`] or a `u16`.
         pub async fn into_response<'b>(
             mut self,
-            status: u16,
+            status: impl IntoStatus,
             message: Option<&'b str>,
             headers: &'b [(&'b str, &'b str)],
         ) -> Result<Response<C>, C::Error> {
-            self.0.initiate_response(status, message, headers).await?;
+            self.0
+                .initiate_response(status.into_status(), message, headers)
+                .await?;
 
             Ok(Response(self.0))
         }
@@ -595,3 +600,64 @@ pub mod asynch {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+
+    use crate::utils::http::server::connection::handle_connection;
+    use crate::utils::io::test::MockSocket;
+
+    use super::{Connection, Handler, Request, StatusCode};
+
+    /// Responds with a typed status, a plain one, or the status of `into_status_response`, depending on the path.
+    struct Status;
+
+    impl<C> Handler<C> for Status
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let request = Request::wrap(connection);
+
+            match request.uri() {
+                "/typed" => request.into_response(
+                    StatusCode::MISDIRECTED_REQUEST,
+                    StatusCode::MISDIRECTED_REQUEST.canonical_reason(),
+                    &[("Content-Length", "0")],
+                )?,
+                "/plain" => {
+                    request.into_response(404, Some("Gone Fishing"), &[("Content-Length", "0")])?
+                }
+                _ => request.into_status_response(503)?,
+            };
+
+            Ok(())
+        }
+    }
+
+    fn serve(request: &str) -> String {
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 1024];
+
+        handle_connection::<_, _, 16>(&mut socket, &mut buf, &Status).unwrap();
+
+        String::from_utf8(socket.output).unwrap()
+    }
+
+    #[test]
+    fn into_response() {
+        assert!(serve("GET /typed HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .starts_with("HTTP/1.1 421 Misdirected Request\r\n"));
+        assert!(serve("GET /plain HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .starts_with("HTTP/1.1 404 Gone Fishing\r\n"));
+        // No reason phrase unless one is given
+        assert!(
+            serve("GET / HTTP/1.1\r\nConnection: close\r\n\r\n").starts_with("HTTP/1.1 503 \r\n")
+        );
+    }
+}
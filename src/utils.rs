@@ -1,2 +1,5 @@
+pub mod base64;
+pub mod hash;
 pub mod http;
 pub mod io;
+pub mod ws;
@@ -0,0 +1,177 @@
+//! Standard (RFC 4648) base64 encoding and decoding into caller-provided buffers.
+
+use core::fmt;
+use core::str;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Base64Error {
+    InvalidEncoding,
+    BufferOverflow,
+}
+
+impl fmt::Display for Base64Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEncoding => write!(f, "Invalid base64 encoding"),
+            Self::BufferOverflow => write!(f, "Buffer overflow"),
+        }
+    }
+}
+
+impl core::error::Error for Base64Error {}
+
+/// Returns the length of the padded base64 encoding of `len` bytes.
+pub const fn encoded_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Encodes `input` into `buf`, with padding.
+pub fn encode<'b>(input: &[u8], buf: &'b mut [u8]) -> Result<&'b str, Base64Error> {
+    let len = encoded_len(input.len());
+    let out = buf.get_mut(..len).ok_or(Base64Error::BufferOverflow)?;
+
+    for (chunk, out) in input.chunks(3).zip(out.chunks_mut(4)) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+
+        out[0] = ALPHABET[(b[0] >> 2) as usize];
+        out[1] = ALPHABET[(((b[0] & 0x03) << 4) | (b[1] >> 4)) as usize];
+        out[2] = if chunk.len() > 1 {
+            ALPHABET[(((b[1] & 0x0f) << 2) | (b[2] >> 6)) as usize]
+        } else {
+            b'='
+        };
+        out[3] = if chunk.len() > 2 {
+            ALPHABET[(b[2] & 0x3f) as usize]
+        } else {
+            b'='
+        };
+    }
+
+    // Safe to unwrap, the alphabet is ASCII
+    Ok(str::from_utf8(&buf[..len]).unwrap())
+}
+
+/// Decodes `input` into `buf`. Padding is optional, but if present it must be correct.
+pub fn decode<'b>(input: &str, buf: &'b mut [u8]) -> Result<&'b [u8], Base64Error> {
+    let input = input.as_bytes();
+
+    let data = match input {
+        [data @ .., b'=', b'='] | [data @ .., b'='] if input.len() % 4 == 0 => data,
+        data => data,
+    };
+
+    if data.len() % 4 == 1 {
+        return Err(Base64Error::InvalidEncoding);
+    }
+
+    let len = data.len() / 4 * 3 + (data.len() % 4).saturating_sub(1);
+    let out = buf.get_mut(..len).ok_or(Base64Error::BufferOverflow)?;
+
+    for (chunk, out) in data.chunks(4).zip(out.chunks_mut(3)) {
+        let mut acc = 0_u32;
+
+        for (index, &c) in chunk.iter().enumerate() {
+            acc |= (value(c)? as u32) << (18 - 6 * index);
+        }
+
+        // Reject non-canonical encodings, where the unused bits of the last character are set
+        if chunk.len() < 4 && acc & (0xff_ff_ff >> (8 * (chunk.len() - 1))) != 0 {
+            return Err(Base64Error::InvalidEncoding);
+        }
+
+        for (index, out) in out.iter_mut().enumerate() {
+            *out = (acc >> (16 - 8 * index)) as u8;
+        }
+    }
+
+    Ok(out)
+}
+
+fn value(c: u8) -> Result<u8, Base64Error> {
+    match c {
+        b'A'..=b'Z' => Ok(c - b'A'),
+        b'a'..=b'z' => Ok(c - b'a' + 26),
+        b'0'..=b'9' => Ok(c - b'0' + 52),
+        b'+' => Ok(62),
+        b'/' => Ok(63),
+        _ => Err(Base64Error::InvalidEncoding),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, encoded_len, Base64Error};
+
+    /// The test vectors of RFC 4648, section 10.
+    const RFC4648_VECTORS: &[(&str, &str)] = &[
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn encode_rfc4648() {
+        let mut buf = [0; 16];
+
+        for (input, output) in RFC4648_VECTORS {
+            assert_eq!(encoded_len(input.len()), output.len());
+            assert_eq!(encode(input.as_bytes(), &mut buf).unwrap(), *output);
+        }
+
+        assert_eq!(
+            encode(&[0xfb, 0xff, 0xbf], &mut buf).unwrap(),
+            "+/+/",
+            "the standard alphabet"
+        );
+        assert_eq!(
+            encode(b"foo", &mut buf[..3]),
+            Err(Base64Error::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn decode_rfc4648() {
+        let mut buf = [0; 16];
+
+        for (output, input) in RFC4648_VECTORS {
+            assert_eq!(decode(input, &mut buf).unwrap(), output.as_bytes());
+            assert_eq!(
+                decode(input.trim_end_matches('='), &mut buf).unwrap(),
+                output.as_bytes()
+            );
+        }
+
+        assert_eq!(decode("+/+/", &mut buf).unwrap(), [0xfb, 0xff, 0xbf]);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let mut buf = [0; 16];
+
+        for input in [
+            "Z", "Zg=", "Zh==", "Zm9=", "Z===", "Zm9v!", "Zm9v-_", "Zm 9v", "Zg==Zg==", "=",
+        ] {
+            assert_eq!(
+                decode(input, &mut buf),
+                Err(Base64Error::InvalidEncoding),
+                "{input}"
+            );
+        }
+
+        assert_eq!(
+            decode("Zm9vYmFy", &mut buf[..5]),
+            Err(Base64Error::BufferOverflow)
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! MD5, SHA-1 and SHA-256 message digests, as used by HTTP authentication and the WebSocket handshake.
+//!
+//! These are not meant for general cryptographic use, and are not hardened against side channels.
+
+pub trait Digest: Default {
+    type Output: AsRef<[u8]>;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Self::Output;
+
+    fn digest(data: &[u8]) -> Self::Output {
+        let mut digest = Self::default();
+        digest.update(data);
+        digest.finalize()
+    }
+}
+
+/// The buffering of the 64-byte blocks processed by the Merkle-Damgård digests.
+#[derive(Clone)]
+struct Blocks {
+    buf: [u8; 64],
+    len: usize,
+    total: u64,
+}
+
+impl Blocks {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 64],
+            len: 0,
+            total: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8], mut compress: impl FnMut(&[u8; 64])) {
+        self.total = self.total.wrapping_add(data.len() as u64);
+
+        while !data.is_empty() {
+            let size = data.len().min(64 - self.len);
+
+            self.buf[self.len..self.len + size].copy_from_slice(&data[..size]);
+            self.len += size;
+            data = &data[size..];
+
+            if self.len == 64 {
+                compress(&self.buf);
+                self.len = 0;
+            }
+        }
+    }
+
+    /// Pads the message and processes the last blocks, with the length in bits encoded by `encode`.
+    fn finish(&mut self, encode: impl FnOnce(u64) -> [u8; 8], mut compress: impl FnMut(&[u8; 64])) {
+        let bits = encode(self.total.wrapping_mul(8));
+
+        self.buf[self.len] = 0x80;
+        self.buf[self.len + 1..].fill(0);
+
+        if self.len >= 56 {
+            compress(&self.buf);
+            self.buf.fill(0);
+        }
+
+        self.buf[56..].copy_from_slice(&bits);
+        compress(&self.buf);
+    }
+}
+
+#[derive(Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    blocks: Blocks,
+}
+
+impl Md5 {
+    pub const fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            blocks: Blocks::new(),
+        }
+    }
+
+    fn compress(state: &mut [u32; 4], block: &[u8; 64]) {
+        const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+        const K: [u32; 64] = [
+            0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613,
+            0xfd469501, 0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193,
+            0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d,
+            0x02441453, 0xd8a1e681, 0xe7d3fbc8, 0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed,
+            0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122,
+            0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa,
+            0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665, 0xf4292244,
+            0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+            0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb,
+            0xeb86d391,
+        ];
+
+        let mut words = [0; 16];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            // Safe to unwrap, the chunk is exactly 4 bytes long
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        let [mut a, mut b, mut c, mut d] = *state;
+
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+
+            let f = f.wrapping_add(a).wrapping_add(K[i]).wrapping_add(words[g]);
+
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[(i / 16) * 4 + i % 4]));
+        }
+
+        for (state, value) in state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Digest for Md5 {
+    type Output = [u8; 16];
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks
+            .update(data, |block| Self::compress(state, block));
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let state = &mut self.state;
+        self.blocks
+            .finish(u64::to_le_bytes, |block| Self::compress(state, block));
+
+        let mut output = [0; 16];
+        for (bytes, word) in output.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+
+        output
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [u32; 5],
+    blocks: Blocks,
+}
+
+impl Sha1 {
+    pub const fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0],
+            blocks: Blocks::new(),
+        }
+    }
+
+    fn compress(state: &mut [u32; 5], block: &[u8; 64]) {
+        let mut words = [0; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            // Safe to unwrap, the chunk is exactly 4 bytes long
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = *state;
+
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i / 20 {
+                0 => ((b & c) | (!b & d), 0x5a827999),
+                1 => (b ^ c ^ d, 0x6ed9eba1),
+                2 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Digest for Sha1 {
+    type Output = [u8; 20];
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks
+            .update(data, |block| Self::compress(state, block));
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let state = &mut self.state;
+        self.blocks
+            .finish(u64::to_be_bytes, |block| Self::compress(state, block));
+
+        let mut output = [0; 20];
+        for (bytes, word) in output.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        output
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    blocks: Blocks,
+}
+
+impl Sha256 {
+    pub const fn new() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            blocks: Blocks::new(),
+        }
+    }
+
+    fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+        const K: [u32; 64] = [
+            0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+            0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+            0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+            0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+            0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+            0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+            0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+            0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+            0xc67178f2,
+        ];
+
+        let mut words = [0; 64];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            // Safe to unwrap, the chunk is exactly 4 bytes long
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        for i in 16..64 {
+            let s0 = words[i - 15].rotate_right(7)
+                ^ words[i - 15].rotate_right(18)
+                ^ (words[i - 15] >> 3);
+            let s1 = words[i - 2].rotate_right(17)
+                ^ words[i - 2].rotate_right(19)
+                ^ (words[i - 2] >> 10);
+
+            words[i] = words[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(words[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+        for (k, word) in K.iter().zip(words) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(word);
+
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Digest for Sha256 {
+    type Output = [u8; 32];
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks
+            .update(data, |block| Self::compress(state, block));
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let state = &mut self.state;
+        self.blocks
+            .finish(u64::to_be_bytes, |block| Self::compress(state, block));
+
+        let mut output = [0; 32];
+        for (bytes, word) in output.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec;
+
+    use super::{Digest, Md5, Sha1, Sha256};
+
+    const MILLION_A: &str = "million a";
+
+    /// The messages of the reference digests. The last block is padded into a second one from 56 bytes on.
+    const MESSAGES: &[&str] = &[
+        "",
+        "abc",
+        "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        MILLION_A,
+    ];
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .map(|byte| std::format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Checks `D` against the reference digests of [`MESSAGES`], both at once and in chunks of various sizes.
+    fn check<D: Digest>(digests: &[&str]) {
+        for (message, expected) in MESSAGES.iter().zip(digests) {
+            let message = match *message {
+                MILLION_A => vec![b'a'; 1_000_000],
+                message => message.as_bytes().into(),
+            };
+
+            assert_eq!(hex(D::digest(&message).as_ref()), *expected);
+
+            for chunk in [1, 3, 63, 64, 65] {
+                let mut digest = D::default();
+
+                for chunk in message.chunks(chunk) {
+                    digest.update(chunk);
+                }
+
+                assert_eq!(hex(digest.finalize().as_ref()), *expected);
+            }
+        }
+    }
+
+    #[test]
+    fn md5() {
+        check::<Md5>(&[
+            "d41d8cd98f00b204e9800998ecf8427e",
+            "900150983cd24fb0d6963f7d28e17f72",
+            "8215ef0796a20bcaaae116d3876c664a",
+            "ef1772b6dff9a122358552954ad0df65",
+            "3b0c8ac703f828b04c6c197006d17218",
+            "014842d480b571495a4a0363793f7367",
+            "7707d6ae4e027c70eea2a935c2296f21",
+        ]);
+
+        // RFC 1321, appendix A.5
+        assert_eq!(
+            hex(&Md5::digest(b"message digest")),
+            "f96b697d7cb7938d525a2f31aaf161d0"
+        );
+        assert_eq!(
+            hex(&Md5::digest(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            )),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn sha1() {
+        check::<Sha1>(&[
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            "a9993e364706816aba3e25717850c26c9cd0d89d",
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            "c1c8bbdc22796e28c0e15163d20899b65621d65a",
+            "c2db330f6083854c99d4b5bfb6e8f29f201be699",
+            "0098ba824b5c16427bd7a1122a5a442a25ec644d",
+            "34aa973cd4c4daa4f61eeb2bdbad27316534016f",
+        ]);
+    }
+
+    #[test]
+    fn sha256() {
+        check::<Sha256>(&[
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+            "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+            "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0",
+        ]);
+    }
+}
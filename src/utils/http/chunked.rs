@@ -0,0 +1,856 @@
+use core::cmp::min;
+use core::fmt;
+use core::str;
+
+use embedded_io::{Error, ErrorKind};
+
+use crate::io::{ErrorType, Read, Write};
+
+/// The maximum length of a chunk size line, including the chunk extensions.
+pub const MAX_CHUNK_LINE_LEN: usize = 1024;
+
+/// The maximum length of the trailer section, when the trailers are discarded.
+pub const MAX_TRAILERS_LEN: usize = 4096;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChunkedError<E> {
+    Io(E),
+    InvalidChunk,
+    LineTooLong,
+    BodyTooLarge,
+    InvalidTrailer,
+    TrailersTooLarge,
+    UnexpectedEof,
+    AlreadyComplete,
+}
+
+impl<E: fmt::Debug> fmt::Display for ChunkedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for ChunkedError<E> {}
+
+impl<E> Error for ChunkedError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::AlreadyComplete => ErrorKind::InvalidInput,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Size,
+    SizeWs,
+    Extension,
+    SizeLf,
+    Data(u64),
+    DataCr,
+    DataLf,
+    TrailerStart,
+    Trailer,
+    TrailerLf,
+    FinalLf,
+    Complete,
+}
+
+/// The I/O-agnostic chunked decoding state machine, shared by the blocking and async readers.
+pub(crate) struct Decoder<'b> {
+    state: State,
+    size: u64,
+    digits: usize,
+    line_len: usize,
+    colon: bool,
+    body_len: u64,
+    max_body_len: u64,
+    trailers: &'b mut [u8],
+    trailers_len: usize,
+    line_start: usize,
+}
+
+impl<'b> Decoder<'b> {
+    pub(crate) fn new(trailers: &'b mut [u8]) -> Self {
+        Self {
+            state: State::Size,
+            size: 0,
+            digits: 0,
+            line_len: 0,
+            colon: false,
+            body_len: 0,
+            max_body_len: u64::MAX,
+            trailers,
+            trailers_len: 0,
+            line_start: 0,
+        }
+    }
+
+    fn data_remaining(&self) -> Option<u64> {
+        match self.state {
+            State::Data(remaining) => Some(remaining),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.state == State::Complete
+    }
+
+    fn consume(&mut self, len: usize) {
+        if let State::Data(remaining) = self.state {
+            let remaining = remaining - len as u64;
+
+            self.state = if remaining > 0 {
+                State::Data(remaining)
+            } else {
+                State::DataCr
+            };
+        }
+    }
+
+    fn feed<E>(&mut self, byte: u8) -> Result<(), ChunkedError<E>> {
+        match self.state {
+            State::Size | State::SizeWs | State::Extension => {
+                self.line_len += 1;
+                if self.line_len > MAX_CHUNK_LINE_LEN {
+                    return Err(ChunkedError::LineTooLong);
+                }
+            }
+            _ => (),
+        }
+
+        self.state = match (self.state, byte) {
+            (State::Size, byte) if byte.is_ascii_hexdigit() => {
+                if self.digits == 16 {
+                    return Err(ChunkedError::InvalidChunk);
+                }
+
+                self.size = (self.size << 4) | (byte as char).to_digit(16).unwrap() as u64;
+                self.digits += 1;
+
+                State::Size
+            }
+            (State::Size, b' ' | b'\t') if self.digits > 0 => State::SizeWs,
+            (State::SizeWs, b' ' | b'\t') => State::SizeWs,
+            (State::Size | State::SizeWs, b';') if self.digits > 0 => State::Extension,
+            (State::Size | State::SizeWs | State::Extension, b'\r') if self.digits > 0 => {
+                State::SizeLf
+            }
+            (State::Extension, byte) if byte == b'\t' || !byte.is_ascii_control() => {
+                State::Extension
+            }
+            (State::SizeLf, b'\n') => {
+                let size = self.size;
+
+                self.size = 0;
+                self.digits = 0;
+                self.line_len = 0;
+
+                if size == 0 {
+                    State::TrailerStart
+                } else {
+                    self.body_len = self
+                        .body_len
+                        .checked_add(size)
+                        .filter(|len| *len <= self.max_body_len)
+                        .ok_or(ChunkedError::BodyTooLarge)?;
+
+                    State::Data(size)
+                }
+            }
+            (State::DataCr, b'\r') => State::DataLf,
+            (State::DataLf, b'\n') => State::Size,
+            (State::TrailerStart, b'\r') => State::FinalLf,
+            (State::Trailer, b'\r') => State::TrailerLf,
+            (State::TrailerStart | State::Trailer, b'\n') => {
+                return Err(ChunkedError::InvalidTrailer)
+            }
+            (State::TrailerStart | State::Trailer, byte) => {
+                if byte.is_ascii_control() && byte != b'\t' {
+                    return Err(ChunkedError::InvalidTrailer);
+                }
+
+                self.colon |= byte == b':';
+                self.store_trailer(byte)?;
+
+                State::Trailer
+            }
+            (State::TrailerLf, b'\n') => {
+                self.complete_trailer()?;
+
+                State::TrailerStart
+            }
+            (State::FinalLf, b'\n') => State::Complete,
+            (State::TrailerLf | State::FinalLf, _) => return Err(ChunkedError::InvalidTrailer),
+            _ => return Err(ChunkedError::InvalidChunk),
+        };
+
+        Ok(())
+    }
+
+    /// Reads decoded body data from `read` into `buf`, returning `Ok(0)` once the body is complete.
+    pub(crate) fn read<R>(
+        &mut self,
+        read: &mut R,
+        buf: &mut [u8],
+    ) -> Result<usize, ChunkedError<R::Error>>
+    where
+        R: Read,
+    {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.is_complete() {
+                return Ok(0);
+            }
+
+            if let Some(remaining) = self.data_remaining() {
+                let len = min(buf.len() as u64, remaining) as usize;
+
+                let read = read.read(&mut buf[..len]).map_err(ChunkedError::Io)?;
+                if read == 0 {
+                    return Err(ChunkedError::UnexpectedEof);
+                }
+
+                self.consume(read);
+
+                return Ok(read);
+            }
+
+            let mut byte = [0];
+            if read.read(&mut byte).map_err(ChunkedError::Io)? == 0 {
+                return Err(ChunkedError::UnexpectedEof);
+            }
+
+            self.feed(byte[0])?;
+        }
+    }
+
+    /// The async counterpart of [`Decoder::read`].
+    pub(crate) async fn read_async<R>(
+        &mut self,
+        read: &mut R,
+        buf: &mut [u8],
+    ) -> Result<usize, ChunkedError<R::Error>>
+    where
+        R: crate::io::asynch::Read,
+    {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.is_complete() {
+                return Ok(0);
+            }
+
+            if let Some(remaining) = self.data_remaining() {
+                let len = min(buf.len() as u64, remaining) as usize;
+
+                let read = read.read(&mut buf[..len]).await.map_err(ChunkedError::Io)?;
+                if read == 0 {
+                    return Err(ChunkedError::UnexpectedEof);
+                }
+
+                self.consume(read);
+
+                return Ok(read);
+            }
+
+            let mut byte = [0];
+            if read.read(&mut byte).await.map_err(ChunkedError::Io)? == 0 {
+                return Err(ChunkedError::UnexpectedEof);
+            }
+
+            self.feed(byte[0])?;
+        }
+    }
+
+    fn store_trailer<E>(&mut self, byte: u8) -> Result<(), ChunkedError<E>> {
+        if self.trailers.is_empty() {
+            self.trailers_len += 1;
+
+            if self.trailers_len > MAX_TRAILERS_LEN {
+                return Err(ChunkedError::TrailersTooLarge);
+            }
+        } else {
+            *self
+                .trailers
+                .get_mut(self.trailers_len)
+                .ok_or(ChunkedError::TrailersTooLarge)? = byte;
+            self.trailers_len += 1;
+        }
+
+        Ok(())
+    }
+
+    fn complete_trailer<E>(&mut self) -> Result<(), ChunkedError<E>> {
+        if !self.colon {
+            return Err(ChunkedError::InvalidTrailer);
+        }
+
+        self.colon = false;
+
+        if !self.trailers.is_empty() {
+            if str::from_utf8(&self.trailers[self.line_start..self.trailers_len]).is_err() {
+                return Err(ChunkedError::InvalidTrailer);
+            }
+
+            self.store_trailer(b'\n')?;
+            self.line_start = self.trailers_len;
+        }
+
+        Ok(())
+    }
+
+    fn trailers(&self) -> Trailers<'_> {
+        let trailers = if self.is_complete() {
+            str::from_utf8(&self.trailers[..min(self.trailers_len, self.trailers.len())])
+                .unwrap_or("")
+        } else {
+            ""
+        };
+
+        Trailers(trailers.lines())
+    }
+}
+
+/// An iterator over the trailer fields received at the end of a chunked body.
+pub struct Trailers<'a>(str::Lines<'a>);
+
+impl<'a> Iterator for Trailers<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .and_then(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim(), value.trim()))
+    }
+}
+
+/// Decodes a body sent with `Transfer-Encoding: chunked`.
+///
+/// Chunk extensions are ignored. Trailers are discarded, unless the reader is created with
+/// [`ChunkedRead::with_trailers`], in which case they are stored in the provided buffer and
+/// are available via [`ChunkedRead::trailers`] once the body is fully read.
+///
+/// So as not to consume any byte following the body, chunk size lines and trailers are read one byte
+/// at a time. Over a socket, `read` should therefore be buffered, e.g. with a
+/// [`super::connection::BufferedSocket`] as the HTTP client and server connections do.
+pub struct ChunkedRead<'b, R> {
+    read: R,
+    decoder: Decoder<'b>,
+}
+
+impl<R> ChunkedRead<'static, R> {
+    pub fn new(read: R) -> Self {
+        Self::with_trailers(read, &mut [])
+    }
+}
+
+impl<'b, R> ChunkedRead<'b, R> {
+    pub fn with_trailers(read: R, trailers_buf: &'b mut [u8]) -> Self {
+        Self {
+            read,
+            decoder: Decoder::new(trailers_buf),
+        }
+    }
+
+    /// Limits the total size of the decoded body to `max_body_len` bytes.
+    pub fn with_max_body_len(mut self, max_body_len: u64) -> Self {
+        self.decoder.max_body_len = max_body_len;
+        self
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.decoder.is_complete()
+    }
+
+    pub fn trailers(&self) -> Trailers<'_> {
+        self.decoder.trailers()
+    }
+
+    pub fn release(self) -> R {
+        self.read
+    }
+}
+
+impl<R> ErrorType for ChunkedRead<'_, R>
+where
+    R: ErrorType,
+{
+    type Error = ChunkedError<R::Error>;
+}
+
+impl<R> Read for ChunkedRead<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.decoder.read(&mut self.read, buf)
+    }
+}
+
+/// Encodes a body with `Transfer-Encoding: chunked`.
+///
+/// Every non-empty `write` is sent as a separate chunk. The body must be terminated
+/// with [`ChunkedWrite::finish`] or [`ChunkedWrite::finish_with_trailers`].
+pub struct ChunkedWrite<W> {
+    write: W,
+    complete: bool,
+}
+
+impl<W> ChunkedWrite<W> {
+    pub const fn new(write: W) -> Self {
+        Self {
+            write,
+            complete: false,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn release(self) -> W {
+        self.write
+    }
+}
+
+impl<W> ChunkedWrite<W>
+where
+    W: Write,
+{
+    pub fn finish(&mut self) -> Result<(), ChunkedError<W::Error>> {
+        self.finish_with_trailers(&[])
+    }
+
+    pub fn finish_with_trailers(
+        &mut self,
+        trailers: &[(&str, &str)],
+    ) -> Result<(), ChunkedError<W::Error>> {
+        check_trailers(self.complete, trailers)?;
+
+        self.write.write_all(b"0\r\n").map_err(ChunkedError::Io)?;
+
+        for (name, value) in trailers {
+            for part in [name.as_bytes(), b": ", value.as_bytes(), b"\r\n"] {
+                self.write.write_all(part).map_err(ChunkedError::Io)?;
+            }
+        }
+
+        self.write.write_all(b"\r\n").map_err(ChunkedError::Io)?;
+        self.complete = true;
+
+        self.write.flush().map_err(ChunkedError::Io)
+    }
+}
+
+impl<W> ErrorType for ChunkedWrite<W>
+where
+    W: ErrorType,
+{
+    type Error = ChunkedError<W::Error>;
+}
+
+impl<W> Write for ChunkedWrite<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if self.complete {
+            return Err(ChunkedError::AlreadyComplete);
+        }
+
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut header = [0; 18];
+
+        for part in [chunk_header(buf.len(), &mut header), buf, b"\r\n"] {
+            self.write.write_all(part).map_err(ChunkedError::Io)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.write.flush().map_err(ChunkedError::Io)
+    }
+}
+
+fn chunk_header(len: usize, buf: &mut [u8; 18]) -> &[u8] {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    let mut offset = 16;
+    let mut len = len;
+
+    loop {
+        offset -= 1;
+        buf[offset] = HEX[len & 0x0f];
+        len >>= 4;
+
+        if len == 0 {
+            break;
+        }
+    }
+
+    buf[16] = b'\r';
+    buf[17] = b'\n';
+
+    &buf[offset..]
+}
+
+fn check_trailers<E>(complete: bool, trailers: &[(&str, &str)]) -> Result<(), ChunkedError<E>> {
+    if complete {
+        return Err(ChunkedError::AlreadyComplete);
+    }
+
+    let valid = trailers.iter().all(|(name, value)| {
+        !name.is_empty()
+            && name.bytes().all(|b| b.is_ascii_graphic() && b != b':')
+            && value.bytes().all(|b| b == b'\t' || !b.is_ascii_control())
+    });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ChunkedError::InvalidTrailer)
+    }
+}
+
+pub mod asynch {
+    use crate::io::asynch::{ErrorType, Read, Write};
+
+    use super::{check_trailers, chunk_header, Decoder};
+
+    pub use super::{ChunkedError, Trailers, MAX_CHUNK_LINE_LEN, MAX_TRAILERS_LEN};
+
+    /// Decodes a body sent with `Transfer-Encoding: chunked`.
+    ///
+    /// See [`super::ChunkedRead`], including why `read` should be buffered.
+    pub struct ChunkedRead<'b, R> {
+        read: R,
+        decoder: Decoder<'b>,
+    }
+
+    impl<R> ChunkedRead<'static, R> {
+        pub fn new(read: R) -> Self {
+            Self::with_trailers(read, &mut [])
+        }
+    }
+
+    impl<'b, R> ChunkedRead<'b, R> {
+        pub fn with_trailers(read: R, trailers_buf: &'b mut [u8]) -> Self {
+            Self {
+                read,
+                decoder: Decoder::new(trailers_buf),
+            }
+        }
+
+        pub fn with_max_body_len(mut self, max_body_len: u64) -> Self {
+            self.decoder.max_body_len = max_body_len;
+            self
+        }
+
+        pub fn is_complete(&self) -> bool {
+            self.decoder.is_complete()
+        }
+
+        pub fn trailers(&self) -> Trailers<'_> {
+            self.decoder.trailers()
+        }
+
+        pub fn release(self) -> R {
+            self.read
+        }
+    }
+
+    impl<R> ErrorType for ChunkedRead<'_, R>
+    where
+        R: ErrorType,
+    {
+        type Error = ChunkedError<R::Error>;
+    }
+
+    impl<R> Read for ChunkedRead<'_, R>
+    where
+        R: Read,
+    {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.decoder.read_async(&mut self.read, buf).await
+        }
+    }
+
+    pub struct ChunkedWrite<W> {
+        write: W,
+        complete: bool,
+    }
+
+    impl<W> ChunkedWrite<W> {
+        pub const fn new(write: W) -> Self {
+            Self {
+                write,
+                complete: false,
+            }
+        }
+
+        pub fn is_complete(&self) -> bool {
+            self.complete
+        }
+
+        pub fn release(self) -> W {
+            self.write
+        }
+    }
+
+    impl<W> ChunkedWrite<W>
+    where
+        W: Write,
+    {
+        pub async fn finish(&mut self) -> Result<(), ChunkedError<W::Error>> {
+            self.finish_with_trailers(&[]).await
+        }
+
+        pub async fn finish_with_trailers(
+            &mut self,
+            trailers: &[(&str, &str)],
+        ) -> Result<(), ChunkedError<W::Error>> {
+            check_trailers(self.complete, trailers)?;
+
+            self.write
+                .write_all(b"0\r\n")
+                .await
+                .map_err(ChunkedError::Io)?;
+
+            for (name, value) in trailers {
+                for part in [name.as_bytes(), b": ", value.as_bytes(), b"\r\n"] {
+                    self.write.write_all(part).await.map_err(ChunkedError::Io)?;
+                }
+            }
+
+            self.write
+                .write_all(b"\r\n")
+                .await
+                .map_err(ChunkedError::Io)?;
+            self.complete = true;
+
+            self.write.flush().await.map_err(ChunkedError::Io)
+        }
+    }
+
+    impl<W> ErrorType for ChunkedWrite<W>
+    where
+        W: ErrorType,
+    {
+        type Error = ChunkedError<W::Error>;
+    }
+
+    impl<W> Write for ChunkedWrite<W>
+    where
+        W: Write,
+    {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            if self.complete {
+                return Err(ChunkedError::AlreadyComplete);
+            }
+
+            if buf.is_empty() {
+                return Ok(0);
+            }
+
+            let mut header = [0; 18];
+
+            for part in [chunk_header(buf.len(), &mut header), buf, b"\r\n"] {
+                self.write.write_all(part).await.map_err(ChunkedError::Io)?;
+            }
+
+            Ok(buf.len())
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.write.flush().await.map_err(ChunkedError::Io)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use crate::io::{Read, Write};
+    use crate::utils::http::connection::BufferedSocket;
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{ChunkedError, ChunkedRead, ChunkedWrite};
+
+    const BODY: &[u8] = b"4\r\nWiki\r\n5;ext=1\r\npedia\r\nE \r\n in\r\n\r\nchunks.\r\n\
+        0\r\nX-Sum: 123\r\nX-Other:  a b \r\n\r\nleftover";
+
+    fn read_to_end<R: Read>(mut read: R, buf_len: usize) -> Result<Vec<u8>, R::Error> {
+        let mut body = Vec::new();
+        let mut buf = [0; 64];
+
+        loop {
+            let len = read.read(&mut buf[..buf_len])?;
+            if len == 0 {
+                break Ok(body);
+            }
+
+            body.extend_from_slice(&buf[..len]);
+        }
+    }
+
+    fn decode_error(body: &[u8]) -> ChunkedError<core::convert::Infallible> {
+        read_to_end(ChunkedRead::new(MockSocket::new(body)), 64).unwrap_err()
+    }
+
+    #[test]
+    fn decode_split_reads() {
+        for socket_chunk in [1, 2, 3, 7, usize::MAX] {
+            for buf_len in [1, 3, 64] {
+                let mut trailers = [0; 64];
+                let mut read = ChunkedRead::with_trailers(
+                    MockSocket::chunked(BODY, socket_chunk),
+                    &mut trailers,
+                );
+
+                let body = read_to_end(&mut read, buf_len).unwrap();
+
+                assert_eq!(body, b"Wikipedia in\r\n\r\nchunks.");
+                assert!(read.is_complete());
+                assert_eq!(
+                    read.trailers().collect::<Vec<_>>(),
+                    [("X-Sum", "123"), ("X-Other", "a b")]
+                );
+                assert_eq!(read.release().input, b"leftover");
+            }
+        }
+    }
+
+    #[test]
+    fn decode_buffered() {
+        let mut buf = [0; 16];
+        let mut read = ChunkedRead::new(BufferedSocket::new(MockSocket::new(BODY), &mut buf));
+
+        let body = read_to_end(&mut read, 64).unwrap();
+        assert_eq!(body, b"Wikipedia in\r\n\r\nchunks.");
+
+        // The bytes following the body stay in the buffer
+        let socket = read.release();
+        let buffered = socket.buffered().to_vec();
+        assert_eq!([&buffered, socket.release().input].concat(), b"leftover");
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(matches!(
+            decode_error(b"zz\r\n"),
+            ChunkedError::InvalidChunk
+        ));
+        assert!(matches!(
+            decode_error(b"3\nabc\r\n"),
+            ChunkedError::InvalidChunk
+        ));
+        assert!(matches!(
+            decode_error(b"3\r\nabcX\r\n"),
+            ChunkedError::InvalidChunk
+        ));
+        assert!(matches!(
+            decode_error(b"3\r\nab"),
+            ChunkedError::UnexpectedEof
+        ));
+        assert!(matches!(
+            decode_error(b"11111111111111111\r\n"),
+            ChunkedError::InvalidChunk
+        ));
+        assert!(matches!(
+            decode_error(b"0\r\nbad\r\n\r\n"),
+            ChunkedError::InvalidTrailer
+        ));
+        assert!(matches!(
+            decode_error(b"0\r\nX: a\n\r\n"),
+            ChunkedError::InvalidTrailer
+        ));
+
+        let mut line = Vec::from(&b"1;"[..]);
+        line.resize(2000, b'x');
+        assert!(matches!(decode_error(&line), ChunkedError::LineTooLong));
+
+        let read = ChunkedRead::new(MockSocket::new(b"5\r\nabcde\r\n5\r\nabcde\r\n0\r\n\r\n"))
+            .with_max_body_len(8);
+        assert!(matches!(
+            read_to_end(read, 64),
+            Err(ChunkedError::BodyTooLarge)
+        ));
+
+        let mut trailers = [0; 4];
+        let read =
+            ChunkedRead::with_trailers(MockSocket::new(b"0\r\nX-Long: 1\r\n\r\n"), &mut trailers);
+        assert!(matches!(
+            read_to_end(read, 64),
+            Err(ChunkedError::TrailersTooLarge)
+        ));
+    }
+
+    #[test]
+    fn encode() {
+        let mut write = ChunkedWrite::new(MockSocket::new(&[]));
+
+        write.write_all(b"hello").unwrap();
+        write.write_all(&[b'x'; 300]).unwrap();
+        assert_eq!(write.write(b"").unwrap(), 0);
+        write.finish_with_trailers(&[("X-A", "1")]).unwrap();
+
+        assert!(matches!(
+            write.write(b"a"),
+            Err(ChunkedError::AlreadyComplete)
+        ));
+
+        let mut expected = Vec::from(&b"5\r\nhello\r\n12c\r\n"[..]);
+        expected.extend_from_slice(&[b'x'; 300]);
+        expected.extend_from_slice(b"\r\n0\r\nX-A: 1\r\n\r\n");
+
+        assert_eq!(write.release().output, expected);
+
+        assert!(matches!(
+            ChunkedWrite::new(MockSocket::new(&[])).finish_with_trailers(&[("Bad:Name", "1")]),
+            Err(ChunkedError::InvalidTrailer)
+        ));
+    }
+
+    #[test]
+    fn asynch() {
+        use crate::io::asynch::{Read, Write};
+
+        use super::asynch::{ChunkedRead, ChunkedWrite};
+
+        block_on(async {
+            let mut write = ChunkedWrite::new(MockSocket::new(&[]));
+            write.write_all(b"abc").await.unwrap();
+            write.finish().await.unwrap();
+
+            let output = write.release().output;
+            let mut read = ChunkedRead::new(MockSocket::chunked(&output, 1));
+            let mut buf = [0; 10];
+
+            read.read_exact(&mut buf[..3]).await.unwrap();
+            assert_eq!(&buf[..3], b"abc");
+            assert_eq!(read.read(&mut buf).await.unwrap(), 0);
+            assert!(read.is_complete());
+        });
+    }
+}
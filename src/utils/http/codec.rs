@@ -0,0 +1,783 @@
+use core::fmt;
+use core::str;
+
+use crate::http::{Method, Query, Status};
+
+use super::Headers;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    Incomplete,
+    InvalidLineEnding,
+    InvalidMethod,
+    UnknownMethod,
+    InvalidTarget,
+    InvalidVersion,
+    InvalidStatus,
+    InvalidReason,
+    InvalidHeaderName,
+    InvalidHeaderValue,
+    InvalidContentLength,
+    TooManyHeaders,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Incomplete => write!(f, "Incomplete message head"),
+            Self::InvalidLineEnding => write!(f, "Line not terminated with CRLF"),
+            Self::InvalidMethod => write!(f, "Invalid method"),
+            Self::UnknownMethod => write!(f, "Unknown method"),
+            Self::InvalidTarget => write!(f, "Invalid request target"),
+            Self::InvalidVersion => write!(f, "Invalid or unsupported HTTP version"),
+            Self::InvalidStatus => write!(f, "Invalid status code"),
+            Self::InvalidReason => write!(f, "Invalid reason phrase"),
+            Self::InvalidHeaderName => write!(f, "Invalid header name"),
+            Self::InvalidHeaderValue => write!(f, "Invalid header value"),
+            Self::InvalidContentLength => write!(f, "Invalid Content-Length"),
+            Self::TooManyHeaders => write!(f, "Too many headers"),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SerializeError {
+    InvalidTarget,
+    InvalidStatus,
+    InvalidReason,
+    InvalidHeader,
+    BufferOverflow,
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTarget => write!(f, "Invalid request target"),
+            Self::InvalidStatus => write!(f, "Invalid status code"),
+            Self::InvalidReason => write!(f, "Invalid reason phrase"),
+            Self::InvalidHeader => write!(f, "Invalid header"),
+            Self::BufferOverflow => write!(f, "Buffer overflow"),
+        }
+    }
+}
+
+impl core::error::Error for SerializeError {}
+
+/// The head (request line and headers) of an HTTP/1.x request.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RequestHead<'b, const N: usize = 64> {
+    pub method: Method,
+    pub uri: &'b str,
+    pub http11: bool,
+    pub headers: Headers<'b, N>,
+}
+
+impl<'b, const N: usize> RequestHead<'b, N> {
+    /// Parses a request head from the beginning of `buf`.
+    ///
+    /// Returns the parsed head, borrowing from `buf`, together with the length of the head,
+    /// i.e. the offset of the body in `buf`. Returns [`ParseError::Incomplete`] if `buf` does not
+    /// (yet) contain the complete head.
+    pub fn parse(buf: &'b [u8]) -> Result<(Self, usize), ParseError> {
+        let mut lines = Lines::new(buf);
+
+        let line = loop {
+            // As per RFC 9112, empty lines preceding the request line should be ignored
+            let line = lines.next_line()?;
+            if !line.is_empty() {
+                break line;
+            }
+        };
+
+        let mut parts = line.splitn(3, |b| *b == b' ');
+
+        let method = parts
+            .next()
+            .filter(|method| is_token_str(method))
+            .ok_or(ParseError::InvalidMethod)?;
+        let method = as_str(method)
+            .parse()
+            .map_err(|_| ParseError::UnknownMethod)?;
+
+        let uri = parts
+            .next()
+            .filter(|uri| !uri.is_empty() && uri.iter().all(u8::is_ascii_graphic))
+            .ok_or(ParseError::InvalidTarget)?;
+
+        let http11 = parse_version(parts.next())?;
+
+        let headers = parse_headers(&mut lines)?;
+
+        Ok((
+            Self {
+                method,
+                uri: as_str(uri),
+                http11,
+                headers,
+            },
+            lines.offset,
+        ))
+    }
+
+    pub fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        write_request_head(
+            self.method,
+            self.uri,
+            self.http11,
+            self.headers.as_slice(),
+            buf,
+        )
+    }
+}
+
+impl<const N: usize> Query for RequestHead<'_, N> {
+    fn uri(&self) -> &'_ str {
+        self.uri
+    }
+
+    fn method(&self) -> Method {
+        self.method
+    }
+}
+
+impl<const N: usize> crate::http::Headers for RequestHead<'_, N> {
+    fn header(&self, name: &str) -> Option<&'_ str> {
+        self.headers.get(name)
+    }
+}
+
+/// The head (status line and headers) of an HTTP/1.x response.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ResponseHead<'b, const N: usize = 64> {
+    pub http11: bool,
+    pub status: u16,
+    pub reason: &'b str,
+    pub headers: Headers<'b, N>,
+}
+
+impl<'b, const N: usize> ResponseHead<'b, N> {
+    /// Parses a response head from the beginning of `buf`.
+    ///
+    /// See [`RequestHead::parse`] for the meaning of the returned values.
+    pub fn parse(buf: &'b [u8]) -> Result<(Self, usize), ParseError> {
+        let mut lines = Lines::new(buf);
+
+        let mut parts = lines.next_line()?.splitn(3, |b| *b == b' ');
+
+        let http11 = parse_version(parts.next())?;
+
+        let status = parts
+            .next()
+            .filter(|status| status.len() == 3 && status.iter().all(u8::is_ascii_digit))
+            .and_then(|status| as_str(status).parse::<u16>().ok())
+            .filter(|status| *status >= 100)
+            .ok_or(ParseError::InvalidStatus)?;
+
+        // Some servers omit the space after the status code when the reason phrase is empty
+        let reason = parts.next().unwrap_or(&[]);
+        if !reason.iter().all(|b| is_field_value_char(*b)) {
+            return Err(ParseError::InvalidReason);
+        }
+
+        let reason = str::from_utf8(reason).map_err(|_| ParseError::InvalidReason)?;
+
+        let headers = parse_headers(&mut lines)?;
+
+        Ok((
+            Self {
+                http11,
+                status,
+                reason,
+                headers,
+            },
+            lines.offset,
+        ))
+    }
+
+    pub fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        write_response_head(
+            self.http11,
+            self.status,
+            Some(self.reason),
+            self.headers.as_slice(),
+            buf,
+        )
+    }
+}
+
+impl<const N: usize> Status for ResponseHead<'_, N> {
+    fn status(&self) -> u16 {
+        self.status
+    }
+
+    fn status_message(&self) -> Option<&'_ str> {
+        Some(self.reason)
+    }
+}
+
+impl<const N: usize> crate::http::Headers for ResponseHead<'_, N> {
+    fn header(&self, name: &str) -> Option<&'_ str> {
+        self.headers.get(name)
+    }
+}
+
+/// Serializes an HTTP/1.1 request line and headers, terminated with an empty line, into `buf`.
+///
+/// Returns the length of the serialized head.
+pub fn serialize_request_head(
+    method: Method,
+    uri: &str,
+    headers: &[(&str, &str)],
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    write_request_head(method, uri, true, headers, buf)
+}
+
+/// Serializes an HTTP/1.1 status line and headers, terminated with an empty line, into `buf`.
+///
+/// Returns the length of the serialized head.
+pub fn serialize_response_head(
+    status: u16,
+    reason: Option<&str>,
+    headers: &[(&str, &str)],
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    write_response_head(true, status, reason, headers, buf)
+}
+
+fn write_request_head(
+    method: Method,
+    uri: &str,
+    http11: bool,
+    headers: &[(&str, &str)],
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    if uri.is_empty() || !uri.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(SerializeError::InvalidTarget);
+    }
+
+    let mut out = BufWriter::new(buf);
+
+    out.write(method.as_str())?;
+    out.write(" ")?;
+    out.write(uri)?;
+    out.write(version(http11))?;
+    out.write("\r\n")?;
+
+    write_headers(&mut out, headers)
+}
+
+fn write_response_head(
+    http11: bool,
+    status: u16,
+    reason: Option<&str>,
+    headers: &[(&str, &str)],
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    if !(100..1000).contains(&status) {
+        return Err(SerializeError::InvalidStatus);
+    }
+
+    let reason = reason.unwrap_or("");
+    if !reason.bytes().all(is_field_value_char) {
+        return Err(SerializeError::InvalidReason);
+    }
+
+    let mut out = BufWriter::new(buf);
+
+    out.write(&version(http11)[1..])?;
+    out.write(" ")?;
+    out.write(heapless::String::<5>::try_from(status).unwrap().as_str())?;
+    out.write(" ")?;
+    out.write(reason)?;
+    out.write("\r\n")?;
+
+    write_headers(&mut out, headers)
+}
+
+fn write_headers(
+    out: &mut BufWriter<'_>,
+    headers: &[(&str, &str)],
+) -> Result<usize, SerializeError> {
+    for (name, value) in headers {
+        if !is_token_str(name.as_bytes()) || !value.bytes().all(is_field_value_char) {
+            return Err(SerializeError::InvalidHeader);
+        }
+
+        out.write(name)?;
+        out.write(": ")?;
+        out.write(value)?;
+        out.write("\r\n")?;
+    }
+
+    out.write("\r\n")?;
+
+    Ok(out.len)
+}
+
+fn version(http11: bool) -> &'static str {
+    if http11 {
+        " HTTP/1.1"
+    } else {
+        " HTTP/1.0"
+    }
+}
+
+fn parse_version(version: Option<&[u8]>) -> Result<bool, ParseError> {
+    match version {
+        Some(b"HTTP/1.1") => Ok(true),
+        Some(b"HTTP/1.0") => Ok(false),
+        _ => Err(ParseError::InvalidVersion),
+    }
+}
+
+fn parse_headers<'b, const N: usize>(lines: &mut Lines<'b>) -> Result<Headers<'b, N>, ParseError> {
+    let mut headers = Headers::new();
+
+    loop {
+        let line = lines.next_line()?;
+        if line.is_empty() {
+            break;
+        }
+
+        let colon = line
+            .iter()
+            .position(|b| *b == b':')
+            .ok_or(ParseError::InvalidHeaderName)?;
+
+        let name = &line[..colon];
+        if !is_token_str(name) {
+            return Err(ParseError::InvalidHeaderName);
+        }
+
+        let value = trim_ows(&line[colon + 1..]);
+        if !value.iter().all(|b| is_field_value_char(*b)) {
+            return Err(ParseError::InvalidHeaderValue);
+        }
+
+        let value = str::from_utf8(value).map_err(|_| ParseError::InvalidHeaderValue)?;
+
+        headers
+            .try_add(as_str(name), value)
+            .map_err(|_| ParseError::TooManyHeaders)?;
+    }
+
+    // `Headers::content_len` expects a valid `Content-Length`, and conflicting
+    // `Content-Length` headers are a well known request smuggling vector
+    let mut content_len = None;
+
+    for (name, value) in headers.iter() {
+        if name.eq_ignore_ascii_case("Content-Length") {
+            if value.is_empty()
+                || !value.bytes().all(|b| b.is_ascii_digit())
+                || value.parse::<u64>().is_err()
+                || content_len.is_some_and(|content_len| content_len != value)
+            {
+                return Err(ParseError::InvalidContentLength);
+            }
+
+            content_len = Some(value);
+        }
+    }
+
+    Ok(headers)
+}
+
+struct Lines<'b> {
+    buf: &'b [u8],
+    offset: usize,
+}
+
+impl<'b> Lines<'b> {
+    const fn new(buf: &'b [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+
+    fn next_line(&mut self) -> Result<&'b [u8], ParseError> {
+        let rest = &self.buf[self.offset..];
+
+        let lf = rest
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or(ParseError::Incomplete)?;
+
+        if lf == 0 || rest[lf - 1] != b'\r' {
+            return Err(ParseError::InvalidLineEnding);
+        }
+
+        self.offset += lf + 1;
+
+        Ok(&rest[..lf - 1])
+    }
+}
+
+struct BufWriter<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> BufWriter<'b> {
+    fn new(buf: &'b mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn write(&mut self, data: &str) -> Result<(), SerializeError> {
+        let end = self.len + data.len();
+
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(SerializeError::BufferOverflow)?
+            .copy_from_slice(data.as_bytes());
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+fn trim_ows(value: &[u8]) -> &[u8] {
+    let start = value
+        .iter()
+        .position(|b| !matches!(b, b' ' | b'\t'))
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|b| !matches!(b, b' ' | b'\t'))
+        .map(|end| end + 1)
+        .unwrap_or(start);
+
+    &value[start..end]
+}
+
+/// Converts a slice already validated to be ASCII into a `&str`.
+fn as_str(ascii: &[u8]) -> &str {
+    str::from_utf8(ascii).unwrap_or("")
+}
+
+fn is_token_str(token: &[u8]) -> bool {
+    !token.is_empty() && token.iter().all(|b| is_token(*b))
+}
+
+pub(crate) fn is_token(b: u8) -> bool {
+    b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b)
+}
+
+fn is_field_value_char(b: u8) -> bool {
+    b == b'\t' || b == b' ' || b.is_ascii_graphic() || b >= 0x80
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::http::Method;
+    use crate::utils::http::server::connection::handle_connection;
+    use crate::utils::io::test::MockSocket;
+
+    use super::{
+        serialize_request_head, serialize_response_head, ParseError, RequestHead, ResponseHead,
+        SerializeError,
+    };
+
+    /// A request head as sent by curl, followed by its body.
+    const CURL_REQUEST: &[u8] = b"POST /api/v1/devices?verbose=1 HTTP/1.1\r\n\
+        Host: 192.168.4.1\r\n\
+        User-Agent: curl/8.5.0\r\n\
+        Accept: */*\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: 13\r\n\
+        \r\n\
+        {\"on\": true}\n";
+
+    /// A response head as sent by nginx, followed by its body.
+    const NGINX_RESPONSE: &[u8] = b"HTTP/1.1 301 Moved Permanently\r\n\
+        Server: nginx/1.24.0\r\n\
+        Date: Mon, 12 Feb 2024 10:00:00 GMT\r\n\
+        Content-Type: text/html\r\n\
+        Content-Length: 5\r\n\
+        Connection: keep-alive\r\n\
+        Location: https://example.com/\r\n\
+        Set-Cookie: a=1; Path=/\r\n\
+        Set-Cookie:  b=2 \r\n\
+        \r\n\
+        hello";
+
+    fn parse_request(head: &[u8]) -> Result<(RequestHead<'_, 8>, usize), ParseError> {
+        RequestHead::parse(head)
+    }
+
+    fn parse_response(head: &[u8]) -> Result<(ResponseHead<'_, 16>, usize), ParseError> {
+        ResponseHead::parse(head)
+    }
+
+    fn serve(request: &[u8], buf_len: usize) -> String {
+        let mut socket = MockSocket::new(request);
+        let mut buf = [0; 1024];
+
+        let _ = handle_connection::<_, _, 16>(
+            &mut socket,
+            &mut buf[..buf_len],
+            &crate::http::server::FnHandler::new(|_| Ok::<_, core::convert::Infallible>(())),
+        );
+
+        String::from_utf8(socket.output).unwrap()
+    }
+
+    #[test]
+    fn parse_recorded_request() {
+        let (head, len) = parse_request(CURL_REQUEST).unwrap();
+
+        assert_eq!(head.method, Method::Post);
+        assert_eq!(head.uri, "/api/v1/devices?verbose=1");
+        assert!(head.http11);
+        assert_eq!(head.headers.host(), Some("192.168.4.1"));
+        assert_eq!(head.headers.content_len(), Some(13));
+        assert_eq!(head.headers.iter().count(), 5);
+        assert_eq!(&CURL_REQUEST[len..], b"{\"on\": true}\n");
+
+        for end in 0..len {
+            assert_eq!(
+                parse_request(&CURL_REQUEST[..end]).unwrap_err(),
+                ParseError::Incomplete
+            );
+        }
+    }
+
+    #[test]
+    fn parse_recorded_response() {
+        let (head, len) = parse_response(NGINX_RESPONSE).unwrap();
+
+        assert!(head.http11);
+        assert_eq!(head.status, 301);
+        assert_eq!(head.reason, "Moved Permanently");
+        assert_eq!(head.headers.get("location"), Some("https://example.com/"));
+        assert_eq!(
+            head.headers
+                .iter()
+                .filter(|(name, _)| *name == "Set-Cookie")
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>(),
+            ["a=1; Path=/", "b=2"]
+        );
+        assert_eq!(&NGINX_RESPONSE[len..], b"hello");
+
+        for end in 0..len {
+            assert_eq!(
+                parse_response(&NGINX_RESPONSE[..end]).unwrap_err(),
+                ParseError::Incomplete
+            );
+        }
+
+        let (head, _) = parse_response(b"HTTP/1.0 204\r\n\r\n").unwrap();
+        assert!(!head.http11);
+        assert_eq!((head.status, head.reason), (204, ""));
+    }
+
+    #[test]
+    fn parse_leading_empty_lines() {
+        let (head, len) = parse_request(b"\r\n\r\nGET / HTTP/1.0\r\n\r\n").unwrap();
+
+        assert_eq!(head.uri, "/");
+        assert!(!head.http11);
+        assert_eq!(len, 22);
+    }
+
+    #[test]
+    fn parse_obs_fold() {
+        assert_eq!(
+            parse_request(b"GET / HTTP/1.1\r\nX-A: 1\r\n  continued\r\n\r\n").unwrap_err(),
+            ParseError::InvalidHeaderName
+        );
+        assert_eq!(
+            parse_response(b"HTTP/1.1 200 OK\r\nX-A: 1\r\n\tcontinued\r\n\r\n").unwrap_err(),
+            ParseError::InvalidHeaderName
+        );
+    }
+
+    #[test]
+    fn parse_bad_tokens() {
+        for (head, error) in [
+            (&b"G(T / HTTP/1.1\r\n\r\n"[..], ParseError::InvalidMethod),
+            (b" GET / HTTP/1.1\r\n\r\n", ParseError::InvalidMethod),
+            (b"FOO / HTTP/1.1\r\n\r\n", ParseError::UnknownMethod),
+            (b"get / HTTP/1.1\r\n\r\n", ParseError::UnknownMethod),
+            (b"GET  / HTTP/1.1\r\n\r\n", ParseError::InvalidTarget),
+            (b"GET /\x7f HTTP/1.1\r\n\r\n", ParseError::InvalidTarget),
+            (b"GET / HTTP/2.0\r\n\r\n", ParseError::InvalidVersion),
+            (b"GET / http/1.1\r\n\r\n", ParseError::InvalidVersion),
+            (b"GET / HTTP/1.1\n\r\n", ParseError::InvalidLineEnding),
+            (
+                b"GET / HTTP/1.1\r\nA : b\r\n\r\n",
+                ParseError::InvalidHeaderName,
+            ),
+            (
+                b"GET / HTTP/1.1\r\nA\"B: b\r\n\r\n",
+                ParseError::InvalidHeaderName,
+            ),
+            (
+                b"GET / HTTP/1.1\r\n: b\r\n\r\n",
+                ParseError::InvalidHeaderName,
+            ),
+            (
+                b"GET / HTTP/1.1\r\nNoColon\r\n\r\n",
+                ParseError::InvalidHeaderName,
+            ),
+            (
+                b"GET / HTTP/1.1\r\nA: b\x00c\r\n\r\n",
+                ParseError::InvalidHeaderValue,
+            ),
+            (
+                b"GET / HTTP/1.1\r\nA: \xff\r\n\r\n",
+                ParseError::InvalidHeaderValue,
+            ),
+        ] {
+            assert_eq!(parse_request(head).unwrap_err(), error, "{head:?}");
+        }
+
+        for (head, error) in [
+            (&b"HTTP/1.1 20 OK\r\n\r\n"[..], ParseError::InvalidStatus),
+            (b"HTTP/1.1 099 OK\r\n\r\n", ParseError::InvalidStatus),
+            (b"HTTP/1.1 +20 OK\r\n\r\n", ParseError::InvalidStatus),
+            (b"HTTP/1.1 200 O\x01K\r\n\r\n", ParseError::InvalidReason),
+            (b"HTTP/1.2 200 OK\r\n\r\n", ParseError::InvalidVersion),
+        ] {
+            assert_eq!(parse_response(head).unwrap_err(), error, "{head:?}");
+        }
+    }
+
+    #[test]
+    fn parse_too_many_headers() {
+        assert_eq!(
+            RequestHead::<2>::parse(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n").unwrap_err(),
+            ParseError::TooManyHeaders
+        );
+    }
+
+    #[test]
+    fn parse_content_length() {
+        let (head, _) =
+            parse_request(b"GET / HTTP/1.1\r\nContent-Length: 5\r\ncontent-length: 5\r\n\r\n")
+                .unwrap();
+        assert_eq!(head.headers.content_len(), Some(5));
+
+        for content_len in [
+            &b"Content-Length: 1\r\nContent-Length: 2\r\n"[..],
+            b"Content-Length: 1, 1\r\n",
+            b"Content-Length: -1\r\n",
+            b"Content-Length: +1\r\n",
+            b"Content-Length: \r\n",
+            b"Content-Length: 99999999999999999999\r\n",
+        ] {
+            let mut head = Vec::from(&b"POST / HTTP/1.1\r\n"[..]);
+            head.extend_from_slice(content_len);
+            head.extend_from_slice(b"\r\n");
+
+            assert_eq!(
+                parse_request(&head).unwrap_err(),
+                ParseError::InvalidContentLength,
+                "{content_len:?}"
+            );
+        }
+    }
+
+    /// Parsing arbitrary corruptions of the recorded messages must fail cleanly rather than panic.
+    #[test]
+    fn parse_mutations() {
+        for message in [CURL_REQUEST, NGINX_RESPONSE] {
+            for index in 0..message.len() {
+                for byte in [0, b' ', b'\t', b':', b'\r', b'\n', b'0', 0x7f, 0xc3, 0xff] {
+                    let mut mutated = Vec::from(message);
+                    mutated[index] = byte;
+
+                    let _ = parse_request(&mutated);
+                    let _ = parse_response(&mutated);
+
+                    mutated.remove(index);
+
+                    let _ = parse_request(&mutated);
+                    let _ = parse_response(&mutated);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn serialize() {
+        let (head, len) = parse_request(CURL_REQUEST).unwrap();
+        let mut buf = [0; 256];
+
+        let serialized_len = head.serialize(&mut buf).unwrap();
+        assert_eq!(&buf[..serialized_len], &CURL_REQUEST[..len]);
+
+        let (head, len) = parse_response(NGINX_RESPONSE).unwrap();
+        let serialized_len = head.serialize(&mut buf).unwrap();
+        let (reparsed, _) = parse_response(&buf[..serialized_len]).unwrap();
+        // The whitespace around `b=2` is not part of the value
+        assert_eq!(serialized_len, len - 2);
+        assert_eq!(
+            reparsed.headers.iter().collect::<Vec<_>>(),
+            head.headers.iter().collect::<Vec<_>>()
+        );
+
+        let len =
+            serialize_response_head(200, Some("OK"), &[("Content-Length", "0")], &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    }
+
+    #[test]
+    fn serialize_invalid() {
+        let mut buf = [0; 64];
+
+        assert_eq!(
+            serialize_request_head(Method::Get, "/", &[("A", "x\r\nB: y")], &mut buf),
+            Err(SerializeError::InvalidHeader)
+        );
+        assert_eq!(
+            serialize_request_head(Method::Get, "/", &[("A B", "x")], &mut buf),
+            Err(SerializeError::InvalidHeader)
+        );
+        assert_eq!(
+            serialize_request_head(Method::Get, "/a b", &[], &mut buf),
+            Err(SerializeError::InvalidTarget)
+        );
+        assert_eq!(
+            serialize_request_head(Method::Get, "/", &[("A", "x")], &mut buf[..10]),
+            Err(SerializeError::BufferOverflow)
+        );
+        assert_eq!(
+            serialize_response_head(99, None, &[], &mut buf),
+            Err(SerializeError::InvalidStatus)
+        );
+        assert_eq!(
+            serialize_response_head(200, Some("O\nK"), &[], &mut buf),
+            Err(SerializeError::InvalidReason)
+        );
+    }
+
+    #[test]
+    fn serve_oversized_head() {
+        let mut request = Vec::from(&b"GET / HTTP/1.1\r\nX-Long: "[..]);
+        request.resize(600, b'x');
+        request.extend_from_slice(b"\r\n\r\n");
+
+        let response = serve(&request, 512);
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+    }
+
+    #[test]
+    fn serve_transfer_encoding_with_content_length() {
+        let response = serve(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\n\r\n0\r\n\r\n",
+            1024,
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+}
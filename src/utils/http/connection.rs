@@ -0,0 +1,796 @@
+//! Building blocks shared by the portable HTTP client and server connections.
+
+use core::cmp::min;
+use core::fmt::{self, Debug};
+use core::str;
+
+use crate::io::{Error, ErrorKind, ErrorType, Read, Write};
+
+use super::chunked::{ChunkedError, ChunkedWrite, Decoder};
+use super::codec::{ParseError, SerializeError};
+use super::HeaderSetError;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConnectionError<E> {
+    Io(E),
+    Parse(ParseError),
+    Serialize(SerializeError),
+    HeadTooLarge,
+    TooManyHeaders,
+    InvalidBody,
+    ContentLengthMismatch,
+    UnexpectedEof,
+    ConnectionClosed,
+    InvalidState,
+}
+
+impl<E> From<ParseError> for ConnectionError<E> {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl<E> From<SerializeError> for ConnectionError<E> {
+    fn from(e: SerializeError) -> Self {
+        match e {
+            SerializeError::BufferOverflow => Self::HeadTooLarge,
+            e => Self::Serialize(e),
+        }
+    }
+}
+
+impl<E> From<HeaderSetError> for ConnectionError<E> {
+    fn from(_: HeaderSetError) -> Self {
+        Self::TooManyHeaders
+    }
+}
+
+impl<E> From<ChunkedError<E>> for ConnectionError<E> {
+    fn from(e: ChunkedError<E>) -> Self {
+        match e {
+            ChunkedError::Io(e) => Self::Io(e),
+            ChunkedError::UnexpectedEof => Self::UnexpectedEof,
+            ChunkedError::AlreadyComplete => Self::InvalidState,
+            _ => Self::InvalidBody,
+        }
+    }
+}
+
+impl<E: Debug> fmt::Display for ConnectionError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: Debug> core::error::Error for ConnectionError<E> {}
+
+impl<E> Error for ConnectionError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::Parse(_) | Self::Serialize(_) | Self::InvalidBody => ErrorKind::InvalidData,
+            Self::HeadTooLarge | Self::TooManyHeaders => ErrorKind::OutOfMemory,
+            Self::ContentLengthMismatch | Self::InvalidState => ErrorKind::InvalidInput,
+            Self::UnexpectedEof => ErrorKind::ConnectionAborted,
+            Self::ConnectionClosed => ErrorKind::NotConnected,
+        }
+    }
+}
+
+/// A socket with a receive buffer.
+///
+/// Bytes received past the end of a message head stay in the buffer and are returned by
+/// subsequent reads, so nothing is lost when the socket is taken over after a protocol upgrade.
+pub struct BufferedSocket<'b, T> {
+    socket: T,
+    buf: &'b mut [u8],
+    start: usize,
+    end: usize,
+}
+
+impl<'b, T> BufferedSocket<'b, T> {
+    pub fn new(socket: T, buf: &'b mut [u8]) -> Self {
+        Self {
+            socket,
+            buf,
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Returns the received bytes which were not read yet.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf[self.start..self.end]
+    }
+
+    pub fn release(self) -> T {
+        self.socket
+    }
+
+    pub(crate) fn into_parts(self) -> (T, &'b mut [u8]) {
+        (self.socket, self.buf)
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+
+        if self.start == self.end {
+            self.start = 0;
+            self.end = 0;
+        }
+    }
+
+    fn copy_buffered(&mut self, buf: &mut [u8]) -> usize {
+        let len = min(buf.len(), self.end - self.start);
+
+        buf[..len].copy_from_slice(&self.buf[self.start..self.start + len]);
+        self.consume(len);
+
+        len
+    }
+
+    /// Moves the buffered bytes to the beginning of the buffer.
+    fn compact(&mut self) {
+        if self.start > 0 {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+    }
+}
+
+impl<T> ErrorType for BufferedSocket<'_, T>
+where
+    T: ErrorType,
+{
+    type Error = T::Error;
+}
+
+impl<T> Read for BufferedSocket<'_, T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.start == self.end && !buf.is_empty() {
+            if buf.len() >= self.buf.len() {
+                return self.socket.read(buf);
+            }
+
+            self.end = self.socket.read(self.buf)?;
+        }
+
+        Ok(self.copy_buffered(buf))
+    }
+}
+
+impl<T> Write for BufferedSocket<'_, T>
+where
+    T: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.socket.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.socket.flush()
+    }
+}
+
+/// The body of an incoming message, read from the underlying socket according to the
+/// framing announced in the message head.
+pub struct Body<'b, T> {
+    socket: BufferedSocket<'b, T>,
+    framing: Framing,
+}
+
+impl<'b, T> Body<'b, T> {
+    pub(crate) fn new(socket: BufferedSocket<'b, T>) -> Self {
+        Self {
+            socket,
+            framing: Framing::Empty,
+        }
+    }
+
+    /// Returns `true` once the whole body has been read.
+    pub fn is_complete(&self) -> bool {
+        match &self.framing {
+            Framing::Empty => true,
+            Framing::Fixed(remaining) => *remaining == 0,
+            Framing::Chunked(decoder) => decoder.is_complete(),
+            Framing::Close => false,
+        }
+    }
+
+    pub(crate) fn socket(&mut self) -> &mut BufferedSocket<'b, T> {
+        &mut self.socket
+    }
+
+    pub(crate) fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    pub(crate) fn release(self) -> T {
+        self.socket.release()
+    }
+
+    pub(crate) fn into_socket(self) -> BufferedSocket<'b, T> {
+        self.socket
+    }
+}
+
+impl<T> ErrorType for Body<'_, T>
+where
+    T: ErrorType,
+{
+    type Error = ConnectionError<T::Error>;
+}
+
+impl<T> Read for Body<'_, T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        match &mut self.framing {
+            Framing::Empty => Ok(0),
+            Framing::Fixed(remaining) => {
+                if *remaining == 0 {
+                    return Ok(0);
+                }
+
+                let len = min(buf.len() as u64, *remaining) as usize;
+
+                let read = self
+                    .socket
+                    .read(&mut buf[..len])
+                    .map_err(ConnectionError::Io)?;
+                if read == 0 {
+                    return Err(ConnectionError::UnexpectedEof);
+                }
+
+                *remaining -= read as u64;
+
+                Ok(read)
+            }
+            Framing::Chunked(decoder) => Ok(decoder.read(&mut self.socket, buf)?),
+            Framing::Close => {
+                let read = self.socket.read(buf).map_err(ConnectionError::Io)?;
+                if read == 0 {
+                    self.framing = Framing::Empty;
+                }
+
+                Ok(read)
+            }
+        }
+    }
+}
+
+/// How the body of an incoming message is delimited.
+pub(crate) enum Framing {
+    Empty,
+    Fixed(u64),
+    Chunked(Decoder<'static>),
+    /// The body extends until the peer closes the connection.
+    Close,
+}
+
+impl Framing {
+    pub(crate) fn chunked() -> Self {
+        Self::Chunked(Decoder::new(&mut []))
+    }
+}
+
+/// How the body of an outgoing message is delimited.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Outgoing {
+    Empty,
+    Fixed(u64),
+    Chunked,
+    /// The body extends until the connection is closed.
+    Close,
+    /// The body is not sent, as in responses to `HEAD` requests.
+    Discard,
+}
+
+/// Storage for the head of the last received message.
+///
+/// Headers are looked up by re-scanning the stored head, which has been validated
+/// by the parser when the message was received.
+pub(crate) struct HeadBuf<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> HeadBuf<'b> {
+    pub(crate) fn new(buf: &'b mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    pub(crate) fn release(self) -> &'b mut [u8] {
+        self.buf
+    }
+
+    pub(crate) fn buf(&mut self) -> &mut [u8] {
+        self.len = 0;
+        self.buf
+    }
+
+    /// Returns the part of the buffer not occupied by the stored head.
+    pub(crate) fn spare(&mut self) -> &mut [u8] {
+        &mut self.buf[self.len..]
+    }
+
+    fn store<E>(&mut self, mut head: &[u8]) -> Result<(), ConnectionError<E>> {
+        while let Some(rest) = head.strip_prefix(b"\r\n") {
+            head = rest;
+        }
+
+        self.len = 0;
+        self.buf
+            .get_mut(..head.len())
+            .ok_or(ConnectionError::HeadTooLarge)?
+            .copy_from_slice(head);
+        self.len = head.len();
+
+        Ok(())
+    }
+
+    fn as_str(&self) -> &str {
+        str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+
+    pub(crate) fn start_line(&self) -> &str {
+        self.as_str().split("\r\n").next().unwrap_or("")
+    }
+
+    pub(crate) fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.as_str()
+            .split("\r\n")
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name, value.trim_matches([' ', '\t'])))
+    }
+
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers()
+            .find(|(hname, _)| hname.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Returns `true` if the comma-separated header `value` contains `token`.
+pub(crate) fn has_token(value: Option<&str>, token: &str) -> bool {
+    value
+        .map(|value| {
+            value
+                .split(',')
+                .any(|part| part.trim().eq_ignore_ascii_case(token))
+        })
+        .unwrap_or(false)
+}
+
+/// Returns `true` if the `Transfer-Encoding` header `value` ends with the `chunked` coding.
+pub(crate) fn is_chunked(value: &str) -> bool {
+    value
+        .rsplit(',')
+        .next()
+        .map(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false)
+}
+
+/// Returns `true` if the connection should be kept open after the message.
+pub(crate) fn is_keep_alive(http11: bool, connection: Option<&str>) -> bool {
+    if has_token(connection, "close") {
+        false
+    } else {
+        http11 || has_token(connection, "keep-alive")
+    }
+}
+
+pub(crate) fn read_head<T, F, R>(
+    socket: &mut BufferedSocket<'_, T>,
+    head: &mut HeadBuf<'_>,
+    parse: F,
+) -> Result<R, ConnectionError<T::Error>>
+where
+    T: Read,
+    F: Fn(&[u8]) -> Result<(R, usize), ParseError>,
+{
+    loop {
+        match parse(socket.buffered()) {
+            Ok((result, len)) => {
+                head.store(&socket.buffered()[..len])?;
+                socket.consume(len);
+
+                return Ok(result);
+            }
+            Err(ParseError::Incomplete) => {
+                let empty = socket.buffered().is_empty();
+
+                socket.compact();
+                if socket.end == socket.buf.len() {
+                    return Err(ConnectionError::HeadTooLarge);
+                }
+
+                let read = socket
+                    .socket
+                    .read(&mut socket.buf[socket.end..])
+                    .map_err(ConnectionError::Io)?;
+                if read == 0 {
+                    return Err(if empty {
+                        ConnectionError::ConnectionClosed
+                    } else {
+                        ConnectionError::UnexpectedEof
+                    });
+                }
+
+                socket.end += read;
+            }
+            Err(e) => return Err(ConnectionError::Parse(e)),
+        }
+    }
+}
+
+pub(crate) fn write_body<W>(
+    write: &mut W,
+    outgoing: &mut Outgoing,
+    buf: &[u8],
+) -> Result<usize, ConnectionError<W::Error>>
+where
+    W: Write,
+{
+    match outgoing {
+        Outgoing::Empty if !buf.is_empty() => return Err(ConnectionError::ContentLengthMismatch),
+        Outgoing::Fixed(remaining) => {
+            if buf.len() as u64 > *remaining {
+                return Err(ConnectionError::ContentLengthMismatch);
+            }
+
+            *remaining -= buf.len() as u64;
+        }
+        Outgoing::Chunked => return Ok(ChunkedWrite::new(write).write(buf)?),
+        Outgoing::Discard => return Ok(buf.len()),
+        _ => (),
+    }
+
+    write.write_all(buf).map_err(ConnectionError::Io)?;
+
+    Ok(buf.len())
+}
+
+pub(crate) fn finish_body<W>(
+    write: &mut W,
+    outgoing: &mut Outgoing,
+) -> Result<(), ConnectionError<W::Error>>
+where
+    W: Write,
+{
+    match outgoing {
+        Outgoing::Fixed(remaining) if *remaining > 0 => {
+            return Err(ConnectionError::ContentLengthMismatch)
+        }
+        Outgoing::Chunked => ChunkedWrite::new(&mut *write).finish()?,
+        _ => (),
+    }
+
+    *outgoing = Outgoing::Empty;
+
+    write.flush().map_err(ConnectionError::Io)
+}
+
+/// Reads and discards the rest of the body.
+pub(crate) fn drain<T>(body: &mut Body<'_, T>) -> Result<(), ConnectionError<T::Error>>
+where
+    T: Read,
+{
+    let mut buf = [0; 64];
+
+    while body.read(&mut buf)? > 0 {}
+
+    Ok(())
+}
+
+pub mod asynch {
+    use core::cmp::min;
+
+    use crate::io::asynch::{ErrorType, Read, Write};
+
+    use super::super::chunked::asynch::ChunkedWrite;
+    use super::super::codec::ParseError;
+    use super::{Body, BufferedSocket, Framing, HeadBuf, Outgoing};
+
+    pub use super::ConnectionError;
+
+    impl<T> Read for BufferedSocket<'_, T>
+    where
+        T: Read,
+    {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if self.start == self.end && !buf.is_empty() {
+                if buf.len() >= self.buf.len() {
+                    return self.socket.read(buf).await;
+                }
+
+                self.end = self.socket.read(self.buf).await?;
+            }
+
+            Ok(self.copy_buffered(buf))
+        }
+    }
+
+    impl<T> Write for BufferedSocket<'_, T>
+    where
+        T: Write,
+    {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.socket.write(buf).await
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.socket.flush().await
+        }
+    }
+
+    impl<T> Read for Body<'_, T>
+    where
+        T: Read,
+    {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+
+            match &mut self.framing {
+                Framing::Empty => Ok(0),
+                Framing::Fixed(remaining) => {
+                    if *remaining == 0 {
+                        return Ok(0);
+                    }
+
+                    let len = min(buf.len() as u64, *remaining) as usize;
+
+                    let read = self
+                        .socket
+                        .read(&mut buf[..len])
+                        .await
+                        .map_err(ConnectionError::Io)?;
+                    if read == 0 {
+                        return Err(ConnectionError::UnexpectedEof);
+                    }
+
+                    *remaining -= read as u64;
+
+                    Ok(read)
+                }
+                Framing::Chunked(decoder) => Ok(decoder.read_async(&mut self.socket, buf).await?),
+                Framing::Close => {
+                    let read = self.socket.read(buf).await.map_err(ConnectionError::Io)?;
+                    if read == 0 {
+                        self.framing = Framing::Empty;
+                    }
+
+                    Ok(read)
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn read_head<T, F, R>(
+        socket: &mut BufferedSocket<'_, T>,
+        head: &mut HeadBuf<'_>,
+        parse: F,
+    ) -> Result<R, ConnectionError<T::Error>>
+    where
+        T: Read,
+        F: Fn(&[u8]) -> Result<(R, usize), ParseError>,
+    {
+        loop {
+            match parse(socket.buffered()) {
+                Ok((result, len)) => {
+                    head.store(&socket.buffered()[..len])?;
+                    socket.consume(len);
+
+                    return Ok(result);
+                }
+                Err(ParseError::Incomplete) => {
+                    let empty = socket.buffered().is_empty();
+
+                    socket.compact();
+                    if socket.end == socket.buf.len() {
+                        return Err(ConnectionError::HeadTooLarge);
+                    }
+
+                    let read = socket
+                        .socket
+                        .read(&mut socket.buf[socket.end..])
+                        .await
+                        .map_err(ConnectionError::Io)?;
+                    if read == 0 {
+                        return Err(if empty {
+                            ConnectionError::ConnectionClosed
+                        } else {
+                            ConnectionError::UnexpectedEof
+                        });
+                    }
+
+                    socket.end += read;
+                }
+                Err(e) => return Err(ConnectionError::Parse(e)),
+            }
+        }
+    }
+
+    pub(crate) async fn write_body<W>(
+        write: &mut W,
+        outgoing: &mut Outgoing,
+        buf: &[u8],
+    ) -> Result<usize, ConnectionError<W::Error>>
+    where
+        W: Write,
+    {
+        match outgoing {
+            Outgoing::Empty if !buf.is_empty() => {
+                return Err(ConnectionError::ContentLengthMismatch)
+            }
+            Outgoing::Fixed(remaining) => {
+                if buf.len() as u64 > *remaining {
+                    return Err(ConnectionError::ContentLengthMismatch);
+                }
+
+                *remaining -= buf.len() as u64;
+            }
+            Outgoing::Chunked => return Ok(ChunkedWrite::new(write).write(buf).await?),
+            Outgoing::Discard => return Ok(buf.len()),
+            _ => (),
+        }
+
+        write.write_all(buf).await.map_err(ConnectionError::Io)?;
+
+        Ok(buf.len())
+    }
+
+    pub(crate) async fn finish_body<W>(
+        write: &mut W,
+        outgoing: &mut Outgoing,
+    ) -> Result<(), ConnectionError<W::Error>>
+    where
+        W: Write,
+    {
+        match outgoing {
+            Outgoing::Fixed(remaining) if *remaining > 0 => {
+                return Err(ConnectionError::ContentLengthMismatch)
+            }
+            Outgoing::Chunked => ChunkedWrite::new(&mut *write).finish().await?,
+            _ => (),
+        }
+
+        *outgoing = Outgoing::Empty;
+
+        write.flush().await.map_err(ConnectionError::Io)
+    }
+
+    pub(crate) async fn drain<T>(body: &mut Body<'_, T>) -> Result<(), ConnectionError<T::Error>>
+    where
+        T: Read + ErrorType,
+    {
+        let mut buf = [0; 64];
+
+        while body.read(&mut buf).await? > 0 {}
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use crate::io::Read;
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{Body, BufferedSocket, ConnectionError, Framing};
+
+    const CHUNKED: &[u8] = b"3\r\nabc\r\n5;ext=1\r\ndefgh\r\n0\r\nX-Trailer: 1\r\n\r\nNEXT";
+
+    fn new_body<'b, 's>(
+        socket: &'b mut MockSocket<'s>,
+        buf: &'b mut [u8],
+        framing: Framing,
+    ) -> Body<'b, &'b mut MockSocket<'s>> {
+        let mut body = Body::new(BufferedSocket::new(socket, buf));
+        body.set_framing(framing);
+
+        body
+    }
+
+    #[test]
+    fn read_chunked() {
+        for chunk in [1, 2, 5, CHUNKED.len()] {
+            let mut socket = MockSocket::chunked(CHUNKED, chunk);
+            let mut socket_buf = [0; 8];
+            let mut body = new_body(&mut socket, &mut socket_buf, Framing::chunked());
+
+            let mut data = Vec::new();
+            let mut buf = [0; 3];
+
+            loop {
+                let len = body.read(&mut buf).unwrap();
+                if len == 0 {
+                    break;
+                }
+
+                data.extend_from_slice(&buf[..len]);
+            }
+
+            assert!(body.is_complete());
+            assert_eq!(data, b"abcdefgh");
+
+            let mut socket = body.into_socket();
+            let mut rest = [0; 4];
+            socket.read_exact(&mut rest).unwrap();
+            assert_eq!(&rest, b"NEXT");
+        }
+    }
+
+    #[test]
+    fn read_chunked_invalid() {
+        let mut socket = MockSocket::new(b"3\r\nabcX\r\n");
+        let mut socket_buf = [0; 8];
+        let mut body = new_body(&mut socket, &mut socket_buf, Framing::chunked());
+
+        let mut buf = [0; 16];
+        assert_eq!(body.read(&mut buf).unwrap(), 3);
+        assert!(matches!(
+            body.read(&mut buf),
+            Err(ConnectionError::InvalidBody)
+        ));
+
+        let mut socket = MockSocket::new(b"3\r\nab");
+        let mut body = new_body(&mut socket, &mut socket_buf, Framing::chunked());
+
+        assert_eq!(body.read(&mut buf).unwrap(), 2);
+        assert!(matches!(
+            body.read(&mut buf),
+            Err(ConnectionError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn read_fixed() {
+        let mut socket = MockSocket::chunked(b"abcdeNEXT", 2);
+        let mut socket_buf = [0; 8];
+        let mut body = new_body(&mut socket, &mut socket_buf, Framing::Fixed(5));
+
+        let mut data = [0; 5];
+        body.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"abcde");
+        assert!(body.is_complete());
+        assert_eq!(body.read(&mut [0; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn read_chunked_async() {
+        let mut socket = MockSocket::chunked(CHUNKED, 3);
+        let mut socket_buf = [0; 8];
+        let mut body = new_body(&mut socket, &mut socket_buf, Framing::chunked());
+
+        let mut data = [0; 8];
+        block_on(crate::io::asynch::Read::read_exact(&mut body, &mut data)).unwrap();
+        assert_eq!(&data, b"abcdefgh");
+        assert_eq!(
+            block_on(crate::io::asynch::Read::read(&mut body, &mut [0; 4])).unwrap(),
+            0
+        );
+        assert!(body.is_complete());
+    }
+}
@@ -0,0 +1,461 @@
+use core::fmt;
+use core::iter;
+use core::str::{FromStr, Split};
+
+use super::codec::is_token;
+
+pub struct Cookies<'a>(&'a str);
+
+impl<'a> Cookies<'a> {
+    pub fn new(cookies_str: &'a str) -> Self {
+        Self(cookies_str)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        Cookies::new(self.0)
+            .into_iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn set<'b, I>(
+        iter: I,
+        name: &'b str,
+        value: &'b str,
+    ) -> impl Iterator<Item = (&'b str, &'b str)>
+    where
+        I: Iterator<Item = (&'b str, &'b str)> + 'b,
+    {
+        iter.filter(move |(key, _)| *key != name)
+            .chain(core::iter::once((name, value)))
+    }
+
+    pub fn remove<'b, I>(iter: I, name: &'b str) -> impl Iterator<Item = (&'b str, &'b str)>
+    where
+        I: Iterator<Item = (&'b str, &'b str)> + 'b,
+    {
+        iter.filter(move |(key, _)| *key != name)
+    }
+
+    pub fn serialize<'b, I>(iter: I) -> impl Iterator<Item = &'b str>
+    where
+        I: Iterator<Item = (&'b str, &'b str)> + 'b,
+    {
+        iter.flat_map(|(k, v)| {
+            iter::once(";")
+                .chain(iter::once(k))
+                .chain(iter::once("="))
+                .chain(iter::once(v))
+        })
+        .skip(1)
+    }
+}
+
+impl<'a> IntoIterator for Cookies<'a> {
+    type Item = (&'a str, &'a str);
+
+    type IntoIter = CookieIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        CookieIterator::new(self.0)
+    }
+}
+
+pub struct CookieIterator<'a>(Split<'a, char>);
+
+impl<'a> CookieIterator<'a> {
+    pub fn new(cookies: &'a str) -> Self {
+        Self(cookies.split(';'))
+    }
+}
+
+impl<'a> Iterator for CookieIterator<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|cookie_pair| cookie_pair.split('='))
+            .and_then(|mut cookie_pair| {
+                cookie_pair
+                    .next()
+                    .map(|name| cookie_pair.next().map(|value| (name.trim(), value.trim())))
+            })
+            .flatten()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CookieError {
+    InvalidName,
+    InvalidValue,
+    InvalidAttribute,
+    BufferOverflow,
+}
+
+impl fmt::Display for CookieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName => write!(f, "Invalid cookie name"),
+            Self::InvalidValue => write!(f, "Invalid cookie value"),
+            Self::InvalidAttribute => write!(f, "Invalid cookie attribute"),
+            Self::BufferOverflow => write!(f, "Buffer overflow"),
+        }
+    }
+}
+
+impl core::error::Error for CookieError {}
+
+impl From<fmt::Error> for CookieError {
+    fn from(_: fmt::Error) -> Self {
+        Self::BufferOverflow
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        }
+    }
+}
+
+impl FromStr for SameSite {
+    type Err = CookieError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("strict") {
+            Ok(Self::Strict)
+        } else if s.eq_ignore_ascii_case("lax") {
+            Ok(Self::Lax)
+        } else if s.eq_ignore_ascii_case("none") {
+            Ok(Self::None)
+        } else {
+            Err(CookieError::InvalidAttribute)
+        }
+    }
+}
+
+/// A cookie with its attributes, as sent by a server in a `Set-Cookie` header (RFC 6265).
+///
+/// On the server side, build it and serialize it with [`SetCookie::header`] into the headers
+/// passed to `Request::into_response`. On the client side, parse the value of a `Set-Cookie`
+/// response header with [`SetCookie::parse`].
+///
+/// `expires` is kept as the raw HTTP-date string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SetCookie<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+    pub path: Option<&'a str>,
+    pub domain: Option<&'a str>,
+    pub max_age: Option<i64>,
+    pub expires: Option<&'a str>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl<'a> SetCookie<'a> {
+    pub const fn new(name: &'a str, value: &'a str) -> Self {
+        Self {
+            name,
+            value,
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// A cookie which instructs the client to remove the cookie named `name`.
+    pub const fn removal(name: &'a str) -> Self {
+        Self::new(name, "").max_age(0)
+    }
+
+    pub const fn path(mut self, path: &'a str) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub const fn domain(mut self, domain: &'a str) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    pub const fn max_age(mut self, max_age: i64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub const fn expires(mut self, expires: &'a str) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub const fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub const fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub const fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Parses the value of a `Set-Cookie` header.
+    ///
+    /// As per RFC 6265, unknown attributes and attributes with invalid values are ignored.
+    pub fn parse(set_cookie: &'a str) -> Result<Self, CookieError> {
+        let mut parts = set_cookie.split(';');
+
+        let (name, value) = parts
+            .next()
+            .and_then(|pair| pair.split_once('='))
+            .ok_or(CookieError::InvalidName)?;
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(CookieError::InvalidName);
+        }
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+
+        let mut cookie = Self::new(name, value);
+
+        for attribute in parts {
+            let (name, value) = match attribute.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+
+            if name.eq_ignore_ascii_case("Path") {
+                if value.starts_with('/') {
+                    cookie.path = Some(value);
+                }
+            } else if name.eq_ignore_ascii_case("Domain") {
+                let value = value.strip_prefix('.').unwrap_or(value);
+
+                if !value.is_empty() {
+                    cookie.domain = Some(value);
+                }
+            } else if name.eq_ignore_ascii_case("Max-Age") {
+                if let Ok(max_age) = value.parse() {
+                    cookie.max_age = Some(max_age);
+                }
+            } else if name.eq_ignore_ascii_case("Expires") {
+                if !value.is_empty() {
+                    cookie.expires = Some(value);
+                }
+            } else if name.eq_ignore_ascii_case("Secure") {
+                cookie.secure = true;
+            } else if name.eq_ignore_ascii_case("HttpOnly") {
+                cookie.http_only = true;
+            } else if name.eq_ignore_ascii_case("SameSite") {
+                if let Ok(same_site) = value.parse() {
+                    cookie.same_site = Some(same_site);
+                }
+            }
+        }
+
+        Ok(cookie)
+    }
+
+    /// Serializes the cookie in the `Set-Cookie` header value format.
+    pub fn serialize<W>(&self, out: &mut W) -> Result<(), CookieError>
+    where
+        W: fmt::Write,
+    {
+        if self.name.is_empty() || !self.name.bytes().all(is_token) {
+            return Err(CookieError::InvalidName);
+        }
+
+        if !self.value.bytes().all(is_cookie_octet) {
+            return Err(CookieError::InvalidValue);
+        }
+
+        write!(out, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = self.path {
+            write!(out, "; Path={}", check_attribute(path)?)?;
+        }
+
+        if let Some(domain) = self.domain {
+            write!(out, "; Domain={}", check_attribute(domain)?)?;
+        }
+
+        if let Some(max_age) = self.max_age {
+            write!(out, "; Max-Age={max_age}")?;
+        }
+
+        if let Some(expires) = self.expires {
+            write!(out, "; Expires={}", check_attribute(expires)?)?;
+        }
+
+        if self.secure {
+            out.write_str("; Secure")?;
+        }
+
+        if self.http_only {
+            out.write_str("; HttpOnly")?;
+        }
+
+        if let Some(same_site) = self.same_site {
+            write!(out, "; SameSite={}", same_site.as_str())?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the cookie into `buf` and returns a `Set-Cookie` header ready to be passed
+    /// to `Request::into_response`.
+    pub fn header<'b, const N: usize>(
+        &self,
+        buf: &'b mut heapless::String<N>,
+    ) -> Result<(&'b str, &'b str), CookieError> {
+        buf.clear();
+        self.serialize(buf)?;
+
+        Ok(("Set-Cookie", buf.as_str()))
+    }
+}
+
+impl fmt::Display for SetCookie<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.serialize(f).map_err(|_| fmt::Error)
+    }
+}
+
+fn check_attribute(value: &str) -> Result<&str, CookieError> {
+    if value.bytes().all(|b| !b.is_ascii_control() && b != b';') {
+        Ok(value)
+    } else {
+        Err(CookieError::InvalidAttribute)
+    }
+}
+
+fn is_cookie_octet(b: u8) -> bool {
+    b.is_ascii_graphic() && !matches!(b, b'"' | b',' | b';' | b'\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CookieError, Cookies, SameSite, SetCookie};
+
+    #[test]
+    fn cookies() {
+        let cookies = Cookies::new(" a = 1; SESSIONID=abc;b=2; invalid");
+
+        assert_eq!(cookies.get("a"), Some("1"));
+        assert_eq!(cookies.get("SESSIONID"), Some("abc"));
+        assert_eq!(cookies.get("invalid"), None);
+
+        let mut serialized = heapless::String::<64>::new();
+        for part in Cookies::serialize(Cookies::remove(
+            Cookies::set(cookies.into_iter(), "b", "3"),
+            "a",
+        )) {
+            serialized.push_str(part).unwrap();
+        }
+
+        assert_eq!(serialized.as_str(), "SESSIONID=abc;b=3");
+    }
+
+    #[test]
+    fn format_set_cookie() {
+        let cookie = SetCookie::new("id", "abc")
+            .path("/")
+            .domain("example.com")
+            .max_age(3600)
+            .expires("Wed, 21 Oct 2015 07:28:00 GMT")
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Strict);
+
+        let mut buf = heapless::String::<256>::new();
+
+        assert_eq!(
+            cookie.header(&mut buf),
+            Ok((
+                "Set-Cookie",
+                "id=abc; Path=/; Domain=example.com; Max-Age=3600; \
+                 Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure; HttpOnly; SameSite=Strict"
+            ))
+        );
+        assert_eq!(SetCookie::parse(buf.as_str()), Ok(cookie));
+
+        buf.clear();
+        SetCookie::removal("x").serialize(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "x=; Max-Age=0");
+    }
+
+    #[test]
+    fn format_invalid_set_cookie() {
+        let mut buf = heapless::String::<64>::new();
+
+        assert_eq!(
+            SetCookie::new("a b", "x").header(&mut buf),
+            Err(CookieError::InvalidName)
+        );
+        assert_eq!(
+            SetCookie::new("a", "x;y").header(&mut buf),
+            Err(CookieError::InvalidValue)
+        );
+        assert_eq!(
+            SetCookie::new("a", "x").path("/;x").header(&mut buf),
+            Err(CookieError::InvalidAttribute)
+        );
+        assert_eq!(
+            SetCookie::new("abc", "def").header(&mut heapless::String::<4>::new()),
+            Err(CookieError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn parse_set_cookie() {
+        let cookie = SetCookie::parse(
+            "a=\"q\";path=/x; DOMAIN=.ex.org; max-age=bad; samesite=lax; foo=bar; secure",
+        )
+        .unwrap();
+
+        assert_eq!(cookie.name, "a");
+        assert_eq!(cookie.value, "q");
+        assert_eq!(cookie.path, Some("/x"));
+        assert_eq!(cookie.domain, Some("ex.org"));
+        assert_eq!(cookie.max_age, None);
+        assert_eq!(cookie.same_site, Some(SameSite::Lax));
+        assert!(cookie.secure);
+        assert!(!cookie.http_only);
+
+        let cookie = SetCookie::parse("a=b; Path=relative; SameSite=Sometimes").unwrap();
+        assert_eq!(cookie.path, None);
+        assert_eq!(cookie.same_site, None);
+
+        assert_eq!(SetCookie::parse("=x"), Err(CookieError::InvalidName));
+        assert_eq!(SetCookie::parse("novalue"), Err(CookieError::InvalidName));
+    }
+}
@@ -0,0 +1,932 @@
+use core::fmt::{self, Write as _};
+
+use crate::http::client::{Client, Connection, Response};
+use crate::http::Method;
+use crate::io::{Error, ErrorKind};
+use crate::utils::hash::{Digest, Md5, Sha256};
+use crate::utils::http::uri::{SliceWriter, Uri};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DigestError<E> {
+    Io(E),
+    TooManyHeaders,
+    /// The challenge does not fit into the [`DigestAuth`] state, or the `Authorization` header into the buffer.
+    BufferOverflow,
+}
+
+impl<E: fmt::Debug> fmt::Display for DigestError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for DigestError<E> {}
+
+impl<E> Error for DigestError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::TooManyHeaders | Self::BufferOverflow => ErrorKind::OutOfMemory,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl Algorithm {
+    pub fn parse(algorithm: &str) -> Option<Self> {
+        [Self::Md5, Self::Md5Sess, Self::Sha256, Self::Sha256Sess]
+            .into_iter()
+            .find(|candidate| candidate.as_str().eq_ignore_ascii_case(algorithm))
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Md5Sess => "MD5-sess",
+            Self::Sha256 => "SHA-256",
+            Self::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    const fn is_session(&self) -> bool {
+        matches!(self, Self::Md5Sess | Self::Sha256Sess)
+    }
+
+    /// Returns the hex-encoded hash of `parts` joined with colons.
+    fn hash(&self, parts: &[&[u8]]) -> heapless::String<64> {
+        match self {
+            Self::Md5 | Self::Md5Sess => hash::<Md5>(parts),
+            Self::Sha256 | Self::Sha256Sess => hash::<Sha256>(parts),
+        }
+    }
+}
+
+/// The quality of protection of a Digest response. `auth-int` also protects the request body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Qop {
+    Auth,
+    AuthInt,
+}
+
+impl Qop {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::AuthInt => "auth-int",
+        }
+    }
+}
+
+/// A `Digest` challenge, as sent by the server in the `WWW-Authenticate` header of a 401 response.
+///
+/// The quoted parameters are kept as they appear in the header, i.e. with any backslash escapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Challenge<'a> {
+    pub realm: &'a str,
+    pub nonce: &'a str,
+    pub opaque: Option<&'a str>,
+    pub algorithm: Algorithm,
+    /// The preferred quality of protection offered by the server, or `None` for a legacy RFC 2069 challenge.
+    pub qop: Option<Qop>,
+    /// Whether the previous request was rejected only because its nonce expired.
+    pub stale: bool,
+    pub userhash: bool,
+}
+
+impl<'a> Challenge<'a> {
+    /// Parses the `Digest` challenge of a `WWW-Authenticate` header, which may also contain challenges of other schemes.
+    ///
+    /// Servers may offer several `Digest` challenges, e.g. one per algorithm; the first one with an
+    /// algorithm and a quality of protection which are supported is returned, or `None` if there is none.
+    pub fn parse(www_authenticate: &'a str) -> Option<Self> {
+        let mut items = Items(www_authenticate);
+
+        loop {
+            items.find(|(name, value)| value.is_none() && name.eq_ignore_ascii_case("Digest"))?;
+
+            let params = items
+                .clone()
+                .map_while(|(name, value)| value.map(|value| (name, value)));
+
+            if let Some(challenge) = Self::parse_params(params) {
+                return Some(challenge);
+            }
+        }
+    }
+
+    /// Parses the parameters of a `Digest` challenge.
+    fn parse_params(params: impl Iterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut realm = None;
+        let mut nonce = None;
+        let mut challenge = Self {
+            realm: "",
+            nonce: "",
+            opaque: None,
+            algorithm: Algorithm::Md5,
+            qop: None,
+            stale: false,
+            userhash: false,
+        };
+
+        let mut qop = None;
+
+        for (name, value) in params {
+            if name.eq_ignore_ascii_case("realm") {
+                realm = Some(value);
+            } else if name.eq_ignore_ascii_case("nonce") {
+                nonce = Some(value);
+            } else if name.eq_ignore_ascii_case("opaque") {
+                challenge.opaque = Some(value);
+            } else if name.eq_ignore_ascii_case("algorithm") {
+                challenge.algorithm = Algorithm::parse(value)?;
+            } else if name.eq_ignore_ascii_case("qop") {
+                qop = Some(value);
+            } else if name.eq_ignore_ascii_case("stale") {
+                challenge.stale = value.eq_ignore_ascii_case("true");
+            } else if name.eq_ignore_ascii_case("userhash") {
+                challenge.userhash = value.eq_ignore_ascii_case("true");
+            }
+        }
+
+        challenge.realm = realm?;
+        challenge.nonce = nonce?;
+
+        if let Some(qop) = qop {
+            let offered = |candidate: Qop| {
+                qop.split(',')
+                    .any(|qop| qop.trim().eq_ignore_ascii_case(candidate.as_str()))
+            };
+
+            challenge.qop = Some(
+                [Qop::Auth, Qop::AuthInt]
+                    .into_iter()
+                    .find(|candidate| offered(*candidate))?,
+            );
+        }
+
+        Some(challenge)
+    }
+}
+
+/// The credentials and the state of the Digest authentication with a server, to be kept across requests
+/// with [`Client::request_with_digest`], so that the nonce of the last challenge can be reused.
+///
+/// `cnonce` fills its argument with random bytes, used for the client nonce of each request.
+/// `N` is the maximum length of each of the realm, the nonce and the opaque value of a challenge.
+pub struct DigestAuth<'a, F, const N: usize = 128> {
+    username: &'a str,
+    password: &'a str,
+    cnonce: F,
+    state: Option<State<N>>,
+}
+
+impl<'a, F, const N: usize> DigestAuth<'a, F, N>
+where
+    F: FnMut(&mut [u8]),
+{
+    pub const fn new(username: &'a str, password: &'a str, cnonce: F) -> Self {
+        Self {
+            username,
+            password,
+            cnonce,
+            state: None,
+        }
+    }
+
+    /// Forgets the last challenge, so that the next request is sent without credentials.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    /// Keeps the challenge for answering it in the next requests.
+    fn challenge<E>(&mut self, challenge: &Challenge<'_>) -> Result<(), DigestError<E>> {
+        let mut state = State {
+            realm: unescape(challenge.realm)?,
+            nonce: unescape(challenge.nonce)?,
+            opaque: challenge.opaque.map(unescape).transpose()?,
+            algorithm: challenge.algorithm,
+            qop: challenge.qop,
+            userhash: challenge.userhash,
+            nc: 0,
+        };
+
+        // Keep counting if the server only repeated its challenge with the same nonce
+        if let Some(current) = &self.state {
+            if current.nonce == state.nonce {
+                state.nc = current.nc;
+            }
+        }
+
+        self.state = Some(state);
+
+        Ok(())
+    }
+
+    /// Formats the `Authorization` header answering the last challenge into `buf`, or returns `None`
+    /// if there is no challenge yet.
+    fn authorization<'b, E>(
+        &mut self,
+        method: Method,
+        uri: &str,
+        body: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<Option<&'b str>, DigestError<E>> {
+        let Some(state) = &mut self.state else {
+            return Ok(None);
+        };
+
+        let uri = request_target(uri);
+
+        state.nc = state.nc.wrapping_add(1);
+
+        let mut cnonce = [0; 16];
+        (self.cnonce)(&mut cnonce);
+        let cnonce = hex::<32>(&cnonce);
+
+        let nc = state.nc();
+        let response = state.response(self.username, self.password, method, uri, body, &cnonce);
+
+        let algorithm = state.algorithm;
+        let realm = state.realm.as_bytes();
+
+        let username = if state.userhash {
+            algorithm.hash(&[self.username.as_bytes(), realm])
+        } else {
+            heapless::String::new()
+        };
+
+        let mut out = SliceWriter::new(buf);
+
+        state
+            .write_authorization(
+                &mut out,
+                if state.userhash {
+                    &username
+                } else {
+                    self.username
+                },
+                uri,
+                &nc,
+                &cnonce,
+                &response,
+            )
+            .map_err(|_| DigestError::BufferOverflow)?;
+
+        Ok(Some(out.into_str()))
+    }
+
+    /// Decides whether to answer the challenge of a 401 response to a request sent with `authorization`.
+    fn retry(&self, authorization: Option<&str>, challenge: &Challenge<'_>) -> bool {
+        // Credentials answering a fresh nonce were rejected, unless the server just replaced the nonce
+        authorization.is_none()
+            || challenge.stale
+            || self
+                .state
+                .as_ref()
+                .is_none_or(|state| !unescaped_eq(challenge.nonce, &state.nonce))
+    }
+}
+
+struct State<const N: usize> {
+    realm: heapless::String<N>,
+    nonce: heapless::String<N>,
+    opaque: Option<heapless::String<N>>,
+    algorithm: Algorithm,
+    qop: Option<Qop>,
+    userhash: bool,
+    nc: u32,
+}
+
+impl<const N: usize> State<N> {
+    /// Returns the nonce count of the current request, as sent in the `nc` parameter.
+    fn nc(&self) -> heapless::String<8> {
+        let mut nc = heapless::String::new();
+        // Cannot fail, the count is exactly 8 characters long
+        let _ = write!(nc, "{:08x}", self.nc);
+
+        nc
+    }
+
+    /// Returns the `response` parameter of the credentials for the current request, as per RFC 7616, section 3.4.1.
+    fn response(
+        &self,
+        username: &str,
+        password: &str,
+        method: Method,
+        uri: &str,
+        body: &[u8],
+        cnonce: &str,
+    ) -> heapless::String<64> {
+        let algorithm = self.algorithm;
+        let realm = self.realm.as_bytes();
+        let nonce = self.nonce.as_bytes();
+        let nc = self.nc();
+
+        let mut ha1 = algorithm.hash(&[username.as_bytes(), realm, password.as_bytes()]);
+        if algorithm.is_session() {
+            ha1 = algorithm.hash(&[ha1.as_bytes(), nonce, cnonce.as_bytes()]);
+        }
+
+        let ha2 = match self.qop {
+            Some(Qop::AuthInt) => algorithm.hash(&[
+                method.as_str().as_bytes(),
+                uri.as_bytes(),
+                algorithm.hash(&[body]).as_bytes(),
+            ]),
+            _ => algorithm.hash(&[method.as_str().as_bytes(), uri.as_bytes()]),
+        };
+
+        match self.qop {
+            Some(qop) => algorithm.hash(&[
+                ha1.as_bytes(),
+                nonce,
+                nc.as_bytes(),
+                cnonce.as_bytes(),
+                qop.as_str().as_bytes(),
+                ha2.as_bytes(),
+            ]),
+            None => algorithm.hash(&[ha1.as_bytes(), nonce, ha2.as_bytes()]),
+        }
+    }
+
+    fn write_authorization(
+        &self,
+        out: &mut impl fmt::Write,
+        username: &str,
+        uri: &str,
+        nc: &str,
+        cnonce: &str,
+        response: &str,
+    ) -> fmt::Result {
+        out.write_str("Digest username=")?;
+        write_quoted(out, username)?;
+        out.write_str(", realm=")?;
+        write_quoted(out, &self.realm)?;
+        out.write_str(", uri=")?;
+        write_quoted(out, uri)?;
+        write!(out, ", algorithm={}, nonce=", self.algorithm.as_str())?;
+        write_quoted(out, &self.nonce)?;
+
+        if let Some(qop) = self.qop {
+            write!(out, ", nc={nc}, cnonce=\"{cnonce}\", qop={}", qop.as_str())?;
+        }
+
+        write!(out, ", response=\"{response}\"")?;
+
+        if let Some(opaque) = &self.opaque {
+            out.write_str(", opaque=")?;
+            write_quoted(out, opaque)?;
+        }
+
+        if self.userhash {
+            out.write_str(", userhash=true")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the request headers with the `Authorization` header answering the challenge, if any.
+fn request_headers<'a, E>(
+    headers: &[(&'a str, &'a str)],
+    authorization: Option<&'a str>,
+) -> Result<heapless::Vec<(&'a str, &'a str), 32>, DigestError<E>> {
+    let mut request_headers = heapless::Vec::new();
+
+    for header in headers
+        .iter()
+        .filter(|(name, _)| authorization.is_none() || !name.eq_ignore_ascii_case("Authorization"))
+    {
+        request_headers
+            .push(*header)
+            .map_err(|_| DigestError::TooManyHeaders)?;
+    }
+
+    if let Some(authorization) = authorization {
+        request_headers
+            .push(("Authorization", authorization))
+            .map_err(|_| DigestError::TooManyHeaders)?;
+    }
+
+    Ok(request_headers)
+}
+
+/// Returns the request target of `uri` as sent in the request line, i.e. without the scheme and the authority.
+fn request_target(uri: &str) -> &str {
+    let parsed = Uri::new(uri);
+
+    if parsed.scheme().is_some() {
+        match parsed.path_and_query() {
+            "" => "/",
+            target => target,
+        }
+    } else {
+        uri
+    }
+}
+
+fn hash<D: Digest>(parts: &[&[u8]]) -> heapless::String<64> {
+    let mut digest = D::default();
+
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            digest.update(b":");
+        }
+
+        digest.update(part);
+    }
+
+    hex(digest.finalize().as_ref())
+}
+
+fn hex<const N: usize>(bytes: &[u8]) -> heapless::String<N> {
+    let mut hex = heapless::String::new();
+
+    for byte in bytes {
+        // Cannot fail, all digests fit
+        let _ = write!(hex, "{byte:02x}");
+    }
+
+    hex
+}
+
+fn write_quoted(out: &mut impl fmt::Write, value: &str) -> fmt::Result {
+    out.write_char('"')?;
+
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            out.write_char('\\')?;
+        }
+
+        out.write_char(c)?;
+    }
+
+    out.write_char('"')
+}
+
+fn unescape<const N: usize, E>(value: &str) -> Result<heapless::String<N>, DigestError<E>> {
+    let mut unescaped = heapless::String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        let c = if c == '\\' {
+            chars.next().unwrap_or(c)
+        } else {
+            c
+        };
+
+        unescaped.push(c).map_err(|_| DigestError::BufferOverflow)?;
+    }
+
+    Ok(unescaped)
+}
+
+fn unescaped_eq(value: &str, unescaped: &str) -> bool {
+    let mut chars = value.chars();
+    let mut unescaped = unescaped.chars();
+
+    loop {
+        let c = match chars.next() {
+            Some('\\') => chars.next().or(Some('\\')),
+            c => c,
+        };
+
+        if c != unescaped.next() {
+            return false;
+        }
+
+        if c.is_none() {
+            return true;
+        }
+    }
+}
+
+/// An iterator over the items of a `WWW-Authenticate` header, yielding `(name, Some(value))` for the parameters
+/// of a challenge, with the quotes of a quoted value removed, and `(scheme, None)` at the start of each challenge.
+#[derive(Clone)]
+struct Items<'a>(&'a str);
+
+impl<'a> Iterator for Items<'a> {
+    type Item = (&'a str, Option<&'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let s = self
+            .0
+            .trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+
+        let end = s
+            .find(|c: char| c == '=' || c == ',' || c.is_ascii_whitespace())
+            .unwrap_or(s.len());
+        let (name, rest) = s.split_at(end);
+
+        if name.is_empty() {
+            self.0 = "";
+            return None;
+        }
+
+        let Some(rest) = rest.trim_start().strip_prefix('=') else {
+            self.0 = rest;
+            return Some((name, None));
+        };
+
+        let rest = rest.trim_start();
+
+        let (value, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut escaped = false;
+            let end = quoted
+                .find(|c| {
+                    let end = !escaped && c == '"';
+                    escaped = !escaped && c == '\\';
+                    end
+                })
+                .unwrap_or(quoted.len());
+
+            (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+        } else {
+            let end = rest
+                .find(|c: char| c == ',' || c.is_ascii_whitespace())
+                .unwrap_or(rest.len());
+
+            rest.split_at(end)
+        };
+
+        self.0 = rest;
+
+        Some((name, Some(value)))
+    }
+}
+
+impl<C> Client<C>
+where
+    C: Connection,
+{
+    /// Sends a request, answering a `Digest` challenge of the server by transparently repeating the request
+    /// with an `Authorization` header.
+    ///
+    /// Once `auth` holds a challenge, the following requests are sent with credentials right away, counting
+    /// the uses of the nonce. The request is repeated at most once, so a 401 response is returned if the
+    /// credentials are rejected, or if the challenge is not supported. `buf` holds the `Authorization` header.
+    pub fn request_with_digest<F, const N: usize>(
+        &mut self,
+        method: Method,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        auth: &mut DigestAuth<'_, F, N>,
+        buf: &mut [u8],
+    ) -> Result<Response<&mut C>, DigestError<C::Error>>
+    where
+        F: FnMut(&mut [u8]),
+    {
+        let mut retried = false;
+
+        loop {
+            let authorization = auth.authorization(method, uri, body, buf)?;
+            let request_headers = request_headers(headers, authorization)?;
+
+            let connection = self.connection();
+
+            connection
+                .initiate_request(method, uri, &request_headers)
+                .map_err(DigestError::Io)?;
+            connection.write_all(body).map_err(DigestError::Io)?;
+            connection.initiate_response().map_err(DigestError::Io)?;
+
+            let challenge = (connection.status() == 401 && !retried)
+                .then(|| connection.header("WWW-Authenticate"))
+                .flatten()
+                .and_then(Challenge::parse)
+                .filter(|challenge| auth.retry(authorization, challenge));
+
+            let Some(challenge) = challenge else {
+                return Ok(Response::wrap(self.connection()));
+            };
+
+            auth.challenge(&challenge)?;
+
+            // Drain the body of the 401 response, so that the connection can be reused
+            let mut drain = [0; 64];
+            while connection.read(&mut drain).map_err(DigestError::Io)? > 0 {}
+
+            retried = true;
+        }
+    }
+}
+
+pub mod asynch {
+    use crate::http::client::asynch::{Client, Connection, Response};
+    use crate::http::Method;
+
+    use super::{request_headers, Challenge, DigestAuth, DigestError};
+
+    impl<C> Client<C>
+    where
+        C: Connection,
+    {
+        /// Sends a request, answering a `Digest` challenge of the server by transparently repeating the request
+        /// with an `Authorization` header.
+        ///
+        /// See [`crate::http::client::Client::request_with_digest`].
+        pub async fn request_with_digest<F, const N: usize>(
+            &mut self,
+            method: Method,
+            uri: &str,
+            headers: &[(&str, &str)],
+            body: &[u8],
+            auth: &mut DigestAuth<'_, F, N>,
+            buf: &mut [u8],
+        ) -> Result<Response<&mut C>, DigestError<C::Error>>
+        where
+            F: FnMut(&mut [u8]),
+        {
+            let mut retried = false;
+
+            loop {
+                let authorization = auth.authorization(method, uri, body, buf)?;
+                let request_headers = request_headers(headers, authorization)?;
+
+                let connection = self.connection();
+
+                connection
+                    .initiate_request(method, uri, &request_headers)
+                    .await
+                    .map_err(DigestError::Io)?;
+                connection.write_all(body).await.map_err(DigestError::Io)?;
+                connection
+                    .initiate_response()
+                    .await
+                    .map_err(DigestError::Io)?;
+
+                let challenge = (connection.status() == 401 && !retried)
+                    .then(|| connection.header("WWW-Authenticate"))
+                    .flatten()
+                    .and_then(Challenge::parse)
+                    .filter(|challenge| auth.retry(authorization, challenge));
+
+                let Some(challenge) = challenge else {
+                    return Ok(Response::wrap(self.connection()));
+                };
+
+                auth.challenge(&challenge)?;
+
+                // Drain the body of the 401 response, so that the connection can be reused
+                let mut drain = [0; 64];
+                while connection.read(&mut drain).await.map_err(DigestError::Io)? > 0 {}
+
+                retried = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::http::client::Client;
+    use crate::http::Method;
+    use crate::utils::http::client::ClientConnection;
+    use crate::utils::io::test::MockSocket;
+
+    use super::{Algorithm, Challenge, DigestAuth, Qop};
+
+    const NONCE: &str = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+    const OPAQUE: &str = "FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS";
+
+    /// Returns the `Authorization` header of a `GET` request answering `www_authenticate`, with the
+    /// nonce count `nc` and the client nonce `cnonce`.
+    fn authorization(
+        www_authenticate: &str,
+        (username, password): (&str, &str),
+        uri: &str,
+        nc: u32,
+        cnonce: &str,
+    ) -> String {
+        let mut auth: DigestAuth<'_, _> = DigestAuth::new(username, password, |_: &mut [u8]| ());
+        auth.challenge::<()>(&Challenge::parse(www_authenticate).unwrap())
+            .unwrap();
+
+        let state = auth.state.as_mut().unwrap();
+        state.nc = nc;
+
+        let nc = state.nc();
+        let response = state.response(username, password, Method::Get, uri, &[], cnonce);
+
+        let mut authorization = String::new();
+        state
+            .write_authorization(&mut authorization, username, uri, &nc, cnonce, &response)
+            .unwrap();
+
+        authorization
+    }
+
+    #[test]
+    fn rfc7616() {
+        // RFC 7616, section 3.9.1
+        let challenge = std::format!(
+            "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=SHA-256, \
+             nonce=\"{NONCE}\", opaque=\"{OPAQUE}\", Digest realm=\"http-auth@example.org\", \
+             qop=\"auth, auth-int\", algorithm=MD5, nonce=\"{NONCE}\", opaque=\"{OPAQUE}\""
+        );
+
+        let parsed = Challenge::parse(&challenge).unwrap();
+        assert_eq!(parsed.realm, "http-auth@example.org");
+        assert_eq!(parsed.algorithm, Algorithm::Sha256);
+        assert_eq!(parsed.qop, Some(Qop::Auth));
+        assert_eq!(parsed.opaque, Some(OPAQUE));
+
+        assert_eq!(
+            authorization(
+                &challenge,
+                ("Mufasa", "Circle of Life"),
+                "/dir/index.html",
+                1,
+                CNONCE
+            ),
+            std::format!(
+                "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", uri=\"/dir/index.html\", \
+                 algorithm=SHA-256, nonce=\"{NONCE}\", nc=00000001, cnonce=\"{CNONCE}\", qop=auth, \
+                 response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\", \
+                 opaque=\"{OPAQUE}\""
+            )
+        );
+
+        let md5 = challenge.split_once(", Digest ").unwrap().1;
+
+        assert_eq!(
+            authorization(
+                &std::format!("Digest {md5}"),
+                ("Mufasa", "Circle of Life"),
+                "/dir/index.html",
+                1,
+                CNONCE
+            ),
+            std::format!(
+                "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", uri=\"/dir/index.html\", \
+                 algorithm=MD5, nonce=\"{NONCE}\", nc=00000001, cnonce=\"{CNONCE}\", qop=auth, \
+                 response=\"8ca523f5e9506fed4657c9700eebdbec\", opaque=\"{OPAQUE}\""
+            )
+        );
+    }
+
+    #[test]
+    fn rfc2617() {
+        // RFC 2617, section 3.5
+        let authorization = authorization(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+             nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+            ("Mufasa", "Circle Of Life"),
+            "/dir/index.html",
+            1,
+            "0a4f113b",
+        );
+
+        assert!(authorization.contains(", response=\"6629fae49393a05397450978507c4ef1\", "));
+    }
+
+    #[test]
+    fn parse_challenge() {
+        let challenge = Challenge::parse(
+            "Basic realm=\"b\", Digest realm=\"r\", algorithm=SHA-256-sess, nonce=\"n\", \
+             opaque=\"a\\\"b\", stale=TRUE, userhash=true, Bearer x=1",
+        )
+        .unwrap();
+        assert_eq!(challenge.realm, "r");
+        assert_eq!(challenge.algorithm, Algorithm::Sha256Sess);
+        assert_eq!(challenge.qop, None);
+        assert_eq!(challenge.opaque, Some("a\\\"b"));
+        assert!(challenge.stale);
+        assert!(challenge.userhash);
+
+        let challenge = Challenge::parse("digest realm=\"x\",nonce=n,qop=auth-int").unwrap();
+        assert_eq!(
+            (challenge.nonce, challenge.qop, challenge.algorithm),
+            ("n", Some(Qop::AuthInt), Algorithm::Md5)
+        );
+
+        assert_eq!(Challenge::parse("Basic realm=\"x\""), None);
+        assert_eq!(
+            Challenge::parse("Digest realm=\"x\", nonce=\"n\", algorithm=SHA-512-256"),
+            None
+        );
+        assert_eq!(
+            Challenge::parse("Digest realm=\"x\", nonce=\"n\", qop=\"foo\""),
+            None
+        );
+        assert_eq!(Challenge::parse("Digest realm=\"x\""), None);
+    }
+
+    #[test]
+    fn parse_several_challenges() {
+        // Challenges with an unsupported algorithm or quality of protection are skipped
+        let challenge = Challenge::parse(
+            "Digest realm=\"x\", nonce=\"1\", algorithm=SHA-512-256, qop=\"auth\", \
+             Basic realm=\"b\", \
+             Digest realm=\"x\", nonce=\"2\", qop=\"foo\", \
+             Digest realm=\"x\", nonce=\"3\", algorithm=SHA-256, qop=\"auth\", \
+             Digest realm=\"x\", nonce=\"4\", algorithm=MD5, qop=\"auth\"",
+        )
+        .unwrap();
+        assert_eq!(
+            (challenge.nonce, challenge.algorithm, challenge.qop),
+            ("3", Algorithm::Sha256, Some(Qop::Auth))
+        );
+
+        assert_eq!(
+            Challenge::parse(
+                "Digest realm=\"x\", nonce=\"1\", algorithm=SHA-512-256, \
+                 Digest realm=\"x\", nonce=\"2\", algorithm=SHA-512-256-sess"
+            ),
+            None
+        );
+    }
+
+    /// Returns the `Authorization` headers of the requests in `output`, if any.
+    fn authorizations(output: &[u8]) -> Vec<Option<String>> {
+        String::from_utf8(output.into())
+            .unwrap()
+            .split("\r\n\r\n")
+            .filter(|request| !request.is_empty())
+            .map(|request| {
+                request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Authorization: "))
+                    .map(String::from)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn request_with_digest() {
+        let mut socket = MockSocket::new(
+            b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Digest realm=\"r\", qop=\"auth\", \
+              nonce=\"n1\", opaque=\"o\"\r\nContent-Length: 4\r\n\r\nnope\
+              HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Digest realm=\"r\", qop=\"auth\", \
+              nonce=\"n2\", stale=true\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Digest realm=\"r\", qop=\"auth\", \
+              nonce=\"n2\"\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut buf = [0; 1024];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        let mut auth: DigestAuth<'_, _> =
+            DigestAuth::new("user", "pw", |cnonce: &mut [u8]| cnonce.fill(0xab));
+        let mut header_buf = [0; 512];
+
+        for status in [200, 200, 401] {
+            let response = client
+                .request_with_digest(
+                    Method::Get,
+                    "http://h/a?x=1",
+                    &[],
+                    &[],
+                    &mut auth,
+                    &mut header_buf,
+                )
+                .unwrap();
+            assert_eq!(response.status(), status);
+        }
+
+        let authorizations = authorizations(&socket.output);
+        assert_eq!(authorizations.len(), 5);
+        assert_eq!(authorizations[0], None);
+
+        let answer = |nonce: &str, nc, opaque: &str| {
+            Some(authorization(
+                &std::format!("Digest realm=\"r\", qop=auth, nonce=\"{nonce}\"{opaque}"),
+                ("user", "pw"),
+                "/a?x=1",
+                nc,
+                "abababababababababababababababab",
+            ))
+        };
+
+        // The challenge is answered, and its nonce is reused for the next request
+        assert_eq!(authorizations[1], answer("n1", 1, ", opaque=\"o\""));
+        assert_eq!(authorizations[2], answer("n1", 2, ", opaque=\"o\""));
+        // A stale nonce is replaced
+        assert_eq!(authorizations[3], answer("n2", 1, ""));
+        // Rejected credentials are not retried
+        assert_eq!(authorizations[4], answer("n2", 2, ""));
+    }
+}
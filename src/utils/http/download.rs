@@ -0,0 +1,701 @@
+use core::cell::Cell;
+use core::fmt::{self, Write as _};
+
+use crate::http::client::{Client, Connection};
+use crate::http::{Headers, Method};
+use crate::io::{Error, ErrorKind, Write};
+use crate::log::svc_log;
+use crate::utils::io::{copy_len_with_progress, CopyError};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DownloadError<C, W> {
+    Connection(C),
+    Write(W),
+    Status(u16),
+    InvalidContentRange,
+    /// The resource changed since the download started, which has to be restarted after a [`Download::reset`].
+    EntityChanged,
+    /// The response ended before the whole resource was downloaded. The download can be resumed.
+    Incomplete,
+}
+
+impl<C: fmt::Debug, W: fmt::Debug> fmt::Display for DownloadError<C, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<C: fmt::Debug, W: fmt::Debug> core::error::Error for DownloadError<C, W> {}
+
+impl<C, W> Error for DownloadError<C, W>
+where
+    C: Error,
+    W: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Connection(e) => e.kind(),
+            Self::Write(e) => e.kind(),
+            Self::Status(_) => ErrorKind::Other,
+            Self::Incomplete => ErrorKind::Interrupted,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl<C, W> From<CopyError<C, W>> for DownloadError<C, W> {
+    fn from(e: CopyError<C, W>) -> Self {
+        match e {
+            CopyError::Read(e) => Self::Connection(e),
+            CopyError::Write(e) => Self::Write(e),
+        }
+    }
+}
+
+/// The state of a download with [`Client::download`], to be kept across attempts for resuming it.
+///
+/// `N` is the maximum length of the entity tag of the resource. A longer tag is not kept, and
+/// resuming then relies on the server honouring the `Range` of the request without validation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Download<const N: usize = 64> {
+    /// The number of bytes downloaded so far, i.e. written to the output.
+    pub offset: u64,
+    /// The length of the resource, once known.
+    pub len: Option<u64>,
+    /// The entity tag of the resource, once known.
+    pub etag: heapless::String<N>,
+}
+
+impl<const N: usize> Download<N> {
+    pub const fn new() -> Self {
+        Self {
+            offset: 0,
+            len: None,
+            etag: heapless::String::new(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.len == Some(self.offset)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Checks the response against the state of the download.
+    ///
+    /// Returns the number of bytes to skip at the start of the body, and the number of bytes to copy after them,
+    /// or `None` if the download is already complete.
+    fn start<C, W>(
+        &mut self,
+        status: u16,
+        headers: impl Headers,
+    ) -> Result<Option<(u64, u64)>, DownloadError<C, W>> {
+        let etag = headers.header("ETag");
+
+        let (skip, len) = match status {
+            206 => {
+                let (start, end, len) = headers
+                    .header("Content-Range")
+                    .and_then(parse_content_range)
+                    .ok_or(DownloadError::InvalidContentRange)?;
+
+                if start != self.offset {
+                    return Err(DownloadError::InvalidContentRange);
+                }
+
+                self.check(etag, len)?;
+
+                (0, end + 1 - start)
+            }
+            200 => {
+                if self.offset > 0 {
+                    // The server ignored the range, either because it does not support ranges, or because
+                    // the resource changed. Only in the former case, the part already downloaded can be skipped
+                    if self.etag.is_empty()
+                        || self.etag.starts_with("W/")
+                        || etag != Some(self.etag.as_str())
+                    {
+                        return Err(DownloadError::EntityChanged);
+                    }
+                }
+
+                let len = headers.content_len();
+
+                self.check(etag, len)?;
+
+                match len {
+                    Some(len) => (self.offset, len - self.offset),
+                    None => (self.offset, u64::MAX),
+                }
+            }
+            416 => {
+                let len = headers
+                    .header("Content-Range")
+                    .and_then(|range| range.trim().strip_prefix("bytes */"))
+                    .and_then(|len| len.parse::<u64>().ok());
+
+                if len.or(self.len) == Some(self.offset) {
+                    self.len = Some(self.offset);
+
+                    return Ok(None);
+                }
+
+                return Err(DownloadError::Status(status));
+            }
+            status => return Err(DownloadError::Status(status)),
+        };
+
+        Ok(Some((skip, len)))
+    }
+
+    /// Checks the entity tag and the length of the resource in the response against the known ones, if any.
+    fn check<C, W>(
+        &mut self,
+        etag: Option<&str>,
+        len: Option<u64>,
+    ) -> Result<(), DownloadError<C, W>> {
+        if let Some(etag) = etag {
+            if self.etag.is_empty() {
+                if self.etag.push_str(etag).is_err() {
+                    svc_log!(
+                        warn,
+                        "Entity tag too long, resuming the download without validation"
+                    );
+                }
+            } else if self.etag != etag {
+                return Err(DownloadError::EntityChanged);
+            }
+        }
+
+        match (self.len, len) {
+            (Some(known), Some(len)) if known != len => Err(DownloadError::EntityChanged),
+            (Some(known), _) if known < self.offset => Err(DownloadError::EntityChanged),
+            (_, Some(len)) if len < self.offset => Err(DownloadError::EntityChanged),
+            (known, len) => {
+                self.len = known.or(len);
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Updates the state after the body of a response with `status` was copied, or failed to.
+    fn finish<C, W>(
+        &mut self,
+        status: u16,
+        result: Result<(), CopyError<C, W>>,
+    ) -> Result<u64, DownloadError<C, W>> {
+        result?;
+
+        match self.len {
+            Some(len) if self.offset < len => Err(DownloadError::Incomplete),
+            // Without a known length, only a whole resource is known to end with the body,
+            // a range may be followed by more
+            None if status != 200 => Err(DownloadError::Incomplete),
+            _ => {
+                self.len = Some(self.offset);
+
+                Ok(self.offset)
+            }
+        }
+    }
+}
+
+/// Returns the headers requesting the rest of a resource of which `offset` bytes are downloaded.
+fn range_headers<'a>(
+    offset: u64,
+    etag: &'a str,
+    range: &'a mut heapless::String<32>,
+) -> heapless::Vec<(&'a str, &'a str), 2> {
+    let mut headers = heapless::Vec::new();
+
+    if offset > 0 {
+        // Cannot fail, the range is at most 27 characters long
+        let _ = write!(range, "bytes={offset}-");
+        let _ = headers.push(("Range", range.as_str()));
+
+        // Weak tags cannot be used for ranges
+        if !etag.is_empty() && !etag.starts_with("W/") {
+            let _ = headers.push(("If-Range", etag));
+        }
+    }
+
+    headers
+}
+
+/// Parses a `Content-Range` header into the inclusive bounds of the range, and the length of the resource if known.
+fn parse_content_range(range: &str) -> Option<(u64, u64, Option<u64>)> {
+    let range = range.trim().strip_prefix("bytes ")?;
+    let (range, len) = range.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+
+    let start = start.trim().parse::<u64>().ok()?;
+    let end = end.trim().parse::<u64>().ok()?;
+
+    let len = match len.trim() {
+        "*" => None,
+        len => Some(len.parse::<u64>().ok()?),
+    };
+
+    (start <= end && len.is_none_or(|len| end < len)).then_some((start, end, len))
+}
+
+impl<C> Client<C>
+where
+    C: Connection,
+{
+    /// Downloads the resource at `uri` into `write`, resuming at `download.offset` if a previous attempt failed.
+    ///
+    /// The range is requested with `Range` and, if the entity tag of the resource is known, `If-Range`.
+    /// If the resource changed since the download started, [`DownloadError::EntityChanged`] is returned and
+    /// the download has to be restarted, as `write` cannot be rewound.
+    ///
+    /// `progress` is called with the number of bytes downloaded so far, and the length of the resource
+    /// or `u64::MAX` if it is not known.
+    ///
+    /// Returns the length of the resource once it is completely downloaded. A range of a resource of
+    /// unknown length (`Content-Range: bytes a-b/*`) fails with [`DownloadError::Incomplete`], as
+    /// only the next attempt tells whether more follows.
+    pub fn download<W, P, const N: usize>(
+        &mut self,
+        uri: &str,
+        download: &mut Download<N>,
+        mut write: W,
+        buf: &mut [u8],
+        progress: P,
+    ) -> Result<u64, DownloadError<C::Error, W::Error>>
+    where
+        W: Write,
+        P: Fn(u64, u64),
+    {
+        if download.is_complete() {
+            return Ok(download.offset);
+        }
+
+        let etag = download.etag.clone();
+        let mut range = heapless::String::new();
+        let headers = range_headers(download.offset, &etag, &mut range);
+
+        let mut response = self
+            .request(Method::Get, uri, &headers)
+            .map_err(DownloadError::Connection)?
+            .submit()
+            .map_err(DownloadError::Connection)?;
+
+        let status = response.status();
+
+        let Some((mut skip, len)) = download.start(status, &response)? else {
+            return Ok(download.offset);
+        };
+
+        while skip > 0 {
+            let size = buf.len().min(skip.try_into().unwrap_or(usize::MAX));
+
+            match response
+                .read(&mut buf[..size])
+                .map_err(DownloadError::Connection)?
+            {
+                0 => return Err(DownloadError::Incomplete),
+                size => skip -= size as u64,
+            }
+        }
+
+        let offset = download.offset;
+        let total = download.len.unwrap_or(u64::MAX);
+        let copied = Cell::new(0);
+
+        let result = copy_len_with_progress(&mut response, &mut write, buf, len, |size, _| {
+            copied.set(size);
+            progress(offset + size, total);
+        });
+
+        download.offset += copied.get();
+
+        download.finish(status, result.map(|_| ()))
+    }
+}
+
+pub mod asynch {
+    use core::cell::Cell;
+
+    use crate::http::client::asynch::{Client, Connection};
+    use crate::http::Method;
+    use crate::io::asynch::Write;
+    use crate::utils::io::asynch::copy_len_with_progress;
+
+    use super::{range_headers, Download, DownloadError};
+
+    impl<C> Client<C>
+    where
+        C: Connection,
+    {
+        /// Downloads the resource at `uri` into `write`, resuming at `download.offset` if a previous attempt failed.
+        ///
+        /// See [`crate::http::client::Client::download`].
+        pub async fn download<W, P, const N: usize>(
+            &mut self,
+            uri: &str,
+            download: &mut Download<N>,
+            mut write: W,
+            buf: &mut [u8],
+            progress: P,
+        ) -> Result<u64, DownloadError<C::Error, W::Error>>
+        where
+            W: Write,
+            P: Fn(u64, u64),
+        {
+            if download.is_complete() {
+                return Ok(download.offset);
+            }
+
+            let etag = download.etag.clone();
+            let mut range = heapless::String::new();
+            let headers = range_headers(download.offset, &etag, &mut range);
+
+            let mut response = self
+                .request(Method::Get, uri, &headers)
+                .await
+                .map_err(DownloadError::Connection)?
+                .submit()
+                .await
+                .map_err(DownloadError::Connection)?;
+
+            let status = response.status();
+
+            let Some((mut skip, len)) = download.start(status, &response)? else {
+                return Ok(download.offset);
+            };
+
+            while skip > 0 {
+                let size = buf.len().min(skip.try_into().unwrap_or(usize::MAX));
+
+                match response
+                    .read(&mut buf[..size])
+                    .await
+                    .map_err(DownloadError::Connection)?
+                {
+                    0 => return Err(DownloadError::Incomplete),
+                    size => skip -= size as u64,
+                }
+            }
+
+            let offset = download.offset;
+            let total = download.len.unwrap_or(u64::MAX);
+            let copied = Cell::new(0);
+
+            let result = copy_len_with_progress(&mut response, &mut write, buf, len, |size, _| {
+                copied.set(size);
+                progress(offset + size, total);
+            })
+            .await;
+
+            download.offset += copied.get();
+
+            download.finish(status, result.map(|_| ()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::RefCell;
+
+    use std::vec::Vec;
+
+    use crate::http::client::Client;
+    use crate::utils::http::client::ClientConnection;
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{Download, DownloadError};
+
+    /// Makes one download attempt with `response` as the reply, and returns its result and the request sent.
+    fn attempt(
+        download: &mut Download,
+        response: &[u8],
+        out: &mut Vec<u8>,
+    ) -> (Result<u64, DownloadError<(), ()>>, std::string::String) {
+        let mut socket = MockSocket::new(response);
+        let mut buf = [0; 512];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+        let mut copy_buf = [0; 4];
+
+        let result = client
+            .download("/file", download, &mut *out, &mut copy_buf, |_, _| ())
+            .map_err(|e| match e {
+                DownloadError::Connection(_) => DownloadError::Connection(()),
+                DownloadError::Write(_) => DownloadError::Write(()),
+                DownloadError::Status(status) => DownloadError::Status(status),
+                DownloadError::InvalidContentRange => DownloadError::InvalidContentRange,
+                DownloadError::EntityChanged => DownloadError::EntityChanged,
+                DownloadError::Incomplete => DownloadError::Incomplete,
+            });
+
+        (
+            result,
+            std::string::String::from_utf8(socket.output).unwrap(),
+        )
+    }
+
+    #[test]
+    fn unknown_len() {
+        let mut download = Download::new();
+        let mut out = Vec::new();
+
+        let (result, _) = attempt(
+            &mut download,
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-5/*\r\nContent-Length: 6\r\n\r\nabcdef",
+            &mut out,
+        );
+        // A range of a resource of unknown length may not be the last one
+        assert!(matches!(result, Err(DownloadError::Incomplete)));
+        assert_eq!(download.offset, 6);
+        assert_eq!(download.len, None);
+        assert!(!download.is_complete());
+
+        let (result, request) = attempt(
+            &mut download,
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 6-8/*\r\nContent-Length: 3\r\n\r\nghi",
+            &mut out,
+        );
+        assert!(request.contains("\r\nRange: bytes=6-\r\n"));
+        assert!(matches!(result, Err(DownloadError::Incomplete)));
+        assert_eq!(download.len, None);
+
+        let (result, request) = attempt(
+            &mut download,
+            b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */9\r\nContent-Length: 0\r\n\r\n",
+            &mut out,
+        );
+        assert!(request.contains("\r\nRange: bytes=9-\r\n"));
+        assert!(matches!(result, Ok(9)));
+        assert!(download.is_complete());
+        assert_eq!(out, b"abcdefghi");
+    }
+
+    #[test]
+    fn known_len() {
+        let mut download = Download::new();
+        let mut out = Vec::new();
+
+        let (result, _) = attempt(
+            &mut download,
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-5/6\r\nContent-Length: 6\r\n\r\nabcdef",
+            &mut out,
+        );
+        assert!(matches!(result, Ok(6)));
+        assert!(download.is_complete());
+
+        // A whole resource ends with the body, even without a `Content-Length`
+        let mut download = Download::new();
+        let mut out = Vec::new();
+
+        let (result, _) = attempt(
+            &mut download,
+            b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nabcdefgh",
+            &mut out,
+        );
+        assert!(matches!(result, Ok(8)));
+        assert_eq!(download.len, Some(8));
+        assert_eq!(out, b"abcdefgh");
+    }
+
+    #[test]
+    fn unknown_len_async() {
+        let mut download = Download::<64>::new();
+        download.offset = 3;
+
+        let mut socket = MockSocket::new(
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 3-5/*\r\nContent-Length: 3\r\n\r\ndef",
+        );
+        let mut buf = [0; 512];
+        let mut client = crate::http::client::asynch::Client::wrap(ClientConnection::<_, 16>::new(
+            &mut socket,
+            &mut buf,
+        ));
+        let mut out = Vec::new();
+        let progress = RefCell::new(Vec::new());
+
+        let result = block_on(client.download(
+            "/file",
+            &mut download,
+            &mut out,
+            &mut [0; 4],
+            |offset, len| progress.borrow_mut().push((offset, len)),
+        ));
+        assert!(matches!(result, Err(DownloadError::Incomplete)));
+        assert_eq!(download.offset, 6);
+        assert_eq!(download.len, None);
+        assert_eq!(out, b"def");
+        assert_eq!(progress.into_inner().last(), Some(&(6, u64::MAX)));
+    }
+
+    /// A download of which the first 3 bytes of 6 are done, with the given entity tag.
+    fn resumed(etag: &str) -> (Download, Vec<u8>) {
+        let mut download = Download::new();
+        download.offset = 3;
+        download.len = Some(6);
+        download.etag.push_str(etag).unwrap();
+
+        (download, b"abc".to_vec())
+    }
+
+    #[test]
+    fn if_range() {
+        let (mut download, mut out) = resumed("\"a\"");
+
+        let (result, request) = attempt(
+            &mut download,
+            b"HTTP/1.1 206 Partial Content\r\nETag: \"a\"\r\nContent-Range: bytes 3-5/6\r\n\
+              Content-Length: 3\r\n\r\ndef",
+            &mut out,
+        );
+        assert_eq!(
+            request,
+            "GET /file HTTP/1.1\r\nRange: bytes=3-\r\nIf-Range: \"a\"\r\n\r\n"
+        );
+        assert!(matches!(result, Ok(6)));
+        assert_eq!(out, b"abcdef");
+
+        // Weak tags are not sent along
+        let (mut download, mut out) = resumed("W/\"a\"");
+
+        let (_, request) = attempt(
+            &mut download,
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 3-5/6\r\n\
+              Content-Length: 3\r\n\r\ndef",
+            &mut out,
+        );
+        assert_eq!(request, "GET /file HTTP/1.1\r\nRange: bytes=3-\r\n\r\n");
+    }
+
+    #[test]
+    fn entity_changed() {
+        for response in [
+            // Another tag
+            &b"HTTP/1.1 206 Partial Content\r\nETag: \"b\"\r\nContent-Range: bytes 3-5/6\r\n\
+               Content-Length: 3\r\n\r\ndef"[..],
+            // Another length
+            b"HTTP/1.1 206 Partial Content\r\nETag: \"a\"\r\nContent-Range: bytes 3-5/7\r\n\
+              Content-Length: 3\r\n\r\ndef",
+            // The whole resource, with another tag
+            b"HTTP/1.1 200 OK\r\nETag: \"b\"\r\nContent-Length: 6\r\n\r\nabcdef",
+            // The whole resource, without a tag
+            b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nabcdef",
+        ] {
+            let (mut download, mut out) = resumed("\"a\"");
+
+            let (result, _) = attempt(&mut download, response, &mut out);
+            assert!(matches!(result, Err(DownloadError::EntityChanged)));
+            assert_eq!(out, b"abc");
+        }
+
+        // A weak tag cannot tell whether the whole resource is the same one
+        let (mut download, mut out) = resumed("W/\"a\"");
+
+        let (result, _) = attempt(
+            &mut download,
+            b"HTTP/1.1 200 OK\r\nETag: W/\"a\"\r\nContent-Length: 6\r\n\r\nabcdef",
+            &mut out,
+        );
+        assert!(matches!(result, Err(DownloadError::EntityChanged)));
+    }
+
+    #[test]
+    fn ignored_range() {
+        // The server ignored the range of an unchanged resource, so the part already downloaded is skipped
+        let (mut download, mut out) = resumed("\"a\"");
+
+        let (result, _) = attempt(
+            &mut download,
+            b"HTTP/1.1 200 OK\r\nETag: \"a\"\r\nContent-Length: 6\r\n\r\nabcdef",
+            &mut out,
+        );
+        assert!(matches!(result, Ok(6)));
+        assert!(download.is_complete());
+        assert_eq!(out, b"abcdef");
+
+        // The body ends within the skipped part
+        let (mut download, mut out) = resumed("\"a\"");
+
+        let (result, _) = attempt(
+            &mut download,
+            b"HTTP/1.1 200 OK\r\nETag: \"a\"\r\nConnection: close\r\n\r\nab",
+            &mut out,
+        );
+        assert!(matches!(result, Err(DownloadError::Incomplete)));
+        assert_eq!(download.offset, 3);
+    }
+
+    #[test]
+    fn invalid_content_range() {
+        for response in [
+            &b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-5/6\r\nContent-Length: 6\r\n\r\nabcdef"[..],
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 4-5/6\r\nContent-Length: 2\r\n\r\nef",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 3-6/6\r\nContent-Length: 4\r\n\r\ndefg",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: 3-5/6\r\nContent-Length: 3\r\n\r\ndef",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 3\r\n\r\ndef",
+        ] {
+            let (mut download, mut out) = resumed("\"a\"");
+
+            let (result, _) = attempt(&mut download, response, &mut out);
+            assert!(matches!(result, Err(DownloadError::InvalidContentRange)));
+            assert_eq!(download.offset, 3);
+        }
+    }
+
+    #[test]
+    fn range_not_satisfiable() {
+        // The whole resource was downloaded before, in a response without a length
+        let mut download = Download::new();
+        download.offset = 6;
+        let mut out = Vec::new();
+
+        let (result, _) = attempt(
+            &mut download,
+            b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */6\r\nContent-Length: 0\r\n\r\n",
+            &mut out,
+        );
+        assert!(matches!(result, Ok(6)));
+        assert!(download.is_complete());
+
+        for response in [
+            &b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */4\r\nContent-Length: 0\r\n\r\n"[..],
+            b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\n\r\n",
+        ] {
+            let (mut download, mut out) = resumed("\"a\"");
+
+            let (result, _) = attempt(&mut download, response, &mut out);
+            assert!(matches!(result, Err(DownloadError::Status(416))));
+            assert!(!download.is_complete());
+        }
+    }
+
+    #[test]
+    fn status() {
+        for (response, status) in [
+            (
+                &b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"[..],
+                404,
+            ),
+            (b"HTTP/1.1 304 Not Modified\r\n\r\n", 304),
+            (
+                b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\n\r\nbusy",
+                503,
+            ),
+        ] {
+            let mut download = Download::new();
+            let mut out = Vec::new();
+
+            let (result, _) = attempt(&mut download, response, &mut out);
+            assert!(matches!(result, Err(DownloadError::Status(s)) if s == status));
+            assert_eq!(download, Download::new());
+            assert!(out.is_empty());
+        }
+    }
+}
@@ -0,0 +1,648 @@
+use core::fmt;
+use core::str;
+
+use crate::http::Headers;
+use crate::io::{Error, ErrorKind, Read};
+
+use super::uri::{encode, QueryPair, QueryPairs, UriError};
+
+#[cfg(feature = "use_serde")]
+pub use de::{from_str, DeserializeError, Deserializer};
+
+pub const CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FormError<E> {
+    Io(E),
+    InvalidContentType,
+    BodyTooLarge,
+    InvalidUtf8,
+}
+
+impl<E: fmt::Debug> fmt::Display for FormError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for FormError<E> {}
+
+impl<E> Error for FormError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::BodyTooLarge => ErrorKind::OutOfMemory,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// A decoded-on-demand `application/x-www-form-urlencoded` body.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Form<'a>(&'a str);
+
+impl<'a> Form<'a> {
+    pub const fn new(form: &'a str) -> Self {
+        Self(form)
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    /// Returns the pairs of the form, still percent-encoded.
+    pub fn pairs(&self) -> QueryPairs<'a> {
+        QueryPairs::new(self.0)
+    }
+
+    /// Decodes into `buf` the value of the first pair named `name`, if any.
+    pub fn get<'b>(&self, name: &str, buf: &'b mut [u8]) -> Result<Option<&'b str>, UriError> {
+        match self.pairs().find(|pair| pair.is(name)) {
+            Some(pair) => pair.decode_value(buf).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Deserializes the form into `T`, using `buf` to decode percent-encoded names and values.
+    #[cfg(feature = "use_serde")]
+    pub fn deserialize<T>(&self, buf: &mut [u8]) -> Result<T, DeserializeError>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        from_str(self.0, buf)
+    }
+}
+
+impl<'a> IntoIterator for Form<'a> {
+    type Item = QueryPair<'a>;
+    type IntoIter = QueryPairs<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs()
+    }
+}
+
+/// Reads a whole form body, e.g. from a `server::Request`, into `buf`.
+///
+/// Fails with [`FormError::BodyTooLarge`] rather than truncating the form if the body does not fit.
+pub fn read<'b, R>(mut read: R, buf: &'b mut [u8]) -> Result<Form<'b>, FormError<R::Error>>
+where
+    R: Read + Headers,
+{
+    check_headers(&read, buf.len())?;
+
+    let mut len = 0;
+
+    loop {
+        if len == buf.len() {
+            // The body must end exactly here
+            return match read.read(&mut [0]).map_err(FormError::Io)? {
+                0 => to_form(buf, len),
+                _ => Err(FormError::BodyTooLarge),
+            };
+        }
+
+        match read.read(&mut buf[len..]).map_err(FormError::Io)? {
+            0 => return to_form(buf, len),
+            size => len += size,
+        }
+    }
+}
+
+fn check_headers<E>(headers: impl Headers, capacity: usize) -> Result<(), FormError<E>> {
+    if let Some(content_type) = headers.content_type() {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+
+        if !mime.eq_ignore_ascii_case(CONTENT_TYPE) {
+            return Err(FormError::InvalidContentType);
+        }
+    }
+
+    if headers
+        .content_len()
+        .is_some_and(|len| len > capacity as u64)
+    {
+        return Err(FormError::BodyTooLarge);
+    }
+
+    Ok(())
+}
+
+fn to_form<E>(buf: &[u8], len: usize) -> Result<Form<'_>, FormError<E>> {
+    str::from_utf8(&buf[..len])
+        .map(Form)
+        .map_err(|_| FormError::InvalidUtf8)
+}
+
+/// Builds an `application/x-www-form-urlencoded` body, e.g. for `Client::post`,
+/// into any [`core::fmt::Write`] output such as a `heapless::String`.
+pub struct FormEncoder<W> {
+    out: W,
+    empty: bool,
+}
+
+impl<W> FormEncoder<W>
+where
+    W: fmt::Write,
+{
+    pub const fn new(out: W) -> Self {
+        Self { out, empty: true }
+    }
+
+    /// Appends a percent-encoded `name=value` pair.
+    pub fn pair(&mut self, name: &str, value: &str) -> Result<&mut Self, UriError> {
+        if !self.empty {
+            self.out.write_char('&')?;
+        }
+
+        self.empty = false;
+
+        encode(name, &mut self.out)?;
+        self.out.write_char('=')?;
+        encode(value, &mut self.out)?;
+
+        Ok(self)
+    }
+
+    pub fn release(self) -> W {
+        self.out
+    }
+}
+
+impl<const N: usize> FormEncoder<heapless::String<N>> {
+    pub fn as_str(&self) -> &str {
+        self.out.as_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl FormEncoder<alloc::string::String> {
+    pub fn as_str(&self) -> &str {
+        self.out.as_str()
+    }
+}
+
+#[cfg(feature = "use_serde")]
+mod de {
+    use core::fmt;
+
+    use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+    use serde::forward_to_deserialize_any;
+
+    use super::super::uri::{decode_query, QueryPairs, UriError};
+
+    /// Deserializes a form body into `T`, using `buf` to decode percent-encoded names and values.
+    ///
+    /// Only flat structures are supported: every field is deserialized from the string value of its pair,
+    /// empty values deserialize as `None` and `on` / `off` are accepted for booleans, as sent by HTML checkboxes.
+    pub fn from_str<'de, T>(form: &'de str, buf: &mut [u8]) -> Result<T, DeserializeError>
+    where
+        T: de::Deserialize<'de>,
+    {
+        T::deserialize(&mut Deserializer::new(form, buf))
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum DeserializeError {
+        Decode(UriError),
+        InvalidValue,
+        Custom,
+    }
+
+    impl fmt::Display for DeserializeError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl core::error::Error for DeserializeError {}
+
+    impl de::Error for DeserializeError {
+        fn custom<T: fmt::Display>(_msg: T) -> Self {
+            Self::Custom
+        }
+    }
+
+    impl From<UriError> for DeserializeError {
+        fn from(e: UriError) -> Self {
+            Self::Decode(e)
+        }
+    }
+
+    pub struct Deserializer<'de, 'b> {
+        pairs: QueryPairs<'de>,
+        value: &'de str,
+        buf: &'b mut [u8],
+    }
+
+    impl<'de, 'b> Deserializer<'de, 'b> {
+        pub fn new(form: &'de str, buf: &'b mut [u8]) -> Self {
+            Self {
+                pairs: QueryPairs::new(form),
+                value: "",
+                buf,
+            }
+        }
+    }
+
+    impl<'de> de::Deserializer<'de> for &mut Deserializer<'de, '_> {
+        type Error = DeserializeError;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_map(self)
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    impl<'de> MapAccess<'de> for Deserializer<'de, '_> {
+        type Error = DeserializeError;
+
+        fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+        where
+            K: DeserializeSeed<'de>,
+        {
+            match self.pairs.next() {
+                Some(pair) => {
+                    self.value = pair.value;
+
+                    seed.deserialize(Value::new(pair.name, self.buf)).map(Some)
+                }
+                None => Ok(None),
+            }
+        }
+
+        fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+        where
+            V: DeserializeSeed<'de>,
+        {
+            seed.deserialize(Value::new(self.value, self.buf))
+        }
+    }
+
+    /// Decodes `raw` into `buf`, unless there is nothing to decode and `raw` can be used as-is.
+    fn decode<'a>(raw: &'a str, buf: &'a mut [u8]) -> Result<&'a str, UriError> {
+        if raw.contains(['%', '+']) {
+            decode_query(raw, buf)
+        } else {
+            Ok(raw)
+        }
+    }
+
+    /// A single name or value of the form.
+    struct Value<'de, 'a> {
+        raw: &'de str,
+        buf: &'a mut [u8],
+    }
+
+    impl<'de, 'a> Value<'de, 'a> {
+        fn new(raw: &'de str, buf: &'a mut [u8]) -> Self {
+            Self { raw, buf }
+        }
+
+        fn parse<T: core::str::FromStr>(self) -> Result<T, DeserializeError> {
+            decode(self.raw, self.buf)?
+                .parse()
+                .map_err(|_| DeserializeError::InvalidValue)
+        }
+    }
+
+    macro_rules! deserialize_parsed {
+        ($($method:ident => $visit:ident,)*) => {
+            $(
+                fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+                where
+                    V: Visitor<'de>,
+                {
+                    visitor.$visit(self.parse()?)
+                }
+            )*
+        };
+    }
+
+    impl<'de> de::Deserializer<'de> for Value<'de, '_> {
+        type Error = DeserializeError;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            if self.raw.contains(['%', '+']) {
+                visitor.visit_str(decode_query(self.raw, self.buf)?)
+            } else {
+                visitor.visit_borrowed_str(self.raw)
+            }
+        }
+
+        fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match decode(self.raw, self.buf)? {
+                "true" | "on" | "1" => visitor.visit_bool(true),
+                "false" | "off" | "0" | "" => visitor.visit_bool(false),
+                _ => Err(DeserializeError::InvalidValue),
+            }
+        }
+
+        deserialize_parsed! {
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_i128 => visit_i128,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_u128 => visit_u128,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char,
+        }
+
+        fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            if self.raw.is_empty() {
+                visitor.visit_none()
+            } else {
+                visitor.visit_some(self)
+            }
+        }
+
+        fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_unit()
+        }
+
+        fn deserialize_newtype_struct<V>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_enum<V>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            // Only unit variants can be expressed in a form
+            let variant: &str = decode(self.raw, self.buf)?;
+
+            visitor.visit_enum(IntoDeserializer::<DeserializeError>::into_deserializer(
+                variant,
+            ))
+        }
+
+        forward_to_deserialize_any! {
+            str string bytes byte_buf unit_struct seq tuple
+            tuple_struct map struct identifier ignored_any
+        }
+    }
+}
+
+pub mod asynch {
+    use crate::http::Headers;
+    use crate::io::asynch::Read;
+
+    use super::{check_headers, to_form, Form, FormError};
+
+    /// Reads a whole form body, e.g. from a `server::asynch::Request`, into `buf`.
+    ///
+    /// Fails with [`FormError::BodyTooLarge`] rather than truncating the form if the body does not fit.
+    pub async fn read<'b, R>(
+        mut read: R,
+        buf: &'b mut [u8],
+    ) -> Result<Form<'b>, FormError<R::Error>>
+    where
+        R: Read + Headers,
+    {
+        check_headers(&read, buf.len())?;
+
+        let mut len = 0;
+
+        loop {
+            if len == buf.len() {
+                // The body must end exactly here
+                return match read.read(&mut [0]).await.map_err(FormError::Io)? {
+                    0 => to_form(buf, len),
+                    _ => Err(FormError::BodyTooLarge),
+                };
+            }
+
+            match read.read(&mut buf[len..]).await.map_err(FormError::Io)? {
+                0 => return to_form(buf, len),
+                size => len += size,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::Headers;
+    use crate::io::{ErrorType, Read};
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{read, FormEncoder, FormError};
+
+    /// A request body with the given `Content-Type` and `Content-Length` headers.
+    struct Request<'a> {
+        socket: MockSocket<'a>,
+        content_type: Option<&'a str>,
+        content_len: Option<&'a str>,
+    }
+
+    impl<'a> Request<'a> {
+        fn new(
+            body: &'a [u8],
+            content_type: Option<&'a str>,
+            content_len: Option<&'a str>,
+        ) -> Self {
+            Self {
+                socket: MockSocket::chunked(body, 3),
+                content_type,
+                content_len,
+            }
+        }
+    }
+
+    impl Headers for Request<'_> {
+        fn header(&self, name: &str) -> Option<&str> {
+            if name.eq_ignore_ascii_case("Content-Type") {
+                self.content_type
+            } else if name.eq_ignore_ascii_case("Content-Length") {
+                self.content_len
+            } else {
+                None
+            }
+        }
+    }
+
+    impl ErrorType for Request<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Request<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.socket.read(buf)
+        }
+    }
+
+    impl crate::io::asynch::Read for Request<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Read::read(&mut self.socket, buf)
+        }
+    }
+
+    const WIFI_FORM: &[u8] =
+        b"ssid=My+Net%21&password=p%26ss&channel=&auth_method=WPA2Personal&extra";
+
+    #[test]
+    fn read_form() {
+        let mut buf = [0; 128];
+        let form = read(
+            Request::new(
+                WIFI_FORM,
+                Some("application/x-www-form-urlencoded; charset=utf-8"),
+                None,
+            ),
+            &mut buf,
+        )
+        .unwrap();
+
+        let mut value_buf = [0; 32];
+        assert_eq!(form.get("ssid", &mut value_buf).unwrap(), Some("My Net!"));
+        assert_eq!(form.get("password", &mut value_buf).unwrap(), Some("p&ss"));
+        assert_eq!(form.get("channel", &mut value_buf).unwrap(), Some(""));
+        assert_eq!(form.get("missing", &mut value_buf).unwrap(), None);
+        assert_eq!(form.pairs().count(), 5);
+
+        let mut exact = [0; 3];
+        let form = read(Request::new(b"a=b", None, None), &mut exact).unwrap();
+        assert_eq!(form.as_str(), "a=b");
+
+        let form = block_on(super::asynch::read(
+            Request::new(b"a=1", None, None),
+            &mut buf,
+        ));
+        assert_eq!(form.unwrap().as_str(), "a=1");
+    }
+
+    #[test]
+    fn read_invalid() {
+        let mut buf = [0; 10];
+
+        assert!(matches!(
+            read(Request::new(WIFI_FORM, None, None), &mut buf),
+            Err(FormError::BodyTooLarge)
+        ));
+        assert!(matches!(
+            read(Request::new(b"", None, Some("100")), &mut buf),
+            Err(FormError::BodyTooLarge)
+        ));
+        assert!(matches!(
+            read(Request::new(b"a=b", Some("text/plain"), None), &mut buf),
+            Err(FormError::InvalidContentType)
+        ));
+        assert!(matches!(
+            read(Request::new(b"a=\xff", None, None), &mut buf),
+            Err(FormError::InvalidUtf8)
+        ));
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn deserialize_wifi_configuration() {
+        use crate::wifi::{AuthMethod, ClientConfiguration};
+
+        let mut buf = [0; 128];
+        let form = read(Request::new(WIFI_FORM, None, None), &mut buf).unwrap();
+
+        let mut decode_buf = [0; 64];
+        let conf: ClientConfiguration = form.deserialize(&mut decode_buf).unwrap();
+
+        assert_eq!(conf.ssid.as_str(), "My Net!");
+        assert_eq!(conf.password.as_str(), "p&ss");
+        assert_eq!(conf.channel, None);
+        assert_eq!(conf.auth_method, AuthMethod::WPA2Personal);
+        assert_eq!(conf.bssid, None);
+
+        let form = read(Request::new(b"ssid=x&channel=300", None, None), &mut buf).unwrap();
+        assert!(form
+            .deserialize::<ClientConfiguration>(&mut decode_buf)
+            .is_err());
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn deserialize_scalars() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Settings<'a> {
+            port: u16,
+            enabled: bool,
+            name: &'a str,
+            offset: Option<i8>,
+            #[serde(default)]
+            debug: bool,
+            ratio: f32,
+        }
+
+        let mut buf = [0; 16];
+
+        assert_eq!(
+            super::from_str::<Settings>(
+                "port=42&enabled=on&name=plain&offset=-3&ratio=1.5&unknown=1",
+                &mut buf
+            )
+            .unwrap(),
+            Settings {
+                port: 42,
+                enabled: true,
+                name: "plain",
+                offset: Some(-3),
+                debug: false,
+                ratio: 1.5,
+            }
+        );
+        assert!(super::from_str::<Settings>("port=x&enabled=on&name=p&ratio=1", &mut buf).is_err());
+    }
+
+    #[test]
+    fn encode() {
+        let mut encoder = FormEncoder::new(heapless::String::<64>::new());
+        encoder
+            .pair("ssid", "My Net!")
+            .unwrap()
+            .pair("password", "a&b=c")
+            .unwrap();
+
+        assert_eq!(encoder.as_str(), "ssid=My%20Net%21&password=a%26b%3Dc");
+    }
+}
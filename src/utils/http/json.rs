@@ -0,0 +1,848 @@
+use core::fmt::{self, Write as _};
+
+use serde::{Deserialize, Serialize};
+
+use crate::http::client::{Client, Connection as ClientConnection};
+use crate::http::server::{Connection as ServerConnection, Request, Response};
+use crate::http::{status, Headers, Method};
+use crate::io::{Error, ErrorKind, Read, Write};
+
+pub use crate::utils::io::Buffer;
+
+pub const CONTENT_TYPE: &str = "application/json";
+
+/// The maximum length of a JSON body read or written by the client and server helpers.
+///
+/// Bounds how far a growable [`Buffer`] is extended; use [`read`] directly for another limit.
+pub const MAX_BODY_LEN: usize = 16 * 1024;
+
+/// The size by which a growable [`Buffer`] is extended at least.
+const CHUNK_SIZE: usize = 64;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum JsonError<E> {
+    Io(E),
+    /// The server replied with a non-success status. The body of the response is not read.
+    Status(u16),
+    InvalidContentType,
+    BodyTooLarge,
+    Serialize,
+    Deserialize(#[cfg_attr(feature = "defmt", defmt(Debug2Format))] serde_json_core::de::Error),
+}
+
+impl<E: fmt::Debug> fmt::Display for JsonError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for JsonError<E> {}
+
+impl<E> Error for JsonError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::Status(_) => ErrorKind::Other,
+            Self::BodyTooLarge => ErrorKind::OutOfMemory,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// Reads a whole JSON body, e.g. from a `server::Request` or a `client::Response`, into `buf`
+/// and deserializes it.
+///
+/// Fails with [`JsonError::BodyTooLarge`] rather than truncating the body if it does not fit,
+/// or if it is longer than `max_len` bytes. A `Content-Length` above `max_len` fails before
+/// anything is read.
+pub fn read<'b, T, R, B>(
+    mut read: R,
+    buf: &'b mut B,
+    max_len: usize,
+) -> Result<T, JsonError<R::Error>>
+where
+    T: Deserialize<'b>,
+    R: Read + Headers,
+    B: Buffer + ?Sized,
+{
+    check_content_type(&read)?;
+    check_content_len(&read, max_len)?;
+
+    let mut len = 0;
+
+    loop {
+        let data = reserve(buf, len, max_len);
+
+        if len == data.len() {
+            // The buffer cannot grow, so the body must end exactly here
+            match read.read(&mut [0]).map_err(JsonError::Io)? {
+                0 => break,
+                _ => return Err(JsonError::BodyTooLarge),
+            }
+        }
+
+        match read.read(&mut data[len..]).map_err(JsonError::Io)? {
+            0 => break,
+            size => len += size,
+        }
+    }
+
+    from_slice(&buf.reserve(len)[..len])
+}
+
+/// Grows `buf` for reading past `len` bytes, up to `max_len` bytes.
+fn reserve<B>(buf: &mut B, len: usize, max_len: usize) -> &mut [u8]
+where
+    B: Buffer + ?Sized,
+{
+    let data = buf.reserve((len * 2).max(CHUNK_SIZE).min(max_len));
+    let capacity = data.len().min(max_len);
+
+    &mut data[..capacity]
+}
+
+fn from_slice<'b, T, E>(data: &'b [u8]) -> Result<T, JsonError<E>>
+where
+    T: Deserialize<'b>,
+{
+    serde_json_core::from_slice(data)
+        .map(|(value, _)| value)
+        .map_err(JsonError::Deserialize)
+}
+
+fn check_content_type<E>(headers: impl Headers) -> Result<(), JsonError<E>> {
+    if let Some(content_type) = headers.content_type() {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+
+        // Also accept the structured syntax suffix, e.g. `application/problem+json`
+        let json = mime.eq_ignore_ascii_case(CONTENT_TYPE)
+            || mime
+                .len()
+                .checked_sub(5)
+                .and_then(|index| mime.get(index..))
+                .is_some_and(|suffix| suffix.eq_ignore_ascii_case("+json"));
+
+        if !json {
+            return Err(JsonError::InvalidContentType);
+        }
+    }
+
+    Ok(())
+}
+
+fn check_content_len<E>(headers: impl Headers, max_len: usize) -> Result<(), JsonError<E>> {
+    match headers.content_len() {
+        Some(len) if len > max_len as u64 => Err(JsonError::BodyTooLarge),
+        _ => Ok(()),
+    }
+}
+
+fn check_status<E>(status: u16) -> Result<(), JsonError<E>> {
+    if status::OK.contains(&status) {
+        Ok(())
+    } else {
+        Err(JsonError::Status(status))
+    }
+}
+
+/// Serializes `value` into `buf`, growing it up to [`MAX_BODY_LEN`] bytes.
+fn serialize<'b, T, B, E>(value: &T, buf: &'b mut B) -> Result<&'b [u8], JsonError<E>>
+where
+    T: Serialize + ?Sized,
+    B: Buffer + ?Sized,
+{
+    let mut capacity = CHUNK_SIZE;
+
+    let len = loop {
+        let data = reserve(buf, capacity / 2, MAX_BODY_LEN);
+
+        match serde_json_core::to_slice(value, data) {
+            Ok(len) => break len,
+            Err(serde_json_core::ser::Error::BufferFull)
+                if data.len() >= capacity && data.len() < MAX_BODY_LEN =>
+            {
+                capacity = data.len() * 2;
+            }
+            Err(serde_json_core::ser::Error::BufferFull) => return Err(JsonError::BodyTooLarge),
+            Err(_) => return Err(JsonError::Serialize),
+        }
+    };
+
+    Ok(&buf.reserve(len)[..len])
+}
+
+fn content_len(len: usize) -> heapless::String<20> {
+    let mut content_len = heapless::String::new();
+    // Cannot fail, a `usize` has at most 20 digits
+    let _ = write!(content_len, "{len}");
+
+    content_len
+}
+
+impl<C> Client<C>
+where
+    C: ClientConnection,
+{
+    /// Sends a `GET` request, and deserializes the JSON body of the response, of at most
+    /// [`MAX_BODY_LEN`] bytes, into `buf`.
+    pub fn get_json<'b, T, B>(
+        &mut self,
+        uri: &str,
+        buf: &'b mut B,
+    ) -> Result<T, JsonError<C::Error>>
+    where
+        T: Deserialize<'b>,
+        B: Buffer + ?Sized,
+    {
+        let response = self
+            .request(Method::Get, uri, &[("Accept", CONTENT_TYPE)])
+            .map_err(JsonError::Io)?
+            .submit()
+            .map_err(JsonError::Io)?;
+
+        check_status(response.status())?;
+
+        read(response, buf, MAX_BODY_LEN)
+    }
+
+    /// Sends a `POST` request with `value` as its JSON body, and deserializes the JSON body of the response.
+    ///
+    /// `buf` is used for both the request and the response body.
+    pub fn post_json<'b, T, R, B>(
+        &mut self,
+        uri: &str,
+        value: &T,
+        buf: &'b mut B,
+    ) -> Result<R, JsonError<C::Error>>
+    where
+        T: Serialize + ?Sized,
+        R: Deserialize<'b>,
+        B: Buffer + ?Sized,
+    {
+        self.request_json(Method::Post, uri, value, buf)
+    }
+
+    /// Sends a request with `value` as its JSON body, and deserializes the JSON body of the response.
+    ///
+    /// `buf` is used for both the request and the response body.
+    pub fn request_json<'b, T, R, B>(
+        &mut self,
+        method: Method,
+        uri: &str,
+        value: &T,
+        buf: &'b mut B,
+    ) -> Result<R, JsonError<C::Error>>
+    where
+        T: Serialize + ?Sized,
+        R: Deserialize<'b>,
+        B: Buffer + ?Sized,
+    {
+        let body = serialize(value, buf)?;
+        let content_len = content_len(body.len());
+
+        let headers = [
+            ("Content-Type", CONTENT_TYPE),
+            ("Content-Length", content_len.as_str()),
+            ("Accept", CONTENT_TYPE),
+        ];
+
+        let mut request = self.request(method, uri, &headers).map_err(JsonError::Io)?;
+        request.write_all(body).map_err(JsonError::Io)?;
+
+        let response = request.submit().map_err(JsonError::Io)?;
+
+        check_status(response.status())?;
+
+        read(response, buf, MAX_BODY_LEN)
+    }
+}
+
+impl<C> Request<C>
+where
+    C: ServerConnection,
+{
+    /// Reads the JSON body of the request, of at most [`MAX_BODY_LEN`] bytes, into `buf` and deserializes it.
+    pub fn read_json<'b, T, B>(&mut self, buf: &'b mut B) -> Result<T, JsonError<C::Error>>
+    where
+        T: Deserialize<'b>,
+        B: Buffer + ?Sized,
+    {
+        read(self, buf, MAX_BODY_LEN)
+    }
+
+    /// Initiates a response with `value` serialized into `buf` as its JSON body.
+    pub fn into_json_response<T, B>(
+        self,
+        status: u16,
+        value: &T,
+        buf: &mut B,
+    ) -> Result<Response<C>, JsonError<C::Error>>
+    where
+        T: Serialize + ?Sized,
+        B: Buffer + ?Sized,
+    {
+        let body = serialize(value, buf)?;
+        let content_len = content_len(body.len());
+
+        let headers = [
+            ("Content-Type", CONTENT_TYPE),
+            ("Content-Length", content_len.as_str()),
+        ];
+
+        let mut response = self
+            .into_response(status, None, &headers)
+            .map_err(JsonError::Io)?;
+        response.write_all(body).map_err(JsonError::Io)?;
+
+        Ok(response)
+    }
+}
+
+impl<C> Response<C>
+where
+    C: ServerConnection,
+{
+    /// Writes `value`, serialized into `buf`, to the body of the response.
+    ///
+    /// The response is expected to be initiated with the `application/json` content type.
+    pub fn write_json<T, B>(&mut self, value: &T, buf: &mut B) -> Result<(), JsonError<C::Error>>
+    where
+        T: Serialize + ?Sized,
+        B: Buffer + ?Sized,
+    {
+        let body = serialize(value, buf)?;
+
+        self.write_all(body).map_err(JsonError::Io)
+    }
+}
+
+pub mod asynch {
+    use serde::{Deserialize, Serialize};
+
+    use crate::http::client::asynch::{Client, Connection as ClientConnection};
+    use crate::http::server::asynch::{Connection as ServerConnection, Request, Response};
+    use crate::http::{Headers, Method};
+    use crate::io::asynch::{Read, Write};
+
+    use super::{
+        check_content_len, check_content_type, check_status, content_len, from_slice, reserve,
+        serialize, Buffer, JsonError, CONTENT_TYPE, MAX_BODY_LEN,
+    };
+
+    /// Reads a whole JSON body, e.g. from a `server::asynch::Request` or a `client::asynch::Response`,
+    /// into `buf` and deserializes it.
+    ///
+    /// Fails with [`JsonError::BodyTooLarge`] rather than truncating the body if it does not fit,
+    /// or if it is longer than `max_len` bytes. A `Content-Length` above `max_len` fails before
+    /// anything is read.
+    pub async fn read<'b, T, R, B>(
+        mut read: R,
+        buf: &'b mut B,
+        max_len: usize,
+    ) -> Result<T, JsonError<R::Error>>
+    where
+        T: Deserialize<'b>,
+        R: Read + Headers,
+        B: Buffer + ?Sized,
+    {
+        check_content_type(&read)?;
+        check_content_len(&read, max_len)?;
+
+        let mut len = 0;
+
+        loop {
+            let data = reserve(buf, len, max_len);
+
+            if len == data.len() {
+                // The buffer cannot grow, so the body must end exactly here
+                match read.read(&mut [0]).await.map_err(JsonError::Io)? {
+                    0 => break,
+                    _ => return Err(JsonError::BodyTooLarge),
+                }
+            }
+
+            match read.read(&mut data[len..]).await.map_err(JsonError::Io)? {
+                0 => break,
+                size => len += size,
+            }
+        }
+
+        from_slice(&buf.reserve(len)[..len])
+    }
+
+    impl<C> Client<C>
+    where
+        C: ClientConnection,
+    {
+        /// Sends a `GET` request, and deserializes the JSON body of the response, of at most
+        /// [`MAX_BODY_LEN`] bytes, into `buf`.
+        pub async fn get_json<'b, T, B>(
+            &mut self,
+            uri: &str,
+            buf: &'b mut B,
+        ) -> Result<T, JsonError<C::Error>>
+        where
+            T: Deserialize<'b>,
+            B: Buffer + ?Sized,
+        {
+            let response = self
+                .request(Method::Get, uri, &[("Accept", CONTENT_TYPE)])
+                .await
+                .map_err(JsonError::Io)?
+                .submit()
+                .await
+                .map_err(JsonError::Io)?;
+
+            check_status(response.status())?;
+
+            read(response, buf, MAX_BODY_LEN).await
+        }
+
+        /// Sends a `POST` request with `value` as its JSON body, and deserializes the JSON body of the response.
+        ///
+        /// `buf` is used for both the request and the response body.
+        pub async fn post_json<'b, T, R, B>(
+            &mut self,
+            uri: &str,
+            value: &T,
+            buf: &'b mut B,
+        ) -> Result<R, JsonError<C::Error>>
+        where
+            T: Serialize + ?Sized,
+            R: Deserialize<'b>,
+            B: Buffer + ?Sized,
+        {
+            self.request_json(Method::Post, uri, value, buf).await
+        }
+
+        /// Sends a request with `value` as its JSON body, and deserializes the JSON body of the response.
+        ///
+        /// `buf` is used for both the request and the response body.
+        pub async fn request_json<'b, T, R, B>(
+            &mut self,
+            method: Method,
+            uri: &str,
+            value: &T,
+            buf: &'b mut B,
+        ) -> Result<R, JsonError<C::Error>>
+        where
+            T: Serialize + ?Sized,
+            R: Deserialize<'b>,
+            B: Buffer + ?Sized,
+        {
+            let body = serialize(value, buf)?;
+            let content_len = content_len(body.len());
+
+            let headers = [
+                ("Content-Type", CONTENT_TYPE),
+                ("Content-Length", content_len.as_str()),
+                ("Accept", CONTENT_TYPE),
+            ];
+
+            let mut request = self
+                .request(method, uri, &headers)
+                .await
+                .map_err(JsonError::Io)?;
+            request.write_all(body).await.map_err(JsonError::Io)?;
+
+            let response = request.submit().await.map_err(JsonError::Io)?;
+
+            check_status(response.status())?;
+
+            read(response, buf, MAX_BODY_LEN).await
+        }
+    }
+
+    impl<C> Request<C>
+    where
+        C: ServerConnection,
+    {
+        /// Reads the JSON body of the request, of at most [`MAX_BODY_LEN`] bytes, into `buf` and deserializes it.
+        pub async fn read_json<'b, T, B>(
+            &mut self,
+            buf: &'b mut B,
+        ) -> Result<T, JsonError<C::Error>>
+        where
+            T: Deserialize<'b>,
+            B: Buffer + ?Sized,
+        {
+            read(self, buf, MAX_BODY_LEN).await
+        }
+
+        /// Initiates a response with `value` serialized into `buf` as its JSON body.
+        pub async fn into_json_response<T, B>(
+            self,
+            status: u16,
+            value: &T,
+            buf: &mut B,
+        ) -> Result<Response<C>, JsonError<C::Error>>
+        where
+            T: Serialize + ?Sized,
+            B: Buffer + ?Sized,
+        {
+            let body = serialize(value, buf)?;
+            let content_len = content_len(body.len());
+
+            let headers = [
+                ("Content-Type", CONTENT_TYPE),
+                ("Content-Length", content_len.as_str()),
+            ];
+
+            let mut response = self
+                .into_response(status, None, &headers)
+                .await
+                .map_err(JsonError::Io)?;
+            response.write_all(body).await.map_err(JsonError::Io)?;
+
+            Ok(response)
+        }
+    }
+
+    impl<C> Response<C>
+    where
+        C: ServerConnection,
+    {
+        /// Writes `value`, serialized into `buf`, to the body of the response.
+        ///
+        /// The response is expected to be initiated with the `application/json` content type.
+        pub async fn write_json<T, B>(
+            &mut self,
+            value: &T,
+            buf: &mut B,
+        ) -> Result<(), JsonError<C::Error>>
+        where
+            T: Serialize + ?Sized,
+            B: Buffer + ?Sized,
+        {
+            let body = serialize(value, buf)?;
+
+            self.write_all(body).await.map_err(JsonError::Io)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use crate::http::client::Client;
+    use crate::http::server::{Connection, Handler};
+    use crate::http::{Headers, Method};
+    use crate::io::{ErrorType, Read};
+    use crate::utils::http::client::ClientConnection;
+    use crate::utils::http::server::connection::{handle_connection, ServerConnection};
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{read, serialize, JsonError, CONTENT_TYPE, MAX_BODY_LEN};
+
+    /// A JSON body with an optional `Content-Length` header.
+    struct Request<'a> {
+        socket: MockSocket<'a>,
+        content_len: Option<&'a str>,
+    }
+
+    impl<'a> Request<'a> {
+        fn new(body: &'a [u8], content_len: Option<&'a str>) -> Self {
+            Self {
+                socket: MockSocket::chunked(body, 7),
+                content_len,
+            }
+        }
+    }
+
+    impl Headers for Request<'_> {
+        fn header(&self, name: &str) -> Option<&str> {
+            if name.eq_ignore_ascii_case("Content-Type") {
+                Some("application/json")
+            } else if name.eq_ignore_ascii_case("Content-Length") {
+                self.content_len
+            } else {
+                None
+            }
+        }
+    }
+
+    impl ErrorType for Request<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Request<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.socket.read(buf)
+        }
+    }
+
+    impl crate::io::asynch::Read for Request<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Read::read(&mut self.socket, buf)
+        }
+    }
+
+    const BODY: &[u8] = b"[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25]";
+
+    #[test]
+    fn read_max_len() {
+        let mut buf = Vec::new();
+        let value: [u8; 25] = read(Request::new(BODY, None), &mut buf, BODY.len()).unwrap();
+        assert_eq!(value[24], 25);
+
+        let mut buf = Vec::new();
+        assert!(matches!(
+            read::<[u8; 25], _, _>(Request::new(BODY, None), &mut buf, BODY.len() - 1),
+            Err(JsonError::BodyTooLarge)
+        ));
+        // The buffer is not grown past the limit
+        assert_eq!(buf.len(), BODY.len() - 1);
+
+        let mut buf = [0; 16];
+        assert!(matches!(
+            read::<[u8; 25], _, _>(Request::new(BODY, None), &mut buf, 1024),
+            Err(JsonError::BodyTooLarge)
+        ));
+    }
+
+    #[test]
+    fn read_content_len() {
+        let mut buf = Vec::new();
+        assert!(matches!(
+            read::<[u8; 25], _, _>(Request::new(BODY, Some("1000000")), &mut buf, 1024),
+            Err(JsonError::BodyTooLarge)
+        ));
+        assert!(buf.is_empty());
+
+        let mut buf = Vec::new();
+        assert!(matches!(
+            block_on(super::asynch::read::<[u8; 25], _, _>(
+                Request::new(BODY, Some("1000000")),
+                &mut buf,
+                1024
+            )),
+            Err(JsonError::BodyTooLarge)
+        ));
+
+        let mut buf = Vec::new();
+        let value: [u8; 25] = block_on(super::asynch::read(
+            Request::new(BODY, Some("68")),
+            &mut buf,
+            1024,
+        ))
+        .unwrap();
+        assert_eq!(value[0], 1);
+    }
+
+    #[test]
+    fn serialize_max_len() {
+        let value = "a".repeat(MAX_BODY_LEN - 2);
+
+        let mut buf = Vec::new();
+        let body = serialize::<_, _, ()>(value.as_str(), &mut buf).unwrap();
+        assert_eq!(body.len(), MAX_BODY_LEN);
+
+        // The buffer is not grown past the limit
+        let value = "a".repeat(MAX_BODY_LEN);
+
+        let mut buf = Vec::new();
+        assert!(matches!(
+            serialize::<_, _, ()>(value.as_str(), &mut buf),
+            Err(JsonError::BodyTooLarge)
+        ));
+        assert_eq!(buf.len(), MAX_BODY_LEN);
+
+        let mut buf = [0; 4];
+        assert!(matches!(
+            serialize::<_, _, ()>(&[1, 2, 3], &mut buf),
+            Err(JsonError::BodyTooLarge)
+        ));
+    }
+
+    #[test]
+    fn client() {
+        let mut socket = MockSocket::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 7\r\n\r\n[1,2,3]\
+              HTTP/1.1 201 Created\r\nContent-Type: application/json; charset=utf-8\r\n\
+              Content-Length: 3\r\n\r\n[6]",
+        );
+        let mut buf = [0; 1024];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        let mut buf = Vec::new();
+        let value: [u8; 3] = client.get_json("/a", &mut buf).unwrap();
+        assert_eq!(value, [1, 2, 3]);
+
+        let value: [u8; 1] = client.post_json("/b", &[1, 2, 3], &mut buf).unwrap();
+        assert_eq!(value, [6]);
+
+        assert_eq!(
+            String::from_utf8(socket.output).unwrap(),
+            "GET /a HTTP/1.1\r\nAccept: application/json\r\n\r\n\
+             POST /b HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 7\r\n\
+             Accept: application/json\r\n\r\n[1,2,3]"
+        );
+    }
+
+    #[test]
+    fn client_errors() {
+        let mut socket = MockSocket::new(
+            b"HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}\
+              HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 2\r\n\r\nhi\
+              HTTP/1.1 200 OK\r\nContent-Type: application/problem+json\r\nContent-Length: 3\r\n\r\n[1]",
+        );
+        let mut buf = [0; 1024];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        let mut buf = [0; 16];
+        assert!(matches!(
+            client.get_json::<[u8; 1], _>("/a", &mut buf),
+            Err(JsonError::Status(404))
+        ));
+        assert!(matches!(
+            client.request_json::<_, [u8; 1], _>(Method::Put, "/b", &[1], &mut buf),
+            Err(JsonError::InvalidContentType)
+        ));
+        assert_eq!(client.get_json::<[u8; 1], _>("/c", &mut buf).unwrap(), [1]);
+    }
+
+    #[test]
+    fn client_async() {
+        let mut socket = MockSocket::new(
+            b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nhi\
+              HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 5\r\n\r\n[1,2]",
+        );
+        let mut buf = [0; 1024];
+        let mut client = crate::http::client::asynch::Client::wrap(ClientConnection::<_, 16>::new(
+            &mut socket,
+            &mut buf,
+        ));
+
+        let mut buf = Vec::new();
+        assert!(matches!(
+            block_on(client.get_json::<[u8; 2], _>("/a", &mut buf)),
+            Err(JsonError::Status(500))
+        ));
+        assert!(matches!(
+            block_on(client.post_json::<_, [u8; 2], _>("/b", &[1], &mut buf)),
+            Err(JsonError::InvalidContentType)
+        ));
+        let value: [u8; 2] =
+            block_on(client.request_json(Method::Patch, "/c", &[3], &mut buf)).unwrap();
+        assert_eq!(value, [1, 2]);
+
+        assert!(String::from_utf8(socket.output).unwrap().ends_with(
+            "PATCH /c HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 3\r\n\
+                        Accept: application/json\r\n\r\n[3]"
+        ));
+    }
+
+    /// Responds with the sum of the JSON array in the body of the request.
+    struct Sum;
+
+    impl<C> Handler<C> for Sum
+    where
+        C: Connection,
+    {
+        type Error = JsonError<C::Error>;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let mut request = crate::http::server::Request::wrap(connection);
+            let mut buf = [0; 16];
+
+            let values: [u32; 3] = request.read_json(&mut buf)?;
+            let sum: u32 = values.iter().sum();
+
+            request.into_json_response(200, &[sum], &mut buf)?;
+
+            Ok(())
+        }
+    }
+
+    /// Streams the JSON array in the body of the request back, one value at a time.
+    struct Stream;
+
+    impl<C> Handler<C> for Stream
+    where
+        C: Connection,
+    {
+        type Error = JsonError<C::Error>;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let mut request = crate::http::server::Request::wrap(connection);
+            let mut buf = [0; 16];
+
+            let values: [u32; 2] = request.read_json(&mut buf)?;
+
+            let mut response = request
+                .into_response(200, None, &[("Content-Type", CONTENT_TYPE)])
+                .map_err(JsonError::Io)?;
+
+            for value in values {
+                response.write_json(&value, &mut buf)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    fn serve<'s, H>(handler: &H, request: &'s str) -> (String, Result<(), ()>)
+    where
+        H: for<'a, 'b> Handler<ServerConnection<'b, &'a mut MockSocket<'s>, 16>>,
+    {
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 512];
+
+        let result = handle_connection::<_, _, 16>(&mut socket, &mut buf, handler).map_err(|_| ());
+
+        (String::from_utf8(socket.output).unwrap(), result)
+    }
+
+    #[test]
+    fn server() {
+        let (response, result) = serve(
+            &Sum,
+            "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 7\r\n\
+             Connection: close\r\n\r\n[1,2,3]",
+        );
+        result.unwrap();
+        assert_eq!(
+            response,
+            "HTTP/1.1 200 \r\nContent-Type: application/json\r\nContent-Length: 3\r\n\
+             Connection: close\r\n\r\n[6]"
+        );
+
+        let (response, result) = serve(
+            &Stream,
+            "POST / HTTP/1.1\r\nContent-Length: 5\r\nConnection: close\r\n\r\n[1,2]",
+        );
+        result.unwrap();
+        assert_eq!(
+            response,
+            "HTTP/1.1 200 \r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\
+             Connection: close\r\n\r\n1\r\n1\r\n1\r\n2\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn server_errors() {
+        for request in [
+            "POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 7\r\n\r\n[1,2,3]",
+            "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 17\r\n\r\n\
+             [1,2,3,4,5,6,7,8]",
+            "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 3\r\n\r\n[1]",
+        ] {
+            let (response, result) = serve(&Sum, request);
+            assert!(result.is_err(), "{request}");
+            assert_eq!(
+                response,
+                "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "{request}"
+            );
+        }
+    }
+}
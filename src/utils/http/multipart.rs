@@ -0,0 +1,746 @@
+use core::cmp::min;
+use core::fmt;
+use core::str;
+
+use crate::http::Headers;
+use crate::io::{Error, ErrorKind, ErrorType, Read};
+
+use super::codec::is_token;
+
+/// The maximum length of a boundary as per RFC 2046.
+pub const MAX_BOUNDARY_LEN: usize = 70;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MultipartError<E> {
+    Io(E),
+    MissingBoundary,
+    InvalidBoundary,
+    BufferTooSmall,
+    InvalidHeaders,
+    HeadersTooLarge,
+    UnexpectedEof,
+}
+
+impl<E: fmt::Debug> fmt::Display for MultipartError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for MultipartError<E> {}
+
+impl<E> Error for MultipartError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::BufferTooSmall | Self::HeadersTooLarge => ErrorKind::OutOfMemory,
+            Self::UnexpectedEof => ErrorKind::ConnectionAborted,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// Returns the boundary of a `multipart/*` content type, e.g.
+/// `multipart/form-data; boundary="----WebKitFormBoundary7MA4YWxkTrZu0gW"`.
+pub fn boundary(content_type: &str) -> Option<&str> {
+    let (mime, params) = content_type.split_once(';').unwrap_or((content_type, ""));
+
+    if !mime
+        .trim()
+        .get(..10)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("multipart/"))
+    {
+        return None;
+    }
+
+    param(params, "boundary")
+}
+
+/// The headers of a part of a `multipart/form-data` body.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartHeaders<'a>(&'a str);
+
+impl<'a> PartHeaders<'a> {
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.0
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name, value.trim_matches([' ', '\t'])))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.iter()
+            .find(|(hname, _)| hname.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    pub fn content_disposition(&self) -> Option<&'a str> {
+        self.get("Content-Disposition")
+    }
+
+    /// Returns the name of the form field, from the `Content-Disposition` header.
+    pub fn name(&self) -> Option<&'a str> {
+        self.content_disposition()
+            .and_then(|value| param(value.split_once(';')?.1, "name"))
+    }
+
+    /// Returns the name of the uploaded file, from the `Content-Disposition` header.
+    pub fn filename(&self) -> Option<&'a str> {
+        self.content_disposition()
+            .and_then(|value| param(value.split_once(';')?.1, "filename"))
+    }
+
+    pub fn content_type(&self) -> Option<&'a str> {
+        self.get("Content-Type")
+    }
+}
+
+impl Headers for PartHeaders<'_> {
+    fn header(&self, name: &str) -> Option<&'_ str> {
+        self.get(name)
+    }
+}
+
+enum Step {
+    /// The given number of bytes at the start of the window is part data.
+    Data(usize),
+    /// The delimiter is at the start of the window.
+    Delimiter,
+    Fill,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Body,
+    Done,
+}
+
+/// The I/O-agnostic parsing state, shared by the blocking and async parsers.
+struct Parser<'b> {
+    delimiter: heapless::Vec<u8, { MAX_BOUNDARY_LEN + 4 }>,
+    headers: &'b mut [u8],
+    headers_len: usize,
+    window: &'b mut [u8],
+    start: usize,
+    end: usize,
+    state: State,
+}
+
+impl<'b> Parser<'b> {
+    fn new<E>(boundary: &str, buf: &'b mut [u8]) -> Result<Self, MultipartError<E>> {
+        if boundary.is_empty()
+            || boundary.len() > MAX_BOUNDARY_LEN
+            || boundary.ends_with(' ')
+            || !boundary.bytes().all(|b| b.is_ascii_graphic() || b == b' ')
+        {
+            return Err(MultipartError::InvalidBoundary);
+        }
+
+        let mut delimiter = heapless::Vec::new();
+        delimiter.extend_from_slice(b"\r\n--").unwrap();
+        delimiter.extend_from_slice(boundary.as_bytes()).unwrap();
+
+        let (headers, window) = buf.split_at_mut(buf.len() / 2);
+
+        if window.len() < delimiter.len() * 2 {
+            return Err(MultipartError::BufferTooSmall);
+        }
+
+        // The first boundary might not be preceded by a CRLF, as there is usually no preamble
+        window[..2].copy_from_slice(b"\r\n");
+
+        Ok(Self {
+            delimiter,
+            headers,
+            headers_len: 0,
+            window,
+            start: 0,
+            end: 2,
+            state: State::Body,
+        })
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.window[self.start..self.end]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+    }
+
+    fn spare(&mut self) -> &mut [u8] {
+        if self.start > 0 {
+            self.window.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+
+        &mut self.window[self.end..]
+    }
+
+    fn filled<E>(&mut self, len: usize) -> Result<(), MultipartError<E>> {
+        if len == 0 {
+            return Err(MultipartError::UnexpectedEof);
+        }
+
+        self.end += len;
+
+        Ok(())
+    }
+
+    fn body_step(&self) -> Step {
+        let data = self.data();
+
+        match data
+            .windows(self.delimiter.len())
+            .position(|window| window == self.delimiter.as_slice())
+        {
+            Some(0) => Step::Delimiter,
+            Some(pos) => Step::Data(pos),
+            None => {
+                // Keep enough bytes to recognize a delimiter split across reads
+                let safe = data.len().saturating_sub(self.delimiter.len() - 1);
+
+                if safe > 0 {
+                    Step::Data(safe)
+                } else {
+                    Step::Fill
+                }
+            }
+        }
+    }
+
+    fn read_data(&mut self, buf: &mut [u8]) -> Option<usize> {
+        match self.body_step() {
+            Step::Data(len) => {
+                let len = min(len, buf.len());
+
+                buf[..len].copy_from_slice(&self.data()[..len]);
+                self.consume(len);
+
+                Some(len)
+            }
+            Step::Delimiter => Some(0),
+            Step::Fill => None,
+        }
+    }
+
+    /// Processes the bytes following a delimiter.
+    ///
+    /// Returns `Ok(Some(true))` if a part follows, `Ok(Some(false))` if this was the
+    /// final delimiter and `Ok(None)` if more data is needed.
+    fn after_delimiter<E>(&mut self) -> Result<Option<bool>, MultipartError<E>> {
+        let data = &self.data()[self.delimiter.len()..];
+
+        if data.starts_with(b"--") {
+            self.state = State::Done;
+
+            return Ok(Some(false));
+        }
+
+        // Skip the optional linear whitespace after the boundary
+        let lwsp = data
+            .iter()
+            .position(|b| *b != b' ' && *b != b'\t')
+            .unwrap_or(data.len());
+
+        if data.len() < lwsp + 2 {
+            return Ok(None);
+        }
+
+        if &data[lwsp..lwsp + 2] != b"\r\n" {
+            return Err(MultipartError::InvalidBoundary);
+        }
+
+        self.consume(self.delimiter.len() + lwsp + 2);
+
+        Ok(Some(true))
+    }
+
+    /// Parses the headers of a part.
+    ///
+    /// Returns `Ok(false)` if more data is needed.
+    fn headers<E>(&mut self) -> Result<bool, MultipartError<E>> {
+        let data = &self.window[self.start..self.end];
+
+        let len = if data.starts_with(b"\r\n") {
+            0
+        } else if let Some(pos) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            pos + 2
+        } else if self.start == 0 && self.end == self.window.len() {
+            return Err(MultipartError::HeadersTooLarge);
+        } else {
+            return Ok(false);
+        };
+
+        let headers = &data[..len];
+
+        let valid = str::from_utf8(headers)
+            .map(|headers| {
+                headers
+                    .split("\r\n")
+                    .filter(|line| !line.is_empty())
+                    .all(|line| {
+                        line.split_once(':')
+                            .map(|(name, _)| !name.is_empty() && name.bytes().all(is_token))
+                            .unwrap_or(false)
+                    })
+            })
+            .unwrap_or(false);
+
+        if !valid {
+            return Err(MultipartError::InvalidHeaders);
+        }
+
+        self.headers
+            .get_mut(..len)
+            .ok_or(MultipartError::HeadersTooLarge)?
+            .copy_from_slice(headers);
+        self.headers_len = len;
+
+        self.consume(len + 2);
+
+        Ok(true)
+    }
+
+    fn part_headers(&self) -> PartHeaders<'_> {
+        PartHeaders(str::from_utf8(&self.headers[..self.headers_len]).unwrap_or(""))
+    }
+}
+
+/// A streaming parser for `multipart/form-data` bodies.
+///
+/// The provided buffer is split in two halves: one stores the headers of the current part
+/// and the other buffers the data read from the body. It needs to be large enough for the
+/// part headers sent by the client; 1024 bytes is usually more than enough.
+pub struct Multipart<'b, R> {
+    read: R,
+    parser: Parser<'b>,
+}
+
+impl<'b, R> Multipart<'b, R>
+where
+    R: Read,
+{
+    /// Creates a parser for the body of `read`, a request with a `multipart/*` content type.
+    pub fn new(read: R, buf: &'b mut [u8]) -> Result<Self, MultipartError<R::Error>>
+    where
+        R: Headers,
+    {
+        let mut boundary = heapless::String::<MAX_BOUNDARY_LEN>::new();
+
+        boundary
+            .push_str(
+                read.content_type()
+                    .and_then(self::boundary)
+                    .ok_or(MultipartError::MissingBoundary)?,
+            )
+            .map_err(|_| MultipartError::InvalidBoundary)?;
+
+        Self::with_boundary(read, &boundary, buf)
+    }
+
+    pub fn with_boundary(
+        read: R,
+        boundary: &str,
+        buf: &'b mut [u8],
+    ) -> Result<Self, MultipartError<R::Error>> {
+        Ok(Self {
+            read,
+            parser: Parser::new(boundary, buf)?,
+        })
+    }
+
+    /// Skips the rest of the current part and returns the next one,
+    /// or `None` once the final boundary has been reached.
+    pub fn next_part(&mut self) -> Result<Option<Part<'_, 'b, R>>, MultipartError<R::Error>> {
+        if self.parser.state == State::Done {
+            return Ok(None);
+        }
+
+        loop {
+            match self.parser.body_step() {
+                Step::Data(len) => self.parser.consume(len),
+                Step::Delimiter => break,
+                Step::Fill => self.fill()?,
+            }
+        }
+
+        loop {
+            match self.parser.after_delimiter()? {
+                Some(true) => break,
+                Some(false) => return Ok(None),
+                None => self.fill()?,
+            }
+        }
+
+        while !self.parser.headers()? {
+            self.fill()?;
+        }
+
+        Ok(Some(Part { multipart: self }))
+    }
+
+    pub fn release(self) -> R {
+        self.read
+    }
+
+    fn fill(&mut self) -> Result<(), MultipartError<R::Error>> {
+        let len = self
+            .read
+            .read(self.parser.spare())
+            .map_err(MultipartError::Io)?;
+
+        self.parser.filled(len)
+    }
+}
+
+/// A part of a `multipart/form-data` body. Reading returns the part data.
+pub struct Part<'a, 'b, R> {
+    multipart: &'a mut Multipart<'b, R>,
+}
+
+impl<R> Part<'_, '_, R> {
+    pub fn headers(&self) -> PartHeaders<'_> {
+        self.multipart.parser.part_headers()
+    }
+}
+
+impl<R> ErrorType for Part<'_, '_, R>
+where
+    R: ErrorType,
+{
+    type Error = MultipartError<R::Error>;
+}
+
+impl<R> Read for Part<'_, '_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if let Some(len) = self.multipart.parser.read_data(buf) {
+                return Ok(len);
+            }
+
+            self.multipart.fill()?;
+        }
+    }
+}
+
+fn param<'a>(params: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = params;
+
+    loop {
+        let (pname, after) = rest.split_once('=')?;
+        let pname = pname.trim_start_matches([';', ' ', '\t']).trim();
+        let after = after.trim_start();
+
+        let (value, next) = if let Some(quoted) = after.strip_prefix('"') {
+            let mut escaped = false;
+            let end = quoted.find(|c| {
+                let quote = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                quote
+            })?;
+
+            (&quoted[..end], &quoted[end + 1..])
+        } else {
+            let end = after.find(';').unwrap_or(after.len());
+
+            (after[..end].trim_end(), &after[end..])
+        };
+
+        if pname.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+
+        rest = next.split_once(';').map(|(_, next)| next).unwrap_or("");
+    }
+}
+
+pub mod asynch {
+    use crate::http::Headers;
+    use crate::io::asynch::{ErrorType, Read};
+
+    use super::{Parser, State, Step, MAX_BOUNDARY_LEN};
+
+    pub use super::{boundary, MultipartError, PartHeaders};
+
+    pub struct Multipart<'b, R> {
+        read: R,
+        parser: Parser<'b>,
+    }
+
+    impl<'b, R> Multipart<'b, R>
+    where
+        R: Read,
+    {
+        pub fn new(read: R, buf: &'b mut [u8]) -> Result<Self, MultipartError<R::Error>>
+        where
+            R: Headers,
+        {
+            let mut boundary = heapless::String::<MAX_BOUNDARY_LEN>::new();
+
+            boundary
+                .push_str(
+                    read.content_type()
+                        .and_then(self::boundary)
+                        .ok_or(MultipartError::MissingBoundary)?,
+                )
+                .map_err(|_| MultipartError::InvalidBoundary)?;
+
+            Self::with_boundary(read, &boundary, buf)
+        }
+
+        pub fn with_boundary(
+            read: R,
+            boundary: &str,
+            buf: &'b mut [u8],
+        ) -> Result<Self, MultipartError<R::Error>> {
+            Ok(Self {
+                read,
+                parser: Parser::new(boundary, buf)?,
+            })
+        }
+
+        pub async fn next_part(
+            &mut self,
+        ) -> Result<Option<Part<'_, 'b, R>>, MultipartError<R::Error>> {
+            if self.parser.state == State::Done {
+                return Ok(None);
+            }
+
+            loop {
+                match self.parser.body_step() {
+                    Step::Data(len) => self.parser.consume(len),
+                    Step::Delimiter => break,
+                    Step::Fill => self.fill().await?,
+                }
+            }
+
+            loop {
+                match self.parser.after_delimiter()? {
+                    Some(true) => break,
+                    Some(false) => return Ok(None),
+                    None => self.fill().await?,
+                }
+            }
+
+            while !self.parser.headers()? {
+                self.fill().await?;
+            }
+
+            Ok(Some(Part { multipart: self }))
+        }
+
+        pub fn release(self) -> R {
+            self.read
+        }
+
+        async fn fill(&mut self) -> Result<(), MultipartError<R::Error>> {
+            let len = self
+                .read
+                .read(self.parser.spare())
+                .await
+                .map_err(MultipartError::Io)?;
+
+            self.parser.filled(len)
+        }
+    }
+
+    pub struct Part<'a, 'b, R> {
+        multipart: &'a mut Multipart<'b, R>,
+    }
+
+    impl<R> Part<'_, '_, R> {
+        pub fn headers(&self) -> PartHeaders<'_> {
+            self.multipart.parser.part_headers()
+        }
+    }
+
+    impl<R> ErrorType for Part<'_, '_, R>
+    where
+        R: ErrorType,
+    {
+        type Error = MultipartError<R::Error>;
+    }
+
+    impl<R> Read for Part<'_, '_, R>
+    where
+        R: Read,
+    {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+
+            loop {
+                if let Some(len) = self.multipart.parser.read_data(buf) {
+                    return Ok(len);
+                }
+
+                self.multipart.fill().await?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::io::Read;
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{boundary, Multipart, MultipartError};
+
+    fn read_all<R: Read>(mut read: R, data: &mut Vec<u8>) -> Result<(), R::Error> {
+        let mut buf = [0; 3];
+
+        loop {
+            let len = read.read(&mut buf)?;
+            if len == 0 {
+                return Ok(());
+            }
+
+            data.extend_from_slice(&buf[..len]);
+        }
+    }
+
+    const BODY: &[u8] = b"preamble\r\n--XyZ\r\n\
+        Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+        value\r\n--XyZ \r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        line1\r\n--XY\r\n--XyZ\r\n\r\n\
+        noheaders\r\n--XyZ--\r\nepilogue";
+
+    #[test]
+    fn content_type_boundary() {
+        assert_eq!(boundary("multipart/form-data; boundary=abc"), Some("abc"));
+        assert_eq!(
+            boundary("Multipart/Form-Data; charset=x; boundary=\"a b;c\""),
+            Some("a b;c")
+        );
+        assert_eq!(boundary("text/plain; boundary=abc"), None);
+        assert_eq!(boundary("multipart"), None);
+        assert_eq!(boundary(""), None);
+    }
+
+    #[test]
+    fn content_type_boundary_non_ascii() {
+        assert_eq!(boundary("multipart\u{e9}x; boundary=a"), None);
+        assert_eq!(boundary("\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}; boundary=a"), None);
+    }
+
+    #[test]
+    fn parse() {
+        for chunk in [1, 2, 3, 7, 64] {
+            let mut socket = MockSocket::chunked(BODY, chunk);
+            let mut buf = [0; 256];
+            let mut multipart = Multipart::with_boundary(&mut socket, "XyZ", &mut buf).unwrap();
+
+            let mut parts = Vec::new();
+
+            while let Some(mut part) = multipart.next_part().unwrap() {
+                let name = part.headers().name().map(String::from);
+                let filename = part.headers().filename().map(String::from);
+
+                let mut data = Vec::new();
+                read_all(&mut part, &mut data).unwrap();
+
+                parts.push((name, filename, String::from_utf8(data).unwrap()));
+            }
+
+            assert!(multipart.next_part().unwrap().is_none());
+            assert_eq!(
+                parts,
+                [
+                    (Some("field".into()), None, "value".into()),
+                    (
+                        Some("file".into()),
+                        Some("a.txt".into()),
+                        "line1\r\n--XY".into()
+                    ),
+                    (None, None, "noheaders".into()),
+                ],
+                "chunk {chunk}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_truncated() {
+        let mut socket = MockSocket::new(b"--XyZ\r\n\r\nabc");
+        let mut buf = [0; 256];
+        let mut multipart = Multipart::with_boundary(&mut socket, "XyZ", &mut buf).unwrap();
+        let mut part = multipart.next_part().unwrap().unwrap();
+
+        let mut data = Vec::new();
+        assert!(matches!(
+            read_all(&mut part, &mut data),
+            Err(MultipartError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn parse_async() {
+        for chunk in [1, 3, 64] {
+            let mut socket = MockSocket::chunked(BODY, chunk);
+            let mut buf = [0; 256];
+            let mut multipart =
+                super::asynch::Multipart::with_boundary(&mut socket, "XyZ", &mut buf).unwrap();
+
+            let parts = block_on(async {
+                let mut parts = Vec::new();
+
+                while let Some(mut part) = multipart.next_part().await.unwrap() {
+                    let name = part.headers().name().map(String::from);
+
+                    let mut data = Vec::new();
+                    let mut buf = [0; 3];
+
+                    loop {
+                        let len = crate::io::asynch::Read::read(&mut part, &mut buf)
+                            .await
+                            .unwrap();
+                        if len == 0 {
+                            break;
+                        }
+
+                        data.extend_from_slice(&buf[..len]);
+                    }
+
+                    parts.push((name, String::from_utf8(data).unwrap()));
+                }
+
+                assert!(multipart.next_part().await.unwrap().is_none());
+
+                parts
+            });
+
+            assert_eq!(
+                parts,
+                [
+                    (Some("field".into()), "value".into()),
+                    (Some("file".into()), "line1\r\n--XY".into()),
+                    (None, "noheaders".into()),
+                ],
+                "chunk {chunk}"
+            );
+        }
+    }
+}
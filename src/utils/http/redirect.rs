@@ -0,0 +1,493 @@
+use core::{fmt, str};
+
+use crate::http::client::{Client, Connection, Response};
+use crate::http::Method;
+use crate::io::{Error, ErrorKind};
+use crate::utils::http::uri::{Uri, UriError};
+
+/// How [`Client::request_with_redirects`] follows redirects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RedirectPolicy {
+    /// The maximum number of redirects to follow before failing with [`RedirectError::TooManyRedirects`].
+    pub max_redirects: u8,
+}
+
+impl RedirectPolicy {
+    pub const fn new() -> Self {
+        Self { max_redirects: 5 }
+    }
+
+    pub const fn max_redirects(mut self, max_redirects: u8) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RedirectError<E> {
+    Io(E),
+    TooManyRedirects,
+    TooManyHeaders,
+    Uri(UriError),
+}
+
+impl<E: fmt::Debug> fmt::Display for RedirectError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for RedirectError<E> {}
+
+impl<E> Error for RedirectError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::TooManyHeaders | Self::Uri(UriError::BufferOverflow) => ErrorKind::OutOfMemory,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl<C> Client<C>
+where
+    C: Connection,
+{
+    /// Sends a request with `body` and follows the redirects of the server as per `policy`.
+    ///
+    /// `buf` holds the URIs of the redirect targets, and must fit two of them.
+    /// Only redirects to the same origin (scheme, host and port) are followed, as the connection is
+    /// bound to the original one; for any other redirect the response is returned instead, so that
+    /// the caller can connect to its target. For absolute `Location`s to be recognized as
+    /// same-origin, `uri` needs to be absolute too.
+    ///
+    /// A `Host` header in `headers` is rewritten to the authority of the redirect target.
+    pub fn request_with_redirects(
+        &mut self,
+        method: Method,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        policy: &RedirectPolicy,
+        buf: &mut [u8],
+    ) -> Result<Response<&mut C>, RedirectError<C::Error>> {
+        let mut redirects = Redirects::new(method, uri, policy, buf);
+        let connection = self.connection();
+
+        loop {
+            {
+                let request_headers = redirects.headers(headers)?;
+
+                connection
+                    .initiate_request(redirects.method, redirects.uri(), &request_headers)
+                    .map_err(RedirectError::Io)?;
+            }
+
+            if !redirects.drop_body {
+                connection.write_all(body).map_err(RedirectError::Io)?;
+            }
+
+            connection.initiate_response().map_err(RedirectError::Io)?;
+
+            if !redirects.follow(connection.status(), connection.header("Location"))? {
+                return Ok(Response::wrap(connection));
+            }
+
+            // Drain the body of the redirect response, so that the connection can be reused
+            let mut drain = [0; 64];
+            while connection.read(&mut drain).map_err(RedirectError::Io)? > 0 {}
+        }
+    }
+}
+
+/// Headers which describe the body, which are not sent along once the redirects changed the method.
+const BODY_HEADERS: &[&str] = &[
+    "Content-Length",
+    "Content-Type",
+    "Content-Encoding",
+    "Transfer-Encoding",
+];
+
+/// The state of a request being redirected, independent of the sync or async connection it is sent over.
+///
+/// The URI of the current request is kept in one half of `buf`, and the next one is resolved into the other.
+struct Redirects<'a> {
+    policy: RedirectPolicy,
+    original: &'a str,
+    buf: &'a mut [u8],
+    /// The half of `buf` holding the current URI and its length, or `None` for the original URI.
+    current: Option<(bool, usize)>,
+    method: Method,
+    redirects: u8,
+    drop_body: bool,
+}
+
+impl<'a> Redirects<'a> {
+    fn new(method: Method, uri: &'a str, policy: &RedirectPolicy, buf: &'a mut [u8]) -> Self {
+        Self {
+            policy: *policy,
+            original: uri,
+            buf,
+            current: None,
+            method,
+            redirects: 0,
+            drop_body: false,
+        }
+    }
+
+    fn uri(&self) -> &str {
+        let half = self.buf.len() / 2;
+
+        match self.current {
+            Some((second, len)) => {
+                let start = if second { half } else { 0 };
+
+                // Safe to unwrap, the URI was resolved into the buffer as a string
+                str::from_utf8(&self.buf[start..start + len]).unwrap()
+            }
+            None => self.original,
+        }
+    }
+
+    /// Returns the value of the `Host` header of a redirected request, i.e. the authority of its
+    /// target without the user information, or `None` if the `Host` header is to be sent as is.
+    fn host(&self) -> Option<&str> {
+        if self.redirects == 0 {
+            return None;
+        }
+
+        Uri::new(self.uri()).authority().map(|authority| {
+            authority
+                .rsplit_once('@')
+                .map_or(authority, |(_, host)| host)
+        })
+    }
+
+    fn headers<'h, E>(
+        &'h self,
+        headers: &'h [(&'h str, &'h str)],
+    ) -> Result<heapless::Vec<(&'h str, &'h str), 32>, RedirectError<E>> {
+        let dropped = |name: &str| {
+            self.drop_body && BODY_HEADERS.iter().any(|n| n.eq_ignore_ascii_case(name))
+        };
+
+        let host = self.host();
+        let mut filtered = heapless::Vec::new();
+
+        for &(name, value) in headers.iter().filter(|(name, _)| !dropped(name)) {
+            let value = match host {
+                Some(host) if name.eq_ignore_ascii_case("Host") => host,
+                _ => value,
+            };
+
+            filtered
+                .push((name, value))
+                .map_err(|_| RedirectError::TooManyHeaders)?;
+        }
+
+        Ok(filtered)
+    }
+
+    /// Returns `true` if the response is a redirect to be followed, after switching to its target.
+    fn follow<E>(&mut self, status: u16, location: Option<&str>) -> Result<bool, RedirectError<E>> {
+        let Some(location) = location.filter(|_| matches!(status, 301 | 302 | 303 | 307 | 308))
+        else {
+            return Ok(false);
+        };
+
+        if self.redirects >= self.policy.max_redirects {
+            return Err(RedirectError::TooManyRedirects);
+        }
+
+        let half = self.buf.len() / 2;
+
+        let (first_half, second_half) = self.buf.split_at_mut(half);
+        let (current, next, second) = match self.current {
+            Some((true, len)) => (&second_half[..len], first_half, false),
+            Some((false, len)) => (&first_half[..len], second_half, true),
+            None => (self.original.as_bytes(), first_half, false),
+        };
+
+        // Safe to unwrap, the URI was resolved into the buffer as a string
+        let current = Uri::new(str::from_utf8(current).unwrap());
+        let next = current
+            .resolve(location, next)
+            .map_err(RedirectError::Uri)?;
+
+        if !current.is_same_origin(&Uri::new(next)) {
+            return Ok(false);
+        }
+
+        let method = match (status, self.method) {
+            (303, Method::Head) => Method::Head,
+            (303, _) | (301 | 302, Method::Post) => Method::Get,
+            (_, method) => method,
+        };
+
+        self.drop_body |= method != self.method;
+        self.method = method;
+        self.current = Some((second, next.len()));
+        self.redirects += 1;
+
+        Ok(true)
+    }
+}
+
+pub mod asynch {
+    use crate::http::client::asynch::{Client, Connection, Response};
+    use crate::http::Method;
+
+    pub use super::{RedirectError, RedirectPolicy};
+
+    use super::Redirects;
+
+    impl<C> Client<C>
+    where
+        C: Connection,
+    {
+        /// Sends a request with `body` and follows the redirects of the server as per `policy`.
+        ///
+        /// See [`crate::http::client::Client::request_with_redirects`].
+        pub async fn request_with_redirects(
+            &mut self,
+            method: Method,
+            uri: &str,
+            headers: &[(&str, &str)],
+            body: &[u8],
+            policy: &RedirectPolicy,
+            buf: &mut [u8],
+        ) -> Result<Response<&mut C>, RedirectError<C::Error>> {
+            let mut redirects = Redirects::new(method, uri, policy, buf);
+            let connection = self.connection();
+
+            loop {
+                {
+                    let request_headers = redirects.headers(headers)?;
+
+                    connection
+                        .initiate_request(redirects.method, redirects.uri(), &request_headers)
+                        .await
+                        .map_err(RedirectError::Io)?;
+                }
+
+                if !redirects.drop_body {
+                    connection
+                        .write_all(body)
+                        .await
+                        .map_err(RedirectError::Io)?;
+                }
+
+                connection
+                    .initiate_response()
+                    .await
+                    .map_err(RedirectError::Io)?;
+
+                if !redirects.follow(connection.status(), connection.header("Location"))? {
+                    return Ok(Response::wrap(connection));
+                }
+
+                // Drain the body of the redirect response, so that the connection can be reused
+                let mut drain = [0; 64];
+                while connection
+                    .read(&mut drain)
+                    .await
+                    .map_err(RedirectError::Io)?
+                    > 0
+                {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::http::client::Client;
+    use crate::http::Method;
+    use crate::utils::http::client::ClientConnection;
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{RedirectError, RedirectPolicy};
+
+    const HEADERS: &[(&str, &str)] = &[
+        ("Host", "h"),
+        ("Authorization", "x"),
+        ("Content-Length", "4"),
+        ("Content-Type", "text/plain"),
+    ];
+
+    /// Sends a `POST` with [`HEADERS`] to `uri` and returns the status and `Location` of the
+    /// final response, along with the requests sent.
+    fn post(
+        uri: &str,
+        responses: &[u8],
+        policy: &RedirectPolicy,
+    ) -> Result<(u16, Option<String>, Vec<String>), RedirectError<()>> {
+        let mut socket = MockSocket::new(responses);
+        let mut buf = [0; 1024];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+        let mut uri_buf = [0; 256];
+
+        let result = client
+            .request_with_redirects(Method::Post, uri, HEADERS, b"data", policy, &mut uri_buf)
+            .map(|response| {
+                (
+                    response.status(),
+                    response.header("Location").map(String::from),
+                )
+            })
+            .map_err(|e| match e {
+                RedirectError::TooManyRedirects => RedirectError::TooManyRedirects,
+                RedirectError::TooManyHeaders => RedirectError::TooManyHeaders,
+                RedirectError::Uri(e) => RedirectError::Uri(e),
+                RedirectError::Io(_) => RedirectError::Io(()),
+            });
+
+        result.map(|(status, location)| (status, location, requests(&socket.output)))
+    }
+
+    fn requests(output: &[u8]) -> Vec<String> {
+        let output = String::from_utf8(output.into()).unwrap();
+
+        output
+            .split_inclusive("\r\n\r\n")
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn follow_same_origin() {
+        let (status, _, requests) = post(
+            "http://h/a/b",
+            b"HTTP/1.1 307 Temporary Redirect\r\nLocation: c\r\nContent-Length: 5\r\n\r\nmoved\
+              HTTP/1.1 303 See Other\r\nLocation: /d?q=1\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+            &RedirectPolicy::new(),
+        )
+        .unwrap();
+
+        assert_eq!(status, 200);
+        assert_eq!(
+            requests,
+            [
+                "POST /a/b HTTP/1.1\r\nHost: h\r\nAuthorization: x\r\nContent-Length: 4\r\n\
+                 Content-Type: text/plain\r\n\r\n",
+                "dataPOST /a/c HTTP/1.1\r\nHost: h\r\nAuthorization: x\r\nContent-Length: 4\r\n\
+                 Content-Type: text/plain\r\n\r\n",
+                "dataGET /d?q=1 HTTP/1.1\r\nHost: h\r\nAuthorization: x\r\n\r\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn other_origin() {
+        for location in ["http://h:8080/c", "https://h/c"] {
+            let responses = std::format!(
+                "HTTP/1.1 301 Moved Permanently\r\nLocation: {location}\r\nContent-Length: 0\r\n\r\n\
+                 HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"
+            );
+
+            // The redirect is returned, as the connection cannot switch to another port or scheme
+            let (status, returned, requests) =
+                post("http://h/a", responses.as_bytes(), &RedirectPolicy::new()).unwrap();
+            assert_eq!(status, 301);
+            assert_eq!(returned.as_deref(), Some(location));
+            assert_eq!(requests[1..], ["data"]);
+        }
+
+        // The default port is the same origin, and the `Host` is rewritten to the new authority
+        let (status, _, requests) = post(
+            "http://h/a",
+            b"HTTP/1.1 301 Moved Permanently\r\nLocation: http://h:80/c\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+            &RedirectPolicy::new(),
+        )
+        .unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(
+            requests[1],
+            "dataGET /c HTTP/1.1\r\nHost: h:80\r\nAuthorization: x\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn other_host() {
+        let (status, location, requests) = post(
+            "http://h/a",
+            b"HTTP/1.1 302 Found\r\nLocation: http://user@other/x\r\nContent-Length: 0\r\n\r\n",
+            &RedirectPolicy::new(),
+        )
+        .unwrap();
+
+        assert_eq!(status, 302);
+        assert_eq!(location.as_deref(), Some("http://user@other/x"));
+        assert_eq!(requests[1..], ["data"]);
+    }
+
+    #[test]
+    fn too_many_redirects() {
+        assert!(matches!(
+            post(
+                "http://h/",
+                b"HTTP/1.1 307 Temporary Redirect\r\nLocation: /1\r\nContent-Length: 0\r\n\r\n\
+                  HTTP/1.1 307 Temporary Redirect\r\nLocation: /2\r\nContent-Length: 0\r\n\r\n",
+                &RedirectPolicy::new().max_redirects(1),
+            ),
+            Err(RedirectError::TooManyRedirects)
+        ));
+
+        assert!(matches!(
+            post(
+                "http://h/",
+                b"HTTP/1.1 307 Temporary Redirect\r\nLocation: /1\r\nContent-Length: 0\r\n\r\n",
+                &RedirectPolicy::new().max_redirects(0),
+            ),
+            Err(RedirectError::TooManyRedirects)
+        ));
+    }
+
+    #[test]
+    fn follow_async() {
+        let mut socket = MockSocket::new(
+            b"HTTP/1.1 302 Found\r\nLocation: /b\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+        );
+        let mut buf = [0; 1024];
+        let mut client = crate::http::client::asynch::Client::wrap(ClientConnection::<_, 16>::new(
+            &mut socket,
+            &mut buf,
+        ));
+        let mut uri_buf = [0; 256];
+
+        let response = block_on(client.request_with_redirects(
+            Method::Post,
+            "http://h/a",
+            HEADERS,
+            b"data",
+            &RedirectPolicy::new(),
+            &mut uri_buf,
+        ))
+        .unwrap();
+        assert_eq!(response.status(), 200);
+
+        assert_eq!(
+            requests(&socket.output)[1],
+            "dataGET /b HTTP/1.1\r\nHost: h\r\nAuthorization: x\r\n\r\n"
+        );
+    }
+}
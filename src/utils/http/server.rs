@@ -0,0 +1,6 @@
+pub mod assets;
+pub mod auth;
+pub mod connection;
+pub mod cors;
+pub mod registration;
+pub mod session;
@@ -0,0 +1,642 @@
+use core::fmt::Write as _;
+
+use crate::http::server::{Connection, Handler, Headers, Method, Query};
+use crate::utils::http::uri::Uri;
+
+/// A file embedded into the firmware, e.g. with `include_bytes!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Asset<'a> {
+    /// The path of the asset, e.g. `/index.html`. A `.gz` suffix denotes the gzip-compressed variant of the
+    /// asset without the suffix, which is served in its place to clients accepting the `gzip` encoding.
+    pub path: &'a str,
+    pub data: &'a [u8],
+    /// The complete entity tag of the asset including the quotes, e.g. `"\"v1.2.3\""`.
+    ///
+    /// If not given, the entity tag is computed from a hash of `data` on each request.
+    pub etag: Option<&'a str>,
+    /// If not given, the content type is derived from the extension of `path`.
+    pub content_type: Option<&'a str>,
+}
+
+impl<'a> Asset<'a> {
+    pub const fn new(path: &'a str, data: &'a [u8]) -> Self {
+        Self {
+            path,
+            data,
+            etag: None,
+            content_type: None,
+        }
+    }
+
+    pub const fn etag(mut self, etag: &'a str) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    pub const fn content_type(mut self, content_type: &'a str) -> Self {
+        self.content_type = Some(content_type);
+        self
+    }
+}
+
+/// Returns the MIME type of a file, derived from the extension of `path`.
+pub fn content_type(path: &str) -> &'static str {
+    let path = path.strip_suffix(".gz").unwrap_or(path);
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+
+    const TYPES: &[(&str, &str)] = &[
+        ("html", "text/html; charset=utf-8"),
+        ("htm", "text/html; charset=utf-8"),
+        ("css", "text/css; charset=utf-8"),
+        ("js", "text/javascript; charset=utf-8"),
+        ("mjs", "text/javascript; charset=utf-8"),
+        ("json", "application/json"),
+        ("map", "application/json"),
+        ("txt", "text/plain; charset=utf-8"),
+        ("xml", "application/xml"),
+        ("svg", "image/svg+xml"),
+        ("png", "image/png"),
+        ("jpg", "image/jpeg"),
+        ("jpeg", "image/jpeg"),
+        ("gif", "image/gif"),
+        ("webp", "image/webp"),
+        ("ico", "image/x-icon"),
+        ("woff", "font/woff"),
+        ("woff2", "font/woff2"),
+        ("ttf", "font/ttf"),
+        ("otf", "font/otf"),
+        ("wasm", "application/wasm"),
+        ("pdf", "application/pdf"),
+        ("webmanifest", "application/manifest+json"),
+    ];
+
+    TYPES
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+        .map(|(_, content_type)| *content_type)
+        .unwrap_or("application/octet-stream")
+}
+
+/// A [`Handler`] serving a table of embedded [`Asset`]s to `GET` and `HEAD` requests.
+///
+/// Requests for a path ending with `/` are served the `index.html` asset of that directory.
+/// Supports conditional requests with `If-None-Match`, and single byte ranges with `Range` and `If-Range`.
+pub struct Assets<'a> {
+    assets: &'a [Asset<'a>],
+}
+
+impl<'a> Assets<'a> {
+    pub const fn new(assets: &'a [Asset<'a>]) -> Self {
+        Self { assets }
+    }
+
+    fn find(&self, path: &str, suffix: &str) -> Option<&'a Asset<'a>> {
+        let path = path.trim_start_matches('/');
+        let (path, index) = if path.is_empty() || path.ends_with('/') {
+            (path, "index.html")
+        } else {
+            (path, "")
+        };
+
+        self.assets.iter().find(|asset| {
+            asset
+                .path
+                .trim_start_matches('/')
+                .strip_suffix(suffix)
+                .and_then(|asset_path| asset_path.strip_prefix(path))
+                == Some(index)
+        })
+    }
+
+    fn reply(&self, request: &(impl Query + Headers)) -> Reply<'a> {
+        if !matches!(request.method(), Method::Get | Method::Head) {
+            return Reply::status(405, "Method Not Allowed");
+        }
+
+        let path = Uri::new(request.uri()).path();
+
+        let accept_encoding = request.header("Accept-Encoding");
+
+        // Without an `Accept-Encoding` header, any encoding is acceptable, but the identity is preferred
+        let (asset, encoded) = match (self.find(path, ""), self.find(path, ".gz")) {
+            (Some(_), Some(gzip)) if accepts_gzip(accept_encoding) => (gzip, true),
+            (Some(asset), _) => (asset, false),
+            (None, Some(gzip)) if accept_encoding.is_none() || accepts_gzip(accept_encoding) => {
+                (gzip, true)
+            }
+            (None, Some(_)) => return Reply::status(406, "Not Acceptable"),
+            (None, None) => return Reply::status(404, "Not Found"),
+        };
+        let vary = encoded || self.find(path, ".gz").is_some();
+
+        let mut reply = Reply::status(200, "OK");
+        reply.content_type = Some(asset.content_type.unwrap_or(content_type(asset.path)));
+        reply.gzip = encoded;
+        reply.vary = vary;
+        reply.etag = match asset.etag {
+            Some(etag) => Etag::Static(etag),
+            None => Etag::Hash(hash(asset.data)),
+        };
+
+        if request
+            .header("If-None-Match")
+            .is_some_and(|tags| matches_etag(tags, reply.etag.as_str()))
+        {
+            reply.status = 304;
+            reply.message = "Not Modified";
+            reply.content_type = None;
+
+            return reply;
+        }
+
+        let len = asset.data.len();
+
+        let range = match request.header("If-Range") {
+            Some(tag) if tag.trim() != reply.etag.as_str() => None,
+            _ => request
+                .header("Range")
+                .and_then(|range| parse_range(range, len)),
+        };
+
+        reply.body = match range {
+            Some(Some((start, end))) => {
+                reply.status = 206;
+                reply.message = "Partial Content";
+                let _ = write!(reply.content_range, "bytes {start}-{end}/{len}");
+
+                &asset.data[start..=end]
+            }
+            Some(None) => {
+                reply.status = 416;
+                reply.message = "Range Not Satisfiable";
+                reply.content_type = None;
+                let _ = write!(reply.content_range, "bytes */{len}");
+
+                &[]
+            }
+            None => asset.data,
+        };
+
+        reply.content_len.clear();
+        let _ = write!(reply.content_len, "{}", reply.body.len());
+
+        reply
+    }
+}
+
+impl<C> Handler<C> for Assets<'_>
+where
+    C: Connection,
+{
+    type Error = C::Error;
+
+    fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+        let reply = self.reply(connection);
+
+        connection.initiate_response(reply.status, Some(reply.message), &reply.headers())?;
+
+        if connection.method() != Method::Head {
+            connection.write_all(reply.body)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<C> crate::http::server::asynch::Handler<C> for Assets<'_>
+where
+    C: crate::http::server::asynch::Connection,
+{
+    type Error = C::Error;
+
+    async fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+        let reply = self.reply(connection);
+
+        connection
+            .initiate_response(reply.status, Some(reply.message), &reply.headers())
+            .await?;
+
+        if connection.method() != Method::Head {
+            connection.write_all(reply.body).await?;
+        }
+
+        Ok(())
+    }
+}
+
+enum Etag<'a> {
+    None,
+    Static(&'a str),
+    Hash(heapless::String<18>),
+}
+
+impl Etag<'_> {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::None => "",
+            Self::Static(etag) => etag,
+            Self::Hash(etag) => etag,
+        }
+    }
+}
+
+/// The response to a request, independent of the sync or async connection it is sent over.
+struct Reply<'a> {
+    status: u16,
+    message: &'static str,
+    content_type: Option<&'a str>,
+    content_len: heapless::String<20>,
+    content_range: heapless::String<64>,
+    etag: Etag<'a>,
+    gzip: bool,
+    vary: bool,
+    body: &'a [u8],
+}
+
+impl<'a> Reply<'a> {
+    fn status(status: u16, message: &'static str) -> Self {
+        let mut content_len = heapless::String::new();
+        let _ = content_len.push('0');
+
+        Self {
+            status,
+            message,
+            content_type: None,
+            content_len,
+            content_range: heapless::String::new(),
+            etag: Etag::None,
+            gzip: false,
+            vary: false,
+            body: &[],
+        }
+    }
+
+    fn headers<'s>(&'s self) -> heapless::Vec<(&'s str, &'s str), 9> {
+        let mut headers = heapless::Vec::new();
+
+        let mut push = |name, value: &'s str| {
+            if !value.is_empty() {
+                // Cannot fail, there are at most 9 headers
+                let _ = headers.push((name, value));
+            }
+        };
+
+        if self.status == 405 {
+            push("Allow", "GET, HEAD");
+        }
+
+        if self.status != 304 {
+            push("Content-Length", self.content_len.as_str());
+        }
+
+        push("Content-Type", self.content_type.unwrap_or(""));
+        push("Content-Range", self.content_range.as_str());
+        push("ETag", self.etag.as_str());
+
+        if matches!(self.status, 200 | 206) {
+            push("Accept-Ranges", "bytes");
+        }
+
+        if self.gzip {
+            push("Content-Encoding", "gzip");
+        }
+
+        if self.vary {
+            push("Vary", "Accept-Encoding");
+        }
+
+        headers
+    }
+}
+
+fn accepts_gzip(accept_encoding: Option<&str>) -> bool {
+    accept_encoding.is_some_and(|accept_encoding| {
+        accept_encoding.split(',').any(|coding| {
+            let mut params = coding.split(';');
+            let name = params.next().unwrap_or("").trim();
+
+            let rejected = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .is_some_and(|q| q <= 0.0)
+            });
+
+            (name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip")) && !rejected
+        })
+    })
+}
+
+/// Checks if the `If-None-Match` list of entity tags matches `etag`, using the weak comparison.
+fn matches_etag(tags: &str, etag: &str) -> bool {
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+
+    tags.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// Parses a `Range` header into the inclusive bounds of the range within `len` bytes.
+///
+/// Returns `None` if the header is to be ignored, i.e. it is invalid or specifies multiple ranges,
+/// and `Some(None)` if the range cannot be satisfied.
+fn parse_range(range: &str, len: usize) -> Option<Option<(usize, usize)>> {
+    let (unit, range) = range.trim().split_once('=')?;
+
+    if !unit.trim().eq_ignore_ascii_case("bytes") || range.contains(',') {
+        return None;
+    }
+
+    let (start, end) = range.trim().split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // A suffix range, i.e. the last bytes
+        let suffix = parse_digits(end)?;
+
+        (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+    } else {
+        let start = parse_digits(start)?;
+        let end = if end.is_empty() {
+            usize::MAX
+        } else {
+            parse_digits(end)?
+        };
+
+        if end < start {
+            return None;
+        }
+
+        (start < len).then(|| (start, end.min(len - 1)))
+    };
+
+    Some(range)
+}
+
+/// Parses a position of a range, which unlike a Rust integer literal cannot have a sign.
+fn parse_digits(digits: &str) -> Option<usize> {
+    if digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        digits.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Computes the entity tag of an asset without one, from an FNV-1a variant processing the data a word at a time.
+fn hash(data: &[u8]) -> heapless::String<18> {
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = 0xcbf29ce484222325 ^ data.len() as u64;
+
+    let mut words = data.chunks_exact(8);
+    for word in &mut words {
+        // Safe to unwrap, the chunk is exactly 8 bytes long
+        let word = u64::from_le_bytes(word.try_into().unwrap());
+
+        hash = (hash ^ word).wrapping_mul(PRIME).rotate_left(23);
+    }
+
+    for &byte in words.remainder() {
+        hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+    }
+
+    let mut etag = heapless::String::new();
+    // Cannot fail, the tag is exactly 18 characters long
+    let _ = write!(etag, "\"{hash:016x}\"");
+
+    etag
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::format;
+    use std::string::String;
+
+    use crate::http::Method;
+    use crate::utils::http::server::connection::{asynch, handle_connection};
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{accepts_gzip, content_type, matches_etag, parse_range, Asset, Assets};
+
+    static ASSETS: &[Asset] = &[
+        Asset::new("/index.html", b"<html>hello</html>"),
+        Asset::new("/index.html.gz", b"GZDATA"),
+        Asset::new("/app.js", b"0123456789").etag("\"v1\""),
+        Asset::new("/sub/index.html", b"sub"),
+        Asset::new("/only.css.gz", b"CSSGZ"),
+    ];
+
+    fn request(method: Method, uri: &str, headers: &[(&str, &str)]) -> String {
+        let mut request = format!("{method} {uri} HTTP/1.1\r\nConnection: close\r\n");
+
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+
+        request.push_str("\r\n");
+        request
+    }
+
+    fn serve(method: Method, uri: &str, headers: &[(&str, &str)]) -> String {
+        let request = request(method, uri, headers);
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 1024];
+
+        handle_connection::<_, _, 16>(&mut socket, &mut buf, &Assets::new(ASSETS)).unwrap();
+
+        String::from_utf8(socket.output).unwrap()
+    }
+
+    fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+        response
+            .split("\r\n\r\n")
+            .next()
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .find(|(hname, _)| *hname == name)
+            .map(|(_, value)| value)
+    }
+
+    fn body(response: &str) -> &str {
+        response.split_once("\r\n\r\n").unwrap().1
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(parse_range("bytes=2-4", 10), Some(Some((2, 4))));
+        assert_eq!(parse_range(" Bytes = 2 - 4 ", 10), Some(Some((2, 4))));
+        assert_eq!(parse_range("bytes=7-", 10), Some(Some((7, 9))));
+        assert_eq!(parse_range("bytes=7-100", 10), Some(Some((7, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Some(Some((7, 9))));
+        assert_eq!(parse_range("bytes=-30", 10), Some(Some((0, 9))));
+
+        // Not satisfiable
+        assert_eq!(parse_range("bytes=10-", 10), Some(None));
+        assert_eq!(parse_range("bytes=-0", 10), Some(None));
+        assert_eq!(parse_range("bytes=-1", 0), Some(None));
+
+        // Ignored
+        for range in [
+            "bytes=0-1,3-4",
+            "items=0-1",
+            "bytes=4-2",
+            "bytes=-",
+            "bytes=a-b",
+            "bytes=1",
+            "bytes=+1-2",
+            "0-1",
+        ] {
+            assert_eq!(parse_range(range, 10), None, "{range}");
+        }
+    }
+
+    #[test]
+    fn etags() {
+        assert!(matches_etag("\"a\"", "\"a\""));
+        assert!(matches_etag("\"x\", W/\"a\"", "\"a\""));
+        assert!(matches_etag("\"a\"", "W/\"a\""));
+        assert!(matches_etag("*", "\"a\""));
+        assert!(!matches_etag("\"b\"", "\"a\""));
+        assert!(!matches_etag("a", "\"a\""));
+    }
+
+    #[test]
+    fn gzip() {
+        assert!(accepts_gzip(Some("gzip")));
+        assert!(accepts_gzip(Some("deflate, GZIP;q=0.8")));
+        assert!(accepts_gzip(Some("x-gzip")));
+        assert!(!accepts_gzip(Some("gzip;q=0")));
+        assert!(!accepts_gzip(Some("identity")));
+        assert!(!accepts_gzip(None));
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!(content_type("a/b.JS"), "text/javascript; charset=utf-8");
+        assert_eq!(content_type("x.svg.gz"), "image/svg+xml");
+        assert_eq!(content_type("noext"), "application/octet-stream");
+    }
+
+    #[test]
+    fn serve_assets() {
+        let response = serve(Method::Get, "/", &[]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(body(&response), "<html>hello</html>");
+        assert_eq!(header(&response, "Content-Length"), Some("18"));
+        assert_eq!(
+            header(&response, "Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(header(&response, "Vary"), Some("Accept-Encoding"));
+
+        let etag = header(&response, "ETag").unwrap();
+        assert_eq!(etag.len(), 18);
+
+        let response = serve(
+            Method::Get,
+            "/index.html?x=1",
+            &[("Accept-Encoding", "gzip")],
+        );
+        assert_eq!(body(&response), "GZDATA");
+        assert_eq!(header(&response, "Content-Encoding"), Some("gzip"));
+        assert_ne!(header(&response, "ETag"), Some(etag));
+
+        assert_eq!(body(&serve(Method::Get, "/sub/", &[])), "sub");
+        assert!(serve(Method::Get, "/sub", &[]).starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert_eq!(body(&serve(Method::Get, "/only.css", &[])), "CSSGZ");
+        assert!(
+            serve(Method::Get, "/only.css", &[("Accept-Encoding", "identity")])
+                .starts_with("HTTP/1.1 406 Not Acceptable\r\n")
+        );
+
+        let response = serve(Method::Post, "/", &[]);
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert_eq!(header(&response, "Allow"), Some("GET, HEAD"));
+
+        let response = serve(Method::Head, "/app.js", &[]);
+        assert_eq!(header(&response, "Content-Length"), Some("10"));
+        assert_eq!(body(&response), "");
+    }
+
+    #[test]
+    fn serve_if_none_match() {
+        let response = serve(Method::Get, "/", &[]);
+        let etag = header(&response, "ETag").unwrap();
+
+        for tags in [
+            String::from(etag),
+            format!("\"x\", W/{etag}"),
+            String::from("*"),
+        ] {
+            let response = serve(Method::Get, "/", &[("If-None-Match", &tags)]);
+            assert!(response.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+            assert_eq!(header(&response, "ETag"), Some(etag));
+            assert_eq!(header(&response, "Content-Length"), None);
+            assert_eq!(body(&response), "");
+        }
+
+        let response = serve(Method::Get, "/app.js", &[("If-None-Match", "\"v0\"")]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn serve_range() {
+        let response = serve(Method::Get, "/app.js", &[("Range", "bytes=2-4")]);
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert_eq!(body(&response), "234");
+        assert_eq!(header(&response, "Content-Range"), Some("bytes 2-4/10"));
+        assert_eq!(header(&response, "Content-Length"), Some("3"));
+
+        let response = serve(Method::Get, "/app.js", &[("Range", "bytes=0-1,3-4")]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(body(&response), "0123456789");
+
+        let response = serve(Method::Get, "/app.js", &[("Range", "bytes=10-")]);
+        assert!(response.starts_with("HTTP/1.1 416 Range Not Satisfiable\r\n"));
+        assert_eq!(header(&response, "Content-Range"), Some("bytes */10"));
+        assert_eq!(body(&response), "");
+    }
+
+    #[test]
+    fn serve_if_range() {
+        for (if_range, expected) in [
+            ("\"v1\"", "234"),
+            (" \"v1\" ", "234"),
+            ("\"v0\"", "0123456789"),
+            // The comparison is strong
+            ("W/\"v1\"", "0123456789"),
+            // Not a modification date of the asset
+            ("Wed, 21 Oct 2015 07:28:00 GMT", "0123456789"),
+        ] {
+            let response = serve(
+                Method::Get,
+                "/app.js",
+                &[("Range", "bytes=2-4"), ("If-Range", if_range)],
+            );
+            assert_eq!(body(&response), expected, "{if_range}");
+        }
+    }
+
+    #[test]
+    fn serve_async() {
+        let request = request(Method::Get, "/app.js", &[("Range", "bytes=-3")]);
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 1024];
+
+        block_on(asynch::handle_connection::<_, _, 16>(
+            &mut socket,
+            &mut buf,
+            &Assets::new(ASSETS),
+        ))
+        .unwrap();
+
+        let response = String::from_utf8(socket.output).unwrap();
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert_eq!(body(&response), "789");
+    }
+}
@@ -0,0 +1,420 @@
+use core::fmt::{self, Debug, Write as _};
+use core::str;
+
+use enumset::{EnumSet, EnumSetType};
+
+use crate::http::server::{Connection, Handler, Middleware};
+use crate::utils::base64;
+
+/// The authentication schemes supported by [`AuthMiddleware`].
+#[derive(EnumSetType, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AuthScheme {
+    Basic,
+    Bearer,
+}
+
+impl AuthScheme {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Basic => "Basic",
+            Self::Bearer => "Bearer",
+        }
+    }
+}
+
+/// The credentials presented by the client in the `Authorization` header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Credentials<'a> {
+    Basic {
+        username: &'a str,
+        password: &'a str,
+    },
+    Bearer(&'a str),
+}
+
+impl Credentials<'_> {
+    pub const fn scheme(&self) -> AuthScheme {
+        match self {
+            Self::Basic { .. } => AuthScheme::Basic,
+            Self::Bearer(_) => AuthScheme::Bearer,
+        }
+    }
+}
+
+/// Parses an `Authorization` header value, using `buf` to decode the Basic credentials.
+pub fn parse<'a>(authorization: &'a str, buf: &'a mut [u8]) -> Option<Credentials<'a>> {
+    let (scheme, params) = authorization.trim().split_once(' ')?;
+    let params = params.trim_start();
+
+    if scheme.eq_ignore_ascii_case(AuthScheme::Basic.as_str()) {
+        let decoded = base64::decode(params, buf).ok()?;
+        let (username, password) = str::from_utf8(decoded).ok()?.split_once(':')?;
+
+        Some(Credentials::Basic { username, password })
+    } else if scheme.eq_ignore_ascii_case(AuthScheme::Bearer.as_str()) && !params.is_empty() {
+        Some(Credentials::Bearer(params))
+    } else {
+        None
+    }
+}
+
+/// A middleware which lets a request through to the wrapped handler only if its `Authorization` header
+/// carries credentials of one of the accepted schemes, and `verifier` accepts them.
+///
+/// Otherwise, a 401 response with a `WWW-Authenticate` challenge for each accepted scheme is sent.
+/// `N` is the size of the buffers used to decode Basic credentials and to format the challenges.
+pub struct AuthMiddleware<'a, F, const N: usize = 128> {
+    realm: &'a str,
+    schemes: EnumSet<AuthScheme>,
+    verifier: F,
+}
+
+impl<'a, F, const N: usize> AuthMiddleware<'a, F, N>
+where
+    F: Fn(&Credentials) -> bool + Send,
+{
+    /// # Panics
+    ///
+    /// If no scheme is given, or if the challenges for `realm` do not fit into `N` bytes.
+    pub fn new(realm: &'a str, schemes: EnumSet<AuthScheme>, verifier: F) -> Self {
+        if schemes.is_empty() {
+            panic!("no authentication scheme given");
+        }
+
+        let this = Self {
+            realm,
+            schemes,
+            verifier,
+        };
+
+        for scheme in schemes {
+            if this.challenge(scheme, true).is_err() {
+                panic!("realm too long");
+            }
+        }
+
+        this
+    }
+
+    pub fn basic(realm: &'a str, verifier: F) -> Self {
+        Self::new(realm, AuthScheme::Basic.into(), verifier)
+    }
+
+    pub fn bearer(realm: &'a str, verifier: F) -> Self {
+        Self::new(realm, AuthScheme::Bearer.into(), verifier)
+    }
+
+    /// On failure, returns the scheme of the credentials rejected by the verifier, if any.
+    fn authorize(&self, authorization: Option<&str>) -> Result<(), Option<AuthScheme>> {
+        let mut buf = [0; N];
+
+        let credentials = authorization
+            .and_then(|authorization| parse(authorization, &mut buf))
+            .filter(|credentials| self.schemes.contains(credentials.scheme()))
+            .ok_or(None)?;
+
+        if (self.verifier)(&credentials) {
+            Ok(())
+        } else {
+            Err(Some(credentials.scheme()))
+        }
+    }
+
+    fn challenge(
+        &self,
+        scheme: AuthScheme,
+        rejected: bool,
+    ) -> Result<heapless::String<N>, fmt::Error> {
+        let mut challenge = heapless::String::new();
+
+        write!(&mut challenge, "{} realm=\"", scheme.as_str())?;
+
+        for c in self.realm.chars() {
+            if matches!(c, '"' | '\\') {
+                challenge.write_char('\\')?;
+            }
+
+            challenge.write_char(c)?;
+        }
+
+        challenge.write_char('"')?;
+
+        match scheme {
+            AuthScheme::Basic => challenge.write_str(", charset=\"UTF-8\"")?,
+            AuthScheme::Bearer if rejected => challenge.write_str(", error=\"invalid_token\"")?,
+            AuthScheme::Bearer => (),
+        }
+
+        Ok(challenge)
+    }
+
+    fn challenges(&self, rejected: Option<AuthScheme>) -> heapless::Vec<heapless::String<N>, 2> {
+        self.schemes
+            .iter()
+            // Cannot fail, checked in `new`
+            .filter_map(|scheme| self.challenge(scheme, rejected == Some(scheme)).ok())
+            .collect()
+    }
+}
+
+impl<F, C, H, const N: usize> Middleware<C, H> for AuthMiddleware<'_, F, N>
+where
+    F: Fn(&Credentials) -> bool + Send,
+    C: Connection,
+    H: Handler<C>,
+    H::Error: From<C::Error>,
+{
+    type Error = H::Error;
+
+    fn handle(&self, connection: &mut C, handler: &H) -> Result<(), Self::Error> {
+        match self.authorize(connection.header("Authorization")) {
+            Ok(()) => handler.handle(connection),
+            Err(rejected) => {
+                let challenges = self.challenges(rejected);
+                let headers: heapless::Vec<_, 2> = challenges
+                    .iter()
+                    .map(|challenge| ("WWW-Authenticate", challenge.as_str()))
+                    .collect();
+
+                connection.initiate_response(401, Some("Unauthorized"), &headers)?;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<F, C, H, const N: usize> crate::http::server::asynch::Middleware<C, H>
+    for AuthMiddleware<'_, F, N>
+where
+    F: Fn(&Credentials) -> bool + Send,
+    C: crate::http::server::asynch::Connection,
+    H: crate::http::server::asynch::Handler<C>,
+    H::Error: From<C::Error>,
+{
+    type Error = H::Error;
+
+    async fn handle(&self, connection: &mut C, handler: &H) -> Result<(), Self::Error> {
+        match self.authorize(connection.header("Authorization")) {
+            Ok(()) => handler.handle(connection).await,
+            Err(rejected) => {
+                let challenges = self.challenges(rejected);
+                let headers: heapless::Vec<_, 2> = challenges
+                    .iter()
+                    .map(|challenge| ("WWW-Authenticate", challenge.as_str()))
+                    .collect();
+
+                connection
+                    .initiate_response(401, Some("Unauthorized"), &headers)
+                    .await?;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::format;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::http::server::{CompositeHandler, Connection, Handler};
+    use crate::utils::base64;
+    use crate::utils::http::server::connection::{asynch, handle_connection};
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{parse, AuthMiddleware, AuthScheme, Credentials};
+
+    struct Ok200;
+
+    impl<C> Handler<C> for Ok200
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            connection.initiate_response(200, Some("OK"), &[])
+        }
+    }
+
+    impl<C> crate::http::server::asynch::Handler<C> for Ok200
+    where
+        C: crate::http::server::asynch::Connection,
+    {
+        type Error = C::Error;
+
+        async fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            connection.initiate_response(200, Some("OK"), &[]).await
+        }
+    }
+
+    fn verify(credentials: &Credentials) -> bool {
+        matches!(
+            credentials,
+            Credentials::Basic {
+                username: "admin",
+                password: "p:w"
+            } | Credentials::Bearer("token")
+        )
+    }
+
+    fn request(authorization: Option<&str>) -> String {
+        let mut request = String::from("GET / HTTP/1.1\r\nConnection: close\r\n");
+
+        if let Some(authorization) = authorization {
+            request.push_str(&format!("Authorization: {authorization}\r\n"));
+        }
+
+        request.push_str("\r\n");
+        request
+    }
+
+    fn serve<H>(handler: &H, authorization: Option<&str>) -> String
+    where
+        H: for<'a, 'b, 's> Handler<
+            crate::utils::http::server::connection::ServerConnection<
+                'b,
+                &'a mut MockSocket<'s>,
+                16,
+            >,
+        >,
+    {
+        let request = request(authorization);
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 1024];
+
+        handle_connection::<_, _, 16>(&mut socket, &mut buf, handler).unwrap();
+
+        String::from_utf8(socket.output).unwrap()
+    }
+
+    fn challenges(response: &str) -> Vec<&str> {
+        response
+            .lines()
+            .filter_map(|line| line.strip_prefix("WWW-Authenticate: "))
+            .collect()
+    }
+
+    #[test]
+    fn parse_credentials() {
+        let mut buf = [0; 64];
+
+        assert_eq!(
+            parse("Basic YWRtaW46cDp3", &mut buf),
+            Some(Credentials::Basic {
+                username: "admin",
+                password: "p:w"
+            })
+        );
+        assert_eq!(
+            parse("  bearer   token ", &mut buf),
+            Some(Credentials::Bearer("token"))
+        );
+
+        for authorization in [
+            "",
+            "Basic",
+            "Basic !!!",
+            // No colon
+            "Basic YWRtaW4=",
+            // Not UTF-8
+            "Basic /w==",
+            "Bearer ",
+            "Digest username=\"a\"",
+        ] {
+            assert_eq!(parse(authorization, &mut buf), None, "{authorization}");
+        }
+    }
+
+    #[test]
+    fn middleware() {
+        let handler = CompositeHandler::new(
+            AuthMiddleware::<_>::new(
+                "my \"realm\"",
+                AuthScheme::Basic | AuthScheme::Bearer,
+                verify,
+            ),
+            Ok200,
+        );
+
+        let mut buf = [0; 64];
+        let basic = format!("Basic {}", base64::encode(b"admin:p:w", &mut buf).unwrap());
+
+        assert!(serve(&handler, Some(&basic)).starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(serve(&handler, Some("Bearer token")).starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let response = serve(&handler, None);
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert_eq!(
+            challenges(&response),
+            [
+                "Basic realm=\"my \\\"realm\\\"\", charset=\"UTF-8\"",
+                "Bearer realm=\"my \\\"realm\\\"\""
+            ]
+        );
+
+        let response = serve(&handler, Some("Bearer bad"));
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert_eq!(
+            challenges(&response)[1],
+            "Bearer realm=\"my \\\"realm\\\"\", error=\"invalid_token\""
+        );
+
+        let basic = format!("Basic {}", base64::encode(b"admin:x", &mut buf).unwrap());
+        assert!(serve(&handler, Some(&basic)).starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(serve(&handler, Some("Basic !!!")).starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+    }
+
+    #[test]
+    fn middleware_single_scheme() {
+        let handler = CompositeHandler::new(AuthMiddleware::<_>::basic("r", verify), Ok200);
+
+        let response = serve(&handler, Some("Bearer token"));
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert_eq!(
+            challenges(&response),
+            ["Basic realm=\"r\", charset=\"UTF-8\""]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn middleware_realm_too_long() {
+        AuthMiddleware::<_, 16>::basic("a realm which does not fit", verify);
+    }
+
+    #[test]
+    fn middleware_async() {
+        let handler = crate::http::server::asynch::CompositeHandler::new(
+            AuthMiddleware::<_>::bearer("r", verify),
+            Ok200,
+        );
+
+        for (authorization, status) in [
+            (Some("Bearer token"), "HTTP/1.1 200 OK\r\n"),
+            (None, "HTTP/1.1 401 Unauthorized\r\n"),
+        ] {
+            let request = request(authorization);
+            let mut socket = MockSocket::new(request.as_bytes());
+            let mut buf = [0; 1024];
+
+            block_on(asynch::handle_connection::<_, _, 16>(
+                &mut socket,
+                &mut buf,
+                &handler,
+            ))
+            .unwrap();
+
+            assert!(String::from_utf8(socket.output)
+                .unwrap()
+                .starts_with(status));
+        }
+    }
+}
@@ -0,0 +1,1142 @@
+use core::fmt::{self, Debug};
+
+use crate::http::server::{Connection, Handler};
+use crate::http::{Headers, Method, Query};
+use crate::io::{ErrorType, Read, Write};
+use crate::log::svc_log;
+
+use super::super::codec::{serialize_response_head, ParseError, RequestHead};
+use super::super::connection::{
+    drain, finish_body, has_token, is_chunked, is_keep_alive, read_head, write_body, Body,
+    BufferedSocket, Framing, HeadBuf, Outgoing,
+};
+
+pub use super::super::connection::ConnectionError;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ServerError<E, H> {
+    Connection(ConnectionError<E>),
+    Handler(H),
+}
+
+impl<E, H> From<ConnectionError<E>> for ServerError<E, H> {
+    fn from(e: ConnectionError<E>) -> Self {
+        Self::Connection(e)
+    }
+}
+
+impl<E: Debug, H: Debug> fmt::Display for ServerError<E, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: Debug, H: Debug> core::error::Error for ServerError<E, H> {}
+
+/// A source of incoming connections, e.g. a listening TCP socket.
+pub trait Acceptor {
+    type Error: Debug;
+
+    type Socket: Read + Write;
+
+    fn accept(&self) -> Result<Self::Socket, Self::Error>;
+}
+
+/// The request line and headers of the request being served by a [`ServerConnection`].
+pub struct RequestHeaders<'b> {
+    head: HeadBuf<'b>,
+    method: Method,
+}
+
+impl RequestHeaders<'_> {
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.head.headers()
+    }
+}
+
+impl Query for RequestHeaders<'_> {
+    fn uri(&self) -> &'_ str {
+        self.head.start_line().split(' ').nth(1).unwrap_or("")
+    }
+
+    fn method(&self) -> Method {
+        self.method
+    }
+}
+
+impl Headers for RequestHeaders<'_> {
+    fn header(&self, name: &str) -> Option<&'_ str> {
+        self.head.header(name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Idle,
+    Request,
+    Response,
+}
+
+struct RequestInfo {
+    method: Method,
+    http11: bool,
+    keep_alive: bool,
+    content_len: Option<u64>,
+    chunked: Option<bool>,
+    expect_continue: bool,
+}
+
+/// A portable HTTP/1.1 server [`Connection`] over any `embedded_io` stream.
+///
+/// Use [`handle_connection`] or [`run`] to serve requests with a [`Handler`].
+///
+/// When the response headers specify neither `Content-Length` nor `Transfer-Encoding`,
+/// the response body is sent chunked (or, for HTTP/1.0 clients, delimited by closing
+/// the connection).
+///
+/// The provided buffer is split in two halves: one stores the request head as well as the
+/// serialized response head, and the other buffers data received from the stream.
+pub struct ServerConnection<'b, T, const N: usize = 64> {
+    headers: RequestHeaders<'b>,
+    body: Body<'b, T>,
+    state: State,
+    outgoing: Outgoing,
+    http11: bool,
+    head_request: bool,
+    keep_alive: bool,
+    raw: bool,
+}
+
+impl<'b, T, const N: usize> ServerConnection<'b, T, N> {
+    pub fn new(socket: T, buf: &'b mut [u8]) -> Self {
+        let (head_buf, socket_buf) = buf.split_at_mut(buf.len() / 2);
+
+        Self::from_parts(socket, head_buf, socket_buf)
+    }
+
+    fn from_parts(socket: T, head_buf: &'b mut [u8], socket_buf: &'b mut [u8]) -> Self {
+        Self {
+            headers: RequestHeaders {
+                head: HeadBuf::new(head_buf),
+                method: Method::Get,
+            },
+            body: Body::new(BufferedSocket::new(socket, socket_buf)),
+            state: State::Idle,
+            outgoing: Outgoing::Empty,
+            http11: true,
+            head_request: false,
+            keep_alive: true,
+            raw: false,
+        }
+    }
+
+    /// Returns `true` if the connection can serve further requests.
+    pub fn is_keep_alive(&self) -> bool {
+        self.keep_alive && !self.raw
+    }
+
+    pub fn release(self) -> T {
+        self.body.release()
+    }
+
+    fn into_parts(self) -> (T, &'b mut [u8], &'b mut [u8]) {
+        let (socket, socket_buf) = self.body.into_socket().into_parts();
+
+        (socket, self.headers.head.release(), socket_buf)
+    }
+
+    fn parse_request(buf: &[u8]) -> Result<(RequestInfo, usize), ParseError> {
+        let (head, len) = RequestHead::<N>::parse(buf)?;
+
+        let info = RequestInfo {
+            method: head.method,
+            http11: head.http11,
+            keep_alive: is_keep_alive(head.http11, head.headers.connection()),
+            content_len: head.headers.content_len(),
+            chunked: head.headers.transfer_encoding().map(is_chunked),
+            expect_continue: head.http11
+                && head
+                    .headers
+                    .get("Expect")
+                    .map(|expect| expect.eq_ignore_ascii_case("100-continue"))
+                    .unwrap_or(false),
+        };
+
+        Ok((info, len))
+    }
+
+    /// Sets up the connection for the received request and returns whether
+    /// `100 Continue` should be sent to the client.
+    fn start_request<E>(&mut self, info: RequestInfo) -> Result<bool, ConnectionError<E>> {
+        self.keep_alive = info.keep_alive;
+
+        let framing = match (info.chunked, info.content_len) {
+            (Some(true), None) => Framing::chunked(),
+            // Only chunked requests can be read, and a request with both
+            // `Transfer-Encoding` and `Content-Length` is a smuggling attempt
+            (Some(_), _) => return Err(ConnectionError::InvalidBody),
+            (None, Some(content_len)) => Framing::Fixed(content_len),
+            (None, None) => Framing::Empty,
+        };
+
+        let expect_continue = info.expect_continue && !matches!(framing, Framing::Empty);
+
+        self.headers.method = info.method;
+        self.body.set_framing(framing);
+        self.state = State::Request;
+        self.outgoing = Outgoing::Empty;
+        self.http11 = info.http11;
+        self.head_request = info.method == Method::Head;
+
+        Ok(expect_continue)
+    }
+
+    fn prepare_response<E>(
+        &mut self,
+        status: u16,
+        message: Option<&str>,
+        headers: &[(&str, &str)],
+    ) -> Result<usize, ConnectionError<E>> {
+        if self.state != State::Request {
+            return Err(ConnectionError::InvalidState);
+        }
+
+        let mut all = super::super::Headers::<N>::new();
+        let mut outgoing = None;
+        let mut connection = false;
+
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("Transfer-Encoding") && !self.http11 {
+                // HTTP/1.0 clients do not understand `Transfer-Encoding`, so the body
+                // is sent as-is and delimited by closing the connection
+                outgoing = Some(Outgoing::Close);
+                continue;
+            }
+
+            all.try_add(name, value)?;
+
+            if name.eq_ignore_ascii_case("Content-Length") {
+                outgoing = Some(Outgoing::Fixed(value.parse().map_err(|_| {
+                    ConnectionError::Serialize(super::super::codec::SerializeError::InvalidHeader)
+                })?));
+            } else if name.eq_ignore_ascii_case("Transfer-Encoding") {
+                outgoing = Some(if is_chunked(value) {
+                    Outgoing::Chunked
+                } else {
+                    Outgoing::Close
+                });
+            } else if name.eq_ignore_ascii_case("Connection") {
+                connection = true;
+
+                if has_token(Some(value), "close") {
+                    self.keep_alive = false;
+                }
+            }
+        }
+
+        let outgoing = if self.head_request {
+            Outgoing::Discard
+        } else if matches!(status, 100..=199 | 204 | 304) {
+            Outgoing::Empty
+        } else if let Some(outgoing) = outgoing {
+            outgoing
+        } else if self.http11 {
+            all.try_add("Transfer-Encoding", "chunked")?;
+            Outgoing::Chunked
+        } else {
+            Outgoing::Close
+        };
+
+        if status == 101 || outgoing == Outgoing::Close {
+            self.keep_alive = false;
+        }
+
+        if !connection {
+            if !self.keep_alive && status != 101 {
+                all.try_add("Connection", "close")?;
+            } else if self.keep_alive && !self.http11 {
+                all.try_add("Connection", "keep-alive")?;
+            }
+        }
+
+        let len =
+            serialize_response_head(status, message, all.as_slice(), self.headers.head.spare())?;
+
+        self.state = State::Response;
+        self.outgoing = outgoing;
+
+        Ok(len)
+    }
+
+    /// Prepares answering a request which could not be read with an error, and closing the connection.
+    fn fail_request(&mut self) {
+        // Nothing is known of the failed request, so the state of the previous one must not be used
+        self.state = State::Request;
+        self.outgoing = Outgoing::Empty;
+        self.http11 = true;
+        self.head_request = false;
+        self.keep_alive = false;
+    }
+
+    fn error_response<E>(e: &ConnectionError<E>) -> Option<(u16, &'static str)> {
+        match e {
+            ConnectionError::HeadTooLarge
+            | ConnectionError::TooManyHeaders
+            | ConnectionError::Parse(ParseError::TooManyHeaders) => {
+                Some((431, "Request Header Fields Too Large"))
+            }
+            ConnectionError::Parse(_) | ConnectionError::InvalidBody => Some((400, "Bad Request")),
+            _ => None,
+        }
+    }
+}
+
+impl<T, const N: usize> ErrorType for ServerConnection<'_, T, N>
+where
+    T: ErrorType,
+{
+    type Error = ConnectionError<T::Error>;
+}
+
+impl<T, const N: usize> Query for ServerConnection<'_, T, N> {
+    fn uri(&self) -> &'_ str {
+        self.headers.uri()
+    }
+
+    fn method(&self) -> Method {
+        self.headers.method()
+    }
+}
+
+impl<T, const N: usize> Headers for ServerConnection<'_, T, N> {
+    fn header(&self, name: &str) -> Option<&'_ str> {
+        self.headers.header(name)
+    }
+}
+
+impl<T, const N: usize> Read for ServerConnection<'_, T, N>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.state == State::Idle {
+            return Err(ConnectionError::InvalidState);
+        }
+
+        self.body.read(buf)
+    }
+}
+
+impl<T, const N: usize> Write for ServerConnection<'_, T, N>
+where
+    T: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if self.state != State::Response {
+            return Err(ConnectionError::InvalidState);
+        }
+
+        write_body(self.body.socket(), &mut self.outgoing, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.body.socket().flush().map_err(ConnectionError::Io)
+    }
+}
+
+impl<'b, T, const N: usize> Connection for ServerConnection<'b, T, N>
+where
+    T: Read + Write,
+{
+    type Headers = RequestHeaders<'b>;
+
+    type Read = Body<'b, T>;
+
+    type RawConnectionError = T::Error;
+
+    type RawConnection = BufferedSocket<'b, T>;
+
+    fn split(&mut self) -> (&Self::Headers, &mut Self::Read) {
+        (&self.headers, &mut self.body)
+    }
+
+    fn initiate_response<'a>(
+        &'a mut self,
+        status: u16,
+        message: Option<&'a str>,
+        headers: &'a [(&'a str, &'a str)],
+    ) -> Result<(), Self::Error> {
+        let len = self.prepare_response(status, message, headers)?;
+
+        let ServerConnection { headers, body, .. } = self;
+
+        body.socket()
+            .write_all(&headers.head.spare()[..len])
+            .map_err(ConnectionError::Io)
+    }
+
+    fn is_response_initiated(&self) -> bool {
+        self.state == State::Response
+    }
+
+    fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error> {
+        if self.state == State::Idle {
+            return Err(ConnectionError::InvalidState);
+        }
+
+        self.raw = true;
+
+        Ok(self.body.socket())
+    }
+}
+
+/// Serves the requests received on `socket` with `handler`, until the client closes the
+/// connection or either side asks for it to be closed.
+///
+/// If the handler returns without initiating a response, `200 OK` with an empty body is sent.
+/// If it fails before initiating a response, `500 Internal Server Error` is sent. Malformed
+/// requests are answered with `400 Bad Request` or `431 Request Header Fields Too Large`.
+/// After a failure the connection is closed.
+pub fn handle_connection<'b, T, H, const N: usize>(
+    socket: T,
+    buf: &'b mut [u8],
+    handler: &H,
+) -> Result<(), ServerError<T::Error, H::Error>>
+where
+    T: Read + Write,
+    H: Handler<ServerConnection<'b, T, N>>,
+{
+    serve(&mut ServerConnection::new(socket, buf), handler)
+}
+
+/// Accepts connections from `acceptor` and serves them one after another with `handler`.
+///
+/// Errors while serving a connection are logged and the connection is dropped.
+/// Returns only if accepting a connection fails.
+pub fn run<'b, A, H, const N: usize>(
+    acceptor: &A,
+    buf: &'b mut [u8],
+    handler: &H,
+) -> Result<(), A::Error>
+where
+    A: Acceptor,
+    H: Handler<ServerConnection<'b, A::Socket, N>>,
+{
+    let (mut head_buf, mut socket_buf) = buf.split_at_mut(buf.len() / 2);
+
+    loop {
+        let socket = acceptor.accept()?;
+
+        let mut connection = ServerConnection::from_parts(socket, head_buf, socket_buf);
+
+        if serve(&mut connection, handler).is_err() {
+            svc_log!(warn, "Error while serving an HTTP connection");
+        }
+
+        (_, head_buf, socket_buf) = connection.into_parts();
+    }
+}
+
+fn serve<'b, T, H, const N: usize>(
+    connection: &mut ServerConnection<'b, T, N>,
+    handler: &H,
+) -> Result<(), ServerError<T::Error, H::Error>>
+where
+    T: Read + Write,
+    H: Handler<ServerConnection<'b, T, N>>,
+{
+    loop {
+        match read_request(connection) {
+            Ok(()) => (),
+            Err(ConnectionError::ConnectionClosed) => return Ok(()),
+            Err(e) => {
+                if let Some((status, message)) = ServerConnection::<T, N>::error_response(&e) {
+                    connection.fail_request();
+
+                    let _ = complete(connection, status, message);
+                }
+
+                return Err(e.into());
+            }
+        }
+
+        if let Err(e) = handler.handle(connection) {
+            if connection.state == State::Request {
+                connection.keep_alive = false;
+
+                let _ = complete(connection, 500, "Internal Server Error");
+            }
+
+            return Err(ServerError::Handler(e));
+        }
+
+        complete(connection, 200, "OK")?;
+
+        if !connection.is_keep_alive() {
+            return Ok(());
+        }
+    }
+}
+
+fn read_request<T, const N: usize>(
+    connection: &mut ServerConnection<'_, T, N>,
+) -> Result<(), ConnectionError<T::Error>>
+where
+    T: Read + Write,
+{
+    connection.state = State::Idle;
+    connection.raw = false;
+
+    let info = read_head(
+        connection.body.socket(),
+        &mut connection.headers.head,
+        ServerConnection::<T, N>::parse_request,
+    )?;
+
+    if connection.start_request(info)? {
+        connection
+            .body
+            .socket()
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .map_err(ConnectionError::Io)?;
+    }
+
+    Ok(())
+}
+
+fn complete<T, const N: usize>(
+    connection: &mut ServerConnection<'_, T, N>,
+    status: u16,
+    message: &str,
+) -> Result<(), ConnectionError<T::Error>>
+where
+    T: Read + Write,
+{
+    if connection.raw {
+        return connection.flush();
+    }
+
+    if connection.state == State::Request {
+        connection.initiate_response(status, Some(message), &[("Content-Length", "0")])?;
+    }
+
+    finish_body(connection.body.socket(), &mut connection.outgoing)?;
+
+    if connection.keep_alive {
+        drain(&mut connection.body)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+pub mod tcp {
+    //! Adapters for serving requests with `std::net` sockets.
+
+    use crate::io::{asynch, ErrorType, Read, Write};
+
+    use super::Acceptor;
+
+    /// An `embedded_io` adapter for [`std::net::TcpStream`].
+    ///
+    /// The async `Read` and `Write` implementations block the executor, so they are only suited
+    /// for tests and tools running on the host.
+    pub struct TcpSocket(pub std::net::TcpStream);
+
+    impl ErrorType for TcpSocket {
+        type Error = std::io::Error;
+    }
+
+    impl Read for TcpSocket {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            std::io::Read::read(&mut self.0, buf)
+        }
+    }
+
+    impl Write for TcpSocket {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            std::io::Write::write(&mut self.0, buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            std::io::Write::flush(&mut self.0)
+        }
+    }
+
+    impl asynch::Read for TcpSocket {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            std::io::Read::read(&mut self.0, buf)
+        }
+    }
+
+    impl asynch::Write for TcpSocket {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            std::io::Write::write(&mut self.0, buf)
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            std::io::Write::flush(&mut self.0)
+        }
+    }
+
+    impl Acceptor for std::net::TcpListener {
+        type Error = std::io::Error;
+
+        type Socket = TcpSocket;
+
+        fn accept(&self) -> Result<Self::Socket, Self::Error> {
+            let (socket, _) = std::net::TcpListener::accept(self)?;
+
+            Ok(TcpSocket(socket))
+        }
+    }
+
+    impl super::asynch::Acceptor for std::net::TcpListener {
+        type Error = std::io::Error;
+
+        type Socket = TcpSocket;
+
+        async fn accept(&self) -> Result<Self::Socket, Self::Error> {
+            let (socket, _) = std::net::TcpListener::accept(self)?;
+
+            Ok(TcpSocket(socket))
+        }
+    }
+}
+
+pub mod asynch {
+    use core::fmt::Debug;
+
+    use crate::http::server::asynch::{Connection, Handler};
+    use crate::io::asynch::{Read, Write};
+    use crate::log::svc_log;
+
+    use super::super::super::connection::asynch::{drain, finish_body, read_head, write_body};
+    use super::super::super::connection::{Body, BufferedSocket};
+    use super::{RequestHeaders, ServerConnection, State};
+
+    pub use super::{ConnectionError, ServerError};
+
+    /// A source of incoming connections, e.g. a listening TCP socket.
+    pub trait Acceptor {
+        type Error: Debug;
+
+        type Socket: Read + Write;
+
+        async fn accept(&self) -> Result<Self::Socket, Self::Error>;
+    }
+
+    impl<T, const N: usize> Read for ServerConnection<'_, T, N>
+    where
+        T: Read,
+    {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if self.state == State::Idle {
+                return Err(ConnectionError::InvalidState);
+            }
+
+            self.body.read(buf).await
+        }
+    }
+
+    impl<T, const N: usize> Write for ServerConnection<'_, T, N>
+    where
+        T: Write,
+    {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            if self.state != State::Response {
+                return Err(ConnectionError::InvalidState);
+            }
+
+            write_body(self.body.socket(), &mut self.outgoing, buf).await
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.body
+                .socket()
+                .flush()
+                .await
+                .map_err(ConnectionError::Io)
+        }
+    }
+
+    impl<'b, T, const N: usize> Connection for ServerConnection<'b, T, N>
+    where
+        T: Read + Write,
+    {
+        type Headers = RequestHeaders<'b>;
+
+        type Read = Body<'b, T>;
+
+        type RawConnectionError = T::Error;
+
+        type RawConnection = BufferedSocket<'b, T>;
+
+        fn split(&mut self) -> (&Self::Headers, &mut Self::Read) {
+            (&self.headers, &mut self.body)
+        }
+
+        async fn initiate_response(
+            &mut self,
+            status: u16,
+            message: Option<&str>,
+            headers: &[(&str, &str)],
+        ) -> Result<(), Self::Error> {
+            let len = self.prepare_response(status, message, headers)?;
+
+            let ServerConnection { headers, body, .. } = self;
+
+            body.socket()
+                .write_all(&headers.head.spare()[..len])
+                .await
+                .map_err(ConnectionError::Io)
+        }
+
+        fn is_response_initiated(&self) -> bool {
+            self.state == State::Response
+        }
+
+        fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error> {
+            if self.state == State::Idle {
+                return Err(ConnectionError::InvalidState);
+            }
+
+            self.raw = true;
+
+            Ok(self.body.socket())
+        }
+    }
+
+    /// Serves the requests received on `socket` with `handler`.
+    ///
+    /// See [`super::handle_connection`] for details.
+    pub async fn handle_connection<'b, T, H, const N: usize>(
+        socket: T,
+        buf: &'b mut [u8],
+        handler: &H,
+    ) -> Result<(), ServerError<T::Error, H::Error>>
+    where
+        T: Read + Write,
+        H: Handler<ServerConnection<'b, T, N>>,
+    {
+        serve(&mut ServerConnection::new(socket, buf), handler).await
+    }
+
+    /// Accepts connections from `acceptor` and serves them one after another with `handler`.
+    ///
+    /// See [`super::run`] for details.
+    pub async fn run<'b, A, H, const N: usize>(
+        acceptor: &A,
+        buf: &'b mut [u8],
+        handler: &H,
+    ) -> Result<(), A::Error>
+    where
+        A: Acceptor,
+        H: Handler<ServerConnection<'b, A::Socket, N>>,
+    {
+        let (mut head_buf, mut socket_buf) = buf.split_at_mut(buf.len() / 2);
+
+        loop {
+            let socket = acceptor.accept().await?;
+
+            let mut connection = ServerConnection::from_parts(socket, head_buf, socket_buf);
+
+            if serve(&mut connection, handler).await.is_err() {
+                svc_log!(warn, "Error while serving an HTTP connection");
+            }
+
+            (_, head_buf, socket_buf) = connection.into_parts();
+        }
+    }
+
+    async fn serve<'b, T, H, const N: usize>(
+        connection: &mut ServerConnection<'b, T, N>,
+        handler: &H,
+    ) -> Result<(), ServerError<T::Error, H::Error>>
+    where
+        T: Read + Write,
+        H: Handler<ServerConnection<'b, T, N>>,
+    {
+        loop {
+            match read_request(connection).await {
+                Ok(()) => (),
+                Err(ConnectionError::ConnectionClosed) => return Ok(()),
+                Err(e) => {
+                    if let Some((status, message)) = ServerConnection::<T, N>::error_response(&e) {
+                        connection.fail_request();
+
+                        let _ = complete(connection, status, message).await;
+                    }
+
+                    return Err(e.into());
+                }
+            }
+
+            if let Err(e) = handler.handle(connection).await {
+                if connection.state == State::Request {
+                    connection.keep_alive = false;
+
+                    let _ = complete(connection, 500, "Internal Server Error").await;
+                }
+
+                return Err(ServerError::Handler(e));
+            }
+
+            complete(connection, 200, "OK").await?;
+
+            if !connection.is_keep_alive() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn read_request<T, const N: usize>(
+        connection: &mut ServerConnection<'_, T, N>,
+    ) -> Result<(), ConnectionError<T::Error>>
+    where
+        T: Read + Write,
+    {
+        connection.state = State::Idle;
+        connection.raw = false;
+
+        let info = read_head(
+            connection.body.socket(),
+            &mut connection.headers.head,
+            ServerConnection::<T, N>::parse_request,
+        )
+        .await?;
+
+        if connection.start_request(info)? {
+            connection
+                .body
+                .socket()
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .await
+                .map_err(ConnectionError::Io)?;
+        }
+
+        Ok(())
+    }
+
+    async fn complete<T, const N: usize>(
+        connection: &mut ServerConnection<'_, T, N>,
+        status: u16,
+        message: &str,
+    ) -> Result<(), ConnectionError<T::Error>>
+    where
+        T: Read + Write,
+    {
+        if connection.raw {
+            return connection.flush().await;
+        }
+
+        if connection.state == State::Request {
+            connection
+                .initiate_response(status, Some(message), &[("Content-Length", "0")])
+                .await?;
+        }
+
+        finish_body(connection.body.socket(), &mut connection.outgoing).await?;
+
+        if connection.keep_alive {
+            drain(&mut connection.body).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::fmt::Write as _;
+
+    use std::string::String;
+
+    use crate::http::server::{Connection, Handler};
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{handle_connection, ServerConnection, ServerError};
+
+    /// Responds with an explicit `Transfer-Encoding: chunked` header.
+    struct Chunked;
+
+    impl<C> Handler<C> for Chunked
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            connection.initiate_response(200, Some("OK"), &[("Transfer-Encoding", "chunked")])?;
+            connection.write_all(b"hello")
+        }
+    }
+
+    /// Does not initiate a response.
+    struct Empty;
+
+    impl<C> Handler<C> for Empty
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, _connection: &mut C) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    impl<C> crate::http::server::asynch::Handler<C> for Empty
+    where
+        C: crate::http::server::asynch::Connection,
+    {
+        type Error = C::Error;
+
+        async fn handle(&self, _connection: &mut C) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// Fails without initiating a response.
+    struct Fail;
+
+    impl<C> Handler<C> for Fail
+    where
+        C: Connection,
+    {
+        type Error = &'static str;
+
+        fn handle(&self, _connection: &mut C) -> Result<(), Self::Error> {
+            Err("failed")
+        }
+    }
+
+    /// Responds with the body of the request.
+    struct Echo;
+
+    impl<C> Handler<C> for Echo
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let mut body = [0; 64];
+            let mut len = 0;
+
+            loop {
+                let read = connection.read(&mut body[len..])?;
+                if read == 0 {
+                    break;
+                }
+
+                len += read;
+            }
+
+            let mut content_len = heapless::String::<8>::new();
+            write!(content_len, "{len}").unwrap();
+
+            connection.initiate_response(200, Some("OK"), &[("Content-Length", &content_len)])?;
+            connection.write_all(&body[..len])
+        }
+    }
+
+    fn serve_with<'s, H>(handler: &H, request: &'s str) -> (String, Result<(), ()>)
+    where
+        H: for<'a, 'b> Handler<ServerConnection<'b, &'a mut MockSocket<'s>, 16>>,
+    {
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 512];
+
+        let result = handle_connection::<_, _, 16>(&mut socket, &mut buf, handler).map_err(|_| ());
+
+        (String::from_utf8(socket.output).unwrap(), result)
+    }
+
+    fn serve(request: &str) -> String {
+        let (response, result) = serve_with(&Chunked, request);
+        result.unwrap();
+
+        response
+    }
+
+    #[test]
+    fn transfer_encoding_http11() {
+        assert_eq!(
+            serve("GET / HTTP/1.1\r\nConnection: close\r\n\r\n"),
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+             5\r\nhello\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn transfer_encoding_http10() {
+        assert_eq!(
+            serve("GET / HTTP/1.0\r\n\r\n"),
+            "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello"
+        );
+    }
+
+    #[test]
+    fn default_response() {
+        assert_eq!(
+            serve_with(&Empty, "GET / HTTP/1.1\r\nConnection: close\r\n\r\n"),
+            (
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".into(),
+                Ok(())
+            )
+        );
+    }
+
+    #[test]
+    fn handler_error() {
+        let mut socket = MockSocket::new(b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n");
+        let mut buf = [0; 512];
+
+        assert!(matches!(
+            handle_connection::<_, _, 16>(&mut socket, &mut buf, &Fail),
+            Err(ServerError::Handler("failed"))
+        ));
+
+        // The connection is closed after the failure
+        assert_eq!(
+            socket.output,
+            b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let bad_request =
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let too_large =
+            "HTTP/1.1 431 Request Header Fields Too Large\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+        let mut too_many_headers = String::from("GET / HTTP/1.1\r\n");
+        for index in 0..17 {
+            too_many_headers.push_str(&std::format!("X-{index}: a\r\n"));
+        }
+        too_many_headers.push_str("\r\n");
+
+        let long_header = std::format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", "a".repeat(512));
+
+        for (request, response) in [
+            ("GARBAGE\r\n\r\n", bad_request),
+            (
+                "GET / HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n",
+                bad_request,
+            ),
+            (
+                "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\n\r\n",
+                bad_request,
+            ),
+            (too_many_headers.as_str(), too_large),
+            (long_header.as_str(), too_large),
+        ] {
+            assert_eq!(
+                serve_with(&Empty, request),
+                (response.into(), Err(())),
+                "{request}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_request_after_head() {
+        let (response, result) = serve_with(
+            &Empty,
+            "HEAD / HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGARBAGE\r\n\r\n",
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            response,
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: keep-alive\r\n\r\n\
+             HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn expect_continue() {
+        assert_eq!(
+            serve_with(
+                &Echo,
+                "POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\
+                 Connection: close\r\n\r\nhello"
+            ),
+            (
+                "HTTP/1.1 100 Continue\r\n\r\n\
+                 HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello"
+                    .into(),
+                Ok(())
+            )
+        );
+
+        // Without a body, nothing is awaited
+        assert_eq!(
+            serve_with(
+                &Echo,
+                "GET / HTTP/1.1\r\nExpect: 100-continue\r\nConnection: close\r\n\r\n"
+            ),
+            (
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".into(),
+                Ok(())
+            )
+        );
+    }
+
+    #[test]
+    fn pipelining() {
+        assert_eq!(
+            serve_with(
+                &Echo,
+                "POST / HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc\
+                 POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nde\r\n0\r\n\r\n\
+                 GET / HTTP/1.1\r\nConnection: close\r\n\r\n"
+            ),
+            (
+                "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabc\
+                 HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nde\
+                 HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .into(),
+                Ok(())
+            )
+        );
+
+        // The body of a request which the handler did not read is skipped
+        let (response, result) = serve_with(
+            &Empty,
+            "POST / HTTP/1.1\r\nContent-Length: 3\r\n\r\nabcGET / HTTP/1.1\r\n\r\n",
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            response,
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn pipelining_async() {
+        let mut socket = MockSocket::new(
+            b"POST / HTTP/1.1\r\nContent-Length: 3\r\n\r\nabcGET / HTTP/1.1\r\n\r\nGARBAGE\r\n\r\n",
+        );
+        let mut buf = [0; 512];
+
+        assert!(block_on(super::asynch::handle_connection::<_, _, 16>(
+            &mut socket,
+            &mut buf,
+            &Empty
+        ))
+        .is_err());
+
+        assert_eq!(
+            core::str::from_utf8(&socket.output).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n\
+             HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+    }
+}
@@ -0,0 +1,803 @@
+use core::fmt::{self, Debug, Write as _};
+
+use crate::http::server::{Connection, Handler, Headers, Method, Middleware, Query};
+use crate::io::{ErrorType, Read, Write};
+
+/// The maximum number of headers of a response, including the CORS headers added by [`CorsConnection`].
+pub const MAX_RESPONSE_HEADERS: usize = 32;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CorsError<E> {
+    Handler(E),
+    /// The headers of a response left no room for the CORS headers, see [`MAX_RESPONSE_HEADERS`].
+    TooManyHeaders,
+}
+
+impl<E: Debug> fmt::Display for CorsError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: Debug> core::error::Error for CorsError<E> {}
+
+/// The CORS policy enforced by a [`CorsMiddleware`].
+///
+/// An origin of `*` allows any origin, and a header of `*` allows any header requested by a preflight.
+/// An origin of `*` cannot be combined with `allow_credentials`, the origins which may send
+/// credentials have to be listed explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CorsConfiguration<'a> {
+    pub allowed_origins: &'a [&'a str],
+    pub allowed_methods: &'a [Method],
+    pub allowed_headers: &'a [&'a str],
+    pub exposed_headers: &'a [&'a str],
+    /// For how many seconds the client may cache the result of a preflight request.
+    pub max_age: Option<u32>,
+    pub allow_credentials: bool,
+}
+
+impl<'a> CorsConfiguration<'a> {
+    pub const fn new() -> Self {
+        Self {
+            allowed_origins: &["*"],
+            allowed_methods: &[
+                Method::Get,
+                Method::Head,
+                Method::Post,
+                Method::Put,
+                Method::Patch,
+                Method::Delete,
+            ],
+            allowed_headers: &[],
+            exposed_headers: &[],
+            max_age: None,
+            allow_credentials: false,
+        }
+    }
+
+    pub const fn allowed_origins(mut self, allowed_origins: &'a [&'a str]) -> Self {
+        self.allowed_origins = allowed_origins;
+        self
+    }
+
+    pub const fn allowed_methods(mut self, allowed_methods: &'a [Method]) -> Self {
+        self.allowed_methods = allowed_methods;
+        self
+    }
+
+    pub const fn allowed_headers(mut self, allowed_headers: &'a [&'a str]) -> Self {
+        self.allowed_headers = allowed_headers;
+        self
+    }
+
+    pub const fn exposed_headers(mut self, exposed_headers: &'a [&'a str]) -> Self {
+        self.exposed_headers = exposed_headers;
+        self
+    }
+
+    pub const fn max_age(mut self, max_age: u32) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub const fn allow_credentials(mut self, allow_credentials: bool) -> Self {
+        self.allow_credentials = allow_credentials;
+        self
+    }
+}
+
+impl Default for CorsConfiguration<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A middleware implementing Cross-Origin Resource Sharing.
+///
+/// Preflight requests are answered by the middleware itself and never reach the wrapped handler.
+/// Other requests with an allowed `Origin` are passed to the handler over a [`CorsConnection`],
+/// which adds the CORS headers to the response. Therefore, the handler has to be generic over
+/// the connection type, as e.g. handlers implemented for any `C: Connection` are.
+///
+/// `N` is the size of the buffers holding the values of the CORS headers.
+pub struct CorsMiddleware<'a, const N: usize = 128> {
+    config: CorsConfiguration<'a>,
+    allow_methods: heapless::String<N>,
+    allow_headers: heapless::String<N>,
+    expose_headers: heapless::String<N>,
+    max_age: heapless::String<10>,
+}
+
+impl<'a, const N: usize> CorsMiddleware<'a, N> {
+    /// # Panics
+    ///
+    /// If the lists of allowed methods, allowed headers or exposed headers do not fit into `N` bytes,
+    /// or if credentials are allowed for the origin `*`.
+    pub fn new(config: CorsConfiguration<'a>) -> Self {
+        if config.allow_credentials && config.allowed_origins.contains(&"*") {
+            panic!("credentials allowed for any origin");
+        }
+
+        let mut this = Self {
+            config,
+            allow_methods: heapless::String::new(),
+            allow_headers: heapless::String::new(),
+            expose_headers: heapless::String::new(),
+            max_age: heapless::String::new(),
+        };
+
+        join(
+            config.allowed_methods.iter().map(Method::as_str),
+            &mut this.allow_methods,
+        )
+        .expect("allowed methods too long");
+        join(
+            config.allowed_headers.iter().copied(),
+            &mut this.allow_headers,
+        )
+        .expect("allowed headers too long");
+        join(
+            config.exposed_headers.iter().copied(),
+            &mut this.expose_headers,
+        )
+        .expect("exposed headers too long");
+
+        if let Some(max_age) = config.max_age {
+            // Cannot fail, a `u32` has at most 10 digits
+            let _ = write!(&mut this.max_age, "{max_age}");
+        }
+
+        this
+    }
+
+    /// Returns the value of the `Access-Control-Allow-Origin` header for `origin`, or `None` if it is not allowed.
+    fn allow_origin(&self, origin: &str) -> Option<heapless::String<N>> {
+        if self.config.allowed_origins.contains(&"*") {
+            return Some("*".try_into().unwrap());
+        }
+
+        if self
+            .config
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+        {
+            origin.try_into().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value of the `Access-Control-Allow-Headers` header of a preflight response.
+    fn preflight_allow_headers(&self, requested: Option<&str>) -> heapless::String<N> {
+        if self.config.allowed_headers.contains(&"*") {
+            // With credentials, a literal `*` is not honored by the clients, hence echo the requested headers
+            requested
+                .and_then(|requested| requested.try_into().ok())
+                .unwrap_or_default()
+        } else {
+            self.allow_headers.clone()
+        }
+    }
+
+    /// Returns the CORS headers to be added to the response to a request from `allow_origin`,
+    /// or to the response to a preflight request if `allow_headers` is given.
+    fn headers<'s>(
+        &'s self,
+        allow_origin: &'s str,
+        allow_headers: Option<&'s str>,
+    ) -> heapless::Vec<(&'s str, &'s str), 6> {
+        let mut headers = heapless::Vec::new();
+
+        let mut push = |name, value: &'s str| {
+            if !value.is_empty() {
+                // Cannot fail, there are at most 6 headers
+                let _ = headers.push((name, value));
+            }
+        };
+
+        push("Access-Control-Allow-Origin", allow_origin);
+
+        if allow_origin != "*" {
+            push("Vary", "Origin");
+        }
+
+        if self.config.allow_credentials {
+            push("Access-Control-Allow-Credentials", "true");
+        }
+
+        match allow_headers {
+            Some(allow_headers) => {
+                push("Access-Control-Allow-Methods", self.allow_methods.as_str());
+                push("Access-Control-Allow-Headers", allow_headers);
+                push("Access-Control-Max-Age", self.max_age.as_str());
+            }
+            None => push(
+                "Access-Control-Expose-Headers",
+                self.expose_headers.as_str(),
+            ),
+        }
+
+        headers
+    }
+}
+
+impl<'m, C, H, const N: usize> Middleware<C, H> for CorsMiddleware<'m, N>
+where
+    C: Connection,
+    H: Handler<C> + for<'c> Handler<CorsConnection<'c, 'm, C, N>, Error = <H as Handler<C>>::Error>,
+    <H as Handler<C>>::Error: From<C::Error>,
+{
+    type Error = CorsError<<H as Handler<C>>::Error>;
+
+    fn handle(&self, connection: &mut C, handler: &H) -> Result<(), Self::Error> {
+        let preflight = is_preflight(connection);
+
+        match connection
+            .header("Origin")
+            .map(|origin| self.allow_origin(origin))
+        {
+            Some(Some(allow_origin)) if preflight => {
+                let allow_headers = self
+                    .preflight_allow_headers(connection.header("Access-Control-Request-Headers"));
+
+                let headers = self.headers(&allow_origin, Some(&allow_headers));
+
+                connection
+                    .initiate_response(204, Some("No Content"), &headers)
+                    .map_err(|e| CorsError::Handler(e.into()))
+            }
+            Some(None) if preflight => connection
+                .initiate_response(403, Some("Forbidden"), &[])
+                .map_err(|e| CorsError::Handler(e.into())),
+            Some(Some(allow_origin)) => {
+                let mut connection = CorsConnection::new(connection, self, allow_origin);
+
+                let result =
+                    Handler::<CorsConnection<'_, 'm, C, N>>::handle(handler, &mut connection);
+
+                connection.complete(result)
+            }
+            // Not a CORS request, or one from an origin which is not allowed and thus gets no CORS headers
+            _ => Handler::<C>::handle(handler, connection).map_err(CorsError::Handler),
+        }
+    }
+}
+
+fn is_preflight(connection: &(impl Query + Headers)) -> bool {
+    connection.method() == Method::Options
+        && connection.header("Access-Control-Request-Method").is_some()
+}
+
+fn join<'s>(items: impl Iterator<Item = &'s str>, out: &mut impl fmt::Write) -> fmt::Result {
+    for (index, item) in items.enumerate() {
+        if index > 0 {
+            out.write_str(", ")?;
+        }
+
+        out.write_str(item)?;
+    }
+
+    Ok(())
+}
+
+/// The connection passed by a [`CorsMiddleware`] to the wrapped handler.
+///
+/// Delegates to the wrapped connection, adding the CORS headers to the headers of the response.
+///
+/// If the headers of the response leave no room for the CORS headers, the response is not initiated,
+/// and the middleware fails with [`CorsError::TooManyHeaders`] once the handler returns.
+pub struct CorsConnection<'c, 'm, C, const N: usize = 128> {
+    connection: &'c mut C,
+    cors: &'c CorsMiddleware<'m, N>,
+    allow_origin: heapless::String<N>,
+    too_many_headers: bool,
+}
+
+impl<'c, 'm, C, const N: usize> CorsConnection<'c, 'm, C, N> {
+    fn new(
+        connection: &'c mut C,
+        cors: &'c CorsMiddleware<'m, N>,
+        allow_origin: heapless::String<N>,
+    ) -> Self {
+        Self {
+            connection,
+            cors,
+            allow_origin,
+            too_many_headers: false,
+        }
+    }
+
+    pub fn connection(&mut self) -> &mut C {
+        self.connection
+    }
+
+    /// Returns the result of the middleware, given the result of the handler.
+    fn complete<E>(&self, result: Result<(), E>) -> Result<(), CorsError<E>> {
+        if self.too_many_headers {
+            Err(CorsError::TooManyHeaders)
+        } else {
+            result.map_err(CorsError::Handler)
+        }
+    }
+}
+
+/// Returns the response headers of the handler followed by the CORS headers, or `None` if they do not fit.
+fn with_cors_headers<'a, const N: usize>(
+    headers: &'a [(&'a str, &'a str)],
+    cors: &'a CorsMiddleware<'_, N>,
+    allow_origin: &'a str,
+) -> Option<heapless::Vec<(&'a str, &'a str), MAX_RESPONSE_HEADERS>> {
+    let mut all = heapless::Vec::new();
+
+    all.extend_from_slice(headers).ok()?;
+
+    for header in cors.headers(allow_origin, None) {
+        all.push(header).ok()?;
+    }
+
+    Some(all)
+}
+
+impl<C, const N: usize> ErrorType for CorsConnection<'_, '_, C, N>
+where
+    C: ErrorType,
+{
+    type Error = C::Error;
+}
+
+impl<C, const N: usize> Read for CorsConnection<'_, '_, C, N>
+where
+    C: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.connection.read(buf)
+    }
+}
+
+impl<C, const N: usize> Write for CorsConnection<'_, '_, C, N>
+where
+    C: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.connection.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.connection.flush()
+    }
+}
+
+impl<C, const N: usize> crate::io::asynch::Read for CorsConnection<'_, '_, C, N>
+where
+    C: crate::io::asynch::Read,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.connection.read(buf).await
+    }
+}
+
+impl<C, const N: usize> crate::io::asynch::Write for CorsConnection<'_, '_, C, N>
+where
+    C: crate::io::asynch::Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.connection.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.connection.flush().await
+    }
+}
+
+impl<C, const N: usize> Headers for CorsConnection<'_, '_, C, N>
+where
+    C: Headers,
+{
+    fn header(&self, name: &str) -> Option<&'_ str> {
+        self.connection.header(name)
+    }
+}
+
+impl<C, const N: usize> Query for CorsConnection<'_, '_, C, N>
+where
+    C: Query,
+{
+    fn uri(&self) -> &'_ str {
+        self.connection.uri()
+    }
+
+    fn method(&self) -> Method {
+        self.connection.method()
+    }
+}
+
+impl<C, const N: usize> Connection for CorsConnection<'_, '_, C, N>
+where
+    C: Connection,
+{
+    type Headers = C::Headers;
+
+    type Read = C::Read;
+
+    type RawConnectionError = C::RawConnectionError;
+
+    type RawConnection = C::RawConnection;
+
+    fn split(&mut self) -> (&Self::Headers, &mut Self::Read) {
+        self.connection.split()
+    }
+
+    fn initiate_response<'a>(
+        &'a mut self,
+        status: u16,
+        message: Option<&'a str>,
+        headers: &'a [(&'a str, &'a str)],
+    ) -> Result<(), Self::Error> {
+        match with_cors_headers(headers, self.cors, &self.allow_origin) {
+            Some(headers) => self.connection.initiate_response(status, message, &headers),
+            None => {
+                self.too_many_headers = true;
+
+                Ok(())
+            }
+        }
+    }
+
+    fn is_response_initiated(&self) -> bool {
+        self.connection.is_response_initiated()
+    }
+
+    fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error> {
+        self.connection.raw_connection()
+    }
+}
+
+impl<C, const N: usize> crate::http::server::asynch::Connection for CorsConnection<'_, '_, C, N>
+where
+    C: crate::http::server::asynch::Connection,
+{
+    type Headers = C::Headers;
+
+    type Read = C::Read;
+
+    type RawConnectionError = C::RawConnectionError;
+
+    type RawConnection = C::RawConnection;
+
+    fn split(&mut self) -> (&Self::Headers, &mut Self::Read) {
+        self.connection.split()
+    }
+
+    async fn initiate_response(
+        &mut self,
+        status: u16,
+        message: Option<&str>,
+        headers: &[(&str, &str)],
+    ) -> Result<(), Self::Error> {
+        match with_cors_headers(headers, self.cors, &self.allow_origin) {
+            Some(headers) => {
+                self.connection
+                    .initiate_response(status, message, &headers)
+                    .await
+            }
+            None => {
+                self.too_many_headers = true;
+
+                Ok(())
+            }
+        }
+    }
+
+    fn is_response_initiated(&self) -> bool {
+        self.connection.is_response_initiated()
+    }
+
+    fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error> {
+        self.connection.raw_connection()
+    }
+}
+
+impl<'m, C, H, const N: usize> crate::http::server::asynch::Middleware<C, H>
+    for CorsMiddleware<'m, N>
+where
+    C: crate::http::server::asynch::Connection,
+    H: crate::http::server::asynch::Handler<C>
+        + for<'c> crate::http::server::asynch::Handler<
+            CorsConnection<'c, 'm, C, N>,
+            Error = <H as crate::http::server::asynch::Handler<C>>::Error,
+        >,
+    <H as crate::http::server::asynch::Handler<C>>::Error: From<C::Error>,
+{
+    type Error = CorsError<<H as crate::http::server::asynch::Handler<C>>::Error>;
+
+    async fn handle(&self, connection: &mut C, handler: &H) -> Result<(), Self::Error> {
+        use crate::http::server::asynch::Handler;
+
+        let preflight = is_preflight(connection);
+
+        match connection
+            .header("Origin")
+            .map(|origin| self.allow_origin(origin))
+        {
+            Some(Some(allow_origin)) if preflight => {
+                let allow_headers = self
+                    .preflight_allow_headers(connection.header("Access-Control-Request-Headers"));
+
+                let headers = self.headers(&allow_origin, Some(&allow_headers));
+
+                connection
+                    .initiate_response(204, Some("No Content"), &headers)
+                    .await
+                    .map_err(|e| CorsError::Handler(e.into()))
+            }
+            Some(None) if preflight => connection
+                .initiate_response(403, Some("Forbidden"), &[])
+                .await
+                .map_err(|e| CorsError::Handler(e.into())),
+            Some(Some(allow_origin)) => {
+                let mut connection = CorsConnection::new(connection, self, allow_origin);
+
+                let result =
+                    Handler::<CorsConnection<'_, 'm, C, N>>::handle(handler, &mut connection).await;
+
+                connection.complete(result)
+            }
+            _ => Handler::<C>::handle(handler, connection)
+                .await
+                .map_err(CorsError::Handler),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+
+    use crate::http::server::{CompositeHandler, Connection, Handler, Method};
+    use crate::utils::http::server::connection::{asynch, handle_connection, ServerConnection};
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{CorsConfiguration, CorsMiddleware, MAX_RESPONSE_HEADERS};
+
+    const HEADERS: &[(&str, &str)] = &[("X-Foo", "1"); MAX_RESPONSE_HEADERS];
+
+    /// Responds with the first `.0` of [`HEADERS`] and a body.
+    struct Respond(usize);
+
+    impl<C> Handler<C> for Respond
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            connection.initiate_response(200, Some("OK"), &HEADERS[..self.0])?;
+            connection.write_all(b"body")
+        }
+    }
+
+    impl<C> crate::http::server::asynch::Handler<C> for Respond
+    where
+        C: crate::http::server::asynch::Connection,
+    {
+        type Error = C::Error;
+
+        async fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            connection
+                .initiate_response(200, Some("OK"), &HEADERS[..self.0])
+                .await?;
+            connection.write_all(b"body").await
+        }
+    }
+
+    fn request(method: Method, headers: &[(&str, &str)]) -> String {
+        let mut request = String::from(method.as_str());
+        request.push_str(" / HTTP/1.1\r\nConnection: close\r\n");
+
+        for (name, value) in headers {
+            for part in [name, ": ", value, "\r\n"] {
+                request.push_str(part);
+            }
+        }
+
+        request.push_str("\r\n");
+        request
+    }
+
+    fn serve<H>(handler: &H, method: Method, headers: &[(&str, &str)]) -> String
+    where
+        H: for<'a, 'b, 's> Handler<ServerConnection<'b, &'a mut MockSocket<'s>, 40>>,
+    {
+        let request = request(method, headers);
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 2048];
+
+        let _ = handle_connection::<_, _, 40>(&mut socket, &mut buf, handler);
+
+        String::from_utf8(socket.output).unwrap()
+    }
+
+    fn cors(
+        config: CorsConfiguration<'static>,
+        headers: usize,
+    ) -> impl for<'a, 'b, 's> Handler<ServerConnection<'b, &'a mut MockSocket<'s>, 40>> {
+        CompositeHandler::new(CorsMiddleware::<128>::new(config), Respond(headers))
+    }
+
+    fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+        response
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .find(|(hname, _)| *hname == name)
+            .map(|(_, value)| value)
+    }
+
+    fn credentials() -> CorsConfiguration<'static> {
+        CorsConfiguration::new()
+            .allowed_origins(&["http://dash.local"])
+            .allowed_methods(&[Method::Get, Method::Post])
+            .allowed_headers(&["Content-Type", "X-Token"])
+            .exposed_headers(&["X-Foo"])
+            .max_age(600)
+            .allow_credentials(true)
+    }
+
+    const PREFLIGHT: &[(&str, &str)] = &[
+        ("Origin", "http://dash.local"),
+        ("Access-Control-Request-Method", "POST"),
+        ("Access-Control-Request-Headers", "content-type"),
+    ];
+
+    #[test]
+    fn any_origin() {
+        let handler = cors(CorsConfiguration::new(), 1);
+
+        let response = serve(&handler, Method::Get, &[]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(header(&response, "Access-Control-Allow-Origin"), None);
+
+        let response = serve(&handler, Method::Get, &[("Origin", "http://x")]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(header(&response, "X-Foo"), Some("1"));
+        assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("*"));
+        assert_eq!(header(&response, "Access-Control-Allow-Credentials"), None);
+        assert_eq!(header(&response, "Vary"), None);
+    }
+
+    #[test]
+    fn listed_origins() {
+        let handler = cors(credentials(), 1);
+
+        let response = serve(&handler, Method::Get, &[("Origin", "http://dash.local")]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Origin"),
+            Some("http://dash.local")
+        );
+        assert_eq!(header(&response, "Vary"), Some("Origin"));
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Credentials"),
+            Some("true")
+        );
+        assert_eq!(
+            header(&response, "Access-Control-Expose-Headers"),
+            Some("X-Foo")
+        );
+        assert_eq!(header(&response, "Access-Control-Max-Age"), None);
+
+        let response = serve(&handler, Method::Get, &[("Origin", "http://evil")]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(header(&response, "Access-Control-Allow-Origin"), None);
+    }
+
+    #[test]
+    fn preflight() {
+        let handler = cors(credentials(), 1);
+
+        let response = serve(&handler, Method::Options, PREFLIGHT);
+        assert!(response.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert_eq!(header(&response, "X-Foo"), None);
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Methods"),
+            Some("GET, POST")
+        );
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Headers"),
+            Some("Content-Type, X-Token")
+        );
+        assert_eq!(header(&response, "Access-Control-Max-Age"), Some("600"));
+
+        let handler = cors(credentials().allowed_headers(&["*"]), 1);
+        let response = serve(&handler, Method::Options, PREFLIGHT);
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Headers"),
+            Some("content-type")
+        );
+
+        let response = serve(
+            &handler,
+            Method::Options,
+            &[
+                ("Origin", "http://evil"),
+                ("Access-Control-Request-Method", "POST"),
+            ],
+        );
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+        // Not a preflight, hence handled by the handler
+        let response = serve(
+            &handler,
+            Method::Options,
+            &[("Origin", "http://dash.local")],
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn any_origin_with_credentials() {
+        CorsMiddleware::<128>::new(CorsConfiguration::new().allow_credentials(true));
+    }
+
+    #[test]
+    fn too_many_headers() {
+        let response = serve(
+            &cors(CorsConfiguration::new(), MAX_RESPONSE_HEADERS - 1),
+            Method::Get,
+            &[("Origin", "http://x")],
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("*"));
+
+        let response = serve(
+            &cors(credentials(), MAX_RESPONSE_HEADERS - 1),
+            Method::Get,
+            &[("Origin", "http://dash.local")],
+        );
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert!(!response.contains("body"));
+
+        // Without CORS headers to add, the headers fit
+        let response = serve(&cors(credentials(), MAX_RESPONSE_HEADERS), Method::Get, &[]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn middleware_async() {
+        for (headers, status) in [
+            (1, "HTTP/1.1 200 OK\r\n"),
+            (
+                MAX_RESPONSE_HEADERS,
+                "HTTP/1.1 500 Internal Server Error\r\n",
+            ),
+        ] {
+            let handler = crate::http::server::asynch::CompositeHandler::new(
+                CorsMiddleware::<128>::new(CorsConfiguration::new()),
+                Respond(headers),
+            );
+
+            let request = request(Method::Get, &[("Origin", "http://x")]);
+            let mut socket = MockSocket::new(request.as_bytes());
+            let mut buf = [0; 2048];
+
+            let _ = block_on(asynch::handle_connection::<_, _, 40>(
+                &mut socket,
+                &mut buf,
+                &handler,
+            ));
+
+            let response = String::from_utf8(socket.output).unwrap();
+            assert!(response.starts_with(status), "{response}");
+
+            if headers == 1 {
+                assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("*"));
+            }
+        }
+    }
+}
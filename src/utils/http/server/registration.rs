@@ -0,0 +1,1021 @@
+use core::fmt::{self, Debug};
+use core::str::Split;
+
+use crate::http::server::{Connection, FnHandler, Handler, Headers, Method, Query, Request};
+use crate::io::{ErrorType, Read, Write};
+use crate::utils::http::uri::{self, Uri, UriError};
+
+pub struct ChainHandler<H, N> {
+    pub path: &'static str,
+    pub method: Method,
+    pub handler: H,
+    pub next: N,
+}
+
+impl<H, N> ChainHandler<H, N> {
+    pub fn get<H2>(self, path: &'static str, handler: H2) -> ChainHandler<H2, ChainHandler<H, N>> {
+        self.request(path, Method::Get, handler)
+    }
+
+    pub fn post<H2>(self, path: &'static str, handler: H2) -> ChainHandler<H2, ChainHandler<H, N>> {
+        self.request(path, Method::Post, handler)
+    }
+
+    pub fn put<H2>(self, path: &'static str, handler: H2) -> ChainHandler<H2, ChainHandler<H, N>> {
+        self.request(path, Method::Put, handler)
+    }
+
+    pub fn delete<H2>(
+        self,
+        path: &'static str,
+        handler: H2,
+    ) -> ChainHandler<H2, ChainHandler<H, N>> {
+        self.request(path, Method::Delete, handler)
+    }
+
+    /// Registers `handler` for requests with `method` whose path matches the route pattern `path`.
+    ///
+    /// # Panics
+    ///
+    /// If a `*` segment of `path` is not its last segment.
+    pub fn request<H2>(
+        self,
+        path: &'static str,
+        method: Method,
+        handler: H2,
+    ) -> ChainHandler<H2, ChainHandler<H, N>> {
+        check_pattern(path);
+
+        ChainHandler {
+            path,
+            method,
+            handler,
+            next: self,
+        }
+    }
+}
+
+pub struct ChainRoot;
+
+impl ChainRoot {
+    pub fn get<H2>(self, path: &'static str, handler: H2) -> ChainHandler<H2, ChainRoot> {
+        self.request(path, Method::Get, handler)
+    }
+
+    pub fn post<H2>(self, path: &'static str, handler: H2) -> ChainHandler<H2, ChainRoot> {
+        self.request(path, Method::Post, handler)
+    }
+
+    pub fn put<H2>(self, path: &'static str, handler: H2) -> ChainHandler<H2, ChainRoot> {
+        self.request(path, Method::Put, handler)
+    }
+
+    pub fn delete<H2>(self, path: &'static str, handler: H2) -> ChainHandler<H2, ChainRoot> {
+        self.request(path, Method::Delete, handler)
+    }
+
+    /// Registers `handler` for requests with `method` whose path matches the route pattern `path`.
+    ///
+    /// # Panics
+    ///
+    /// If a `*` segment of `path` is not its last segment.
+    pub fn request<H2>(
+        self,
+        path: &'static str,
+        method: Method,
+        handler: H2,
+    ) -> ChainHandler<H2, ChainRoot> {
+        check_pattern(path);
+
+        ChainHandler {
+            path,
+            method,
+            handler,
+            next: ChainRoot,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RouteError<E> {
+    Handler(E),
+    /// The routes matching the path of the request have more methods than fit in the `Allow` header
+    /// of the `405 Method Not Allowed` response.
+    TooManyMethods,
+}
+
+impl<E: Debug> fmt::Display for RouteError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: Debug> core::error::Error for RouteError<E> {}
+
+/// The distinct methods of the routes whose path matched a request, but whose method did not.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AllowedMethods {
+    methods: heapless::Vec<Method, 8>,
+    overflow: bool,
+}
+
+impl AllowedMethods {
+    pub const fn new() -> Self {
+        Self {
+            methods: heapless::Vec::new(),
+            overflow: false,
+        }
+    }
+
+    /// Adds `method`, unless already added.
+    pub fn add(&mut self, method: Method) {
+        if !self.methods.contains(&method) && self.methods.push(method).is_err() {
+            self.overflow = true;
+        }
+    }
+
+    pub fn contains(&self, method: Method) -> bool {
+        self.methods.contains(&method)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.methods.is_empty() && !self.overflow
+    }
+
+    /// Returns `true` if some of the added methods did not fit.
+    pub fn is_overflow(&self) -> bool {
+        self.overflow
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Method> + '_ {
+        self.methods.iter().copied()
+    }
+}
+
+/// A route pattern matched against the path of an incoming request.
+///
+/// Pattern segments starting with `:` (as in `/devices/:id/config`) capture exactly one
+/// non-empty path segment. A `*` segment, optionally named (as in `/files/*path`),
+/// captures the remaining tail of the path and must be the last segment of the pattern.
+/// A `*` without a name is captured under the name `*`.
+///
+/// The captured values are returned as they appear in the path, i.e. still percent-encoded,
+/// see [`PathParams::decode`].
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PathParams<'a> {
+    pattern: &'a str,
+    path: &'a str,
+}
+
+impl<'a> PathParams<'a> {
+    pub fn new(pattern: &'a str, path: &'a str) -> Option<Self> {
+        matches(pattern, path).then_some(Self { pattern, path })
+    }
+
+    pub fn pattern(&self) -> &'a str {
+        self.pattern
+    }
+
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Finds the parameter named `name` and percent-decodes its value into `buf`.
+    pub fn decode<'b>(&self, name: &str, buf: &'b mut [u8]) -> Result<Option<&'b str>, UriError> {
+        match self.get(name) {
+            Some(value) => uri::decode(value, buf).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn iter(&self) -> PathParamsIterator<'a> {
+        PathParamsIterator {
+            pattern: trim_root(self.pattern).split('/'),
+            path: Some(trim_root(self.path)),
+        }
+    }
+}
+
+impl<'a> IntoIterator for PathParams<'a> {
+    type Item = (&'a str, &'a str);
+
+    type IntoIter = PathParamsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct PathParamsIterator<'a> {
+    pattern: Split<'a, char>,
+    path: Option<&'a str>,
+}
+
+impl<'a> Iterator for PathParamsIterator<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for segment in self.pattern.by_ref() {
+            if let Some(name) = segment.strip_prefix('*') {
+                let tail = self.path.take().unwrap_or("");
+
+                return Some((if name.is_empty() { "*" } else { name }, tail));
+            }
+
+            let (value, next) = split_segment(self.path?);
+            self.path = next;
+
+            if let Some(name) = segment.strip_prefix(':') {
+                return Some((name, value));
+            }
+        }
+
+        None
+    }
+}
+
+/// Returns `true` if `path` matches the route `pattern`.
+///
+/// See [`PathParams`] for the pattern syntax.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let mut path = Some(trim_root(path));
+
+    for segment in trim_root(pattern).split('/') {
+        if segment.starts_with('*') {
+            return true;
+        }
+
+        let Some(current) = path else {
+            return false;
+        };
+
+        let (value, next) = split_segment(current);
+        path = next;
+
+        let matched = if segment.starts_with(':') {
+            !value.is_empty()
+        } else {
+            segment == value
+        };
+
+        if !matched {
+            return false;
+        }
+    }
+
+    path.is_none()
+}
+
+fn check_pattern(pattern: &str) {
+    let mut segments = trim_root(pattern).split('/');
+
+    assert!(
+        !segments.any(|segment| segment.starts_with('*')) || segments.next().is_none(),
+        "A `*` segment must be the last segment of a route pattern"
+    );
+}
+
+fn trim_root(path: &str) -> &str {
+    path.strip_prefix('/').unwrap_or(path)
+}
+
+fn split_segment(path: &str) -> (&str, Option<&str>) {
+    match path.split_once('/') {
+        Some((segment, rest)) => (segment, Some(rest)),
+        None => (path, None),
+    }
+}
+
+/// The connection passed to handlers registered in a [`ChainHandler`] router.
+///
+/// Delegates to the wrapped connection and additionally exposes the parameters captured
+/// from the request path by the route pattern the handler was registered with.
+pub struct RouteConnection<'r, C> {
+    connection: &'r mut C,
+    pattern: &'static str,
+}
+
+impl<'r, C> RouteConnection<'r, C> {
+    pub fn new(connection: &'r mut C, pattern: &'static str) -> Self {
+        Self {
+            connection,
+            pattern,
+        }
+    }
+
+    pub fn pattern(&self) -> &'static str {
+        self.pattern
+    }
+
+    pub fn connection(&mut self) -> &mut C {
+        self.connection
+    }
+}
+
+impl<C> RouteConnection<'_, C>
+where
+    C: Query,
+{
+    pub fn params(&self) -> PathParams<'_> {
+        PathParams {
+            pattern: self.pattern,
+            path: Uri::new(self.connection.uri()).path(),
+        }
+    }
+
+    pub fn param(&self, name: &str) -> Option<&'_ str> {
+        self.params().get(name)
+    }
+
+    /// Finds the parameter named `name` and percent-decodes its value into `buf`.
+    pub fn decode_param<'b>(
+        &self,
+        name: &str,
+        buf: &'b mut [u8],
+    ) -> Result<Option<&'b str>, UriError> {
+        self.params().decode(name, buf)
+    }
+}
+
+impl<C> ErrorType for RouteConnection<'_, C>
+where
+    C: ErrorType,
+{
+    type Error = C::Error;
+}
+
+impl<C> Read for RouteConnection<'_, C>
+where
+    C: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.connection.read(buf)
+    }
+}
+
+impl<C> Write for RouteConnection<'_, C>
+where
+    C: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.connection.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.connection.flush()
+    }
+}
+
+impl<C> crate::io::asynch::Read for RouteConnection<'_, C>
+where
+    C: crate::io::asynch::Read,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.connection.read(buf).await
+    }
+}
+
+impl<C> crate::io::asynch::Write for RouteConnection<'_, C>
+where
+    C: crate::io::asynch::Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.connection.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.connection.flush().await
+    }
+}
+
+impl<C> Headers for RouteConnection<'_, C>
+where
+    C: Headers,
+{
+    fn header(&self, name: &str) -> Option<&'_ str> {
+        self.connection.header(name)
+    }
+}
+
+impl<C> Query for RouteConnection<'_, C>
+where
+    C: Query,
+{
+    fn uri(&self) -> &'_ str {
+        self.connection.uri()
+    }
+
+    fn method(&self) -> Method {
+        self.connection.method()
+    }
+}
+
+impl<C> Connection for RouteConnection<'_, C>
+where
+    C: Connection,
+{
+    type Headers = C::Headers;
+
+    type Read = C::Read;
+
+    type RawConnectionError = C::RawConnectionError;
+
+    type RawConnection = C::RawConnection;
+
+    fn split(&mut self) -> (&Self::Headers, &mut Self::Read) {
+        self.connection.split()
+    }
+
+    fn initiate_response<'a>(
+        &'a mut self,
+        status: u16,
+        message: Option<&'a str>,
+        headers: &'a [(&'a str, &'a str)],
+    ) -> Result<(), Self::Error> {
+        self.connection.initiate_response(status, message, headers)
+    }
+
+    fn is_response_initiated(&self) -> bool {
+        self.connection.is_response_initiated()
+    }
+
+    fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error> {
+        self.connection.raw_connection()
+    }
+}
+
+impl<C> crate::http::server::asynch::Connection for RouteConnection<'_, C>
+where
+    C: crate::http::server::asynch::Connection,
+{
+    type Headers = C::Headers;
+
+    type Read = C::Read;
+
+    type RawConnectionError = C::RawConnectionError;
+
+    type RawConnection = C::RawConnection;
+
+    fn split(&mut self) -> (&Self::Headers, &mut Self::Read) {
+        self.connection.split()
+    }
+
+    async fn initiate_response(
+        &mut self,
+        status: u16,
+        message: Option<&str>,
+        headers: &[(&str, &str)],
+    ) -> Result<(), Self::Error> {
+        self.connection
+            .initiate_response(status, message, headers)
+            .await
+    }
+
+    fn is_response_initiated(&self) -> bool {
+        self.connection.is_response_initiated()
+    }
+
+    fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error> {
+        self.connection.raw_connection()
+    }
+}
+
+/// Creates a [`FnHandler`] which can be registered in a [`ChainHandler`] router.
+///
+/// Unlike [`FnHandler::new`], the closure is required to accept a request over a
+/// [`RouteConnection`] of any lifetime, which is what the router passes to its handlers.
+pub fn route_fn<C, F, E>(f: F) -> FnHandler<F>
+where
+    C: Connection,
+    F: for<'r, 'c> Fn(Request<&'c mut RouteConnection<'r, C>>) -> Result<(), E> + Send,
+    E: Debug,
+{
+    FnHandler::new(f)
+}
+
+/// A chain of routes which can be dispatched.
+///
+/// Implemented by [`ChainHandler`] and [`ChainRoot`]. Routes are tried in registration order,
+/// matching their method against `method` rather than the one of the request, so that a `HEAD`
+/// request can be routed to a `GET` route.
+/// If no route matches, `route` returns `None` and collects into `allowed` the methods of
+/// the routes whose path did match, but whose method did not.
+pub trait Route<C>: Send
+where
+    C: Connection,
+{
+    type Error: Debug;
+
+    fn route(
+        &self,
+        connection: &mut C,
+        method: Method,
+        allowed: &mut AllowedMethods,
+    ) -> Option<Result<(), Self::Error>>;
+}
+
+impl<C> Route<C> for ChainRoot
+where
+    C: Connection,
+{
+    type Error = C::Error;
+
+    fn route(
+        &self,
+        _connection: &mut C,
+        _method: Method,
+        _allowed: &mut AllowedMethods,
+    ) -> Option<Result<(), C::Error>> {
+        None
+    }
+}
+
+impl<C, H, N, E> Route<C> for ChainHandler<H, N>
+where
+    C: Connection,
+    H: for<'r> Handler<RouteConnection<'r, C>, Error = E>,
+    N: Route<C>,
+    N::Error: Into<E>,
+    E: Debug,
+{
+    type Error = E;
+
+    fn route(
+        &self,
+        connection: &mut C,
+        method: Method,
+        allowed: &mut AllowedMethods,
+    ) -> Option<Result<(), Self::Error>> {
+        if let Some(result) = self.next.route(connection, method, allowed) {
+            return Some(result.map_err(Into::into));
+        }
+
+        if !matches(self.path, Uri::new(connection.uri()).path()) {
+            return None;
+        }
+
+        if method != self.method {
+            allowed.add(self.method);
+
+            return None;
+        }
+
+        Some(
+            self.handler
+                .handle(&mut RouteConnection::new(connection, self.path)),
+        )
+    }
+}
+
+impl<C, H, N, E> Handler<C> for ChainHandler<H, N>
+where
+    C: Connection,
+    H: for<'r> Handler<RouteConnection<'r, C>, Error = E>,
+    E: From<C::Error> + Debug,
+    N: Route<C>,
+    N::Error: Into<E>,
+{
+    type Error = RouteError<E>;
+
+    fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+        let mut allowed = AllowedMethods::new();
+        let method = connection.method();
+
+        let mut result = self.route(connection, method, &mut allowed);
+
+        // Without a `HEAD` route, a `HEAD` request is answered like a `GET` request, without the body
+        if result.is_none() && method == Method::Head && allowed.contains(Method::Get) {
+            result = self.route(connection, Method::Get, &mut AllowedMethods::new());
+        }
+
+        match result {
+            Some(result) => result.map_err(RouteError::Handler),
+            None => {
+                let mut allow = heapless::String::<128>::new();
+
+                let result = if allowed.is_empty() {
+                    connection.initiate_response(404, Some("Not Found"), &[])
+                } else {
+                    format_allow(&allowed, &mut allow)?;
+
+                    connection.initiate_response(
+                        405,
+                        Some("Method Not Allowed"),
+                        &[("Allow", allow.as_str())],
+                    )
+                };
+
+                result.map_err(|e| RouteError::Handler(e.into()))
+            }
+        }
+    }
+}
+
+fn format_allow<E, const N: usize>(
+    allowed: &AllowedMethods,
+    allow: &mut heapless::String<N>,
+) -> Result<(), RouteError<E>> {
+    if allowed.is_overflow() {
+        return Err(RouteError::TooManyMethods);
+    }
+
+    for (index, method) in allowed.iter().enumerate() {
+        if index > 0 {
+            allow
+                .push_str(", ")
+                .map_err(|_| RouteError::TooManyMethods)?;
+        }
+
+        allow
+            .push_str(method.as_str())
+            .map_err(|_| RouteError::TooManyMethods)?;
+    }
+
+    Ok(())
+}
+
+pub mod asynch {
+    use core::fmt::Debug;
+
+    pub use crate::http::server::asynch::{Connection, Handler};
+    use crate::http::{Method, Query};
+
+    use crate::utils::http::uri::Uri;
+
+    use super::{format_allow, matches, ChainHandler, ChainRoot};
+
+    pub use super::{AllowedMethods, PathParams, RouteConnection, RouteError};
+
+    /// The async counterpart of [`super::Route`].
+    pub trait Route<C>: Send
+    where
+        C: Connection,
+    {
+        type Error: Debug;
+
+        async fn route(
+            &self,
+            connection: &mut C,
+            method: Method,
+            allowed: &mut AllowedMethods,
+        ) -> Option<Result<(), Self::Error>>;
+    }
+
+    impl<C> Route<C> for ChainRoot
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        async fn route(
+            &self,
+            _connection: &mut C,
+            _method: Method,
+            _allowed: &mut AllowedMethods,
+        ) -> Option<Result<(), C::Error>> {
+            None
+        }
+    }
+
+    impl<C, H, N, E> Route<C> for ChainHandler<H, N>
+    where
+        C: Connection,
+        H: for<'r> Handler<RouteConnection<'r, C>, Error = E>,
+        N: Route<C>,
+        N::Error: Into<E>,
+        E: Debug,
+    {
+        type Error = E;
+
+        async fn route(
+            &self,
+            connection: &mut C,
+            method: Method,
+            allowed: &mut AllowedMethods,
+        ) -> Option<Result<(), Self::Error>> {
+            if let Some(result) = self.next.route(connection, method, allowed).await {
+                return Some(result.map_err(Into::into));
+            }
+
+            if !matches(self.path, Uri::new(connection.uri()).path()) {
+                return None;
+            }
+
+            if method != self.method {
+                allowed.add(self.method);
+
+                return None;
+            }
+
+            Some(
+                self.handler
+                    .handle(&mut RouteConnection::new(connection, self.path))
+                    .await,
+            )
+        }
+    }
+
+    impl<C, H, N, E> Handler<C> for ChainHandler<H, N>
+    where
+        C: Connection,
+        H: for<'r> Handler<RouteConnection<'r, C>, Error = E>,
+        E: From<C::Error> + Debug,
+        N: Route<C>,
+        N::Error: Into<E>,
+    {
+        type Error = RouteError<E>;
+
+        async fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let mut allowed = AllowedMethods::new();
+            let method = connection.method();
+
+            let mut result = self.route(connection, method, &mut allowed).await;
+
+            // Without a `HEAD` route, a `HEAD` request is answered like a `GET` request, without the body
+            if result.is_none() && method == Method::Head && allowed.contains(Method::Get) {
+                result = self
+                    .route(connection, Method::Get, &mut AllowedMethods::new())
+                    .await;
+            }
+
+            match result {
+                Some(result) => result.map_err(RouteError::Handler),
+                None => {
+                    let mut allow = heapless::String::<128>::new();
+
+                    let result = if allowed.is_empty() {
+                        connection
+                            .initiate_response(404, Some("Not Found"), &[])
+                            .await
+                    } else {
+                        format_allow(&allowed, &mut allow)?;
+
+                        connection
+                            .initiate_response(
+                                405,
+                                Some("Method Not Allowed"),
+                                &[("Allow", allow.as_str())],
+                            )
+                            .await
+                    };
+
+                    result.map_err(|e| RouteError::Handler(e.into()))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+
+    use crate::http::server::{Connection, Handler, Method};
+    use crate::io::Write;
+    use crate::utils::http::server::connection::{handle_connection, ServerConnection};
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use crate::utils::http::uri::UriError;
+
+    use super::{matches, AllowedMethods, ChainRoot, PathParams, RouteConnection};
+
+    struct Param(&'static str);
+
+    impl<C> Handler<RouteConnection<'_, C>> for Param
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, connection: &mut RouteConnection<'_, C>) -> Result<(), Self::Error> {
+            let mut value = heapless::String::<64>::new();
+            value.push_str(connection.param(self.0).unwrap()).unwrap();
+
+            connection.initiate_response(200, Some("OK"), &[])?;
+            connection.write_all(value.as_bytes())
+        }
+    }
+
+    struct Status(u16);
+
+    impl<C> Handler<RouteConnection<'_, C>> for Status
+    where
+        C: Connection,
+    {
+        type Error = C::Error;
+
+        fn handle(&self, connection: &mut RouteConnection<'_, C>) -> Result<(), Self::Error> {
+            connection.initiate_response(self.0, Some("No Content"), &[])
+        }
+    }
+
+    impl<C> crate::http::server::asynch::Handler<RouteConnection<'_, C>> for Param
+    where
+        C: crate::http::server::asynch::Connection,
+    {
+        type Error = C::Error;
+
+        async fn handle(&self, connection: &mut RouteConnection<'_, C>) -> Result<(), Self::Error> {
+            let mut value = heapless::String::<64>::new();
+            value.push_str(connection.param(self.0).unwrap()).unwrap();
+
+            crate::http::server::asynch::Connection::initiate_response(
+                connection,
+                200,
+                Some("OK"),
+                &[],
+            )
+            .await?;
+            crate::io::asynch::Write::write_all(connection, value.as_bytes()).await
+        }
+    }
+
+    fn serve<'s, H>(handler: &H, request: &'s str) -> String
+    where
+        H: for<'a, 'b> Handler<ServerConnection<'b, &'a mut MockSocket<'s>, 16>>,
+    {
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 1024];
+
+        let _ = handle_connection::<_, _, 16>(&mut socket, &mut buf, handler);
+
+        String::from_utf8(socket.output).unwrap()
+    }
+
+    #[test]
+    fn match_patterns() {
+        assert!(matches("/", "/"));
+        assert!(matches("/devices/:id/config", "/devices/42/config"));
+        assert!(!matches("/devices/:id/config", "/devices//config"));
+        assert!(!matches("/devices/:id/config", "/devices/42"));
+        assert!(!matches("/devices/:id", "/devices/42/config"));
+        assert!(matches("/files/*", "/files/a/b"));
+        assert!(matches("/files/*path", "/files/"));
+        assert!(!matches("/files/*path", "/other/a"));
+    }
+
+    #[test]
+    fn path_params() {
+        let params =
+            PathParams::new("/devices/:id/files/*path", "/devices/42/files/a/b.txt").unwrap();
+
+        assert_eq!(params.get("id"), Some("42"));
+        assert_eq!(params.get("path"), Some("a/b.txt"));
+        assert_eq!(params.get("other"), None);
+
+        let params = PathParams::new("/static/*", "/static/index.html").unwrap();
+
+        assert_eq!(params.get("*"), Some("index.html"));
+        assert!(PathParams::new("/devices/:id", "/devices").is_none());
+    }
+
+    #[test]
+    fn decode_params() {
+        let params = PathParams::new("/users/:name/*path", "/users/J%C3%BCrgen/a%2Fb/c+d").unwrap();
+        let mut buf = [0; 16];
+
+        assert_eq!(params.get("name"), Some("J%C3%BCrgen"));
+        assert_eq!(params.decode("name", &mut buf), Ok(Some("Jürgen")));
+        // Unlike in a query, `+` is not a space
+        assert_eq!(params.decode("path", &mut buf), Ok(Some("a/b/c+d")));
+        assert_eq!(params.decode("other", &mut buf), Ok(None));
+        assert_eq!(
+            params.decode("name", &mut [0; 4]),
+            Err(UriError::BufferOverflow)
+        );
+
+        let params = PathParams::new("/users/:name", "/users/%E").unwrap();
+        assert_eq!(
+            params.decode("name", &mut buf),
+            Err(UriError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn wildcard_not_last() {
+        let _ = ChainRoot.get("/files/*path/info", Param("path"));
+    }
+
+    #[test]
+    fn allowed_methods() {
+        let mut allowed = AllowedMethods::new();
+
+        allowed.add(Method::Get);
+        allowed.add(Method::Get);
+        assert_eq!(allowed.iter().count(), 1);
+
+        for method in [
+            Method::Post,
+            Method::Put,
+            Method::Delete,
+            Method::Head,
+            Method::Options,
+            Method::Patch,
+            Method::Trace,
+        ] {
+            allowed.add(method);
+        }
+        assert!(!allowed.is_overflow());
+
+        allowed.add(Method::Connect);
+        assert!(allowed.is_overflow());
+    }
+
+    #[test]
+    fn dispatch() {
+        let router = ChainRoot
+            .get("/devices/:id", Param("id"))
+            .post("/devices/:id", Param("id"))
+            .get("/devices/:id", Param("id"))
+            .get("/files/*path", Param("path"));
+
+        let response = serve(&router, "GET /devices/7?verbose HTTP/1.0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n7"));
+
+        let response = serve(&router, "GET /files/a/b.txt HTTP/1.0\r\n\r\n");
+        assert!(response.ends_with("\r\n\r\na/b.txt"));
+
+        let response = serve(&router, "DELETE /devices/7 HTTP/1.0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("\r\nAllow: GET, POST\r\n"));
+
+        let response = serve(&router, "GET /devices HTTP/1.0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn head() {
+        let router = ChainRoot
+            .get("/devices/:id", Param("id"))
+            .request("/files/*path", Method::Head, Status(204))
+            .get("/files/*path", Param("path"));
+
+        // Answered by the `GET` route, without the body
+        let response = serve(&router, "HEAD /devices/7 HTTP/1.0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+
+        // The `HEAD` route takes precedence, even over a `GET` route registered before
+        let response = serve(&router, "HEAD /files/a HTTP/1.0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 204 No Content\r\n"));
+
+        let response = serve(&router, "HEAD /other HTTP/1.0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn too_many_methods() {
+        let router = ChainRoot
+            .request("/", Method::Get, Param("x"))
+            .request("/", Method::Post, Param("x"))
+            .request("/", Method::Put, Param("x"))
+            .request("/", Method::Delete, Param("x"))
+            .request("/", Method::Head, Param("x"))
+            .request("/", Method::Options, Param("x"))
+            .request("/", Method::Patch, Param("x"))
+            .request("/", Method::Trace, Param("x"))
+            .request("/", Method::Connect, Param("x"));
+
+        let response = serve(&router, "LOCK / HTTP/1.0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+    }
+
+    #[test]
+    fn dispatch_async() {
+        let router = ChainRoot
+            .get("/devices/:id", Param("id"))
+            .post("/devices/:id", Param("id"));
+
+        let mut socket = MockSocket::new(
+            b"GET /devices/7 HTTP/1.1\r\n\r\nPUT /devices/7 HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        let mut buf = [0; 1024];
+
+        block_on(
+            crate::utils::http::server::connection::asynch::handle_connection::<_, _, 16>(
+                &mut socket,
+                &mut buf,
+                &router,
+            ),
+        )
+        .unwrap();
+
+        let response = String::from_utf8(socket.output).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\n7\r\n0\r\n\r\nHTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("\r\nAllow: GET, POST\r\n"));
+    }
+}
@@ -0,0 +1,445 @@
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+use crate::http::server::*;
+
+use crate::utils::http::cookies::*;
+
+pub const SESSION_COOKIE: &str = "SESSIONID";
+
+pub type SessionId = heapless::String<32>;
+
+/// The number of random IDs tried by [`Session::create`] before giving up.
+const MAX_ID_ATTEMPTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SessionError {
+    MaxSessionsReachedError,
+    InvalidSessionIdError,
+    BufferOverflowError,
+    /// The random number generator repeatedly produced the IDs of existing sessions.
+    IdGenerationError,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaxSessionsReachedError => {
+                write!(f, "Max number of sessions reached")
+            }
+            Self::InvalidSessionIdError => write!(f, "Invalid session ID"),
+            Self::BufferOverflowError => write!(f, "Buffer overflow"),
+            Self::IdGenerationError => write!(f, "Failed to generate a unique session ID"),
+        }
+    }
+}
+
+impl core::error::Error for SessionError {}
+
+/// A minimal lock used by [`SessionImpl`] to protect the session table.
+///
+/// Implemented for `RefCell` (single-threaded use) and - with feature `std` - for `std::sync::Mutex`.
+/// Implement it for a platform-specific mutex to share the sessions between threads in `no_std`.
+pub trait Lock<T> {
+    fn new(data: T) -> Self;
+
+    fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R;
+}
+
+impl<T> Lock<T> for RefCell<T> {
+    fn new(data: T) -> Self {
+        RefCell::new(data)
+    }
+
+    fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        f(&mut self.borrow_mut())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Lock<T> for std::sync::Mutex<T> {
+    fn new(data: T) -> Self {
+        std::sync::Mutex::new(data)
+    }
+
+    fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let mut guard = std::sync::Mutex::lock(self).unwrap_or_else(|e| e.into_inner());
+
+        f(&mut guard)
+    }
+}
+
+pub trait Session: Send {
+    type SessionData;
+
+    fn is_existing(&self, session_id: Option<&str>) -> bool;
+
+    fn with_existing<R, F>(&self, session_id: Option<&str>, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Self::SessionData) -> R;
+
+    /// Calls `f` with the data of the existing session `session_id`.
+    ///
+    /// Fails with [`SessionError::InvalidSessionIdError`] if there is no such session: sessions
+    /// are only ever started with [`Session::create`], so that clients cannot choose their session IDs.
+    fn with<R, F>(&self, session_id: &str, f: F) -> Result<R, SessionError>
+    where
+        F: FnOnce(&mut Self::SessionData) -> R;
+
+    fn create(&self) -> Result<SessionId, SessionError>;
+
+    fn invalidate(&self, session_id: Option<&str>) -> bool;
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SessionData<S> {
+    id: SessionId,
+    last_accessed: Duration,
+    timeout: Duration,
+    data: S,
+}
+
+impl<S> SessionData<S> {
+    fn is_free(&self) -> bool {
+        self.id.is_empty()
+    }
+
+    fn is_expired(&self, current_time: Duration) -> bool {
+        self.last_accessed + self.timeout < current_time
+    }
+}
+
+/// A fixed-capacity session store for up to `N` concurrent sessions.
+///
+/// - `current_time` returns a monotonic time and is used to expire sessions
+///   which were not accessed within their timeout
+/// - `rng` fills the provided buffer with random data and is used to generate new session IDs
+pub struct SessionImpl<L, S, T, G, const N: usize = 16> {
+    current_time: T,
+    rng: G,
+    data: L,
+    default_session_timeout: Duration,
+    _data: core::marker::PhantomData<fn() -> S>,
+}
+
+impl<L, S, T, G, const N: usize> SessionImpl<L, S, T, G, N>
+where
+    L: Lock<[SessionData<S>; N]>,
+    S: Default,
+    T: Fn() -> Duration,
+    G: Fn(&mut [u8]),
+{
+    pub fn new(current_time: T, rng: G, default_session_timeout: Duration) -> Self {
+        Self {
+            current_time,
+            rng,
+            data: L::new(core::array::from_fn(|_| Default::default())),
+            default_session_timeout,
+            _data: core::marker::PhantomData,
+        }
+    }
+
+    fn cleanup(&self, current_time: Duration) {
+        self.data.lock(|data| {
+            for entry in data {
+                if !entry.is_free() && entry.is_expired(current_time) {
+                    entry.id = SessionId::new();
+                    entry.data = Default::default();
+                }
+            }
+        });
+    }
+
+    fn generate_id(&self, data: &[SessionData<S>; N]) -> Result<SessionId, SessionError> {
+        for _ in 0..MAX_ID_ATTEMPTS {
+            let mut random = [0_u8; 16];
+            (self.rng)(&mut random);
+
+            let mut id = SessionId::new();
+            for byte in random {
+                write!(&mut id, "{byte:02x}").unwrap();
+            }
+
+            if !data.iter().any(|entry| entry.id == id) {
+                return Ok(id);
+            }
+        }
+
+        Err(SessionError::IdGenerationError)
+    }
+}
+
+impl<L, S, T, G, const N: usize> Session for SessionImpl<L, S, T, G, N>
+where
+    L: Lock<[SessionData<S>; N]> + Send,
+    S: Default,
+    T: Fn() -> Duration + Send,
+    G: Fn(&mut [u8]) + Send,
+{
+    type SessionData = S;
+
+    fn is_existing(&self, session_id: Option<&str>) -> bool {
+        let current_time = (self.current_time)();
+        self.cleanup(current_time);
+
+        if let Some(session_id) = session_id {
+            self.data.lock(|data| {
+                data.iter_mut()
+                    .find(|entry| !entry.is_free() && entry.id.as_str() == session_id)
+                    .map(|entry| entry.last_accessed = current_time)
+                    .is_some()
+            })
+        } else {
+            false
+        }
+    }
+
+    fn with_existing<R, F>(&self, session_id: Option<&str>, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Self::SessionData) -> R,
+    {
+        let current_time = (self.current_time)();
+        self.cleanup(current_time);
+
+        if let Some(session_id) = session_id {
+            self.data.lock(|data| {
+                data.iter_mut()
+                    .find(|entry| !entry.is_free() && entry.id.as_str() == session_id)
+                    .map(|entry| {
+                        entry.last_accessed = current_time;
+                        f(&mut entry.data)
+                    })
+            })
+        } else {
+            None
+        }
+    }
+
+    fn with<R, F>(&self, session_id: &str, f: F) -> Result<R, SessionError>
+    where
+        F: FnOnce(&mut Self::SessionData) -> R,
+    {
+        let current_time = (self.current_time)();
+        self.cleanup(current_time);
+
+        self.data.lock(|data| {
+            let entry = data
+                .iter_mut()
+                .find(|entry| !entry.is_free() && entry.id.as_str() == session_id)
+                .ok_or(SessionError::InvalidSessionIdError)?;
+
+            entry.last_accessed = current_time;
+
+            Ok(f(&mut entry.data))
+        })
+    }
+
+    fn create(&self) -> Result<SessionId, SessionError> {
+        let current_time = (self.current_time)();
+        self.cleanup(current_time);
+
+        self.data.lock(|data| {
+            if !data.iter().any(|entry| entry.is_free()) {
+                return Err(SessionError::MaxSessionsReachedError);
+            }
+
+            let id = self.generate_id(data)?;
+
+            let entry = data.iter_mut().find(|entry| entry.is_free()).unwrap();
+
+            entry.id = id.clone();
+            entry.data = Default::default();
+            entry.timeout = self.default_session_timeout;
+            entry.last_accessed = current_time;
+
+            Ok(id)
+        })
+    }
+
+    fn invalidate(&self, session_id: Option<&str>) -> bool {
+        let current_time = (self.current_time)();
+        self.cleanup(current_time);
+
+        if let Some(session_id) = session_id {
+            self.data.lock(|data| {
+                if let Some(entry) = data
+                    .iter_mut()
+                    .find(|entry| !entry.is_free() && entry.id.as_str() == session_id)
+                {
+                    entry.id = SessionId::new();
+                    entry.data = Default::default();
+                    true
+                } else {
+                    false
+                }
+            })
+        } else {
+            false
+        }
+    }
+}
+
+pub fn get_cookie_session_id<H>(headers: &H) -> Option<&str>
+where
+    H: Headers,
+{
+    headers
+        .header("Cookie")
+        .and_then(|cookies_str| Cookies::new(cookies_str).get(SESSION_COOKIE))
+}
+
+/// A `Set-Cookie` for the session cookie, restricted to HTTP (not accessible from scripts)
+/// and to same-site requests.
+pub fn session_set_cookie(session_id: &str) -> SetCookie<'_> {
+    SetCookie::new(SESSION_COOKIE, session_id)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+}
+
+pub fn set_cookie_session_id<const N: usize, H>(
+    headers: H,
+    session_id: &str,
+    cookies: &mut heapless::String<N>,
+) -> Result<(), SessionError>
+where
+    H: Headers,
+{
+    let cookies_str = headers.header("Cookie").unwrap_or("");
+
+    for cookie in Cookies::serialize(Cookies::set(
+        Cookies::new(cookies_str).into_iter(),
+        SESSION_COOKIE,
+        session_id,
+    )) {
+        cookies
+            .push_str(cookie)
+            .map_err(|_| SessionError::BufferOverflowError)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::RefCell;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::time::Duration;
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::utils::http::Headers;
+
+    use super::{get_cookie_session_id, set_cookie_session_id, Session, SessionError, SessionImpl};
+
+    #[derive(Default)]
+    struct Data {
+        count: u32,
+    }
+
+    #[test]
+    fn sessions() {
+        let now = Arc::new(Mutex::new(Duration::ZERO));
+        let clock = now.clone();
+        let counter = Mutex::new(0_u8);
+
+        let sessions = SessionImpl::<Mutex<_>, Data, _, _, 2>::new(
+            move || *clock.lock().unwrap(),
+            move |buf: &mut [u8]| {
+                let mut counter = counter.lock().unwrap();
+                *counter += 1;
+                buf.fill(*counter);
+            },
+            Duration::from_secs(10),
+        );
+
+        let a = sessions.create().unwrap();
+        let b = sessions.create().unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 32);
+        assert_eq!(
+            sessions.create(),
+            Err(SessionError::MaxSessionsReachedError)
+        );
+
+        sessions.with(&a, |data| data.count += 1).unwrap();
+        assert_eq!(sessions.with_existing(Some(&a), |data| data.count), Some(1));
+
+        *now.lock().unwrap() = Duration::from_secs(5);
+        assert!(sessions.is_existing(Some(&a)));
+
+        // `b` was last accessed at 0, `a` at 5
+        *now.lock().unwrap() = Duration::from_secs(12);
+        assert!(sessions.is_existing(Some(&a)));
+        assert!(!sessions.is_existing(Some(&b)));
+
+        assert!(sessions.invalidate(Some(&a)));
+        assert!(!sessions.is_existing(Some(&a)));
+
+        let c = sessions.create().unwrap();
+        assert_eq!(sessions.with_existing(Some(&c), |data| data.count), Some(0));
+    }
+
+    #[test]
+    fn unknown_session() {
+        let sessions = SessionImpl::<RefCell<_>, Data, _, _, 2>::new(
+            || Duration::ZERO,
+            |buf: &mut [u8]| buf.fill(1),
+            Duration::from_secs(10),
+        );
+
+        assert_eq!(
+            sessions.with("0123456789abcdef", |_| ()),
+            Err(SessionError::InvalidSessionIdError)
+        );
+        assert_eq!(
+            sessions.with(&"x".repeat(40), |_| ()),
+            Err(SessionError::InvalidSessionIdError)
+        );
+        assert!(!sessions.is_existing(Some("0123456789abcdef")));
+    }
+
+    #[test]
+    fn constant_rng() {
+        let calls = AtomicUsize::new(0);
+
+        let sessions = SessionImpl::<RefCell<_>, Data, _, _, 2>::new(
+            || Duration::ZERO,
+            |buf: &mut [u8]| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                buf.fill(7);
+            },
+            Duration::from_secs(10),
+        );
+
+        sessions.create().unwrap();
+        assert_eq!(sessions.create(), Err(SessionError::IdGenerationError));
+        assert_eq!(calls.load(Ordering::Relaxed), 1 + super::MAX_ID_ATTEMPTS);
+    }
+
+    #[test]
+    fn cookies() {
+        let mut headers = Headers::<4>::new();
+        headers.set("Cookie", "a=1; SESSIONID=abc; b=2");
+
+        assert_eq!(get_cookie_session_id(&headers), Some("abc"));
+
+        let mut cookies = heapless::String::<64>::new();
+        set_cookie_session_id(&headers, "xyz", &mut cookies).unwrap();
+
+        assert_eq!(cookies.as_str(), "a=1;b=2;SESSIONID=xyz");
+    }
+}
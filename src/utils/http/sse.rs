@@ -0,0 +1,751 @@
+//! Server-Sent Events, as per the HTML Living Standard, section 9.2.
+
+use core::fmt::{self, Write as _};
+use core::iter;
+use core::str;
+
+use crate::http::server::{Connection, Request, Response};
+use crate::io::{Error, ErrorKind, Read, Write};
+
+pub const CONTENT_TYPE: &str = "text/event-stream";
+
+/// The headers starting an event stream response.
+const HEADERS: &[(&str, &str)] = &[
+    ("Content-Type", CONTENT_TYPE),
+    ("Cache-Control", "no-cache"),
+];
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SseError<E> {
+    Io(E),
+    /// The event type or ID contains a line break or a NUL character.
+    InvalidField,
+    BufferOverflow,
+    InvalidUtf8,
+}
+
+impl<E: fmt::Debug> fmt::Display for SseError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for SseError<E> {}
+
+impl<E> Error for SseError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::InvalidField => ErrorKind::InvalidInput,
+            Self::BufferOverflow => ErrorKind::OutOfMemory,
+            Self::InvalidUtf8 => ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// An event, as sent by [`EventWriter`] and received by [`EventReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Event<'a> {
+    /// The type of the event. Without one, the event is of the `message` type.
+    pub event: Option<&'a str>,
+    /// The ID of the event. A received event carries the last ID sent in the stream.
+    pub id: Option<&'a str>,
+    /// The data of the event, which may span multiple lines.
+    pub data: &'a str,
+    /// The reconnection time in milliseconds.
+    pub retry: Option<u32>,
+}
+
+impl<'a> Event<'a> {
+    pub const fn new(data: &'a str) -> Self {
+        Self {
+            event: None,
+            id: None,
+            data,
+            retry: None,
+        }
+    }
+
+    pub const fn event(mut self, event: &'a str) -> Self {
+        self.event = Some(event);
+        self
+    }
+
+    pub const fn id(mut self, id: &'a str) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub const fn retry(mut self, retry: u32) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+}
+
+/// Splits `text` at any of the `\r\n`, `\n` and `\r` line breaks.
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n')
+        .flat_map(|line| line.strip_suffix('\r').unwrap_or(line).split('\r'))
+}
+
+/// Returns the chunks of the serialized `event`, with `retry` holding its formatted reconnection time.
+fn serialize<'a, E>(
+    event: &Event<'a>,
+    retry: &'a mut heapless::String<10>,
+) -> Result<impl Iterator<Item = &'a str>, SseError<E>> {
+    let invalid =
+        |value: Option<&str>| value.is_some_and(|value| value.contains(['\r', '\n', '\0']));
+
+    if invalid(event.event) || invalid(event.id) {
+        return Err(SseError::InvalidField);
+    }
+
+    if let Some(ms) = event.retry {
+        // Cannot fail, a `u32` has at most 10 digits
+        let _ = write!(retry, "{ms}");
+    }
+
+    let field =
+        |name, value: Option<&'a str>| value.into_iter().flat_map(move |value| [name, value, "\n"]);
+
+    let retry = event.retry.map(|_| retry.as_str());
+
+    Ok(field("event: ", event.event)
+        .chain(field("id: ", event.id))
+        .chain(field("retry: ", retry))
+        .chain(lines(event.data).flat_map(|line| ["data: ", line, "\n"]))
+        .chain(iter::once("\n")))
+}
+
+fn comment(comment: &str) -> impl Iterator<Item = &str> {
+    lines(comment).flat_map(|line| [": ", line, "\n"])
+}
+
+/// The comment sent by [`EventWriter::keep_alive`].
+const KEEP_ALIVE: &str = ":\n\n";
+
+/// Writes events to an event stream, e.g. to the `server::Response` returned by [`Request::into_event_stream`].
+///
+/// Every event is flushed once written.
+pub struct EventWriter<W>(W);
+
+impl<W> EventWriter<W>
+where
+    W: Write,
+{
+    pub const fn new(write: W) -> Self {
+        Self(write)
+    }
+
+    pub fn write(&mut self, event: &Event) -> Result<(), SseError<W::Error>> {
+        let mut retry = heapless::String::new();
+
+        for chunk in serialize(event, &mut retry)? {
+            self.0.write_all(chunk.as_bytes()).map_err(SseError::Io)?;
+        }
+
+        self.0.flush().map_err(SseError::Io)
+    }
+
+    /// Writes an event of the `message` type.
+    pub fn data(&mut self, data: &str) -> Result<(), SseError<W::Error>> {
+        self.write(&Event::new(data))
+    }
+
+    /// Writes a comment, which is ignored by the client.
+    pub fn comment(&mut self, text: &str) -> Result<(), SseError<W::Error>> {
+        for chunk in comment(text) {
+            self.0.write_all(chunk.as_bytes()).map_err(SseError::Io)?;
+        }
+
+        self.0.flush().map_err(SseError::Io)
+    }
+
+    /// Writes an empty comment, to be sent periodically so that idle connections are not closed by proxies.
+    pub fn keep_alive(&mut self) -> Result<(), SseError<W::Error>> {
+        self.0
+            .write_all(KEEP_ALIVE.as_bytes())
+            .map_err(SseError::Io)?;
+
+        self.0.flush().map_err(SseError::Io)
+    }
+
+    pub fn release(self) -> W {
+        self.0
+    }
+}
+
+impl<C> Request<C>
+where
+    C: Connection,
+{
+    /// Initiates a `text/event-stream` response, for sending events to the client.
+    pub fn into_event_stream(self) -> Result<EventWriter<Response<C>>, C::Error> {
+        let response = self.into_response(200, Some("OK"), HEADERS)?;
+
+        Ok(EventWriter::new(response))
+    }
+}
+
+/// The state of the parsing of an event stream, independent of the sync or async input it is read from.
+///
+/// The data of the event being parsed is gathered at the start of `buf`, in front of the unparsed input.
+struct Parser<'b, const N: usize> {
+    buf: &'b mut [u8],
+    data_len: usize,
+    has_data: bool,
+    pos: usize,
+    end: usize,
+    eof: bool,
+    started: bool,
+    event: heapless::String<N>,
+    last_id: heapless::String<N>,
+    event_retry: Option<u32>,
+    retry: Option<u32>,
+}
+
+impl<'b, const N: usize> Parser<'b, N> {
+    fn new(buf: &'b mut [u8]) -> Self {
+        Self {
+            buf,
+            data_len: 0,
+            has_data: false,
+            pos: 0,
+            end: 0,
+            eof: false,
+            started: false,
+            event: heapless::String::new(),
+            last_id: heapless::String::new(),
+            event_retry: None,
+            retry: None,
+        }
+    }
+
+    /// Parses the buffered input until an event is complete, returning `false` if more input is needed.
+    fn parse<E>(&mut self) -> Result<bool, SseError<E>> {
+        if !self.started {
+            const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+            // A leading byte order mark is skipped
+            let input = &self.buf[self.pos..self.end];
+            if input.len() < BOM.len() && BOM.starts_with(input) && !self.eof {
+                return Ok(false);
+            }
+
+            if input.starts_with(BOM) {
+                self.pos += BOM.len();
+            }
+
+            self.started = true;
+        }
+
+        while let Some((len, terminator)) = self.next_line() {
+            let start = self.pos;
+            self.pos += len + terminator;
+
+            if len == 0 {
+                if self.has_data {
+                    return Ok(true);
+                }
+
+                self.reset();
+            } else {
+                self.field(start, start + len)?;
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Returns the length of the next complete line and of its terminator, if any.
+    fn next_line(&self) -> Option<(usize, usize)> {
+        let input = &self.buf[self.pos..self.end];
+        let len = input.iter().position(|&b| b == b'\r' || b == b'\n')?;
+
+        match (input[len], input.get(len + 1)) {
+            (b'\r', Some(b'\n')) => Some((len, 2)),
+            // Whether the line break is `\r\n` is not known yet
+            (b'\r', None) if !self.eof => None,
+            _ => Some((len, 1)),
+        }
+    }
+
+    fn field<E>(&mut self, start: usize, end: usize) -> Result<(), SseError<E>> {
+        let line = &self.buf[start..end];
+
+        let (name, value) = match line.iter().position(|&b| b == b':') {
+            // A comment
+            Some(0) => return Ok(()),
+            Some(colon) => {
+                let value = colon + 1 + usize::from(line.get(colon + 1) == Some(&b' '));
+
+                (&line[..colon], start + value..end)
+            }
+            None => (line, end..end),
+        };
+
+        match name {
+            b"data" => {
+                if self.has_data {
+                    self.buf[self.data_len] = b'\n';
+                    self.data_len += 1;
+                }
+
+                // The data always precedes the line it is taken from
+                self.buf.copy_within(value.clone(), self.data_len);
+                self.data_len += value.len();
+                self.has_data = true;
+            }
+            b"event" => {
+                let value = str::from_utf8(&self.buf[value]).map_err(|_| SseError::InvalidUtf8)?;
+
+                self.event.clear();
+                self.event
+                    .push_str(value)
+                    .map_err(|_| SseError::BufferOverflow)?;
+            }
+            b"id" if !self.buf[value.clone()].contains(&0) => {
+                let value = str::from_utf8(&self.buf[value]).map_err(|_| SseError::InvalidUtf8)?;
+
+                self.last_id.clear();
+                self.last_id
+                    .push_str(value)
+                    .map_err(|_| SseError::BufferOverflow)?;
+            }
+            b"retry" => {
+                let retry = str::from_utf8(&self.buf[value])
+                    .ok()
+                    .filter(|retry| retry.bytes().all(|b| b.is_ascii_digit()))
+                    .and_then(|retry| retry.parse().ok());
+
+                if retry.is_some() {
+                    self.event_retry = retry;
+                    self.retry = retry;
+                }
+            }
+            // Unknown fields are ignored
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn event<E>(&self) -> Result<Event<'_>, SseError<E>> {
+        let data = str::from_utf8(&self.buf[..self.data_len]).map_err(|_| SseError::InvalidUtf8)?;
+
+        Ok(Event {
+            event: (!self.event.is_empty()).then_some(self.event.as_str()),
+            id: (!self.last_id.is_empty()).then_some(self.last_id.as_str()),
+            data,
+            retry: self.event_retry,
+        })
+    }
+
+    /// Starts the parsing of the next event.
+    fn reset(&mut self) {
+        self.data_len = 0;
+        self.has_data = false;
+        self.event.clear();
+        self.event_retry = None;
+    }
+
+    /// Returns the free space of the buffer for more input, after moving the unparsed input next to the data.
+    fn spare<E>(&mut self) -> Result<&mut [u8], SseError<E>> {
+        if self.end == self.buf.len() {
+            self.buf.copy_within(self.pos..self.end, self.data_len);
+            self.end -= self.pos - self.data_len;
+            self.pos = self.data_len;
+
+            if self.end == self.buf.len() {
+                return Err(SseError::BufferOverflow);
+            }
+        }
+
+        Ok(&mut self.buf[self.end..])
+    }
+
+    fn fill(&mut self, len: usize) {
+        self.end += len;
+        self.eof = len == 0;
+    }
+}
+
+/// Reads the events of an event stream, e.g. from a `client::Response` to a request with
+/// an `Accept: text/event-stream` header.
+///
+/// `buf` must fit the data of an event, and `N` is the maximum length of the event types and IDs.
+pub struct EventReader<'b, R, const N: usize = 64> {
+    read: R,
+    parser: Parser<'b, N>,
+}
+
+impl<'b, R, const N: usize> EventReader<'b, R, N>
+where
+    R: Read,
+{
+    pub fn new(read: R, buf: &'b mut [u8]) -> Self {
+        Self {
+            read,
+            parser: Parser::new(buf),
+        }
+    }
+
+    /// Returns the next event, or `None` once the stream ended.
+    pub fn next_event(&mut self) -> Result<Option<Event<'_>>, SseError<R::Error>> {
+        if self.parser.has_data {
+            self.parser.reset();
+        }
+
+        while !self.parser.parse()? {
+            if self.parser.eof {
+                // An incomplete event at the end of the stream is discarded
+                return Ok(None);
+            }
+
+            let spare = self.parser.spare()?;
+            let len = self.read.read(spare).map_err(SseError::Io)?;
+
+            self.parser.fill(len);
+        }
+
+        self.parser.event().map(Some)
+    }
+
+    /// Returns the ID of the last event, to be sent in the `Last-Event-ID` header when reconnecting.
+    pub fn last_event_id(&self) -> Option<&str> {
+        (!self.parser.last_id.is_empty()).then_some(self.parser.last_id.as_str())
+    }
+
+    /// Returns the reconnection time in milliseconds last requested by the server.
+    pub fn retry(&self) -> Option<u32> {
+        self.parser.retry
+    }
+
+    pub fn release(self) -> R {
+        self.read
+    }
+}
+
+pub mod asynch {
+    use crate::http::server::asynch::{Connection, Request, Response};
+    use crate::io::asynch::{Read, Write};
+
+    use super::{comment, serialize, Parser, HEADERS, KEEP_ALIVE};
+
+    pub use super::{Event, SseError, CONTENT_TYPE};
+
+    /// Writes events to an event stream, e.g. to the `server::asynch::Response` returned by
+    /// [`Request::into_event_stream`].
+    ///
+    /// Every event is flushed once written.
+    pub struct EventWriter<W>(W);
+
+    impl<W> EventWriter<W>
+    where
+        W: Write,
+    {
+        pub const fn new(write: W) -> Self {
+            Self(write)
+        }
+
+        pub async fn write(&mut self, event: &Event<'_>) -> Result<(), SseError<W::Error>> {
+            let mut retry = heapless::String::new();
+
+            for chunk in serialize(event, &mut retry)? {
+                self.0
+                    .write_all(chunk.as_bytes())
+                    .await
+                    .map_err(SseError::Io)?;
+            }
+
+            self.0.flush().await.map_err(SseError::Io)
+        }
+
+        /// Writes an event of the `message` type.
+        pub async fn data(&mut self, data: &str) -> Result<(), SseError<W::Error>> {
+            self.write(&Event::new(data)).await
+        }
+
+        /// Writes a comment, which is ignored by the client.
+        pub async fn comment(&mut self, text: &str) -> Result<(), SseError<W::Error>> {
+            for chunk in comment(text) {
+                self.0
+                    .write_all(chunk.as_bytes())
+                    .await
+                    .map_err(SseError::Io)?;
+            }
+
+            self.0.flush().await.map_err(SseError::Io)
+        }
+
+        /// Writes an empty comment, to be sent periodically so that idle connections are not closed by proxies.
+        pub async fn keep_alive(&mut self) -> Result<(), SseError<W::Error>> {
+            self.0
+                .write_all(KEEP_ALIVE.as_bytes())
+                .await
+                .map_err(SseError::Io)?;
+
+            self.0.flush().await.map_err(SseError::Io)
+        }
+
+        pub fn release(self) -> W {
+            self.0
+        }
+    }
+
+    impl<C> Request<C>
+    where
+        C: Connection,
+    {
+        /// Initiates a `text/event-stream` response, for sending events to the client.
+        pub async fn into_event_stream(self) -> Result<EventWriter<Response<C>>, C::Error> {
+            let response = self.into_response(200, Some("OK"), HEADERS).await?;
+
+            Ok(EventWriter::new(response))
+        }
+    }
+
+    /// Reads the events of an event stream, e.g. from a `client::asynch::Response` to a request with
+    /// an `Accept: text/event-stream` header.
+    ///
+    /// `buf` must fit the data of an event, and `N` is the maximum length of the event types and IDs.
+    pub struct EventReader<'b, R, const N: usize = 64> {
+        read: R,
+        parser: Parser<'b, N>,
+    }
+
+    impl<'b, R, const N: usize> EventReader<'b, R, N>
+    where
+        R: Read,
+    {
+        pub fn new(read: R, buf: &'b mut [u8]) -> Self {
+            Self {
+                read,
+                parser: Parser::new(buf),
+            }
+        }
+
+        /// Returns the next event, or `None` once the stream ended.
+        pub async fn next_event(&mut self) -> Result<Option<Event<'_>>, SseError<R::Error>> {
+            if self.parser.has_data {
+                self.parser.reset();
+            }
+
+            while !self.parser.parse()? {
+                if self.parser.eof {
+                    // An incomplete event at the end of the stream is discarded
+                    return Ok(None);
+                }
+
+                let spare = self.parser.spare()?;
+                let len = self.read.read(spare).await.map_err(SseError::Io)?;
+
+                self.parser.fill(len);
+            }
+
+            self.parser.event().map(Some)
+        }
+
+        /// Returns the ID of the last event, to be sent in the `Last-Event-ID` header when reconnecting.
+        pub fn last_event_id(&self) -> Option<&str> {
+            (!self.parser.last_id.is_empty()).then_some(self.parser.last_id.as_str())
+        }
+
+        /// Returns the reconnection time in milliseconds last requested by the server.
+        pub fn retry(&self) -> Option<u32> {
+            self.parser.retry
+        }
+
+        pub fn release(self) -> R {
+            self.read
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+
+    use crate::utils::io::test::{block_on, MockSocket};
+
+    use super::{Event, EventReader, EventWriter, SseError};
+
+    #[test]
+    fn write() {
+        let mut socket = MockSocket::new(b"");
+        let mut writer = EventWriter::new(&mut socket);
+
+        writer
+            .write(&Event::new("a\nb\r\nc\rd").event("temp").id("7").retry(1500))
+            .unwrap();
+        writer.data("").unwrap();
+        writer.comment("x\ny").unwrap();
+        writer.keep_alive().unwrap();
+
+        assert!(matches!(
+            writer.write(&Event::new("x").id("a\nb")),
+            Err(SseError::InvalidField)
+        ));
+        assert!(matches!(
+            writer.write(&Event::new("x").event("a\0")),
+            Err(SseError::InvalidField)
+        ));
+
+        assert_eq!(
+            String::from_utf8(socket.output).unwrap(),
+            "event: temp\nid: 7\nretry: 1500\ndata: a\ndata: b\ndata: c\ndata: d\n\n\
+             data: \n\n: x\n: y\n:\n\n"
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut socket = MockSocket::new(b"");
+        let mut writer = EventWriter::new(&mut socket);
+
+        writer
+            .write(&Event::new(" lead\n\ntrail\n").event("e").id("1"))
+            .unwrap();
+        writer.keep_alive().unwrap();
+        writer.data("plain").unwrap();
+
+        let output = socket.output;
+
+        for chunk in [1, 3, 1000] {
+            let mut buf = [0; 64];
+            let mut reader: EventReader<_> =
+                EventReader::new(MockSocket::chunked(&output, chunk), &mut buf);
+
+            assert_eq!(
+                reader.next_event().unwrap(),
+                Some(Event::new(" lead\n\ntrail\n").event("e").id("1"))
+            );
+            assert_eq!(
+                reader.next_event().unwrap(),
+                Some(Event::new("plain").id("1"))
+            );
+            assert_eq!(reader.next_event().unwrap(), None);
+            assert_eq!(reader.next_event().unwrap(), None);
+            assert_eq!(reader.last_event_id(), Some("1"));
+        }
+    }
+
+    #[test]
+    fn read_line_breaks() {
+        // `\r\n`, `\n` and `\r` line breaks, mixed, with a `\r\n` possibly split across reads
+        const INPUT: &[u8] = b"data: a\r\ndata: b\ndata: c\rdata: d\r\n\r\n\
+                               event: e\rdata:\r\rdata: x\n\r\n\
+                               data: y\r\r";
+
+        for chunk in [1, 2, 3, 5, 1000] {
+            let mut buf = [0; 32];
+            let mut reader: EventReader<_> =
+                EventReader::new(MockSocket::chunked(INPUT, chunk), &mut buf);
+
+            assert_eq!(reader.next_event().unwrap(), Some(Event::new("a\nb\nc\nd")));
+            assert_eq!(
+                reader.next_event().unwrap(),
+                Some(Event::new("").event("e"))
+            );
+            assert_eq!(reader.next_event().unwrap(), Some(Event::new("x")));
+            // A trailing `\r` ends the line at the end of the stream
+            assert_eq!(reader.next_event().unwrap(), Some(Event::new("y")));
+            assert_eq!(reader.next_event().unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn read_bom() {
+        for chunk in [1, 2, 1000] {
+            let mut buf = [0; 32];
+            let mut reader: EventReader<_> = EventReader::new(
+                MockSocket::chunked(b"\xEF\xBB\xBFdata: x\n\n", chunk),
+                &mut buf,
+            );
+            assert_eq!(reader.next_event().unwrap(), Some(Event::new("x")));
+            assert_eq!(reader.next_event().unwrap(), None);
+
+            // Only a leading byte order mark is skipped, a second one is part of the field name
+            let mut buf = [0; 32];
+            let mut reader: EventReader<_> = EventReader::new(
+                MockSocket::chunked(b"\xEF\xBB\xBF\xEF\xBB\xBFdata: x\n\ndata: y\n\n", chunk),
+                &mut buf,
+            );
+            assert_eq!(reader.next_event().unwrap(), Some(Event::new("y")));
+        }
+
+        // A stream shorter than a byte order mark
+        let mut buf = [0; 32];
+        let mut reader: EventReader<_> = EventReader::new(MockSocket::new(b"\xEF\xBB"), &mut buf);
+        assert_eq!(reader.next_event().unwrap(), None);
+    }
+
+    #[test]
+    fn read_fields() {
+        const INPUT: &[u8] = b"ignored\r\n: comment\r\ndata:no-space\r\ndata\r\n\
+                               retry: 12x\r\nretry: 300\r\n\r\n\
+                               event: only\n\n\
+                               id: z\0\ndata: a\nid: 9\n\n\
+                               data: tail";
+
+        for chunk in [1, 2, 7, 1000] {
+            let mut buf = [0; 24];
+            let mut reader: EventReader<_, 8> =
+                EventReader::new(MockSocket::chunked(INPUT, chunk), &mut buf);
+
+            // A `data` field without a value adds an empty line
+            let event = reader.next_event().unwrap().unwrap();
+            assert_eq!(event, Event::new("no-space\n").retry(300));
+
+            // An event without data is not dispatched, and an ID with NUL is ignored
+            assert_eq!(reader.next_event().unwrap(), Some(Event::new("a").id("9")));
+
+            // An unterminated event at the end of the stream is discarded
+            assert_eq!(reader.next_event().unwrap(), None);
+            assert_eq!(reader.retry(), Some(300));
+        }
+    }
+
+    #[test]
+    fn read_overflow() {
+        let mut buf = [0; 8];
+        let mut reader: EventReader<_> =
+            EventReader::new(MockSocket::new(b"data: 0123456789\n\n"), &mut buf);
+        assert!(matches!(reader.next_event(), Err(SseError::BufferOverflow)));
+
+        let mut buf = [0; 64];
+        let mut reader: EventReader<_, 4> =
+            EventReader::new(MockSocket::new(b"event: 01234\ndata\n\n"), &mut buf);
+        assert!(matches!(reader.next_event(), Err(SseError::BufferOverflow)));
+    }
+
+    #[test]
+    fn roundtrip_async() {
+        use super::asynch::{EventReader, EventWriter};
+
+        let mut socket = MockSocket::new(b"");
+        let mut writer = EventWriter::new(&mut socket);
+
+        block_on(writer.write(&Event::new("x\r\ny").event("e"))).unwrap();
+
+        let output = socket.output;
+        assert_eq!(output, b"event: e\ndata: x\ndata: y\n\n");
+
+        let mut buf = [0; 32];
+        let mut reader: EventReader<_> =
+            EventReader::new(MockSocket::chunked(&output, 3), &mut buf);
+
+        assert_eq!(
+            block_on(reader.next_event()).unwrap(),
+            Some(Event::new("x\ny").event("e"))
+        );
+        assert_eq!(block_on(reader.next_event()).unwrap(), None);
+    }
+}
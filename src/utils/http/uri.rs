@@ -0,0 +1,791 @@
+use core::fmt::{self, Write};
+use core::str;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UriError {
+    InvalidEncoding,
+    InvalidUtf8,
+    InvalidComponentOrder,
+    BufferOverflow,
+}
+
+impl fmt::Display for UriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEncoding => write!(f, "Invalid percent-encoding"),
+            Self::InvalidUtf8 => write!(f, "Decoded data is not valid UTF-8"),
+            Self::InvalidComponentOrder => write!(f, "URI components appended out of order"),
+            Self::BufferOverflow => write!(f, "Buffer overflow"),
+        }
+    }
+}
+
+impl core::error::Error for UriError {}
+
+impl From<fmt::Error> for UriError {
+    fn from(_: fmt::Error) -> Self {
+        Self::BufferOverflow
+    }
+}
+
+/// A URI split into its components, as per RFC 3986.
+///
+/// Works with both the origin form (`/path?query`) received by servers
+/// and the absolute form (`http://host:port/path?query`) used by clients.
+/// The authority form (`host:port`) of `CONNECT` requests is split into an authority,
+/// rather than into a scheme followed by a path.
+/// All components are returned raw, i.e. still percent-encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Uri<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path_and_query: &'a str,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Uri<'a> {
+    pub fn new(uri: &'a str) -> Self {
+        let (target, fragment) = match uri.split_once('#') {
+            Some((target, fragment)) => (target, Some(fragment)),
+            None => (uri, None),
+        };
+
+        let authority_form = is_authority_form(target);
+
+        let (scheme, rest) = match target.split_once(':') {
+            Some((scheme, rest)) if is_scheme(scheme) && !authority_form => (Some(scheme), rest),
+            _ => (None, target),
+        };
+
+        let authority = if authority_form {
+            Some(target)
+        } else {
+            rest.strip_prefix("//")
+        };
+
+        let (authority, path_and_query) = match authority {
+            Some(rest) => {
+                let index = rest.find(['/', '?']).unwrap_or(rest.len());
+
+                (Some(&rest[..index]), &rest[index..])
+            }
+            None => (None, rest),
+        };
+
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query, None),
+        };
+
+        Self {
+            scheme,
+            authority,
+            path_and_query,
+            path,
+            query,
+            fragment,
+        }
+    }
+
+    pub fn scheme(&self) -> Option<&'a str> {
+        self.scheme
+    }
+
+    pub fn authority(&self) -> Option<&'a str> {
+        self.authority
+    }
+
+    pub fn userinfo(&self) -> Option<&'a str> {
+        self.authority
+            .and_then(|authority| authority.rsplit_once('@'))
+            .map(|(userinfo, _)| userinfo)
+    }
+
+    pub fn host(&self) -> Option<&'a str> {
+        self.host_port().map(|(host, _)| host)
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.host_port()
+            .and_then(|(_, port)| port)
+            .and_then(|port| port.parse().ok())
+    }
+
+    /// The port of the URI, or the default port of its scheme if the URI has no explicit port.
+    pub fn port_or_default(&self) -> Option<u16> {
+        self.port().or_else(|| match self.scheme {
+            Some(scheme) if scheme.eq_ignore_ascii_case("http") => Some(80),
+            Some(scheme) if scheme.eq_ignore_ascii_case("ws") => Some(80),
+            Some(scheme) if scheme.eq_ignore_ascii_case("https") => Some(443),
+            Some(scheme) if scheme.eq_ignore_ascii_case("wss") => Some(443),
+            _ => None,
+        })
+    }
+
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    pub fn query(&self) -> Option<&'a str> {
+        self.query
+    }
+
+    pub fn fragment(&self) -> Option<&'a str> {
+        self.fragment
+    }
+
+    /// The path and the query of the URI, as sent in the request line of an HTTP request.
+    pub fn path_and_query(&self) -> &'a str {
+        self.path_and_query
+    }
+
+    pub fn query_pairs(&self) -> QueryPairs<'a> {
+        QueryPairs::new(self.query.unwrap_or(""))
+    }
+
+    /// Finds the first query parameter named `name` and decodes its value into `buf`.
+    pub fn query_value<'b>(
+        &self,
+        name: &str,
+        buf: &'b mut [u8],
+    ) -> Result<Option<&'b str>, UriError> {
+        match self.query_pairs().find(|pair| pair.is(name)) {
+            Some(pair) => pair.decode_value(buf).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `true` if both URIs have the same scheme, host and port.
+    pub fn is_same_origin(&self, other: &Uri<'_>) -> bool {
+        fn eq(a: Option<&str>, b: Option<&str>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                (a, b) => a == b,
+            }
+        }
+
+        eq(self.scheme, other.scheme)
+            && eq(self.host(), other.host())
+            && self.port_or_default() == other.port_or_default()
+    }
+
+    /// Resolves `reference` (e.g. the value of a `Location` header) against this URI into `buf`,
+    /// as per RFC 3986, section 5.2.
+    pub fn resolve<'b>(&self, reference: &str, buf: &'b mut [u8]) -> Result<&'b str, UriError> {
+        let reference = Uri::new(reference);
+        let mut out = SliceWriter { buf, len: 0 };
+
+        // The "directory" of the base path, to be merged with a relative reference path
+        let mut base_dir = None;
+
+        let (authority, path, query) =
+            if reference.scheme.is_some() || reference.authority.is_some() {
+                (reference.authority, reference.path, reference.query)
+            } else if reference.path.is_empty() {
+                (self.authority, self.path, reference.query.or(self.query))
+            } else {
+                if !reference.path.starts_with('/') {
+                    base_dir = match self.path.rfind('/') {
+                        Some(index) => Some(&self.path[..index]),
+                        None if self.authority.is_some() => Some(""),
+                        None => None,
+                    };
+                }
+
+                (self.authority, reference.path, reference.query)
+            };
+
+        if let Some(scheme) = reference.scheme.or(self.scheme) {
+            write!(out, "{scheme}:")?;
+        }
+
+        if let Some(authority) = authority {
+            write!(out, "//{authority}")?;
+        }
+
+        remove_dot_segments(
+            base_dir
+                .map(|dir| dir.split('/'))
+                .into_iter()
+                .flatten()
+                .chain(path.split('/')),
+            &mut out,
+        )?;
+
+        if let Some(query) = query {
+            write!(out, "?{query}")?;
+        }
+
+        if let Some(fragment) = reference.fragment {
+            write!(out, "#{fragment}")?;
+        }
+
+        let len = out.len;
+
+        str::from_utf8(&buf[..len]).map_err(|_| UriError::InvalidUtf8)
+    }
+
+    fn host_port(&self) -> Option<(&'a str, Option<&'a str>)> {
+        let authority = self.authority?;
+        let host_port = authority
+            .rsplit_once('@')
+            .map(|(_, host_port)| host_port)
+            .unwrap_or(authority);
+
+        let port_separator = if host_port.starts_with('[') {
+            host_port.find("]:").map(|index| index + 1)
+        } else {
+            host_port.rfind(':')
+        };
+
+        Some(match port_separator {
+            Some(index) => (&host_port[..index], Some(&host_port[index + 1..])),
+            None => (host_port, None),
+        })
+    }
+}
+
+/// Writes the segments of a path, removing the `.` and `..` segments, as per RFC 3986, section 5.2.4.
+fn remove_dot_segments<'a>(
+    segments: impl Iterator<Item = &'a str>,
+    out: &mut SliceWriter<'_>,
+) -> Result<(), UriError> {
+    let start = out.len;
+    let mut segments = segments.peekable();
+
+    // A relative path, i.e. one without a leading empty segment, is written without a leading slash
+    let relative = segments.next_if(|segment| segment.is_empty()).is_none();
+
+    while let Some(segment) = segments.next() {
+        match segment {
+            "." => (),
+            ".." => {
+                let path = &out.buf[start..out.len];
+                out.len = start + path.iter().rposition(|&b| b == b'/').unwrap_or(0);
+            }
+            segment => {
+                if !relative || out.len > start {
+                    out.write_char('/')?;
+                }
+
+                out.write_str(segment)?;
+            }
+        }
+
+        // A trailing dot segment still denotes a directory
+        if segments.peek().is_none() && matches!(segment, "." | "..") {
+            out.write_char('/')?;
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) struct SliceWriter<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> SliceWriter<'b> {
+    pub(crate) fn new(buf: &'b mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    pub(crate) fn into_str(self) -> &'b str {
+        // Safe to unwrap, only strings are written
+        str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let buf = self
+            .buf
+            .get_mut(self.len..self.len + s.len())
+            .ok_or(fmt::Error)?;
+        buf.copy_from_slice(s.as_bytes());
+        self.len += s.len();
+
+        Ok(())
+    }
+}
+
+/// Returns `true` if `target` starts with `host:port`, where `host` is a name, an IPv4 address
+/// or a bracketed IPv6 address.
+fn is_authority_form(target: &str) -> bool {
+    let authority = &target[..target.find(['/', '?']).unwrap_or(target.len())];
+
+    authority.rsplit_once(':').is_some_and(|(host, port)| {
+        !host.is_empty()
+            && (host.starts_with('[') || !host.contains(':'))
+            && !port.is_empty()
+            && port.bytes().all(|byte| byte.is_ascii_digit())
+    })
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// A single `name=value` pair of a query string, still percent-encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QueryPair<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+impl QueryPair<'_> {
+    /// Returns `true` if the decoded name of the pair is equal to `name`.
+    pub fn is(&self, name: &str) -> bool {
+        Decoder::new(self.name, true).eq(name.bytes().map(Ok))
+    }
+
+    pub fn decode_name<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, UriError> {
+        decode_query(self.name, buf)
+    }
+
+    pub fn decode_value<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, UriError> {
+        decode_query(self.value, buf)
+    }
+
+    pub fn decode<'b, 'c>(
+        &self,
+        name_buf: &'b mut [u8],
+        value_buf: &'c mut [u8],
+    ) -> Result<(&'b str, &'c str), UriError> {
+        Ok((self.decode_name(name_buf)?, self.decode_value(value_buf)?))
+    }
+}
+
+pub struct QueryPairs<'a>(str::Split<'a, char>);
+
+impl<'a> QueryPairs<'a> {
+    pub fn new(query: &'a str) -> Self {
+        Self(query.split('&'))
+    }
+}
+
+impl<'a> Iterator for QueryPairs<'a> {
+    type Item = QueryPair<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .by_ref()
+            .find(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) => QueryPair { name, value },
+                None => QueryPair {
+                    name: pair,
+                    value: "",
+                },
+            })
+    }
+}
+
+/// Percent-decodes `input` into `buf`.
+pub fn decode<'b>(input: &str, buf: &'b mut [u8]) -> Result<&'b str, UriError> {
+    decode_into(Decoder::new(input, false), buf)
+}
+
+/// Percent-decodes a query string component into `buf`, treating `+` as a space.
+pub fn decode_query<'b>(input: &str, buf: &'b mut [u8]) -> Result<&'b str, UriError> {
+    decode_into(Decoder::new(input, true), buf)
+}
+
+fn decode_into<'b>(decoder: Decoder<'_>, buf: &'b mut [u8]) -> Result<&'b str, UriError> {
+    let mut len = 0;
+
+    for byte in decoder {
+        *buf.get_mut(len).ok_or(UriError::BufferOverflow)? = byte?;
+        len += 1;
+    }
+
+    str::from_utf8(&buf[..len]).map_err(|_| UriError::InvalidUtf8)
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    plus_as_space: bool,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(input: &'a str, plus_as_space: bool) -> Self {
+        Self {
+            input: input.as_bytes(),
+            plus_as_space,
+        }
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = Result<u8, UriError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&byte, rest) = self.input.split_first()?;
+
+        Some(match byte {
+            b'%' => {
+                let byte = rest
+                    .get(..2)
+                    .and_then(|hex| Some((hex_digit(hex[0])? << 4) | hex_digit(hex[1])?));
+
+                match byte {
+                    Some(byte) => {
+                        self.input = &rest[2..];
+                        Ok(byte)
+                    }
+                    None => {
+                        self.input = &[];
+                        Err(UriError::InvalidEncoding)
+                    }
+                }
+            }
+            b'+' if self.plus_as_space => {
+                self.input = rest;
+                Ok(b' ')
+            }
+            byte => {
+                self.input = rest;
+                Ok(byte)
+            }
+        })
+    }
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+/// Percent-encodes `input` into `out`.
+///
+/// Only the RFC 3986 unreserved characters are left as-is, so the output is safe
+/// to use as a path segment, a query name or value, or a fragment.
+pub fn encode<W>(input: &str, out: &mut W) -> fmt::Result
+where
+    W: Write,
+{
+    let mut plain = 0;
+
+    for (index, byte) in input.bytes().enumerate() {
+        if !is_unreserved(byte) {
+            if plain < index {
+                out.write_str(&input[plain..index])?;
+            }
+
+            write!(out, "%{byte:02X}")?;
+
+            plain = index + 1;
+        }
+    }
+
+    if plain < input.len() {
+        out.write_str(&input[plain..])?;
+    }
+
+    Ok(())
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum BuilderState {
+    Path,
+    Query,
+    Fragment,
+}
+
+/// Builds a URI with correctly percent-encoded path segments, query pairs and fragment
+/// into any [`core::fmt::Write`] output, e.g. a `heapless::String`.
+pub struct UriBuilder<W> {
+    out: W,
+    state: BuilderState,
+}
+
+impl<W> UriBuilder<W>
+where
+    W: Write,
+{
+    pub const fn new(out: W) -> Self {
+        Self {
+            out,
+            state: BuilderState::Path,
+        }
+    }
+
+    /// Appends `base` as-is; typically the scheme, authority and the beginning of the path.
+    pub fn base(&mut self, base: &str) -> Result<&mut Self, UriError> {
+        self.check_state(BuilderState::Path)?;
+        self.out.write_str(base)?;
+
+        Ok(self)
+    }
+
+    /// Appends a `/` followed by the percent-encoded `segment`.
+    pub fn segment(&mut self, segment: &str) -> Result<&mut Self, UriError> {
+        self.check_state(BuilderState::Path)?;
+        self.out.write_char('/')?;
+        encode(segment, &mut self.out)?;
+
+        Ok(self)
+    }
+
+    /// Appends a percent-encoded `name=value` query pair.
+    pub fn query(&mut self, name: &str, value: &str) -> Result<&mut Self, UriError> {
+        self.check_state(BuilderState::Query)?;
+
+        let separator = if self.state == BuilderState::Query {
+            '&'
+        } else {
+            '?'
+        };
+
+        self.state = BuilderState::Query;
+
+        self.out.write_char(separator)?;
+        encode(name, &mut self.out)?;
+        self.out.write_char('=')?;
+        encode(value, &mut self.out)?;
+
+        Ok(self)
+    }
+
+    pub fn fragment(&mut self, fragment: &str) -> Result<&mut Self, UriError> {
+        self.check_state(BuilderState::Fragment)?;
+        self.state = BuilderState::Fragment;
+
+        self.out.write_char('#')?;
+        encode(fragment, &mut self.out)?;
+
+        Ok(self)
+    }
+
+    pub fn release(self) -> W {
+        self.out
+    }
+
+    fn check_state(&self, state: BuilderState) -> Result<(), UriError> {
+        // Components must be appended in order, and there can be only one fragment
+        if self.state > state || self.state == BuilderState::Fragment {
+            Err(UriError::InvalidComponentOrder)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<const N: usize> UriBuilder<heapless::String<N>> {
+    pub fn as_str(&self) -> &str {
+        self.out.as_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl UriBuilder<alloc::string::String> {
+    pub fn as_str(&self) -> &str {
+        self.out.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, decode_query, Uri, UriBuilder, UriError};
+
+    #[test]
+    fn split() {
+        let uri = Uri::new("http://user:pw@example.com:8080/a/b%20c?x=1&y=a+b%26&z#frag");
+
+        assert_eq!(uri.scheme(), Some("http"));
+        assert_eq!(uri.authority(), Some("user:pw@example.com:8080"));
+        assert_eq!(uri.userinfo(), Some("user:pw"));
+        assert_eq!(uri.host(), Some("example.com"));
+        assert_eq!(uri.port(), Some(8080));
+        assert_eq!(uri.path(), "/a/b%20c");
+        assert_eq!(uri.query(), Some("x=1&y=a+b%26&z"));
+        assert_eq!(uri.fragment(), Some("frag"));
+        assert_eq!(uri.path_and_query(), "/a/b%20c?x=1&y=a+b%26&z");
+
+        let uri = Uri::new("/devices/1?a=b");
+        assert_eq!(uri.scheme(), None);
+        assert_eq!(uri.host(), None);
+        assert_eq!(uri.path(), "/devices/1");
+        assert_eq!(uri.query(), Some("a=b"));
+
+        let uri = Uri::new("http://[::1]:81/x");
+        assert_eq!(uri.host(), Some("[::1]"));
+        assert_eq!(uri.port(), Some(81));
+
+        let uri = Uri::new("https://example.com?q");
+        assert_eq!(uri.path(), "");
+        assert_eq!(uri.query(), Some("q"));
+        assert_eq!(uri.port_or_default(), Some(443));
+        assert_eq!(uri.path_and_query(), "?q");
+
+        let uri = Uri::new("mailto:user@example.com");
+        assert_eq!(uri.scheme(), Some("mailto"));
+        assert_eq!(uri.path(), "user@example.com");
+    }
+
+    #[test]
+    fn split_authority_form() {
+        let uri = Uri::new("host:8080");
+        assert_eq!(uri.scheme(), None);
+        assert_eq!(uri.host(), Some("host"));
+        assert_eq!(uri.port(), Some(8080));
+        assert_eq!(uri.path(), "");
+
+        let uri = Uri::new("192.168.1.1:443");
+        assert_eq!(uri.host(), Some("192.168.1.1"));
+        assert_eq!(uri.port(), Some(443));
+
+        let uri = Uri::new("[::1]:443");
+        assert_eq!(uri.host(), Some("[::1]"));
+        assert_eq!(uri.port(), Some(443));
+
+        assert_eq!(Uri::new("urn:isbn:123").scheme(), Some("urn"));
+        assert_eq!(Uri::new("g:h").scheme(), Some("g"));
+    }
+
+    #[test]
+    fn query() {
+        let uri = Uri::new("/?x=1&y=a+b%26&&z");
+        let mut buf = [0; 32];
+
+        assert_eq!(uri.query_value("y", &mut buf), Ok(Some("a b&")));
+        assert_eq!(uri.query_value("z", &mut buf), Ok(Some("")));
+        assert_eq!(uri.query_value("q", &mut buf), Ok(None));
+        assert_eq!(uri.query_pairs().count(), 3);
+        assert_eq!(
+            uri.query_value("y", &mut [0; 2]),
+            Err(UriError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn percent_decode() {
+        let mut buf = [0; 32];
+
+        assert_eq!(decode("%e2%82%ac+%2F", &mut buf), Ok("€+/"));
+        assert_eq!(decode_query("a+b%20c", &mut buf), Ok("a b c"));
+        assert_eq!(decode("%zz", &mut buf), Err(UriError::InvalidEncoding));
+        assert_eq!(decode("%+1", &mut buf), Err(UriError::InvalidEncoding));
+        assert_eq!(decode("%-1", &mut buf), Err(UriError::InvalidEncoding));
+        assert_eq!(decode("%1", &mut buf), Err(UriError::InvalidEncoding));
+        assert_eq!(decode("%", &mut buf), Err(UriError::InvalidEncoding));
+        assert_eq!(decode("%ff", &mut buf), Err(UriError::InvalidUtf8));
+    }
+
+    #[test]
+    fn build() {
+        let mut builder = UriBuilder::new(heapless::String::<128>::new());
+
+        builder
+            .base("http://h")
+            .unwrap()
+            .segment("a b")
+            .unwrap()
+            .segment("€/")
+            .unwrap()
+            .query("k", "v w")
+            .unwrap()
+            .query("x~", "1")
+            .unwrap()
+            .fragment("f")
+            .unwrap();
+
+        assert_eq!(
+            builder.as_str(),
+            "http://h/a%20b/%E2%82%AC%2F?k=v%20w&x~=1#f"
+        );
+        assert_eq!(
+            builder.segment("x").err(),
+            Some(UriError::InvalidComponentOrder)
+        );
+
+        let mut builder = UriBuilder::new(heapless::String::<4>::new());
+        assert_eq!(
+            builder.segment("abcdef").err(),
+            Some(UriError::BufferOverflow)
+        );
+    }
+
+    /// The examples of RFC 3986, section 5.4
+    #[test]
+    fn resolve() {
+        let base = Uri::new("http://a/b/c/d;p?q");
+        let mut buf = [0; 128];
+
+        for (reference, expected) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("g?y#s", "http://a/b/c/g?y#s"),
+            (";x", "http://a/b/c/;x"),
+            ("g;x", "http://a/b/c/g;x"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("../../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            (".g", "http://a/b/c/.g"),
+            ("g..", "http://a/b/c/g.."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+            ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("g?y/./x", "http://a/b/c/g?y/./x"),
+            ("g#s/../x", "http://a/b/c/g#s/../x"),
+            ("http:g", "http:g"),
+        ] {
+            assert_eq!(
+                base.resolve(reference, &mut buf),
+                Ok(expected),
+                "{reference}"
+            );
+        }
+
+        assert_eq!(
+            Uri::new("http://h").resolve("x", &mut buf),
+            Ok("http://h/x")
+        );
+        assert_eq!(
+            Uri::new("http://h/a").resolve("/aaaaaaaaaaaa", &mut [0; 8]),
+            Err(UriError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn same_origin() {
+        assert!(Uri::new("http://h:80/a").is_same_origin(&Uri::new("HTTP://H/b")));
+        assert!(!Uri::new("http://h/a").is_same_origin(&Uri::new("https://h/b")));
+        assert!(!Uri::new("http://h/a").is_same_origin(&Uri::new("http://h:8080/a")));
+    }
+}
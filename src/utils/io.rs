@@ -104,6 +104,47 @@ where
     Ok(copied)
 }
 
+/// The storage of data of unknown length: a fixed-size `[u8]` slice or `heapless::Vec`,
+/// or with `alloc`, a `Vec<u8>` which grows as needed.
+pub trait Buffer {
+    /// Returns the whole storage, after growing it to `len` bytes if possible.
+    fn reserve(&mut self, len: usize) -> &mut [u8];
+}
+
+impl Buffer for [u8] {
+    fn reserve(&mut self, _len: usize) -> &mut [u8] {
+        self
+    }
+}
+
+impl<const N: usize> Buffer for [u8; N] {
+    fn reserve(&mut self, _len: usize) -> &mut [u8] {
+        self
+    }
+}
+
+impl<const N: usize> Buffer for heapless::Vec<u8, N> {
+    fn reserve(&mut self, len: usize) -> &mut [u8] {
+        if self.len() < len {
+            // Cannot fail, the length is capped to the capacity
+            let _ = self.resize(len.min(N), 0);
+        }
+
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Buffer for alloc::vec::Vec<u8> {
+    fn reserve(&mut self, len: usize) -> &mut [u8] {
+        if self.len() < len {
+            self.resize(len, 0);
+        }
+
+        self
+    }
+}
+
 pub mod asynch {
     use crate::io::asynch::{Read, Write};
 
@@ -191,3 +232,107 @@ pub mod asynch {
         Ok(copied)
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    extern crate std;
+
+    use core::convert::Infallible;
+    use core::future::Future;
+    use core::pin::{pin, Pin};
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use std::vec::Vec;
+
+    use crate::io::{ErrorType, Read, Write};
+
+    /// An in-memory socket, reading from `input` in reads of at most `chunk` bytes and writing to `output`.
+    pub struct MockSocket<'a> {
+        pub input: &'a [u8],
+        pub output: Vec<u8>,
+        pub chunk: usize,
+    }
+
+    impl<'a> MockSocket<'a> {
+        pub fn new(input: &'a [u8]) -> Self {
+            Self::chunked(input, usize::MAX)
+        }
+
+        pub fn chunked(input: &'a [u8], chunk: usize) -> Self {
+            Self {
+                input,
+                output: Vec::new(),
+                chunk,
+            }
+        }
+    }
+
+    impl ErrorType for MockSocket<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for MockSocket<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = buf.len().min(self.input.len()).min(self.chunk);
+
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+
+            Ok(len)
+        }
+    }
+
+    impl Write for MockSocket<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.output.extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    impl crate::io::asynch::Read for MockSocket<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Read::read(self, buf)
+        }
+    }
+
+    impl crate::io::asynch::Write for MockSocket<'_> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Write::write(self, buf)
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// Runs a future which never waits on anything to completion.
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+
+        loop {
+            if let Poll::Ready(output) = poll_once(future.as_mut()) {
+                return output;
+            }
+        }
+    }
+
+    /// Polls a future once, with a waker doing nothing.
+    pub fn poll_once<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(
+            |_| RawWaker::new(core::ptr::null(), &VTABLE),
+            |_| (),
+            |_| (),
+            |_| (),
+        );
+
+        // Safety: the vtable functions do nothing with the null data pointer
+        let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) };
+
+        future.poll(&mut Context::from_waker(&waker))
+    }
+}
@@ -0,0 +1,980 @@
+//! An RFC 6455 WebSocket frame codec over `embedded_io` streams.
+
+use core::fmt;
+
+use crate::io::{Error, ErrorKind, Read, ReadExactError, Write};
+use crate::ws::{ErrorType, FrameType, Receiver, Sender};
+
+pub mod client;
+#[cfg(feature = "deflate")]
+pub mod deflate;
+pub mod message;
+pub mod server;
+pub mod session;
+
+/// The maximum length of the payload of a control frame, i.e. a `Close`, `Ping` or `Pong` frame.
+pub const MAX_CONTROL_PAYLOAD_LEN: usize = 125;
+
+const MAX_HEADER_LEN: usize = 14;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WsError<E> {
+    Io(E),
+    /// The stream ended within a frame.
+    UnexpectedEof,
+    /// The peer sent a frame violating the protocol, or a frame to send is not valid.
+    InvalidFrame,
+    /// The payload of a received frame does not fit into the buffer. It is skipped by the next receive.
+    BufferOverflow,
+}
+
+impl<E: fmt::Debug> fmt::Display for WsError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for WsError<E> {}
+
+impl<E> Error for WsError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::UnexpectedEof => ErrorKind::ConnectionAborted,
+            Self::InvalidFrame => ErrorKind::InvalidData,
+            Self::BufferOverflow => ErrorKind::OutOfMemory,
+        }
+    }
+}
+
+impl<E> From<ReadExactError<E>> for WsError<E> {
+    fn from(e: ReadExactError<E>) -> Self {
+        match e {
+            ReadExactError::UnexpectedEof => Self::UnexpectedEof,
+            ReadExactError::Other(e) => Self::Io(e),
+        }
+    }
+}
+
+/// A WebSocket connection over a stream, after the opening handshake.
+///
+/// Each [`Receiver::recv`] returns a single frame, with [`FrameType::SocketClose`] once the stream ends,
+/// and each [`Sender::send`] sends a single frame. See [`message`] for whole messages.
+///
+/// The client side masks the frames it sends with keys generated by `mask`, which fills the provided
+/// buffer with random data, as required by RFC 6455.
+///
+/// Receiving a frame resumes where it stopped if the previous receive failed with an I/O error, e.g. a read
+/// timeout, or if it was an async one which got cancelled, provided that it is given the same buffer.
+/// A frame too large for the buffer fails with [`WsError::BufferOverflow`], and its payload is skipped
+/// by the next receive, so that the connection stays in sync.
+pub struct WsConnection<T, M = fn(&mut [u8])> {
+    io: T,
+    mask: Option<M>,
+    recv: RecvState,
+}
+
+impl<T> WsConnection<T> {
+    /// Creates the server side of a connection, receiving masked frames and sending unmasked ones.
+    pub const fn server(io: T) -> Self {
+        Self {
+            io,
+            mask: None,
+            recv: RecvState::new(),
+        }
+    }
+}
+
+impl<T, M> WsConnection<T, M>
+where
+    M: FnMut(&mut [u8]),
+{
+    /// Creates the client side of a connection, receiving unmasked frames and sending masked ones.
+    pub const fn client(io: T, mask: M) -> Self {
+        Self {
+            io,
+            mask: Some(mask),
+            recv: RecvState::new(),
+        }
+    }
+}
+
+impl<T, M> WsConnection<T, M> {
+    pub fn is_client(&self) -> bool {
+        self.mask.is_some()
+    }
+
+    pub fn io(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    pub fn release(self) -> T {
+        self.io
+    }
+}
+
+impl<T, M> WsConnection<T, M>
+where
+    M: FnMut(&mut [u8]),
+{
+    /// Returns the header of a frame to send, with a new masking key on the client side.
+    fn header(
+        &mut self,
+        frame_type: FrameType,
+        compressed: bool,
+        frame_data: &[u8],
+    ) -> FrameHeader {
+        let mask_key = self.mask.as_mut().map(|mask| {
+            let mut key = [0; 4];
+            mask(&mut key);
+            key
+        });
+
+        FrameHeader {
+            frame_type,
+            compressed,
+            payload_len: frame_data.len() as u64,
+            mask_key,
+        }
+    }
+}
+
+impl<T, M> ErrorType for WsConnection<T, M>
+where
+    T: crate::io::ErrorType,
+{
+    type Error = WsError<T::Error>;
+}
+
+impl<T, M> Receiver for WsConnection<T, M>
+where
+    T: Read,
+    M: FnMut(&mut [u8]),
+{
+    fn recv(&mut self, frame_data_buf: &mut [u8]) -> Result<(FrameType, usize), Self::Error> {
+        let client = self.is_client();
+
+        loop {
+            let buf = self.recv.remaining(frame_data_buf)?;
+            let read = self.io.read(buf).map_err(WsError::Io)?;
+
+            if let Some((header, len)) = self.recv.advance(read, frame_data_buf, client, false)? {
+                return Ok((header.frame_type, len));
+            }
+        }
+    }
+}
+
+impl<T, M> Sender for WsConnection<T, M>
+where
+    T: Write,
+    M: FnMut(&mut [u8]),
+{
+    fn send(&mut self, frame_type: FrameType, frame_data: &[u8]) -> Result<(), Self::Error> {
+        let header = self.header(frame_type, false, frame_data);
+
+        write_frame(&mut self.io, &header, frame_data)
+    }
+}
+
+/// The progress of the frame being received.
+struct RecvState {
+    header: [u8; MAX_HEADER_LEN],
+    header_len: usize,
+    /// The header of the frame whose payload is being received, with the length received so far.
+    frame: Option<(FrameHeader, usize)>,
+    /// The length of the payload of a frame which did not fit into the buffer, which remains to be skipped.
+    skip: u64,
+}
+
+impl RecvState {
+    const fn new() -> Self {
+        Self {
+            header: [0; MAX_HEADER_LEN],
+            header_len: 0,
+            frame: None,
+            skip: 0,
+        }
+    }
+
+    /// Returns the part of the header, or of the payload in `frame_data_buf`, which remains to be read.
+    ///
+    /// A payload to be skipped is read into `frame_data_buf`, or into the header if there is no room.
+    fn remaining<'a, E>(
+        &'a mut self,
+        frame_data_buf: &'a mut [u8],
+    ) -> Result<&'a mut [u8], WsError<E>> {
+        if self.skip > 0 {
+            let buf = if frame_data_buf.is_empty() {
+                &mut self.header[..]
+            } else {
+                frame_data_buf
+            };
+            let len = buf.len().min(self.skip.try_into().unwrap_or(usize::MAX));
+
+            return Ok(&mut buf[..len]);
+        }
+
+        match &self.frame {
+            // The length fits into a `usize`, as it was checked against a buffer
+            Some((header, read)) => frame_data_buf
+                .get_mut(*read..header.payload_len as usize)
+                .ok_or(WsError::BufferOverflow),
+            None => {
+                let len = if self.header_len < 2 {
+                    2
+                } else {
+                    FrameHeader::len(self.header[1])
+                };
+
+                Ok(&mut self.header[self.header_len..len])
+            }
+        }
+    }
+
+    /// Accounts for `read` bytes read into the part returned by [`Self::remaining`], and returns the header
+    /// of the frame and the length of its payload once it is complete, or a [`FrameType::SocketClose`] header
+    /// if the stream ended before it.
+    ///
+    /// Compressed frames are only accepted with `compression`, i.e. with permessage-deflate.
+    fn advance<E>(
+        &mut self,
+        read: usize,
+        frame_data_buf: &mut [u8],
+        client: bool,
+        compression: bool,
+    ) -> Result<Option<(FrameHeader, usize)>, WsError<E>> {
+        if read == 0 {
+            let started = self.header_len > 0 || self.frame.is_some() || self.skip > 0;
+            *self = Self::new();
+
+            return if started {
+                Err(WsError::UnexpectedEof)
+            } else {
+                let header = FrameHeader {
+                    frame_type: FrameType::SocketClose,
+                    compressed: false,
+                    payload_len: 0,
+                    mask_key: None,
+                };
+
+                Ok(Some((header, 0)))
+            };
+        }
+
+        if self.skip > 0 {
+            self.skip -= read as u64;
+
+            return Ok(None);
+        }
+
+        if let Some((_, received)) = &mut self.frame {
+            *received += read;
+        } else {
+            self.header_len += read;
+
+            if self.header_len < 2 || self.header_len < FrameHeader::len(self.header[1]) {
+                return Ok(None);
+            }
+
+            let header = FrameHeader::parse(&self.header[..self.header_len]);
+            self.header_len = 0;
+            let header = header?;
+
+            // Compressed frames are only valid with permessage-deflate
+            if header.compressed && !compression {
+                return Err(WsError::InvalidFrame);
+            }
+
+            if let Err(e) = header.check(client, frame_data_buf) {
+                if matches!(e, WsError::BufferOverflow) {
+                    self.skip = header.payload_len;
+                }
+
+                return Err(e);
+            }
+
+            self.frame = Some((header, 0));
+        }
+
+        match self.frame.take() {
+            Some((header, len)) if len as u64 == header.payload_len => {
+                header.mask(&mut frame_data_buf[..len], 0);
+
+                Ok(Some((header, len)))
+            }
+            frame => {
+                self.frame = frame;
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn write_frame<T>(
+    io: &mut T,
+    header: &FrameHeader,
+    frame_data: &[u8],
+) -> Result<(), WsError<T::Error>>
+where
+    T: Write,
+{
+    io.write_all(&header.serialize()?).map_err(WsError::Io)?;
+
+    if header.mask_key.is_some() {
+        let mut buf = [0; 64];
+
+        for (index, chunk) in frame_data.chunks(buf.len()).enumerate() {
+            let buf = &mut buf[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            header.mask(buf, index * 64);
+
+            io.write_all(buf).map_err(WsError::Io)?;
+        }
+    } else {
+        io.write_all(frame_data).map_err(WsError::Io)?;
+    }
+
+    io.flush().map_err(WsError::Io)
+}
+
+/// The header of a frame as sent on the wire.
+struct FrameHeader {
+    frame_type: FrameType,
+    /// The `RSV1` bit, set on the first frame of messages compressed with permessage-deflate.
+    compressed: bool,
+    payload_len: u64,
+    mask_key: Option<[u8; 4]>,
+}
+
+impl FrameHeader {
+    /// Returns the length of a header, given its second byte.
+    fn len(second: u8) -> usize {
+        let len = match second & 0x7f {
+            126 => 4,
+            127 => 10,
+            _ => 2,
+        };
+
+        if second & 0x80 != 0 {
+            len + 4
+        } else {
+            len
+        }
+    }
+
+    fn parse<E>(buf: &[u8]) -> Result<Self, WsError<E>> {
+        let fin = buf[0] & 0x80 != 0;
+
+        // No supported extension defines `RSV2` and `RSV3`
+        if buf[0] & 0x30 != 0 {
+            return Err(WsError::InvalidFrame);
+        }
+
+        let compressed = buf[0] & 0x40 != 0;
+        let opcode = buf[0] & 0x0f;
+
+        // Only the first frame of a data message can be compressed
+        if compressed && !matches!(opcode, 1 | 2) {
+            return Err(WsError::InvalidFrame);
+        }
+
+        let frame_type = match opcode {
+            0 => FrameType::Continue(fin),
+            1 => FrameType::Text(!fin),
+            2 => FrameType::Binary(!fin),
+            8 => FrameType::Close,
+            9 => FrameType::Ping,
+            10 => FrameType::Pong,
+            _ => return Err(WsError::InvalidFrame),
+        };
+
+        let (payload_len, rest) = match buf[1] & 0x7f {
+            126 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, &buf[4..]),
+            127 => {
+                // Safe to unwrap, the slice is exactly 8 bytes long
+                let len = u64::from_be_bytes(buf[2..10].try_into().unwrap());
+
+                // The most significant bit must be 0
+                if len >> 63 != 0 {
+                    return Err(WsError::InvalidFrame);
+                }
+
+                (len, &buf[10..])
+            }
+            len => (len as u64, &buf[2..]),
+        };
+
+        // Control frames cannot be fragmented, and their payload is limited
+        if opcode >= 8 && (!fin || payload_len > MAX_CONTROL_PAYLOAD_LEN as u64) {
+            return Err(WsError::InvalidFrame);
+        }
+
+        let mask_key = (buf[1] & 0x80 != 0).then(|| [rest[0], rest[1], rest[2], rest[3]]);
+
+        Ok(Self {
+            frame_type,
+            compressed,
+            payload_len,
+            mask_key,
+        })
+    }
+
+    fn serialize<E>(&self) -> Result<heapless::Vec<u8, MAX_HEADER_LEN>, WsError<E>> {
+        let (opcode, fin) = match self.frame_type {
+            FrameType::Continue(final_) => (0, final_),
+            FrameType::Text(fragmented) => (1, !fragmented),
+            FrameType::Binary(fragmented) => (2, !fragmented),
+            FrameType::Close => (8, true),
+            FrameType::Ping => (9, true),
+            FrameType::Pong => (10, true),
+            FrameType::SocketClose => return Err(WsError::InvalidFrame),
+        };
+
+        if (opcode >= 8 && self.payload_len > MAX_CONTROL_PAYLOAD_LEN as u64)
+            || (self.compressed && !matches!(opcode, 1 | 2))
+        {
+            return Err(WsError::InvalidFrame);
+        }
+
+        let mut buf = heapless::Vec::new();
+        let fin = if fin { 0x80 } else { 0 };
+        let rsv1 = if self.compressed { 0x40 } else { 0 };
+        let masked = if self.mask_key.is_some() { 0x80 } else { 0 };
+
+        // Cannot fail, the header is at most 14 bytes long
+        let _ = buf.push(fin | rsv1 | opcode);
+
+        if self.payload_len < 126 {
+            let _ = buf.push(masked | self.payload_len as u8);
+        } else if let Ok(len) = u16::try_from(self.payload_len) {
+            let _ = buf.push(masked | 126);
+            let _ = buf.extend_from_slice(&len.to_be_bytes());
+        } else {
+            let _ = buf.push(masked | 127);
+            let _ = buf.extend_from_slice(&self.payload_len.to_be_bytes());
+        }
+
+        if let Some(mask_key) = self.mask_key {
+            let _ = buf.extend_from_slice(&mask_key);
+        }
+
+        Ok(buf)
+    }
+
+    /// Checks a received header, and returns the length of its payload if it fits into `buf`.
+    fn check<E>(&self, client: bool, buf: &[u8]) -> Result<usize, WsError<E>> {
+        // Only frames sent by clients are masked
+        if self.mask_key.is_some() == client {
+            return Err(WsError::InvalidFrame);
+        }
+
+        usize::try_from(self.payload_len)
+            .ok()
+            .filter(|len| *len <= buf.len())
+            .ok_or(WsError::BufferOverflow)
+    }
+
+    /// Masks or unmasks `data`, which is at `offset` within the payload.
+    fn mask(&self, data: &mut [u8], offset: usize) {
+        if let Some(mask_key) = self.mask_key {
+            for (index, byte) in data.iter_mut().enumerate() {
+                *byte ^= mask_key[(offset + index) % 4];
+            }
+        }
+    }
+}
+
+pub mod asynch {
+    use crate::io::asynch::{Read, Write};
+    use crate::ws::asynch::{FrameType, Receiver, Sender};
+
+    use super::{FrameHeader, WsConnection, WsError};
+
+    impl<T, M> Receiver for WsConnection<T, M>
+    where
+        T: Read,
+        M: FnMut(&mut [u8]),
+    {
+        async fn recv(
+            &mut self,
+            frame_data_buf: &mut [u8],
+        ) -> Result<(FrameType, usize), Self::Error> {
+            let client = self.is_client();
+
+            loop {
+                let buf = self.recv.remaining(frame_data_buf)?;
+                let read = self.io.read(buf).await.map_err(WsError::Io)?;
+
+                if let Some((header, len)) =
+                    self.recv.advance(read, frame_data_buf, client, false)?
+                {
+                    return Ok((header.frame_type, len));
+                }
+            }
+        }
+    }
+
+    impl<T, M> Sender for WsConnection<T, M>
+    where
+        T: Write,
+        M: FnMut(&mut [u8]),
+    {
+        async fn send(
+            &mut self,
+            frame_type: FrameType,
+            frame_data: &[u8],
+        ) -> Result<(), Self::Error> {
+            let header = self.header(frame_type, false, frame_data);
+
+            write_frame(&mut self.io, &header, frame_data).await
+        }
+    }
+
+    pub(super) async fn write_frame<T>(
+        io: &mut T,
+        header: &FrameHeader,
+        frame_data: &[u8],
+    ) -> Result<(), WsError<T::Error>>
+    where
+        T: Write,
+    {
+        io.write_all(&header.serialize()?)
+            .await
+            .map_err(WsError::Io)?;
+
+        if header.mask_key.is_some() {
+            let mut buf = [0; 64];
+
+            for (index, chunk) in frame_data.chunks(buf.len()).enumerate() {
+                let buf = &mut buf[..chunk.len()];
+                buf.copy_from_slice(chunk);
+                header.mask(buf, index * 64);
+
+                io.write_all(buf).await.map_err(WsError::Io)?;
+            }
+        } else {
+            io.write_all(frame_data).await.map_err(WsError::Io)?;
+        }
+
+        io.flush().await.map_err(WsError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use crate::utils::io::test::{block_on, MockSocket};
+    use crate::ws::{FrameType, Receiver, Sender};
+
+    use super::{WsConnection, WsError};
+
+    /// The masking key of the examples of RFC 6455, section 5.7.
+    fn mask(key: &mut [u8]) {
+        key.copy_from_slice(&[0x37, 0xfa, 0x21, 0x3d]);
+    }
+
+    /// Sends a frame from the client side if `masked`, or else from the server side.
+    fn send(frame_type: FrameType, data: &[u8], masked: bool) -> Vec<u8> {
+        let mut socket = MockSocket::new(b"");
+
+        if masked {
+            WsConnection::client(&mut socket, mask)
+                .send(frame_type, data)
+                .unwrap();
+        } else {
+            WsConnection::server(&mut socket)
+                .send(frame_type, data)
+                .unwrap();
+        }
+
+        socket.output
+    }
+
+    /// Receives a frame on the server side if `masked`, or else on the client side.
+    fn recv(input: &[u8], masked: bool) -> Result<(FrameType, Vec<u8>), WsError<()>> {
+        let mut socket = MockSocket::chunked(input, 5);
+        let mut buf = vec![0; 70000];
+
+        let result = if masked {
+            WsConnection::server(&mut socket).recv(&mut buf)
+        } else {
+            WsConnection::client(&mut socket, mask).recv(&mut buf)
+        };
+
+        result
+            .map(|(frame_type, len)| (frame_type, buf[..len].into()))
+            .map_err(|e| match e {
+                WsError::Io(_) => WsError::Io(()),
+                WsError::UnexpectedEof => WsError::UnexpectedEof,
+                WsError::InvalidFrame => WsError::InvalidFrame,
+                WsError::BufferOverflow => WsError::BufferOverflow,
+            })
+    }
+
+    #[test]
+    fn rfc6455_examples() {
+        // RFC 6455, section 5.7
+        const UNMASKED: &[u8] = b"\x81\x05\x48\x65\x6c\x6c\x6f";
+        const MASKED: &[u8] = b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58";
+        const PING: &[u8] = b"\x89\x05\x48\x65\x6c\x6c\x6f";
+        const PONG: &[u8] = b"\x8a\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58";
+
+        assert_eq!(send(FrameType::Text(false), b"Hello", false), UNMASKED);
+        assert_eq!(send(FrameType::Text(false), b"Hello", true), MASKED);
+        assert_eq!(send(FrameType::Ping, b"Hello", false), PING);
+        assert_eq!(send(FrameType::Pong, b"Hello", true), PONG);
+
+        assert_eq!(
+            recv(UNMASKED, false).unwrap(),
+            (FrameType::Text(false), b"Hello".into())
+        );
+        assert_eq!(
+            recv(MASKED, true).unwrap(),
+            (FrameType::Text(false), b"Hello".into())
+        );
+        assert_eq!(
+            recv(PING, false).unwrap(),
+            (FrameType::Ping, b"Hello".into())
+        );
+        assert_eq!(
+            recv(PONG, true).unwrap(),
+            (FrameType::Pong, b"Hello".into())
+        );
+
+        // A fragmented unmasked text message
+        assert_eq!(send(FrameType::Text(true), b"Hel", false), b"\x01\x03Hel");
+        assert_eq!(send(FrameType::Continue(true), b"lo", false), b"\x80\x02lo");
+        assert_eq!(
+            recv(b"\x01\x03Hel", false).unwrap(),
+            (FrameType::Text(true), b"Hel".into())
+        );
+        assert_eq!(
+            recv(b"\x80\x02lo", false).unwrap(),
+            (FrameType::Continue(true), b"lo".into())
+        );
+
+        // 256 bytes and 64 KiB binary messages in a single unmasked frame
+        let data = vec![0x5a; 65536];
+
+        let frame = send(FrameType::Binary(false), &data[..256], false);
+        assert_eq!(frame[..4], *b"\x82\x7e\x01\x00");
+        assert_eq!(frame.len(), 4 + 256);
+
+        let frame = send(FrameType::Binary(false), &data, false);
+        assert_eq!(frame[..10], *b"\x82\x7f\x00\x00\x00\x00\x00\x01\x00\x00");
+        assert_eq!(frame.len(), 10 + 65536);
+    }
+
+    #[test]
+    fn payload_lengths() {
+        for len in [0, 125, 126, 65535, 65536, 69999] {
+            let data = (0..len).map(|index| index as u8).collect::<Vec<_>>();
+
+            let header_len = match len {
+                0..=125 => 2,
+                126..=65535 => 4,
+                _ => 10,
+            };
+
+            for masked in [false, true] {
+                let frame = send(FrameType::Binary(false), &data, masked);
+                let mask_len = if masked { 4 } else { 0 };
+
+                assert_eq!(frame.len(), header_len + mask_len + len, "{len} {masked}");
+                assert_eq!(frame[1] & 0x80 != 0, masked);
+                assert_eq!(
+                    recv(&frame, masked).unwrap(),
+                    (FrameType::Binary(false), data.clone())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_frames() {
+        let invalid =
+            |input: &[u8], masked| matches!(recv(input, masked), Err(WsError::InvalidFrame));
+
+        // The RSV bits, RSV1 being only valid with permessage-deflate
+        assert!(invalid(b"\xc1\x00", false));
+        assert!(invalid(b"\xa1\x00", false));
+        assert!(invalid(b"\x91\x00", false));
+        // Reserved opcodes
+        assert!(invalid(b"\x83\x00", false));
+        assert!(invalid(b"\x8b\x00", false));
+        // Fragmented control frames
+        assert!(invalid(b"\x09\x00", false));
+        assert!(invalid(b"\x08\x00", false));
+        // Control frames with more than 125 bytes of payload
+        assert!(invalid(b"\x89\x7e\x00\x7e", false));
+        assert!(invalid(b"\x8a\x7f\x00\x00\x00\x00\x00\x00\x00\x7e", false));
+        // A 64-bit length with the most significant bit set
+        assert!(invalid(b"\x82\x7f\x80\x00\x00\x00\x00\x00\x00\x01", false));
+        // Masked frames sent to the client, and unmasked ones sent to the server
+        assert!(invalid(
+            b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58",
+            false
+        ));
+        assert!(invalid(b"\x81\x05Hello", true));
+
+        // Frames which cannot be sent
+        let mut socket = MockSocket::new(b"");
+        let mut connection = WsConnection::server(&mut socket);
+        assert!(matches!(
+            connection.send(FrameType::Ping, &[0; 126]),
+            Err(WsError::InvalidFrame)
+        ));
+        assert!(matches!(
+            connection.send(FrameType::SocketClose, &[]),
+            Err(WsError::InvalidFrame)
+        ));
+        assert!(socket.output.is_empty());
+    }
+
+    #[test]
+    fn end_of_stream() {
+        assert_eq!(
+            recv(b"", false).unwrap(),
+            (FrameType::SocketClose, Vec::new())
+        );
+        assert!(matches!(recv(b"\x81", false), Err(WsError::UnexpectedEof)));
+        assert!(matches!(
+            recv(b"\x81\x7e\x01", false),
+            Err(WsError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            recv(b"\x81\x05Hell", false),
+            Err(WsError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            recv(b"\x81\x85\x37\xfa", true),
+            Err(WsError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn buffer_overflow() {
+        let mut socket = MockSocket::chunked(
+            b"\x82\x05Hello\x81\x00\x82\x7e\x00\x10abcdefghijklmnop\x81\x02hi\x81\x02ok",
+            3,
+        );
+        let mut connection = WsConnection::client(&mut socket, mask);
+        let mut buf = [0; 4];
+
+        // The payloads of the frames which do not fit are skipped, even without a buffer
+        assert!(matches!(
+            connection.recv(&mut buf),
+            Err(WsError::BufferOverflow)
+        ));
+        assert_eq!(
+            connection.recv(&mut []).unwrap(),
+            (FrameType::Text(false), 0)
+        );
+        assert!(matches!(
+            connection.recv(&mut []),
+            Err(WsError::BufferOverflow)
+        ));
+        assert!(matches!(
+            connection.recv(&mut []),
+            Err(WsError::BufferOverflow)
+        ));
+        assert_eq!(
+            connection.recv(&mut buf).unwrap(),
+            (FrameType::Text(false), 2)
+        );
+        assert_eq!(&buf[..2], b"ok");
+        assert_eq!(
+            connection.recv(&mut buf).unwrap(),
+            (FrameType::SocketClose, 0)
+        );
+
+        // The stream ends within a skipped payload
+        let mut socket = MockSocket::new(b"\x82\x05Hel");
+        let mut connection = WsConnection::client(&mut socket, mask);
+        assert!(matches!(
+            connection.recv(&mut buf),
+            Err(WsError::BufferOverflow)
+        ));
+        assert!(matches!(
+            connection.recv(&mut buf),
+            Err(WsError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn browser_frames() {
+        // Masked frames in the form browsers send them: a text message, an empty ping, and
+        // a close with the status code 1000
+        const FRAMES: &[u8] =
+            b"\x81\x8d\xa1\xb2\xc3\xd4\xe9\xd7\xaf\xb8\xce\x9e\xe3\xa3\xce\xc0\xaf\xb0\x80\
+                                \x89\x80\x0f\x1e\x2d\x3c\
+                                \x88\x85\x5e\x6f\x70\x81\x5d\x87\x12\xf8\x3b";
+
+        for chunk in [1, 2, 7, FRAMES.len()] {
+            let mut socket = MockSocket::chunked(FRAMES, chunk);
+            let mut connection = WsConnection::server(&mut socket);
+            let mut buf = [0; 16];
+
+            let mut recv = |expected_type, expected: &[u8]| {
+                let (frame_type, len) = connection.recv(&mut buf).unwrap();
+                assert_eq!(
+                    (frame_type, &buf[..len]),
+                    (expected_type, expected),
+                    "{chunk}"
+                );
+            };
+
+            recv(FrameType::Text(false), b"Hello, world!");
+            recv(FrameType::Ping, b"");
+            recv(FrameType::Close, b"\x03\xe8bye");
+            recv(FrameType::SocketClose, b"");
+        }
+    }
+
+    /// A xorshift64* generator, for reproducible random tests.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn roundtrip_random() {
+        const LENGTHS: &[usize] = &[0, 1, 125, 126, 127, 65535, 65536, 69999];
+
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+        for round in 0..16 {
+            let masked = round % 2 == 1;
+
+            let frames = LENGTHS
+                .iter()
+                .map(|&len| {
+                    let frame_type = match rng.below(if len <= 125 { 7 } else { 4 }) {
+                        0 => FrameType::Text(false),
+                        1 => FrameType::Binary(true),
+                        2 => FrameType::Continue(false),
+                        3 => FrameType::Continue(true),
+                        4 => FrameType::Ping,
+                        5 => FrameType::Pong,
+                        _ => FrameType::Close,
+                    };
+                    let data = (0..len).map(|_| rng.next() as u8).collect::<Vec<_>>();
+
+                    (frame_type, data)
+                })
+                .collect::<Vec<_>>();
+
+            let mut socket = MockSocket::new(b"");
+
+            if masked {
+                let mut mask_rng = Rng(rng.next() | 1);
+                let mut connection = WsConnection::client(&mut socket, move |key: &mut [u8]| {
+                    key.copy_from_slice(&mask_rng.next().to_le_bytes()[..4]);
+                });
+
+                for (frame_type, data) in &frames {
+                    connection.send(*frame_type, data).unwrap();
+                }
+            } else {
+                let mut connection = WsConnection::server(&mut socket);
+
+                for (frame_type, data) in &frames {
+                    connection.send(*frame_type, data).unwrap();
+                }
+            }
+
+            let output = socket.output;
+            let mut socket = MockSocket::new(&output);
+            let mut buf = vec![0; 70000];
+
+            let mut connection = if masked {
+                WsConnection::server(&mut socket)
+            } else {
+                WsConnection::client(&mut socket, mask as fn(&mut [u8]))
+            };
+
+            for (frame_type, data) in frames
+                .iter()
+                .map(|(t, d)| (*t, d.as_slice()))
+                .chain([(FrameType::SocketClose, &[][..])])
+            {
+                connection.io().chunk = match rng.below(3) {
+                    0 => 1 + rng.below(8),
+                    1 => 100 + rng.below(2000),
+                    _ => usize::MAX,
+                };
+
+                // Either exactly the payload, or with room to spare
+                let buf = &mut buf[..data.len() + rng.below(2)];
+
+                let received = if rng.below(2) == 0 {
+                    connection.recv(buf)
+                } else {
+                    block_on(crate::ws::asynch::Receiver::recv(&mut connection, buf))
+                }
+                .unwrap();
+
+                assert_eq!(received, (frame_type, data.len()), "{round}");
+                assert_eq!(&buf[..data.len()], data, "{round}");
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_async() {
+        let mut socket = MockSocket::new(b"");
+        block_on(crate::ws::asynch::Sender::send(
+            &mut WsConnection::client(&mut socket, mask),
+            FrameType::Pong,
+            b"Hello",
+        ))
+        .unwrap();
+        assert_eq!(
+            socket.output,
+            b"\x8a\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58"
+        );
+
+        let output = socket.output;
+        let mut socket = MockSocket::chunked(&output, 3);
+        let mut buf = [0; 8];
+        assert_eq!(
+            block_on(crate::ws::asynch::Receiver::recv(
+                &mut WsConnection::server(&mut socket),
+                &mut buf
+            ))
+            .unwrap(),
+            (FrameType::Pong, 5)
+        );
+        assert_eq!(&buf[..5], b"Hello");
+
+        let mut socket = MockSocket::new(b"\x89\x7e\x00\x7e");
+        assert!(matches!(
+            block_on(crate::ws::asynch::Receiver::recv(
+                &mut WsConnection::server(&mut socket),
+                &mut buf
+            )),
+            Err(WsError::InvalidFrame)
+        ));
+    }
+}
@@ -0,0 +1,378 @@
+use core::fmt;
+
+use crate::http::client::{Client, Connection};
+use crate::http::{Headers, Method, Status};
+use crate::io::{Error, ErrorKind};
+use crate::utils::base64;
+use crate::utils::http::connection::has_token;
+
+use super::server::{accept_key, VERSION};
+use super::WsConnection;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConnectError<E> {
+    Io(E),
+    TooManyHeaders,
+    /// The server did not switch protocols, and answered with this status instead.
+    Status(u16),
+    /// The server switched protocols, but its response is not a valid WebSocket handshake response.
+    InvalidResponse,
+}
+
+impl<E: fmt::Debug> fmt::Display for ConnectError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for ConnectError<E> {}
+
+impl<E> Error for ConnectError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::TooManyHeaders => ErrorKind::OutOfMemory,
+            Self::Status(_) => ErrorKind::Other,
+            Self::InvalidResponse => ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// The headers negotiated by the handshake, which the server may only answer if the client sent them.
+const NEGOTIATED_HEADERS: &[&str] = &["Sec-WebSocket-Protocol", "Sec-WebSocket-Extensions"];
+
+/// Generates the `Sec-WebSocket-Key` of a handshake into `buf`.
+fn key<'b>(rng: &mut impl FnMut(&mut [u8]), buf: &'b mut [u8; 24]) -> &'b str {
+    let mut nonce = [0; 16];
+    rng(&mut nonce);
+
+    // Cannot fail, the encoded nonce is exactly 24 characters long
+    base64::encode(&nonce, buf).unwrap()
+}
+
+/// Returns the headers of the upgrade request, followed by `headers` except for the ones set by the handshake.
+fn request_headers<'a, E>(
+    key: &'a str,
+    headers: &[(&'a str, &'a str)],
+) -> Result<heapless::Vec<(&'a str, &'a str), 32>, ConnectError<E>> {
+    let handshake = [
+        ("Upgrade", "websocket"),
+        ("Connection", "Upgrade"),
+        ("Sec-WebSocket-Key", key),
+        ("Sec-WebSocket-Version", VERSION),
+    ];
+
+    let mut request_headers = heapless::Vec::new();
+
+    for header in handshake.iter().chain(headers.iter().filter(|(name, _)| {
+        !handshake
+            .iter()
+            .any(|(handshake_name, _)| handshake_name.eq_ignore_ascii_case(name))
+    })) {
+        request_headers
+            .push(*header)
+            .map_err(|_| ConnectError::TooManyHeaders)?;
+    }
+
+    Ok(request_headers)
+}
+
+/// Checks the response of the server to the upgrade request sent with `key` and `headers`.
+fn check_response<E>(
+    response: &(impl Status + Headers),
+    key: &str,
+    headers: &[(&str, &str)],
+) -> Result<(), ConnectError<E>> {
+    if response.status() != 101 {
+        return Err(ConnectError::Status(response.status()));
+    }
+
+    let mut buf = [0; 28];
+    let accept = accept_key(key, &mut buf);
+
+    let valid = has_token(response.header("Upgrade"), "websocket")
+        && has_token(response.header("Connection"), "Upgrade")
+        && response
+            .header("Sec-WebSocket-Accept")
+            .is_some_and(|value| value.trim() == accept)
+        && NEGOTIATED_HEADERS.iter().all(|negotiated| {
+            response.header(negotiated).is_none()
+                || headers
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case(negotiated))
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ConnectError::InvalidResponse)
+    }
+}
+
+/// Opens a WebSocket connection to `uri` with the opening handshake, and returns it over the raw connection
+/// of `client`.
+///
+/// `headers` are sent along with the handshake headers, e.g. `Sec-WebSocket-Protocol` or `Authorization`.
+/// `rng` fills the provided buffer with random data, and is used for the key of the handshake and for masking
+/// the frames sent by the client.
+pub fn connect<'a, C, M>(
+    client: &'a mut Client<C>,
+    uri: &str,
+    headers: &[(&str, &str)],
+    rng: M,
+) -> Result<WsConnection<&'a mut C::RawConnection, M>, ConnectError<C::Error>>
+where
+    C: Connection,
+    M: FnMut(&mut [u8]),
+{
+    connect_with(client, uri, headers, rng, |_| true)
+}
+
+/// Like [`connect`], additionally passing the headers of the `101` response to `check`, e.g. to find out
+/// the extensions agreed upon by the server.
+///
+/// The handshake fails with [`ConnectError::InvalidResponse`] if `check` returns `false`.
+pub fn connect_with<'a, C, M, F>(
+    client: &'a mut Client<C>,
+    uri: &str,
+    headers: &[(&str, &str)],
+    mut rng: M,
+    check: F,
+) -> Result<WsConnection<&'a mut C::RawConnection, M>, ConnectError<C::Error>>
+where
+    C: Connection,
+    M: FnMut(&mut [u8]),
+    F: FnOnce(&dyn Headers) -> bool,
+{
+    let mut buf = [0; 24];
+    let key = key(&mut rng, &mut buf);
+
+    let request_headers = request_headers(key, headers)?;
+
+    let response = client
+        .request(Method::Get, uri, &request_headers)
+        .map_err(ConnectError::Io)?
+        .submit()
+        .map_err(ConnectError::Io)?;
+
+    check_response(&response, key, headers)?;
+    if !check(&response) {
+        return Err(ConnectError::InvalidResponse);
+    }
+
+    let raw = client.raw_connection().map_err(ConnectError::Io)?;
+
+    Ok(WsConnection::client(raw, rng))
+}
+
+pub mod asynch {
+    use crate::http::client::asynch::{Client, Connection};
+    use crate::http::{Headers, Method};
+    use crate::utils::ws::WsConnection;
+
+    use super::{check_response, key, request_headers, ConnectError};
+
+    /// Opens a WebSocket connection to `uri` with the opening handshake, and returns it over the raw connection
+    /// of `client`.
+    ///
+    /// See [`super::connect`].
+    pub async fn connect<'a, C, M>(
+        client: &'a mut Client<C>,
+        uri: &str,
+        headers: &[(&str, &str)],
+        rng: M,
+    ) -> Result<WsConnection<&'a mut C::RawConnection, M>, ConnectError<C::Error>>
+    where
+        C: Connection,
+        M: FnMut(&mut [u8]),
+    {
+        connect_with(client, uri, headers, rng, |_| true).await
+    }
+
+    /// Like [`connect`], additionally passing the headers of the `101` response to `check`.
+    ///
+    /// See [`super::connect_with`].
+    pub async fn connect_with<'a, C, M, F>(
+        client: &'a mut Client<C>,
+        uri: &str,
+        headers: &[(&str, &str)],
+        mut rng: M,
+        check: F,
+    ) -> Result<WsConnection<&'a mut C::RawConnection, M>, ConnectError<C::Error>>
+    where
+        C: Connection,
+        M: FnMut(&mut [u8]),
+        F: FnOnce(&dyn Headers) -> bool,
+    {
+        let mut buf = [0; 24];
+        let key = key(&mut rng, &mut buf);
+
+        let request_headers = request_headers(key, headers)?;
+
+        let response = client
+            .request(Method::Get, uri, &request_headers)
+            .await
+            .map_err(ConnectError::Io)?
+            .submit()
+            .await
+            .map_err(ConnectError::Io)?;
+
+        check_response(&response, key, headers)?;
+        if !check(&response) {
+            return Err(ConnectError::InvalidResponse);
+        }
+
+        let raw = client.raw_connection().map_err(ConnectError::Io)?;
+
+        Ok(WsConnection::client(raw, rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+
+    use crate::http::client::Client;
+    use crate::utils::http::client::ClientConnection;
+    use crate::utils::io::test::{block_on, MockSocket};
+    use crate::ws::{FrameType, Receiver, Sender};
+
+    use super::{connect, connect_with, ConnectError};
+
+    /// Generates the nonce of the sample handshake of RFC 6455, section 1.3, and masking keys from its start.
+    fn rng(buf: &mut [u8]) {
+        buf.copy_from_slice(&b"the sample nonce"[..buf.len()]);
+    }
+
+    const RESPONSE: &str =
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                            Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n";
+
+    /// Connects with `headers`, with `response` as the reply, and returns the result of the handshake.
+    fn handshake(
+        response: &str,
+        headers: &[(&str, &str)],
+        check: bool,
+    ) -> Result<(), ConnectError<()>> {
+        let mut socket = MockSocket::new(response.as_bytes());
+        let mut buf = [0; 512];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        connect_with(&mut client, "ws://h/chat", headers, rng, |_| check)
+            .map(|_| ())
+            .map_err(|e| match e {
+                ConnectError::Io(_) => ConnectError::Io(()),
+                ConnectError::TooManyHeaders => ConnectError::TooManyHeaders,
+                ConnectError::Status(status) => ConnectError::Status(status),
+                ConnectError::InvalidResponse => ConnectError::InvalidResponse,
+            })
+    }
+
+    #[test]
+    fn connect_sample() {
+        let input = [RESPONSE.as_bytes(), b"\r\n\x01\x02hi"].concat();
+
+        let mut socket = MockSocket::new(&input);
+        let mut buf = [0; 512];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        let mut ws = connect(
+            &mut client,
+            "ws://h/chat",
+            &[("Sec-WebSocket-Version", "8"), ("Origin", "http://h")],
+            rng,
+        )
+        .unwrap();
+
+        // The frames following the response are not lost
+        let mut frame = [0; 8];
+        assert_eq!(ws.recv(&mut frame).unwrap(), (FrameType::Text(true), 2));
+        assert_eq!(&frame[..2], b"hi");
+
+        ws.send(FrameType::Close, &[]).unwrap();
+
+        let output = socket.output;
+        let (request, frame) = output.split_at(output.len() - 6);
+
+        // The handshake headers cannot be overridden
+        assert_eq!(
+            String::from_utf8(request.into()).unwrap(),
+            "GET /chat HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+             Origin: http://h\r\nHost: h\r\n\r\n"
+        );
+        assert_eq!(frame, b"\x88\x80the ");
+    }
+
+    #[test]
+    fn invalid_response() {
+        assert!(handshake(&std::format!("{RESPONSE}\r\n"), &[], true).is_ok());
+
+        assert!(matches!(
+            handshake("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n", &[], true),
+            Err(ConnectError::Status(200))
+        ));
+
+        for response in [
+            RESPONSE.replace("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=", "dGhlIHNhbXBsZSBub25jZQ=="),
+            RESPONSE.replace("Upgrade: websocket\r\n", ""),
+            RESPONSE.replace("Connection: Upgrade\r\n", "Connection: keep-alive\r\n"),
+        ] {
+            assert!(matches!(
+                handshake(&std::format!("{response}\r\n"), &[], true),
+                Err(ConnectError::InvalidResponse)
+            ));
+        }
+
+        // A subprotocol can only be selected if the client offered some
+        let response = std::format!("{RESPONSE}Sec-WebSocket-Protocol: chat\r\n\r\n");
+        assert!(matches!(
+            handshake(&response, &[], true),
+            Err(ConnectError::InvalidResponse)
+        ));
+        assert!(handshake(&response, &[("Sec-WebSocket-Protocol", "chat")], true).is_ok());
+
+        assert!(matches!(
+            handshake(&std::format!("{RESPONSE}\r\n"), &[], false),
+            Err(ConnectError::InvalidResponse)
+        ));
+    }
+
+    #[test]
+    fn connect_async() {
+        let input = [RESPONSE.as_bytes(), b"\r\n\x89\x00"].concat();
+
+        let mut socket = MockSocket::new(&input);
+        let mut buf = [0; 512];
+        let mut client = crate::http::client::asynch::Client::wrap(ClientConnection::<_, 16>::new(
+            &mut socket,
+            &mut buf,
+        ));
+
+        let mut ws = block_on(super::asynch::connect(&mut client, "/chat", &[], rng)).unwrap();
+
+        let mut frame = [0; 8];
+        assert_eq!(
+            block_on(crate::ws::asynch::Receiver::recv(&mut ws, &mut frame)).unwrap(),
+            (FrameType::Ping, 0)
+        );
+
+        let mut socket = MockSocket::new(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+        let mut buf = [0; 512];
+        let mut client = crate::http::client::asynch::Client::wrap(ClientConnection::<_, 16>::new(
+            &mut socket,
+            &mut buf,
+        ));
+
+        assert!(matches!(
+            block_on(super::asynch::connect(&mut client, "/chat", &[], rng)),
+            Err(ConnectError::Status(404))
+        ));
+    }
+}
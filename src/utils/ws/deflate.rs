@@ -0,0 +1,1431 @@
+//! The permessage-deflate extension of RFC 7692, compressing the messages of a [`WsConnection`].
+//!
+//! The compressor always uses a window of 32 KiB, so offers and responses limiting it are declined.
+//!
+//! Each connection agreeing upon the extension allocates about 300 KiB for its compressor, whatever the
+//! compression level, and about 45 KiB for its decompressor.
+
+use core::fmt::Write as _;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use miniz_oxide::deflate::core::CompressorOxide;
+use miniz_oxide::deflate::stream::deflate;
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+
+use crate::http::client::{Client, Connection as ClientConnection};
+use crate::http::server::Connection as ServerConnection;
+use crate::io::{Read, Write};
+use crate::utils::http::uri::SliceWriter;
+use crate::ws::{ErrorType, FrameType, Receiver, Sender};
+
+use super::client::{self, ConnectError};
+use super::server::{self, UpgradeError};
+use super::{write_frame, FrameHeader, WsConnection, WsError};
+
+/// The name of the extension in the `Sec-WebSocket-Extensions` header.
+pub const EXTENSION: &str = "permessage-deflate";
+
+const EXTENSIONS_HEADER: &str = "Sec-WebSocket-Extensions";
+
+const MAX_WINDOW_BITS: u8 = 15;
+
+/// The compression level of new connections, see [`Deflate::set_compression_level`].
+pub const DEFAULT_COMPRESSION_LEVEL: u8 = 6;
+
+/// The empty stored block ending each message compressed with a sync flush, which is not sent.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// The parameters of the extension, as offered by a client, accepted by a server, or agreed upon
+/// in the handshake.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeflateParams {
+    /// The server resets its compression context after each message.
+    pub server_no_context_takeover: bool,
+    /// The client resets its compression context after each message.
+    pub client_no_context_takeover: bool,
+    /// The base-2 logarithm of the window size of the server, between 8 and 15.
+    ///
+    /// Clients may offer a smaller window than the default of 15 to the server.
+    pub server_max_window_bits: Option<u8>,
+    /// The base-2 logarithm of the window size of the client, between 8 and 15.
+    ///
+    /// Servers may limit the window of clients which announce support for it. This is never offered by
+    /// the clients of this module.
+    pub client_max_window_bits: Option<u8>,
+}
+
+impl DeflateParams {
+    /// Parses the extensions of a `Sec-WebSocket-Extensions` header, with `None` for the ones which are not
+    /// a valid permessage-deflate extension.
+    fn parse_all(extensions: &str) -> impl Iterator<Item = Option<Self>> + '_ {
+        extensions.split(',').map(|extension| {
+            let mut params = extension.split(';');
+
+            // Safe to unwrap, splitting yields at least one item
+            if params.next().unwrap().trim() == EXTENSION {
+                Self::parse(params)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn parse<'a>(params: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut this = Self::default();
+
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param.trim(), None),
+            };
+
+            match (name, value) {
+                ("server_no_context_takeover", None) if !this.server_no_context_takeover => {
+                    this.server_no_context_takeover = true
+                }
+                ("client_no_context_takeover", None) if !this.client_no_context_takeover => {
+                    this.client_no_context_takeover = true
+                }
+                ("server_max_window_bits", Some(value))
+                    if this.server_max_window_bits.is_none() =>
+                {
+                    this.server_max_window_bits = Some(window_bits(value)?)
+                }
+                // Without a value, the client only announces support for the parameter
+                ("client_max_window_bits", value) if this.client_max_window_bits.is_none() => {
+                    this.client_max_window_bits =
+                        Some(value.map_or(Some(MAX_WINDOW_BITS), window_bits)?)
+                }
+                _ => return None,
+            }
+        }
+
+        Some(this)
+    }
+
+    fn format<'b>(&self, buf: &'b mut [u8; 128]) -> &'b str {
+        let mut out = SliceWriter::new(buf);
+
+        // Cannot fail, all the parameters fit into the buffer
+        let _ = out.write_str(EXTENSION);
+
+        if self.server_no_context_takeover {
+            let _ = out.write_str("; server_no_context_takeover");
+        }
+
+        if self.client_no_context_takeover {
+            let _ = out.write_str("; client_no_context_takeover");
+        }
+
+        if let Some(bits) = self.server_max_window_bits {
+            let _ = write!(out, "; server_max_window_bits={bits}");
+        }
+
+        if let Some(bits) = self.client_max_window_bits {
+            let _ = write!(out, "; client_max_window_bits={bits}");
+        }
+
+        out.into_str()
+    }
+
+    /// Returns the offer of a client with these parameters.
+    fn offer(&self) -> Self {
+        Self {
+            client_max_window_bits: None,
+            ..*self
+        }
+    }
+
+    /// Returns the parameters agreed upon by the server with these parameters, for the first acceptable offer
+    /// in `offers`.
+    fn accept(&self, offers: &str) -> Option<Self> {
+        Self::parse_all(offers).flatten().find_map(|offer| {
+            if offer
+                .server_max_window_bits
+                .is_some_and(|bits| bits < MAX_WINDOW_BITS)
+            {
+                return None;
+            }
+
+            Some(Self {
+                server_no_context_takeover: offer.server_no_context_takeover
+                    || self.server_no_context_takeover,
+                client_no_context_takeover: self.client_no_context_takeover,
+                server_max_window_bits: offer.server_max_window_bits,
+                client_max_window_bits: offer
+                    .client_max_window_bits
+                    .and_then(|offered| self.client_max_window_bits.map(|bits| bits.min(offered))),
+            })
+        })
+    }
+
+    /// Returns the parameters agreed upon by the client which offered these parameters, given the `response`
+    /// of the server, or `None` if the response is not valid for the offer.
+    fn agree(&self, response: &str) -> Option<Self> {
+        let mut extensions = Self::parse_all(response);
+
+        let params = extensions.next()??;
+
+        let valid = extensions.next().is_none()
+            && params.client_max_window_bits.is_none()
+            && (!self.server_no_context_takeover || params.server_no_context_takeover)
+            && self.server_max_window_bits.is_none_or(|offered| {
+                params
+                    .server_max_window_bits
+                    .is_some_and(|bits| bits <= offered)
+            });
+
+        valid.then_some(Self {
+            client_no_context_takeover: params.client_no_context_takeover
+                || self.client_no_context_takeover,
+            ..params
+        })
+    }
+}
+
+fn is_data(frame_type: FrameType) -> bool {
+    matches!(
+        frame_type,
+        FrameType::Text(_) | FrameType::Binary(_) | FrameType::Continue(_)
+    )
+}
+
+fn window_bits(value: &str) -> Option<u8> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    value
+        .parse()
+        .ok()
+        .filter(|bits| (8..=MAX_WINDOW_BITS).contains(bits))
+}
+
+/// The compression state of a connection.
+struct Codec {
+    compressor: Box<CompressorOxide>,
+    decompressor: Box<InflateState>,
+    /// Reset the compressor after each message.
+    compressor_no_context_takeover: bool,
+    /// Reset the decompressor after each message.
+    decompressor_no_context_takeover: bool,
+    /// The message being received is compressed.
+    inflating: bool,
+    /// The compressed stream of the message being received ended with a block with `BFINAL` set.
+    ended: bool,
+    /// The compressed payload of the frame being received.
+    buf: Vec<u8>,
+    /// The compressed payload of the frame being sent.
+    out: Vec<u8>,
+}
+
+impl Codec {
+    fn new(params: &DeflateParams, client: bool) -> Self {
+        let mut compressor = Box::<CompressorOxide>::default();
+        compressor.set_format_and_level(DataFormat::Raw, DEFAULT_COMPRESSION_LEVEL);
+
+        let (compressor_no_context_takeover, decompressor_no_context_takeover) = if client {
+            (
+                params.client_no_context_takeover,
+                params.server_no_context_takeover,
+            )
+        } else {
+            (
+                params.server_no_context_takeover,
+                params.client_no_context_takeover,
+            )
+        };
+
+        Self {
+            compressor,
+            decompressor: InflateState::new_boxed(DataFormat::Raw),
+            compressor_no_context_takeover,
+            decompressor_no_context_takeover,
+            inflating: false,
+            ended: false,
+            buf: Vec::new(),
+            out: Vec::new(),
+        }
+    }
+
+    /// Returns whether the payload of a received frame is compressed, given the message it belongs to.
+    fn is_compressed(&self, header: &FrameHeader) -> bool {
+        match header.frame_type {
+            FrameType::Text(_) | FrameType::Binary(_) => header.compressed,
+            FrameType::Continue(_) => self.inflating,
+            _ => false,
+        }
+    }
+
+    /// Returns the buffer receiving the compressed payload of a frame.
+    ///
+    /// The buffer is kept across receives, so that a payload received in part is not lost.
+    fn payload(&mut self, len: usize) -> &mut [u8] {
+        if self.buf.len() < len {
+            self.buf.resize(len, 0);
+        }
+
+        &mut self.buf[..len]
+    }
+
+    /// Processes a received frame with a payload of `len` bytes, decompressing it from [`Self::payload`]
+    /// into `buf` if it is compressed, and returns its type and the length of its data.
+    fn received<E>(
+        &mut self,
+        header: &FrameHeader,
+        len: usize,
+        buf: &mut [u8],
+    ) -> Result<(FrameType, usize), WsError<E>> {
+        let compressed = self.is_compressed(header);
+
+        if matches!(header.frame_type, FrameType::Text(_) | FrameType::Binary(_)) {
+            self.inflating = header.compressed;
+        }
+
+        let len = if compressed {
+            self.inflate(len, buf, header.frame_type.is_final())?
+        } else {
+            len
+        };
+
+        Ok((header.frame_type, len))
+    }
+
+    /// Decompresses the payload of `len` bytes of a received frame into `buf`, and returns the length of the data.
+    fn inflate<E>(
+        &mut self,
+        len: usize,
+        buf: &mut [u8],
+        final_: bool,
+    ) -> Result<usize, WsError<E>> {
+        let mut written = 0;
+
+        // Anything following the end of the stream, e.g. the trailer, is ignored
+        if !self.ended {
+            (written, self.ended) =
+                Self::inflate_into(&mut self.decompressor, &self.buf[..len], buf)?;
+        }
+
+        if final_ && !self.ended {
+            let (len, ended) =
+                Self::inflate_into(&mut self.decompressor, &TRAILER, &mut buf[written..])?;
+
+            written += len;
+            self.ended = ended;
+        }
+
+        // Data still pending in the decompressor does not fit into the buffer
+        if !self.ended && Self::inflate_into::<E>(&mut self.decompressor, &[], &mut [0])?.0 > 0 {
+            return Err(WsError::BufferOverflow);
+        }
+
+        // A stream which ended cannot be continued by the next message
+        if final_ && (self.ended || self.decompressor_no_context_takeover) {
+            self.decompressor.reset(DataFormat::Raw);
+            self.ended = false;
+        }
+
+        Ok(written)
+    }
+
+    /// Decompresses `input` into `output`, and returns the length of the data, and whether the stream ended.
+    fn inflate_into<E>(
+        decompressor: &mut InflateState,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(usize, bool), WsError<E>> {
+        let result = inflate(decompressor, input, output, MZFlush::Sync);
+
+        match result.status {
+            // The message may end the stream with a block with `BFINAL` set, as per RFC 7692, section 7.2.3.4
+            Ok(MZStatus::StreamEnd) => Ok((result.bytes_written, true)),
+            // Not making progress only means that all the input is consumed, or the output is full
+            Ok(_) | Err(MZError::Buf) if result.bytes_consumed == input.len() => {
+                Ok((result.bytes_written, false))
+            }
+            Ok(_) | Err(MZError::Buf) => Err(WsError::BufferOverflow),
+            Err(_) => Err(WsError::InvalidFrame),
+        }
+    }
+
+    /// Compresses the payload of a frame to send, and returns the compressed data.
+    fn deflate<E>(&mut self, data: &[u8], final_: bool) -> Result<&[u8], WsError<E>> {
+        self.out.clear();
+
+        let mut consumed = 0;
+
+        loop {
+            let len = self.out.len();
+            let available = (data.len() - consumed) / 2 + 64;
+            self.out.resize(len + available, 0);
+
+            let result = deflate(
+                &mut self.compressor,
+                &data[consumed..],
+                &mut self.out[len..],
+                MZFlush::Sync,
+            );
+
+            // Not making progress is not an error, as the data is flushed in any case
+            if matches!(result.status, Err(error) if error != MZError::Buf) {
+                return Err(WsError::InvalidFrame);
+            }
+
+            consumed += result.bytes_consumed;
+            self.out.truncate(len + result.bytes_written);
+
+            // The data is flushed once the output is not full anymore
+            if consumed == data.len() && result.bytes_written < available {
+                break;
+            }
+        }
+
+        if final_ {
+            if self.out.ends_with(&TRAILER) {
+                self.out.truncate(self.out.len() - TRAILER.len());
+            }
+
+            if self.compressor_no_context_takeover {
+                self.compressor.reset();
+            }
+        }
+
+        Ok(&self.out)
+    }
+}
+
+/// A [`WsConnection`] compressing its messages with permessage-deflate, if agreed upon in the handshake.
+///
+/// Data frames are compressed and decompressed one by one, so each decompressed frame must fit into
+/// the buffer passed to [`Receiver::recv`]. Control frames are never compressed.
+///
+/// Like with [`WsConnection`], receiving a frame resumes where it stopped after an I/O error or a cancelled
+/// async receive, the compressed payload being kept by the connection. A frame which fails to decompress
+/// leaves the decompression context of the message broken, so the connection must then be closed.
+pub struct Deflate<T, M = fn(&mut [u8])> {
+    connection: WsConnection<T, M>,
+    codec: Option<Codec>,
+}
+
+impl<T, M> Deflate<T, M> {
+    fn new(connection: WsConnection<T, M>, params: Option<DeflateParams>) -> Self {
+        let client = connection.is_client();
+
+        Self {
+            connection,
+            codec: params.map(|params| Codec::new(&params, client)),
+        }
+    }
+
+    /// Returns `true` if the extension was agreed upon in the handshake.
+    pub fn is_compressed(&self) -> bool {
+        self.codec.is_some()
+    }
+
+    /// Sets the compression level of the messages to send, between `0`, which stores the data uncompressed,
+    /// and `10`, [`DEFAULT_COMPRESSION_LEVEL`] being a good compromise between speed and compression ratio.
+    ///
+    /// This does not change the memory used by the compressor.
+    pub fn set_compression_level(&mut self, level: u8) {
+        if let Some(codec) = &mut self.codec {
+            codec.compressor.set_compression_level_raw(level.min(10));
+        }
+    }
+
+    pub fn release(self) -> WsConnection<T, M> {
+        self.connection
+    }
+}
+
+impl<T, M> ErrorType for Deflate<T, M>
+where
+    T: crate::io::ErrorType,
+{
+    type Error = WsError<T::Error>;
+}
+
+impl<T, M> Receiver for Deflate<T, M>
+where
+    T: Read,
+    M: FnMut(&mut [u8]),
+{
+    fn recv(&mut self, frame_data_buf: &mut [u8]) -> Result<(FrameType, usize), Self::Error> {
+        let Some(codec) = &mut self.codec else {
+            return self.connection.recv(frame_data_buf);
+        };
+
+        let client = self.connection.is_client();
+
+        loop {
+            // The compressed payload is received into the codec, once the header tells it is compressed.
+            // Its length fits into a `usize`, as it was checked against `frame_data_buf`
+            let buf = match &self.connection.recv.frame {
+                Some((header, _)) if codec.is_compressed(header) => {
+                    codec.payload(header.payload_len as usize)
+                }
+                _ => &mut *frame_data_buf,
+            };
+
+            let read = self
+                .connection
+                .io
+                .read(self.connection.recv.remaining(buf)?)
+                .map_err(WsError::Io)?;
+
+            if let Some((header, len)) = self.connection.recv.advance(read, buf, client, true)? {
+                return codec.received(&header, len, frame_data_buf);
+            }
+        }
+    }
+}
+
+impl<T, M> Sender for Deflate<T, M>
+where
+    T: Write,
+    M: FnMut(&mut [u8]),
+{
+    fn send(&mut self, frame_type: FrameType, frame_data: &[u8]) -> Result<(), Self::Error> {
+        match &mut self.codec {
+            Some(codec) if is_data(frame_type) => {
+                let frame_data = codec.deflate(frame_data, frame_type.is_final())?;
+                let header = self.connection.header(
+                    frame_type,
+                    !matches!(frame_type, FrameType::Continue(_)),
+                    frame_data,
+                );
+
+                write_frame(&mut self.connection.io, &header, frame_data)
+            }
+            _ => self.connection.send(frame_type, frame_data),
+        }
+    }
+}
+
+/// Opens a WebSocket connection to `uri` like [`client::connect`], offering permessage-deflate with `params`.
+///
+/// The returned connection compresses its messages if the server accepted the offer.
+pub fn connect<'a, C, M>(
+    client: &'a mut Client<C>,
+    uri: &str,
+    headers: &[(&str, &str)],
+    rng: M,
+    params: &DeflateParams,
+) -> Result<Deflate<&'a mut C::RawConnection, M>, ConnectError<C::Error>>
+where
+    C: ClientConnection,
+    M: FnMut(&mut [u8]),
+{
+    let mut buf = [0; 128];
+    let headers = offer_headers(headers, params, &mut buf)?;
+
+    let mut agreed = None;
+
+    let connection = client::connect_with(client, uri, &headers, rng, |response| {
+        agree(params, response.header(EXTENSIONS_HEADER), &mut agreed)
+    })?;
+
+    Ok(Deflate::new(connection, agreed))
+}
+
+/// Accepts the WebSocket upgrade request received on `connection` like [`server::accept`], and agrees upon
+/// permessage-deflate with `params` if the client offered it.
+pub fn accept<'a, C>(
+    connection: &'a mut C,
+    params: &DeflateParams,
+) -> Result<Deflate<&'a mut C::RawConnection>, UpgradeError<C::Error>>
+where
+    C: ServerConnection,
+{
+    let params = connection
+        .header(EXTENSIONS_HEADER)
+        .and_then(|offers| params.accept(offers));
+
+    let mut buf = [0; 128];
+    let response = params.as_ref().map(|params| params.format(&mut buf));
+
+    let connection = match response {
+        Some(response) => {
+            server::accept_with_headers(connection, &[(EXTENSIONS_HEADER, response)])?
+        }
+        None => server::accept(connection)?,
+    };
+
+    Ok(Deflate::new(connection, params))
+}
+
+/// Returns `headers` followed by the offer of the extension with `params`.
+fn offer_headers<'a, E>(
+    headers: &[(&'a str, &'a str)],
+    params: &DeflateParams,
+    buf: &'a mut [u8; 128],
+) -> Result<heapless::Vec<(&'a str, &'a str), 32>, ConnectError<E>> {
+    let mut offer_headers = heapless::Vec::new();
+
+    for header in headers
+        .iter()
+        .copied()
+        .chain([(EXTENSIONS_HEADER, params.offer().format(buf))])
+    {
+        offer_headers
+            .push(header)
+            .map_err(|_| ConnectError::TooManyHeaders)?;
+    }
+
+    Ok(offer_headers)
+}
+
+/// Stores the parameters agreed upon for the offer with `params` into `agreed`, if the server accepted it,
+/// and returns `false` if its `response` is not valid.
+fn agree(
+    params: &DeflateParams,
+    response: Option<&str>,
+    agreed: &mut Option<DeflateParams>,
+) -> bool {
+    *agreed = match response {
+        Some(response) => match params.agree(response) {
+            Some(params) => Some(params),
+            None => return false,
+        },
+        None => None,
+    };
+
+    true
+}
+
+pub mod asynch {
+    use crate::http::client::asynch::{Client, Connection as ClientConnection};
+    use crate::http::server::asynch::Connection as ServerConnection;
+    use crate::io::asynch::{Read, Write};
+    use crate::utils::ws::asynch::write_frame;
+    use crate::utils::ws::client::asynch as client;
+    use crate::utils::ws::client::ConnectError;
+    use crate::utils::ws::server::asynch as server;
+    use crate::utils::ws::server::UpgradeError;
+    use crate::utils::ws::WsError;
+    use crate::ws::asynch::{FrameType, Receiver, Sender};
+
+    use super::{agree, is_data, offer_headers, EXTENSIONS_HEADER};
+
+    pub use super::{Deflate, DeflateParams, DEFAULT_COMPRESSION_LEVEL, EXTENSION};
+
+    impl<T, M> Receiver for Deflate<T, M>
+    where
+        T: Read,
+        M: FnMut(&mut [u8]),
+    {
+        async fn recv(
+            &mut self,
+            frame_data_buf: &mut [u8],
+        ) -> Result<(FrameType, usize), Self::Error> {
+            let Some(codec) = &mut self.codec else {
+                return self.connection.recv(frame_data_buf).await;
+            };
+
+            let client = self.connection.is_client();
+
+            loop {
+                // The compressed payload is received into the codec, once the header tells it is compressed.
+                // Its length fits into a `usize`, as it was checked against `frame_data_buf`
+                let buf = match &self.connection.recv.frame {
+                    Some((header, _)) if codec.is_compressed(header) => {
+                        codec.payload(header.payload_len as usize)
+                    }
+                    _ => &mut *frame_data_buf,
+                };
+
+                let read = self
+                    .connection
+                    .io
+                    .read(self.connection.recv.remaining(buf)?)
+                    .await
+                    .map_err(WsError::Io)?;
+
+                if let Some((header, len)) =
+                    self.connection.recv.advance(read, buf, client, true)?
+                {
+                    return codec.received(&header, len, frame_data_buf);
+                }
+            }
+        }
+    }
+
+    impl<T, M> Sender for Deflate<T, M>
+    where
+        T: Write,
+        M: FnMut(&mut [u8]),
+    {
+        async fn send(
+            &mut self,
+            frame_type: FrameType,
+            frame_data: &[u8],
+        ) -> Result<(), Self::Error> {
+            match &mut self.codec {
+                Some(codec) if is_data(frame_type) => {
+                    let frame_data = codec.deflate(frame_data, frame_type.is_final())?;
+                    let header = self.connection.header(
+                        frame_type,
+                        !matches!(frame_type, FrameType::Continue(_)),
+                        frame_data,
+                    );
+
+                    write_frame(&mut self.connection.io, &header, frame_data).await
+                }
+                _ => self.connection.send(frame_type, frame_data).await,
+            }
+        }
+    }
+
+    /// Opens a WebSocket connection to `uri`, offering permessage-deflate with `params`.
+    ///
+    /// See [`super::connect`].
+    pub async fn connect<'a, C, M>(
+        client: &'a mut Client<C>,
+        uri: &str,
+        headers: &[(&str, &str)],
+        rng: M,
+        params: &DeflateParams,
+    ) -> Result<Deflate<&'a mut C::RawConnection, M>, ConnectError<C::Error>>
+    where
+        C: ClientConnection,
+        M: FnMut(&mut [u8]),
+    {
+        let mut buf = [0; 128];
+        let headers = offer_headers(headers, params, &mut buf)?;
+
+        let mut agreed = None;
+
+        let connection = client::connect_with(client, uri, &headers, rng, |response| {
+            agree(params, response.header(EXTENSIONS_HEADER), &mut agreed)
+        })
+        .await?;
+
+        Ok(Deflate::new(connection, agreed))
+    }
+
+    /// Accepts the WebSocket upgrade request received on `connection`, and agrees upon permessage-deflate
+    /// with `params` if the client offered it.
+    ///
+    /// See [`super::accept`].
+    pub async fn accept<'a, C>(
+        connection: &'a mut C,
+        params: &DeflateParams,
+    ) -> Result<Deflate<&'a mut C::RawConnection>, UpgradeError<C::Error>>
+    where
+        C: ServerConnection,
+    {
+        let params = connection
+            .header(EXTENSIONS_HEADER)
+            .and_then(|offers| params.accept(offers));
+
+        let mut buf = [0; 128];
+        let response = params.as_ref().map(|params| params.format(&mut buf));
+
+        let connection = match response {
+            Some(response) => {
+                server::accept_with_headers(connection, &[(EXTENSIONS_HEADER, response)]).await?
+            }
+            None => server::accept(connection).await?,
+        };
+
+        Ok(Deflate::new(connection, params))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec::Vec;
+
+    use miniz_oxide::deflate::compress_to_vec;
+
+    use crate::http::client::Client;
+    use crate::http::server::{Connection, Handler};
+    use crate::io::{ErrorKind, ErrorType, Read};
+    use crate::utils::http::client::ClientConnection;
+    use crate::utils::http::server::connection::handle_connection;
+    use crate::utils::io::test::{block_on, MockSocket};
+    use crate::utils::ws::client::ConnectError;
+    use crate::utils::ws::server::UpgradeError;
+    use crate::utils::ws::{WsConnection, WsError};
+    use crate::ws::{FrameType, Receiver, Sender};
+
+    use super::{accept, connect, Deflate, DeflateParams, TRAILER};
+
+    fn mask(key: &mut [u8]) {
+        key.fill(0);
+    }
+
+    /// Sends `frames` from a client to a server with `params`, and returns what was sent.
+    fn send(params: DeflateParams, level: Option<u8>, frames: &[(FrameType, &[u8])]) -> Vec<u8> {
+        let mut socket = MockSocket::new(&[]);
+        let mut client = Deflate::new(WsConnection::client(&mut socket, mask), Some(params));
+
+        if let Some(level) = level {
+            client.set_compression_level(level);
+        }
+
+        for (frame_type, data) in frames {
+            client.send(*frame_type, data).unwrap();
+        }
+
+        socket.output
+    }
+
+    fn server(input: &[u8], params: DeflateParams) -> Deflate<MockSocket<'_>> {
+        Deflate::new(WsConnection::server(MockSocket::new(input)), Some(params))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = b"Hello, Hello, Hello, Hello";
+
+        for params in [
+            DeflateParams::default(),
+            DeflateParams {
+                server_no_context_takeover: true,
+                client_no_context_takeover: true,
+                ..Default::default()
+            },
+        ] {
+            let frames = [
+                (FrameType::Text(false), &data[..]),
+                (FrameType::Binary(true), &data[..13]),
+                (FrameType::Continue(true), &data[13..]),
+                (FrameType::Ping, b"ping"),
+                (FrameType::Text(false), &data[..]),
+            ];
+
+            let output = send(params, None, &frames);
+            let mut server = server(&output, params);
+            let mut buf = [0; 64];
+
+            for (frame_type, data) in frames {
+                let (received, len) = server.recv(&mut buf).unwrap();
+
+                assert_eq!(received, frame_type);
+                assert_eq!(&buf[..len], data);
+            }
+
+            assert_eq!(server.recv(&mut buf).unwrap(), (FrameType::SocketClose, 0));
+        }
+    }
+
+    #[test]
+    fn compression_level() {
+        let data = [b'a'; 64];
+        let frames = [(FrameType::Binary(false), &data[..])];
+
+        let compressed = send(DeflateParams::default(), None, &frames);
+        let stored = send(DeflateParams::default(), Some(0), &frames);
+
+        // Stored blocks carry the data as is
+        assert!(compressed.len() < data.len());
+        assert!(stored.len() > data.len());
+        assert!(stored.windows(data.len()).any(|window| window == data));
+
+        // The compressed payload must fit into the buffer as well
+        let mut server = server(&stored, DeflateParams::default());
+        let mut buf = [0; 80];
+
+        assert_eq!(
+            server.recv(&mut buf).unwrap(),
+            (FrameType::Binary(false), 64)
+        );
+        assert_eq!(buf[..64], data);
+    }
+
+    #[test]
+    fn overflow() {
+        let data = [b'a'; 64];
+
+        // The payload of a frame which is not final may also decompress beyond the buffer
+        for frame_type in [FrameType::Binary(false), FrameType::Binary(true)] {
+            let output = send(DeflateParams::default(), None, &[(frame_type, &data)]);
+            let mut server = server(&output, DeflateParams::default());
+
+            assert!(matches!(
+                server.recv(&mut [0; 32]),
+                Err(WsError::BufferOverflow)
+            ));
+        }
+    }
+
+    #[test]
+    fn roundtrip_async() {
+        let output = send(
+            DeflateParams::default(),
+            None,
+            &[(FrameType::Text(false), b"Hello, Hello")],
+        );
+        let mut server = server(&output, DeflateParams::default());
+        let mut buf = [0; 16];
+
+        assert_eq!(
+            block_on(crate::ws::asynch::Receiver::recv(&mut server, &mut buf)).unwrap(),
+            (FrameType::Text(false), 12)
+        );
+        assert_eq!(&buf[..12], b"Hello, Hello");
+    }
+
+    /// Returns a frame sent by a client with the masking key of [`mask`], of which `first` is the first byte.
+    fn frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        assert!(payload.len() < 126);
+
+        [
+            &[first, 0x80 | payload.len() as u8, 0, 0, 0, 0][..],
+            payload,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn final_block() {
+        // Messages may end the compressed stream with a block with `BFINAL` set, followed by the trailer or not
+        let hello = compress_to_vec(b"Hello, Hello", 6);
+        let abcdef = compress_to_vec(b"abcdef", 6);
+
+        let input = [
+            frame(0xc1, &hello),
+            frame(0xc2, &[&hello[..], &TRAILER].concat()),
+            // The stream ends within the first fragment of a message
+            frame(0x42, &abcdef[..abcdef.len() - 2]),
+            frame(0x80, &abcdef[abcdef.len() - 2..]),
+            frame(0x42, &abcdef),
+            frame(0x80, &[]),
+            // A message of a new stream, starting with a fresh context
+            send(
+                DeflateParams::default(),
+                None,
+                &[(FrameType::Text(false), b"Hello again")],
+            ),
+        ]
+        .concat();
+
+        let mut server = server(&input, DeflateParams::default());
+        let mut buf = [0; 16];
+        let mut messages = Vec::new();
+
+        loop {
+            let (frame_type, len) = server.recv(&mut buf).unwrap();
+
+            match frame_type {
+                FrameType::SocketClose => break,
+                FrameType::Continue(_) => {}
+                frame_type => messages.push((frame_type, Vec::new())),
+            }
+
+            // Safe to unwrap, the stream starts with a message
+            messages
+                .last_mut()
+                .unwrap()
+                .1
+                .extend_from_slice(&buf[..len]);
+        }
+
+        assert_eq!(
+            messages,
+            [
+                (FrameType::Text(false), b"Hello, Hello".to_vec()),
+                (FrameType::Binary(false), b"Hello, Hello".to_vec()),
+                (FrameType::Binary(true), b"abcdef".to_vec()),
+                (FrameType::Binary(true), b"abcdef".to_vec()),
+                (FrameType::Text(false), b"Hello again".to_vec()),
+            ]
+        );
+    }
+
+    /// A socket whose reads fail every other time.
+    struct Flaky<'a> {
+        socket: MockSocket<'a>,
+        fail: bool,
+    }
+
+    impl ErrorType for Flaky<'_> {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Flaky<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.fail = !self.fail;
+
+            if self.fail {
+                Err(ErrorKind::TimedOut)
+            } else {
+                self.socket.read(buf).map_err(|e| match e {})
+            }
+        }
+    }
+
+    impl crate::io::Write for Flaky<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.socket.write(buf).map_err(|e| match e {})
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recv_resumed() {
+        let data = b"Hello, Hello, Hello, Hello";
+        let frames = [
+            (FrameType::Text(false), &data[..]),
+            (FrameType::Binary(true), &data[..13]),
+            (FrameType::Ping, b"ping"),
+            (FrameType::Continue(true), &data[13..]),
+        ];
+        let output = send(DeflateParams::default(), None, &frames);
+
+        let socket = Flaky {
+            socket: MockSocket::chunked(&output, 3),
+            fail: false,
+        };
+        let mut server = Deflate::new(WsConnection::server(socket), Some(DeflateParams::default()));
+        let mut buf = [0; 32];
+
+        for (frame_type, data) in frames {
+            let mut failures = 0;
+
+            let received = loop {
+                match server.recv(&mut buf) {
+                    Ok(received) => break received,
+                    Err(WsError::Io(ErrorKind::TimedOut)) => {
+                        failures += 1;
+
+                        // Sending in between does not disturb the frame being received
+                        server.send(FrameType::Text(false), b"pong").unwrap();
+                    }
+                    Err(e) => panic!("{e:?}"),
+                }
+            };
+
+            assert!(failures > 1);
+            assert_eq!((received.0, &buf[..received.1]), (frame_type, data));
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let params = |header: &str| DeflateParams::parse_all(header).collect::<Vec<_>>();
+
+        for (header, expected) in [
+            ("permessage-deflate", Some(DeflateParams::default())),
+            (
+                " permessage-deflate ; server_no_context_takeover;client_no_context_takeover ",
+                Some(DeflateParams {
+                    server_no_context_takeover: true,
+                    client_no_context_takeover: true,
+                    ..Default::default()
+                }),
+            ),
+            // Without a value, the client only announces support for the parameter
+            (
+                "permessage-deflate; client_max_window_bits",
+                Some(DeflateParams {
+                    client_max_window_bits: Some(15),
+                    ..Default::default()
+                }),
+            ),
+            (
+                "permessage-deflate; server_max_window_bits=\"10\"; client_max_window_bits = 8",
+                Some(DeflateParams {
+                    server_max_window_bits: Some(10),
+                    client_max_window_bits: Some(8),
+                    ..Default::default()
+                }),
+            ),
+            // Duplicate parameters
+            (
+                "permessage-deflate; server_no_context_takeover; server_no_context_takeover",
+                None,
+            ),
+            (
+                "permessage-deflate; client_max_window_bits; client_max_window_bits=9",
+                None,
+            ),
+            (
+                "permessage-deflate; server_max_window_bits=9; server_max_window_bits=9",
+                None,
+            ),
+            // Invalid values
+            ("permessage-deflate; server_max_window_bits", None),
+            ("permessage-deflate; server_max_window_bits=7", None),
+            ("permessage-deflate; server_max_window_bits=16", None),
+            ("permessage-deflate; server_max_window_bits=+9", None),
+            ("permessage-deflate; client_max_window_bits=\"\"", None),
+            ("permessage-deflate; client_no_context_takeover=true", None),
+            // Unknown parameters and extensions
+            ("permessage-deflate; x", None),
+            ("x-webkit-deflate-frame", None),
+        ] {
+            assert_eq!(params(header), [expected], "{header}");
+        }
+
+        assert_eq!(
+            params("x-webkit-deflate-frame, permessage-deflate; client_no_context_takeover"),
+            [
+                None,
+                Some(DeflateParams {
+                    client_no_context_takeover: true,
+                    ..Default::default()
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn format() {
+        let mut buf = [0; 128];
+
+        assert_eq!(
+            DeflateParams::default().format(&mut buf),
+            "permessage-deflate"
+        );
+
+        let params = DeflateParams {
+            server_no_context_takeover: true,
+            client_no_context_takeover: true,
+            server_max_window_bits: Some(10),
+            client_max_window_bits: Some(12),
+        };
+        let formatted = String::from(params.format(&mut buf));
+        assert_eq!(
+            formatted,
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover; \
+             server_max_window_bits=10; client_max_window_bits=12"
+        );
+        assert_eq!(
+            DeflateParams::parse_all(&formatted).collect::<Vec<_>>(),
+            [Some(params)]
+        );
+    }
+
+    #[test]
+    fn accept_offers() {
+        let limit_client = DeflateParams {
+            client_max_window_bits: Some(10),
+            ..Default::default()
+        };
+        let no_context_takeover = DeflateParams {
+            server_no_context_takeover: true,
+            client_no_context_takeover: true,
+            ..Default::default()
+        };
+
+        for (server, offers, expected) in [
+            (
+                DeflateParams::default(),
+                "permessage-deflate; client_max_window_bits",
+                Some(DeflateParams::default()),
+            ),
+            // The client window is only limited if the client announces support for it
+            (
+                limit_client,
+                "permessage-deflate",
+                Some(DeflateParams::default()),
+            ),
+            (
+                limit_client,
+                "permessage-deflate; client_max_window_bits",
+                Some(limit_client),
+            ),
+            (
+                limit_client,
+                "permessage-deflate; client_max_window_bits=9",
+                Some(DeflateParams {
+                    client_max_window_bits: Some(9),
+                    ..Default::default()
+                }),
+            ),
+            // Offers limiting the window of the server are declined
+            (
+                DeflateParams::default(),
+                "permessage-deflate; server_max_window_bits=10",
+                None,
+            ),
+            (
+                DeflateParams::default(),
+                "permessage-deflate; server_max_window_bits=10, permessage-deflate",
+                Some(DeflateParams::default()),
+            ),
+            (
+                DeflateParams::default(),
+                "permessage-deflate; server_max_window_bits=15",
+                Some(DeflateParams {
+                    server_max_window_bits: Some(15),
+                    ..Default::default()
+                }),
+            ),
+            // The server may reset its context whatever the offer, and the one of the client
+            (
+                no_context_takeover,
+                "permessage-deflate",
+                Some(no_context_takeover),
+            ),
+            (
+                DeflateParams::default(),
+                "permessage-deflate; server_no_context_takeover",
+                Some(DeflateParams {
+                    server_no_context_takeover: true,
+                    ..Default::default()
+                }),
+            ),
+            // Invalid offers are skipped
+            (
+                DeflateParams::default(),
+                "permessage-deflate; x, permessage-deflate; client_no_context_takeover",
+                Some(DeflateParams::default()),
+            ),
+            (DeflateParams::default(), "x-webkit-deflate-frame", None),
+        ] {
+            assert_eq!(server.accept(offers), expected, "{offers}");
+        }
+    }
+
+    #[test]
+    fn agree() {
+        let offer = |params: DeflateParams, response: &str| params.agree(response);
+
+        let default = DeflateParams::default();
+        let server_no_context_takeover = DeflateParams {
+            server_no_context_takeover: true,
+            ..Default::default()
+        };
+        let client_no_context_takeover = DeflateParams {
+            client_no_context_takeover: true,
+            ..Default::default()
+        };
+        let limit_server = DeflateParams {
+            server_max_window_bits: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(offer(default, "permessage-deflate"), Some(default));
+        assert_eq!(
+            offer(default, "permessage-deflate; server_no_context_takeover"),
+            Some(server_no_context_takeover)
+        );
+        assert_eq!(
+            offer(default, "permessage-deflate; client_no_context_takeover"),
+            Some(client_no_context_takeover)
+        );
+        assert_eq!(
+            offer(client_no_context_takeover, "permessage-deflate"),
+            Some(client_no_context_takeover)
+        );
+
+        // The client window was not offered to be limited
+        assert_eq!(
+            offer(default, "permessage-deflate; client_max_window_bits=10"),
+            None
+        );
+        // A second extension
+        assert_eq!(
+            offer(default, "permessage-deflate, permessage-deflate"),
+            None
+        );
+        assert_eq!(offer(default, "permessage-deflate; x"), None);
+        assert_eq!(offer(default, "x-webkit-deflate-frame"), None);
+
+        // The parameters limiting the server must be agreed upon
+        assert_eq!(
+            offer(server_no_context_takeover, "permessage-deflate"),
+            None
+        );
+        assert_eq!(
+            offer(
+                server_no_context_takeover,
+                "permessage-deflate; server_no_context_takeover"
+            ),
+            Some(server_no_context_takeover)
+        );
+        assert_eq!(offer(limit_server, "permessage-deflate"), None);
+        assert_eq!(
+            offer(
+                limit_server,
+                "permessage-deflate; server_max_window_bits=12"
+            ),
+            None
+        );
+        assert_eq!(
+            offer(limit_server, "permessage-deflate; server_max_window_bits=9"),
+            Some(DeflateParams {
+                server_max_window_bits: Some(9),
+                ..Default::default()
+            })
+        );
+    }
+
+    /// Generates the nonce of the sample handshake of RFC 6455, section 1.3, and masking keys from its start.
+    fn rng(buf: &mut [u8]) {
+        buf.copy_from_slice(&b"the sample nonce"[..buf.len()]);
+    }
+
+    #[test]
+    fn connect_handshake() {
+        // A server message compressed with the context of the server
+        let mut socket = MockSocket::new(b"");
+        let mut server = Deflate::new(
+            WsConnection::server(&mut socket),
+            Some(DeflateParams::default()),
+        );
+        server.send(FrameType::Text(false), b"Hello").unwrap();
+        let message = socket.output;
+
+        let response = |extensions: &str| {
+            [
+                &b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                   Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"[..],
+                extensions.as_bytes(),
+                b"\r\n",
+                &message,
+            ]
+            .concat()
+        };
+
+        let params = DeflateParams {
+            client_no_context_takeover: true,
+            client_max_window_bits: Some(10),
+            ..Default::default()
+        };
+
+        let input = response("Sec-WebSocket-Extensions: permessage-deflate\r\n");
+        let mut socket = MockSocket::new(&input);
+        let mut buf = [0; 512];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        let mut ws = connect(&mut client, "ws://h/chat", &[], rng, &params).unwrap();
+        assert!(ws.is_compressed());
+
+        let mut buf = [0; 8];
+        assert_eq!(ws.recv(&mut buf).unwrap(), (FrameType::Text(false), 5));
+        assert_eq!(&buf[..5], b"Hello");
+
+        ws.send(FrameType::Text(false), b"Hello").unwrap();
+
+        let output = String::from_utf8_lossy(&socket.output).into_owned();
+        // The client window is never offered to be limited, and the compressed message has RSV1 set
+        assert!(output.contains(
+            "\r\nSec-WebSocket-Extensions: permessage-deflate; client_no_context_takeover\r\n"
+        ));
+        let end = output.find("\r\n\r\n").unwrap() + 4;
+        assert_eq!(socket.output[end], 0xc1);
+        assert_eq!(&socket.output[end + 2..end + 6], b"the ");
+
+        // Without the extension in the response, the messages are not compressed
+        let input = response("");
+        let mut socket = MockSocket::new(&input);
+        let mut buf = [0; 512];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        let ws = connect(&mut client, "ws://h/chat", &[], rng, &params).unwrap();
+        assert!(!ws.is_compressed());
+
+        // A response which does not match the offer fails the handshake
+        let input =
+            response("Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits=10\r\n");
+        let mut socket = MockSocket::new(&input);
+        let mut buf = [0; 512];
+        let mut client = Client::wrap(ClientConnection::<_, 16>::new(&mut socket, &mut buf));
+
+        assert!(matches!(
+            connect(
+                &mut client,
+                "ws://h/chat",
+                &[],
+                rng,
+                &DeflateParams::default()
+            ),
+            Err(ConnectError::InvalidResponse)
+        ));
+    }
+
+    /// Accepts the upgrade request with permessage-deflate, and echoes a message.
+    struct Echo;
+
+    impl<C> Handler<C> for Echo
+    where
+        C: Connection,
+    {
+        type Error = UpgradeError<C::Error>;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let params = DeflateParams {
+                server_no_context_takeover: true,
+                ..Default::default()
+            };
+            let mut ws = accept(connection, &params)?;
+
+            let mut buf = [0; 32];
+            let (frame_type, len) = ws.recv(&mut buf).unwrap();
+            ws.send(frame_type, &buf[..len]).unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn accept_handshake() {
+        let serve = |extensions: &str, message: &[u8]| {
+            let input = [
+                std::format!(
+                    "GET /chat HTTP/1.1\r\nHost: h\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                     Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+                     {extensions}\r\n"
+                )
+                .as_bytes(),
+                message,
+            ]
+            .concat();
+
+            let mut socket = MockSocket::new(&input);
+            let mut buf = [0; 1024];
+            handle_connection::<_, _, 16>(&mut socket, &mut buf, &Echo).unwrap();
+
+            socket.output
+        };
+
+        let message = send(
+            DeflateParams::default(),
+            None,
+            &[(FrameType::Text(false), b"Hello, Hello")],
+        );
+        let output = serve(
+            "Sec-WebSocket-Extensions: x-webkit-deflate-frame, permessage-deflate; client_max_window_bits\r\n",
+            &message,
+        );
+
+        let head =
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                     Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n";
+        // The server resets its context whatever the offer
+        let extension =
+            b"Sec-WebSocket-Extensions: permessage-deflate; server_no_context_takeover\r\n\r\n";
+
+        let (response, frame) = output.split_at(head.len() + extension.len());
+        assert_eq!(response, [&head[..], extension].concat());
+        assert_eq!(frame[0], 0xc1);
+
+        let mut client = Deflate::new(
+            WsConnection::client(MockSocket::new(frame), mask),
+            Some(DeflateParams {
+                server_no_context_takeover: true,
+                ..Default::default()
+            }),
+        );
+        let mut buf = [0; 16];
+        assert_eq!(client.recv(&mut buf).unwrap(), (FrameType::Text(false), 12));
+        assert_eq!(&buf[..12], b"Hello, Hello");
+
+        // Without an offer, the messages are not compressed
+        let output = serve("", b"\x81\x85\0\0\0\0Hello");
+        assert!(output.ends_with(b"\r\n\r\n\x81\x05Hello"));
+    }
+}
@@ -0,0 +1,551 @@
+use core::fmt;
+use core::ops::Range;
+
+use crate::io::{Error, ErrorKind};
+use crate::utils::io::Buffer;
+use crate::ws::{ErrorType, FrameType, Receiver, Sender};
+
+use super::MAX_CONTROL_PAYLOAD_LEN;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MessageError<E> {
+    Io(E),
+    /// A continuation frame without a fragmented message, or a new message within a fragmented one.
+    UnexpectedFrame,
+    /// The message is longer than the maximum size of the receiver. It is discarded, along with
+    /// its remaining fragments.
+    MessageTooLarge,
+    /// A text message is not valid UTF-8.
+    InvalidUtf8,
+}
+
+impl<E: fmt::Debug> fmt::Display for MessageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for MessageError<E> {}
+
+impl<E> Error for MessageError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::UnexpectedFrame | Self::InvalidUtf8 => ErrorKind::InvalidData,
+            Self::MessageTooLarge => ErrorKind::OutOfMemory,
+        }
+    }
+}
+
+/// A complete message, or a control frame received between the fragments of a message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Message<'a> {
+    Text(&'a str),
+    Binary(&'a [u8]),
+    Ping(&'a [u8]),
+    Pong(&'a [u8]),
+    Close(&'a [u8]),
+    SocketClose,
+}
+
+impl<'a> Message<'a> {
+    fn new<E>(frame_type: FrameType, data: &'a [u8]) -> Result<Self, MessageError<E>> {
+        Ok(match frame_type {
+            FrameType::Text(_) => {
+                Self::Text(core::str::from_utf8(data).map_err(|_| MessageError::InvalidUtf8)?)
+            }
+            FrameType::Binary(_) => Self::Binary(data),
+            FrameType::Ping => Self::Ping(data),
+            FrameType::Pong => Self::Pong(data),
+            FrameType::Close => Self::Close(data),
+            FrameType::SocketClose | FrameType::Continue(_) => Self::SocketClose,
+        })
+    }
+}
+
+/// The state of the message being reassembled from its fragments.
+struct Assembly {
+    max_len: usize,
+    frame_type: Option<FrameType>,
+    len: usize,
+    /// Whether the remaining fragments of a message which is too large are being discarded.
+    discarding: bool,
+}
+
+impl Assembly {
+    const fn new(max_len: usize) -> Self {
+        Self {
+            max_len,
+            frame_type: None,
+            len: 0,
+            discarding: false,
+        }
+    }
+
+    /// Returns the part of the buffer receiving the next frame, after the fragments received so far,
+    /// with room for a whole message or an interleaved control frame.
+    fn frame_buf<'b, B>(&self, buf: &'b mut B) -> &'b mut [u8]
+    where
+        B: Buffer + ?Sized,
+    {
+        let limit = self.max_len + MAX_CONTROL_PAYLOAD_LEN;
+        let data = buf.reserve(limit);
+        let end = data.len().min(limit);
+
+        &mut data[self.len..end]
+    }
+
+    /// Processes a received frame of `len` bytes, and returns the type and range within the buffer of
+    /// a complete message or a control frame.
+    fn frame<E>(
+        &mut self,
+        frame_type: FrameType,
+        len: usize,
+    ) -> Result<Option<(FrameType, Range<usize>)>, MessageError<E>> {
+        let complete = match (frame_type, self.frame_type) {
+            (FrameType::Ping | FrameType::Pong | FrameType::Close, _) => {
+                return Ok(Some((frame_type, self.len..self.len + len)));
+            }
+            (FrameType::SocketClose, _) => {
+                self.reset();
+                return Ok(Some((frame_type, 0..0)));
+            }
+            (FrameType::Text(fragmented) | FrameType::Binary(fragmented), None)
+                if !self.discarding =>
+            {
+                self.frame_type = Some(frame_type);
+                !fragmented
+            }
+            (FrameType::Continue(final_), Some(_)) => final_,
+            (FrameType::Continue(final_), None) if self.discarding => {
+                self.discarding = !final_;
+                return Ok(None);
+            }
+            _ => {
+                self.reset();
+                return Err(MessageError::UnexpectedFrame);
+            }
+        };
+
+        self.len += len;
+
+        if self.len > self.max_len {
+            self.reset();
+            self.discarding = !complete;
+
+            Err(MessageError::MessageTooLarge)
+        } else if complete {
+            let message = self.frame_type.map(|frame_type| (frame_type, 0..self.len));
+            self.reset();
+
+            Ok(message)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn reset(&mut self) {
+        self.frame_type = None;
+        self.len = 0;
+        self.discarding = false;
+    }
+}
+
+/// Receives whole messages, reassembling fragmented ones, over a frame [`Receiver`].
+///
+/// Control frames received between the fragments of a message are returned as they come, with their payload
+/// stored after the fragments received so far. The same buffer must then be passed to the next call
+/// of [`MessageReceiver::recv`], which carries on with the message.
+pub struct MessageReceiver<R> {
+    receiver: R,
+    assembly: Assembly,
+}
+
+impl<R> MessageReceiver<R>
+where
+    R: Receiver,
+{
+    /// Creates a receiver of messages of up to `max_len` bytes.
+    pub const fn new(receiver: R, max_len: usize) -> Self {
+        Self {
+            receiver,
+            assembly: Assembly::new(max_len),
+        }
+    }
+
+    pub fn release(self) -> R {
+        self.receiver
+    }
+
+    /// Receives the next message or control frame into `buf`.
+    ///
+    /// A growable `buf` grows up to the maximum message size, plus room for a control frame.
+    pub fn recv<'b, B>(&mut self, buf: &'b mut B) -> Result<Message<'b>, MessageError<R::Error>>
+    where
+        B: Buffer + ?Sized,
+    {
+        let (frame_type, range) = loop {
+            let (frame_type, len) = self
+                .receiver
+                .recv(self.assembly.frame_buf(buf))
+                .map_err(MessageError::Io)?;
+
+            if let Some(message) = self.assembly.frame(frame_type, len)? {
+                break message;
+            }
+        };
+
+        Message::new(frame_type, &buf.reserve(range.end)[range])
+    }
+}
+
+/// Sends messages over a frame [`Sender`], splitting the ones larger than the fragment length into fragments.
+///
+/// Frames which are already fragmented, and control frames, are sent as they are.
+pub struct MessageSender<S> {
+    sender: S,
+    fragment_len: usize,
+}
+
+impl<S> MessageSender<S> {
+    /// Creates a sender of fragments of up to `fragment_len` bytes.
+    pub const fn new(sender: S, fragment_len: usize) -> Self {
+        Self {
+            sender,
+            fragment_len,
+        }
+    }
+
+    pub fn release(self) -> S {
+        self.sender
+    }
+}
+
+/// Returns the frames to send for `frame_data`.
+fn fragments(
+    frame_type: FrameType,
+    frame_data: &[u8],
+    fragment_len: usize,
+) -> impl Iterator<Item = (FrameType, &[u8])> {
+    let fragment_len = fragment_len.max(1);
+
+    let count = match frame_type {
+        FrameType::Text(false) | FrameType::Binary(false) if frame_data.len() > fragment_len => {
+            frame_data.len().div_ceil(fragment_len)
+        }
+        _ => 1,
+    };
+
+    (0..count).map(move |index| {
+        if count == 1 {
+            return (frame_type, frame_data);
+        }
+
+        let start = index * fragment_len;
+        let end = (start + fragment_len).min(frame_data.len());
+
+        let fragment_type = match frame_type {
+            FrameType::Text(_) if index == 0 => FrameType::Text(true),
+            FrameType::Binary(_) if index == 0 => FrameType::Binary(true),
+            _ => FrameType::Continue(index == count - 1),
+        };
+
+        (fragment_type, &frame_data[start..end])
+    })
+}
+
+impl<S> ErrorType for MessageSender<S>
+where
+    S: ErrorType,
+{
+    type Error = S::Error;
+}
+
+impl<S> Sender for MessageSender<S>
+where
+    S: Sender,
+{
+    fn send(&mut self, frame_type: FrameType, frame_data: &[u8]) -> Result<(), Self::Error> {
+        for (frame_type, frame_data) in fragments(frame_type, frame_data, self.fragment_len) {
+            self.sender.send(frame_type, frame_data)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub mod asynch {
+    use crate::utils::io::Buffer;
+    use crate::ws::asynch::{FrameType, Receiver, Sender};
+
+    use super::{fragments, Assembly};
+
+    pub use super::{Message, MessageError, MessageSender};
+
+    /// Receives whole messages, reassembling fragmented ones, over a frame [`Receiver`].
+    ///
+    /// See [`super::MessageReceiver`].
+    pub struct MessageReceiver<R> {
+        receiver: R,
+        assembly: Assembly,
+    }
+
+    impl<R> MessageReceiver<R>
+    where
+        R: Receiver,
+    {
+        /// Creates a receiver of messages of up to `max_len` bytes.
+        pub const fn new(receiver: R, max_len: usize) -> Self {
+            Self {
+                receiver,
+                assembly: Assembly::new(max_len),
+            }
+        }
+
+        pub fn release(self) -> R {
+            self.receiver
+        }
+
+        /// Receives the next message or control frame into `buf`.
+        pub async fn recv<'b, B>(
+            &mut self,
+            buf: &'b mut B,
+        ) -> Result<Message<'b>, MessageError<R::Error>>
+        where
+            B: Buffer + ?Sized,
+        {
+            let (frame_type, range) = loop {
+                let (frame_type, len) = self
+                    .receiver
+                    .recv(self.assembly.frame_buf(buf))
+                    .await
+                    .map_err(MessageError::Io)?;
+
+                if let Some(message) = self.assembly.frame(frame_type, len)? {
+                    break message;
+                }
+            };
+
+            Message::new(frame_type, &buf.reserve(range.end)[range])
+        }
+    }
+
+    impl<S> Sender for MessageSender<S>
+    where
+        S: Sender,
+    {
+        async fn send(
+            &mut self,
+            frame_type: FrameType,
+            frame_data: &[u8],
+        ) -> Result<(), Self::Error> {
+            for (frame_type, frame_data) in fragments(frame_type, frame_data, self.fragment_len) {
+                self.sender.send(frame_type, frame_data).await?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use crate::utils::io::test::{block_on, MockSocket};
+    use crate::utils::ws::WsConnection;
+    use crate::ws::{FrameType, Sender};
+
+    use super::{Message, MessageError, MessageReceiver, MessageSender};
+
+    /// A message fragmented as `Hel` `lo, w` `orld`, with pings and pongs between the fragments.
+    const INTERLEAVED: &[u8] = b"\x89\x02p0\x01\x03Hel\x89\x02p1\x8a\x00\x00\x05lo, w\
+                                 \x89\x02p2\x80\x04orld\x82\x01\x2a";
+
+    fn mask(key: &mut [u8]) {
+        key.fill(0);
+    }
+
+    #[test]
+    fn interleaved_control_frames() {
+        let mut socket = MockSocket::chunked(INTERLEAVED, 3);
+        let mut receiver = MessageReceiver::new(WsConnection::client(&mut socket, mask), 16);
+        let mut buf = [0; 16 + 125];
+
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Ping(b"p0"));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Ping(b"p1"));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Pong(b""));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Ping(b"p2"));
+        assert_eq!(
+            receiver.recv(&mut buf).unwrap(),
+            Message::Text("Hello, world")
+        );
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Binary(b"\x2a"));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::SocketClose);
+    }
+
+    #[test]
+    fn interleaved_control_frames_vec() {
+        let mut socket = MockSocket::new(INTERLEAVED);
+        let mut receiver = MessageReceiver::new(WsConnection::client(&mut socket, mask), 12);
+        let mut buf = Vec::new();
+
+        let mut messages = Vec::new();
+        loop {
+            match receiver.recv(&mut buf).unwrap() {
+                Message::SocketClose => break,
+                Message::Text(text) => messages.push(text.as_bytes().to_vec()),
+                Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => {
+                    messages.push(data.to_vec())
+                }
+                Message::Close(_) => unreachable!(),
+            }
+        }
+
+        assert_eq!(
+            messages,
+            [&b"p0"[..], b"p1", b"", b"p2", b"Hello, world", b"\x2a"]
+        );
+        // The buffer grows up to the maximum message size plus a control frame
+        assert_eq!(buf.len(), 12 + 125);
+    }
+
+    #[test]
+    fn text() {
+        // A character split across fragments
+        let mut socket = MockSocket::new(b"\x01\x01\xc3\x80\x01\xa9\x81\x02\xc3\x28");
+        let mut receiver = MessageReceiver::new(WsConnection::client(&mut socket, mask), 16);
+        let mut buf = [0; 256];
+
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Text("é"));
+        assert!(matches!(
+            receiver.recv(&mut buf),
+            Err(MessageError::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn unexpected_frames() {
+        let mut socket = MockSocket::new(b"\x80\x01a\x01\x01a\x81\x01b\x82\x01c");
+        let mut receiver = MessageReceiver::new(WsConnection::client(&mut socket, mask), 16);
+        let mut buf = [0; 256];
+
+        // A continuation without a message, and a new message within a fragmented one
+        assert!(matches!(
+            receiver.recv(&mut buf),
+            Err(MessageError::UnexpectedFrame)
+        ));
+        assert!(matches!(
+            receiver.recv(&mut buf),
+            Err(MessageError::UnexpectedFrame)
+        ));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Binary(b"c"));
+    }
+
+    #[test]
+    fn too_large() {
+        let mut socket = MockSocket::new(b"\x02\x03abc\x89\x00\x80\x02de\x82\x01f");
+        let mut receiver = MessageReceiver::new(WsConnection::client(&mut socket, mask), 4);
+        let mut buf = [0; 256];
+
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Ping(b""));
+        assert!(matches!(
+            receiver.recv(&mut buf),
+            Err(MessageError::MessageTooLarge)
+        ));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Binary(b"f"));
+    }
+
+    #[test]
+    fn too_large_fragments() {
+        // The remaining fragments are discarded, with the control frames between them still received
+        let mut socket =
+            MockSocket::new(b"\x02\x03abc\x00\x02de\x89\x00\x00\x02gh\x80\x01i\x82\x01f");
+        let mut receiver = MessageReceiver::new(WsConnection::client(&mut socket, mask), 4);
+        let mut buf = [0; 256];
+
+        assert!(matches!(
+            receiver.recv(&mut buf),
+            Err(MessageError::MessageTooLarge)
+        ));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Ping(b""));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Binary(b"f"));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::SocketClose);
+
+        // A new message before the final fragment
+        let mut socket = MockSocket::new(b"\x02\x03abc\x00\x02de\x82\x01x\x82\x01y");
+        let mut receiver = MessageReceiver::new(WsConnection::client(&mut socket, mask), 4);
+
+        assert!(matches!(
+            receiver.recv(&mut buf),
+            Err(MessageError::MessageTooLarge)
+        ));
+        assert!(matches!(
+            receiver.recv(&mut buf),
+            Err(MessageError::UnexpectedFrame)
+        ));
+        assert_eq!(receiver.recv(&mut buf).unwrap(), Message::Binary(b"y"));
+    }
+
+    #[test]
+    fn fragment() {
+        let mut socket = MockSocket::new(b"");
+        let mut sender = MessageSender::new(WsConnection::server(&mut socket), 5);
+
+        sender
+            .send(FrameType::Text(false), b"Hello, world")
+            .unwrap();
+        sender.send(FrameType::Ping, b"ping pong").unwrap();
+        sender.send(FrameType::Binary(false), b"12345").unwrap();
+
+        assert_eq!(
+            socket.output,
+            b"\x01\x05Hello\x00\x05, wor\x80\x02ld\x89\x09ping pong\x82\x0512345"
+        );
+    }
+
+    #[test]
+    fn roundtrip_async() {
+        use super::asynch::{MessageReceiver, MessageSender};
+
+        let mut socket = MockSocket::new(b"");
+        let mut sender = MessageSender::new(WsConnection::client(&mut socket, mask), 3);
+        block_on(crate::ws::asynch::Sender::send(
+            &mut sender,
+            FrameType::Binary(false),
+            b"abcdefg",
+        ))
+        .unwrap();
+        block_on(crate::ws::asynch::Sender::send(
+            &mut sender,
+            FrameType::Pong,
+            b"",
+        ))
+        .unwrap();
+
+        // Move the pong between the fragments
+        let mut input = socket.output;
+        let pong = input.split_off(input.len() - 6);
+        input.splice(9..9, pong);
+
+        let mut socket = MockSocket::chunked(&input, 4);
+        let mut receiver = MessageReceiver::new(WsConnection::server(&mut socket), 8);
+        let mut buf = [0; 8 + 125];
+
+        assert_eq!(
+            block_on(receiver.recv(&mut buf)).unwrap(),
+            Message::Pong(b"")
+        );
+        assert_eq!(
+            block_on(receiver.recv(&mut buf)).unwrap(),
+            Message::Binary(b"abcdefg")
+        );
+    }
+}
@@ -0,0 +1,424 @@
+use core::fmt;
+
+use crate::http::server::{Connection, Request};
+use crate::http::{Headers, Method, Query};
+use crate::io::{Error, ErrorKind};
+use crate::utils::base64;
+use crate::utils::hash::{Digest, Sha1};
+use crate::utils::http::connection::has_token;
+
+use super::WsConnection;
+
+/// The GUID appended to the `Sec-WebSocket-Key` of the client to compute the `Sec-WebSocket-Accept` hash.
+pub const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The only WebSocket version defined by RFC 6455.
+pub const VERSION: &str = "13";
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UpgradeError<E> {
+    Io(E),
+    /// The request is not a valid WebSocket upgrade request, and was answered with `400 Bad Request`.
+    InvalidRequest,
+    /// The request asks for another WebSocket version, and was answered with `426 Upgrade Required`.
+    UnsupportedVersion,
+    TooManyHeaders,
+}
+
+impl<E: fmt::Debug> fmt::Display for UpgradeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for UpgradeError<E> {}
+
+impl<E> Error for UpgradeError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::InvalidRequest => ErrorKind::InvalidData,
+            Self::UnsupportedVersion => ErrorKind::Unsupported,
+            Self::TooManyHeaders => ErrorKind::OutOfMemory,
+        }
+    }
+}
+
+/// Checks if the request asks for a WebSocket, i.e. is a `GET` request with `Upgrade: websocket`
+/// and `Connection: Upgrade`, regardless of the validity of its other headers.
+pub fn is_upgrade_request(request: &(impl Query + Headers)) -> bool {
+    request.method() == Method::Get
+        && has_token(request.header("Upgrade"), "websocket")
+        && has_token(request.header("Connection"), "Upgrade")
+}
+
+/// Computes the `Sec-WebSocket-Accept` hash answering the `Sec-WebSocket-Key` of the client.
+pub fn accept_key<'b>(key: &str, buf: &'b mut [u8; 28]) -> &'b str {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.trim().as_bytes());
+    sha1.update(GUID.as_bytes());
+
+    // Cannot fail, the encoded hash is exactly 28 characters long
+    base64::encode(&sha1.finalize(), buf).unwrap()
+}
+
+/// Validates the upgrade request, and returns its `Sec-WebSocket-Key`.
+fn validate<E>(request: &(impl Query + Headers)) -> Result<&str, UpgradeError<E>> {
+    if !is_upgrade_request(request) {
+        return Err(UpgradeError::InvalidRequest);
+    }
+
+    // Only a request for another version is answered with the supported one
+    match request.header("Sec-WebSocket-Version").map(str::trim) {
+        Some(VERSION) => (),
+        Some(_) => return Err(UpgradeError::UnsupportedVersion),
+        None => return Err(UpgradeError::InvalidRequest),
+    }
+
+    let key = request
+        .header("Sec-WebSocket-Key")
+        .ok_or(UpgradeError::InvalidRequest)?;
+
+    // The key is the base64 encoding of 16 random bytes
+    let mut buf = [0; 18];
+    match base64::decode(key.trim(), &mut buf) {
+        Ok(nonce) if nonce.len() == 16 => Ok(key),
+        _ => Err(UpgradeError::InvalidRequest),
+    }
+}
+
+/// Returns the response rejecting an invalid upgrade request.
+fn rejection<E>(
+    error: &UpgradeError<E>,
+) -> (u16, &'static str, &'static [(&'static str, &'static str)]) {
+    match error {
+        UpgradeError::UnsupportedVersion => (
+            426,
+            "Upgrade Required",
+            &[("Sec-WebSocket-Version", VERSION), ("Content-Length", "0")],
+        ),
+        _ => (400, "Bad Request", &[("Content-Length", "0")]),
+    }
+}
+
+/// Returns the headers of the `101` response, followed by `headers`.
+fn upgrade_headers<'a, E>(
+    accept: &'a str,
+    headers: &[(&'a str, &'a str)],
+) -> Result<heapless::Vec<(&'a str, &'a str), 16>, UpgradeError<E>> {
+    let mut upgrade_headers = heapless::Vec::new();
+
+    for header in [
+        ("Upgrade", "websocket"),
+        ("Connection", "Upgrade"),
+        ("Sec-WebSocket-Accept", accept),
+    ]
+    .iter()
+    .chain(headers)
+    {
+        upgrade_headers
+            .push(*header)
+            .map_err(|_| UpgradeError::TooManyHeaders)?;
+    }
+
+    Ok(upgrade_headers)
+}
+
+/// Accepts the WebSocket upgrade request received on `connection`, and returns the WebSocket connection
+/// over its raw connection.
+///
+/// If the request is not a valid upgrade request, it is answered with an error response, and
+/// [`UpgradeError::InvalidRequest`] or [`UpgradeError::UnsupportedVersion`] is returned.
+pub fn accept<C>(
+    connection: &mut C,
+) -> Result<WsConnection<&mut C::RawConnection>, UpgradeError<C::Error>>
+where
+    C: Connection,
+{
+    accept_with_headers(connection, &[])
+}
+
+/// Like [`accept`], additionally sending `headers` with the `101` response, e.g. `Sec-WebSocket-Protocol`
+/// with the subprotocol selected by the server.
+pub fn accept_with_headers<'a, C>(
+    connection: &'a mut C,
+    headers: &[(&str, &str)],
+) -> Result<WsConnection<&'a mut C::RawConnection>, UpgradeError<C::Error>>
+where
+    C: Connection,
+{
+    let mut buf = [0; 28];
+
+    let accept = match validate(connection) {
+        Ok(key) => accept_key(key, &mut buf),
+        Err(e) => {
+            let (status, message, headers) = rejection(&e);
+            connection
+                .initiate_response(status, Some(message), headers)
+                .map_err(UpgradeError::Io)?;
+
+            return Err(e);
+        }
+    };
+
+    connection
+        .initiate_response(
+            101,
+            Some("Switching Protocols"),
+            &upgrade_headers(accept, headers)?,
+        )
+        .map_err(UpgradeError::Io)?;
+    connection.flush().map_err(UpgradeError::Io)?;
+
+    let raw = connection.raw_connection().map_err(UpgradeError::Io)?;
+
+    Ok(WsConnection::server(raw))
+}
+
+impl<'a, C> Request<&'a mut C>
+where
+    C: Connection,
+{
+    /// Accepts the request as a WebSocket upgrade request, and returns the WebSocket connection over
+    /// the raw connection.
+    ///
+    /// See [`accept`].
+    pub fn into_websocket(
+        self,
+    ) -> Result<WsConnection<&'a mut C::RawConnection>, UpgradeError<C::Error>> {
+        accept(self.release())
+    }
+}
+
+pub mod asynch {
+    use crate::http::server::asynch::{Connection, Request};
+    use crate::utils::ws::WsConnection;
+
+    use super::{accept_key, rejection, upgrade_headers, validate, UpgradeError};
+
+    pub use super::{is_upgrade_request, GUID, VERSION};
+
+    /// Accepts the WebSocket upgrade request received on `connection`, and returns the WebSocket connection
+    /// over its raw connection.
+    ///
+    /// See [`super::accept`].
+    pub async fn accept<C>(
+        connection: &mut C,
+    ) -> Result<WsConnection<&mut C::RawConnection>, UpgradeError<C::Error>>
+    where
+        C: Connection,
+    {
+        accept_with_headers(connection, &[]).await
+    }
+
+    /// Like [`accept`], additionally sending `headers` with the `101` response.
+    ///
+    /// See [`super::accept_with_headers`].
+    pub async fn accept_with_headers<'a, C>(
+        connection: &'a mut C,
+        headers: &[(&str, &str)],
+    ) -> Result<WsConnection<&'a mut C::RawConnection>, UpgradeError<C::Error>>
+    where
+        C: Connection,
+    {
+        let mut buf = [0; 28];
+
+        let accept = match validate(connection) {
+            Ok(key) => accept_key(key, &mut buf),
+            Err(e) => {
+                let (status, message, headers) = rejection(&e);
+                connection
+                    .initiate_response(status, Some(message), headers)
+                    .await
+                    .map_err(UpgradeError::Io)?;
+
+                return Err(e);
+            }
+        };
+
+        connection
+            .initiate_response(
+                101,
+                Some("Switching Protocols"),
+                &upgrade_headers(accept, headers)?,
+            )
+            .await
+            .map_err(UpgradeError::Io)?;
+        connection.flush().await.map_err(UpgradeError::Io)?;
+
+        let raw = connection.raw_connection().map_err(UpgradeError::Io)?;
+
+        Ok(WsConnection::server(raw))
+    }
+
+    impl<'a, C> Request<&'a mut C>
+    where
+        C: Connection,
+    {
+        /// Accepts the request as a WebSocket upgrade request, and returns the WebSocket connection over
+        /// the raw connection.
+        ///
+        /// See [`accept`].
+        pub async fn into_websocket(
+            self,
+        ) -> Result<WsConnection<&'a mut C::RawConnection>, UpgradeError<C::Error>> {
+            accept(self.release()).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::http::server::{Connection, Handler};
+    use crate::utils::http::server::connection::handle_connection;
+    use crate::utils::io::test::{block_on, MockSocket};
+    use crate::ws::{FrameType, Sender};
+
+    use super::{accept, accept_key, UpgradeError};
+
+    /// Accepts the upgrade request, and sends a text frame on the WebSocket connection.
+    struct Upgrade;
+
+    impl<C> Handler<C> for Upgrade
+    where
+        C: Connection,
+    {
+        type Error = UpgradeError<C::Error>;
+
+        fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let mut ws = accept(connection)?;
+
+            ws.send(FrameType::Text(false), b"hi").unwrap();
+
+            Ok(())
+        }
+    }
+
+    impl<C> crate::http::server::asynch::Handler<C> for Upgrade
+    where
+        C: crate::http::server::asynch::Connection,
+    {
+        type Error = UpgradeError<C::Error>;
+
+        async fn handle(&self, connection: &mut C) -> Result<(), Self::Error> {
+            let mut ws = super::asynch::accept(connection).await?;
+
+            crate::ws::asynch::Sender::send(&mut ws, FrameType::Text(false), b"hi")
+                .await
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    /// Serves an upgrade request with `headers` after its `Upgrade` and `Connection` headers.
+    fn serve(headers: &str) -> String {
+        String::from_utf8_lossy(&serve_raw(headers)).into_owned()
+    }
+
+    fn serve_raw(headers: &str) -> Vec<u8> {
+        let request = request(headers);
+
+        let mut socket = MockSocket::new(request.as_bytes());
+        let mut buf = [0; 1024];
+
+        let _ = handle_connection::<_, _, 16>(&mut socket, &mut buf, &Upgrade);
+
+        socket.output
+    }
+
+    fn request(headers: &str) -> String {
+        std::format!(
+            "GET /ws HTTP/1.1\r\nHost: h\r\nUpgrade: websocket\r\nConnection: keep-alive, Upgrade\r\n{headers}\r\n"
+        )
+    }
+
+    #[test]
+    fn accept_key_sample() {
+        // RFC 6455, section 1.3
+        let mut buf = [0; 28];
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ==", &mut buf),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(
+            accept_key(" dGhlIHNhbXBsZSBub25jZQ== ", &mut buf),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn upgrade() {
+        assert_eq!(
+            serve_raw(
+                "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n"
+            ),
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n\x81\x02hi"
+        );
+    }
+
+    #[test]
+    fn version() {
+        // A missing version is a malformed request
+        assert!(serve("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n")
+            .starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        // Another version is answered with the supported one
+        let response =
+            serve("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 8\r\n");
+        assert!(response.starts_with("HTTP/1.1 426 Upgrade Required\r\n"));
+        assert!(response.contains("\r\nSec-WebSocket-Version: 13\r\n"));
+    }
+
+    #[test]
+    fn invalid_key() {
+        for key in [
+            "",
+            "Sec-WebSocket-Key: dGhlIHNhbXBsZQ==\r\n",
+            "Sec-WebSocket-Key: ???\r\n",
+        ] {
+            assert!(serve(&std::format!("{key}Sec-WebSocket-Version: 13\r\n"))
+                .starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        }
+    }
+
+    #[test]
+    fn upgrade_async() {
+        let serve = |headers: &str| {
+            let request = request(headers);
+
+            let mut socket = MockSocket::new(request.as_bytes());
+            let mut buf = [0; 1024];
+
+            let _ = block_on(
+                crate::utils::http::server::connection::asynch::handle_connection::<_, _, 16>(
+                    &mut socket,
+                    &mut buf,
+                    &Upgrade,
+                ),
+            );
+
+            socket.output
+        };
+
+        assert_eq!(
+            serve("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n"),
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n\x81\x02hi"
+        );
+
+        let response =
+            serve("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 8\r\n");
+        assert!(response.starts_with(b"HTTP/1.1 426 Upgrade Required\r\n"));
+    }
+}
@@ -0,0 +1,764 @@
+use core::fmt;
+use core::time::Duration;
+
+use crate::io::{Error, ErrorKind};
+use crate::ws::{CloseCode, ErrorType, FrameType, Receiver, Sender};
+
+use super::MAX_CONTROL_PAYLOAD_LEN;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SessionError<E> {
+    Io(E),
+    /// The peer did not answer a ping within the pong timeout.
+    Timeout,
+    /// A frame is sent after the `Close` frame.
+    Closed,
+    /// The peer sent an invalid `Close` frame, which was answered with a `Close` frame failing the connection.
+    InvalidClose,
+    /// The close reason does not fit into a control frame.
+    ReasonTooLong,
+}
+
+impl<E: fmt::Debug> fmt::Display for SessionError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for SessionError<E> {}
+
+impl<E> Error for SessionError<E>
+where
+    E: Error,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::Timeout => ErrorKind::TimedOut,
+            Self::Closed => ErrorKind::NotConnected,
+            Self::InvalidClose => ErrorKind::InvalidData,
+            Self::ReasonTooLong => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+/// The payload of a `Close` frame carrying a close code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CloseFrame<'a> {
+    pub code: CloseCode,
+    pub reason: &'a str,
+}
+
+impl<'a> CloseFrame<'a> {
+    /// Returns `None` if the payload is empty, or is not a valid close code followed by a UTF-8 reason.
+    pub fn parse(payload: &'a [u8]) -> Option<Self> {
+        let (code, reason) = payload.split_first_chunk::<2>()?;
+
+        Some(Self {
+            code: CloseCode::from_u16(u16::from_be_bytes(*code))?,
+            reason: core::str::from_utf8(reason).ok()?,
+        })
+    }
+
+    /// Serializes the payload into `buf`, and returns `None` if the reason is too long for a control frame.
+    pub fn serialize<'b>(&self, buf: &'b mut [u8; MAX_CONTROL_PAYLOAD_LEN]) -> Option<&'b [u8]> {
+        let len = 2 + self.reason.len();
+        let payload = buf.get_mut(..len)?;
+
+        payload[..2].copy_from_slice(&self.code.as_u16().to_be_bytes());
+        payload[2..].copy_from_slice(self.reason.as_bytes());
+
+        Some(payload)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CloseState {
+    Open,
+    /// The `Close` frame was sent, and the one of the peer is awaited.
+    Sent,
+    Closed,
+}
+
+/// What to do with a received frame.
+enum Step {
+    Deliver,
+    Skip,
+    /// Answer a `Ping` with a `Pong` frame carrying the same payload.
+    Pong,
+    /// Deliver the `Close` frame of the peer, after answering it with a `Close` frame if not already sent.
+    Close(Option<CloseCode>),
+    /// Fail the connection, with a `Close` frame carrying this code if not already sent.
+    Reject(Option<CloseCode>),
+}
+
+/// The close handshake and keepalive state of a session.
+struct State<T> {
+    current_time: T,
+    ping_interval: Duration,
+    pong_timeout: Duration,
+    next_ping: Duration,
+    ping_sent: Option<(Duration, u32)>,
+    ping_id: u32,
+    close: CloseState,
+    close_code: Option<CloseCode>,
+}
+
+impl<T> State<T>
+where
+    T: Fn() -> Duration,
+{
+    fn new(current_time: T, ping_interval: Duration, pong_timeout: Duration) -> Self {
+        let next_ping = current_time() + ping_interval;
+
+        Self {
+            current_time,
+            ping_interval,
+            pong_timeout,
+            next_ping,
+            ping_sent: None,
+            ping_id: 0,
+            close: CloseState::Open,
+            close_code: None,
+        }
+    }
+
+    /// Returns the payload of the ping to send if one is due, or an error if the pending one timed out.
+    fn poll<E>(&mut self) -> Result<Option<[u8; 4]>, SessionError<E>> {
+        if self.close != CloseState::Open {
+            return Ok(None);
+        }
+
+        let now = (self.current_time)();
+
+        match self.ping_sent {
+            Some((sent, _)) if now.saturating_sub(sent) >= self.pong_timeout => {
+                Err(SessionError::Timeout)
+            }
+            None if now >= self.next_ping => {
+                self.ping_id = self.ping_id.wrapping_add(1);
+                self.ping_sent = Some((now, self.ping_id));
+
+                Ok(Some(self.ping_id.to_be_bytes()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn received(&mut self, frame_type: FrameType, payload: &[u8]) -> Step {
+        match frame_type {
+            // No frame can be sent after the `Close` frame
+            FrameType::Ping if self.close == CloseState::Open => Step::Pong,
+            FrameType::Ping => Step::Skip,
+            FrameType::Pong => {
+                // Unsolicited pongs, and the ones answering previous pings, are ignored
+                if let Some((_, id)) = self.ping_sent {
+                    if payload == id.to_be_bytes() {
+                        self.ping_sent = None;
+                        self.next_ping = (self.current_time)() + self.ping_interval;
+                    }
+                }
+
+                Step::Skip
+            }
+            FrameType::Close => {
+                let reply = self.close == CloseState::Open;
+                self.close = CloseState::Closed;
+
+                match CloseFrame::parse(payload) {
+                    Some(frame) => self.close_code = Some(frame.code),
+                    None if payload.is_empty() => (),
+                    None => {
+                        let valid_code = payload
+                            .first_chunk::<2>()
+                            .and_then(|code| CloseCode::from_u16(u16::from_be_bytes(*code)));
+
+                        return Step::Reject(reply.then_some(if valid_code.is_some() {
+                            CloseCode::INVALID_PAYLOAD
+                        } else {
+                            CloseCode::PROTOCOL_ERROR
+                        }));
+                    }
+                }
+
+                if reply {
+                    Step::Close(self.close_code)
+                } else {
+                    Step::Deliver
+                }
+            }
+            _ => Step::Deliver,
+        }
+    }
+
+    fn deadline(&self) -> Option<Duration> {
+        if self.close != CloseState::Open {
+            return None;
+        }
+
+        Some(match self.ping_sent {
+            Some((sent, _)) => sent + self.pong_timeout,
+            None => self.next_ping,
+        })
+    }
+
+    /// Checks that a frame can be sent, and tracks the `Close` frame.
+    fn send<E>(&mut self, frame_type: FrameType) -> Result<(), SessionError<E>> {
+        match (self.close, frame_type) {
+            (CloseState::Open, FrameType::Close) => {
+                self.close = CloseState::Sent;
+                Ok(())
+            }
+            (CloseState::Open, _) => Ok(()),
+            _ => Err(SessionError::Closed),
+        }
+    }
+}
+
+/// A WebSocket session over a frame [`Sender`] and [`Receiver`], e.g. a [`super::WsConnection`], handling
+/// the close handshake and keeping the connection alive.
+///
+/// - `Ping` frames are answered with `Pong` frames, and neither is returned by [`Receiver::recv`]
+/// - a `Close` frame of the peer is answered with a `Close` frame carrying the same code, and is then returned
+///   by [`Receiver::recv`]; sending is no longer possible afterwards
+/// - `current_time` returns a monotonic time; a ping is sent every `ping_interval`, and if the peer does not
+///   answer it within `pong_timeout`, [`SessionError::Timeout`] is returned
+///
+/// Pings are only sent, and timeouts only detected, when [`Session::poll`] or [`Receiver::recv`] is called.
+/// Since the latter blocks until a frame arrives, the underlying connection should have a read timeout expiring
+/// at [`Session::deadline`], after which `poll` is called and receiving resumes, as [`super::WsConnection`]
+/// supports.
+pub struct Session<C, T> {
+    connection: C,
+    state: State<T>,
+}
+
+impl<C, T> Session<C, T>
+where
+    C: Sender + Receiver,
+    T: Fn() -> Duration,
+{
+    pub fn new(
+        connection: C,
+        current_time: T,
+        ping_interval: Duration,
+        pong_timeout: Duration,
+    ) -> Self {
+        Self {
+            connection,
+            state: State::new(current_time, ping_interval, pong_timeout),
+        }
+    }
+
+    /// The close code received from the peer, if any.
+    pub fn close_code(&self) -> Option<CloseCode> {
+        self.state.close_code
+    }
+
+    /// Returns `true` once the `Close` frame of the peer is received.
+    pub fn is_closed(&self) -> bool {
+        self.state.close == CloseState::Closed
+    }
+
+    /// The time at which [`Session::poll`] should be called next, to send a ping or to detect a timeout, or
+    /// `None` once the close handshake started.
+    pub fn deadline(&self) -> Option<Duration> {
+        self.state.deadline()
+    }
+
+    pub fn release(self) -> C {
+        self.connection
+    }
+
+    /// Sends a ping if one is due, and fails with [`SessionError::Timeout`] if the peer did not answer the
+    /// pending one in time.
+    pub fn poll(&mut self) -> Result<(), SessionError<C::Error>> {
+        if let Some(payload) = self.state.poll()? {
+            self.connection
+                .send(FrameType::Ping, &payload)
+                .map_err(SessionError::Io)?;
+        }
+
+        Ok(())
+    }
+
+    /// Starts the close handshake by sending a `Close` frame with `code` and `reason`.
+    ///
+    /// The connection should then be received from until [`Receiver::recv`] returns the `Close` frame of the peer.
+    pub fn close(&mut self, code: CloseCode, reason: &str) -> Result<(), SessionError<C::Error>> {
+        let mut buf = [0; MAX_CONTROL_PAYLOAD_LEN];
+        let payload = CloseFrame { code, reason }
+            .serialize(&mut buf)
+            .ok_or(SessionError::ReasonTooLong)?;
+
+        self.send(FrameType::Close, payload)
+    }
+
+    fn send_close(&mut self, code: Option<CloseCode>) -> Result<(), SessionError<C::Error>> {
+        let payload = code.map(|code| code.as_u16().to_be_bytes());
+
+        self.connection
+            .send(
+                FrameType::Close,
+                payload.as_ref().map_or(&[][..], |payload| &payload[..]),
+            )
+            .map_err(SessionError::Io)
+    }
+}
+
+impl<C, T> ErrorType for Session<C, T>
+where
+    C: ErrorType,
+{
+    type Error = SessionError<C::Error>;
+}
+
+impl<C, T> Receiver for Session<C, T>
+where
+    C: Sender + Receiver,
+    T: Fn() -> Duration,
+{
+    fn recv(&mut self, frame_data_buf: &mut [u8]) -> Result<(FrameType, usize), Self::Error> {
+        loop {
+            self.poll()?;
+
+            let (frame_type, len) = self
+                .connection
+                .recv(frame_data_buf)
+                .map_err(SessionError::Io)?;
+
+            match self.state.received(frame_type, &frame_data_buf[..len]) {
+                Step::Deliver => return Ok((frame_type, len)),
+                Step::Skip => (),
+                Step::Pong => self
+                    .connection
+                    .send(FrameType::Pong, &frame_data_buf[..len])
+                    .map_err(SessionError::Io)?,
+                Step::Close(code) => {
+                    self.send_close(code)?;
+                    return Ok((frame_type, len));
+                }
+                Step::Reject(code) => {
+                    if code.is_some() {
+                        self.send_close(code)?;
+                    }
+
+                    return Err(SessionError::InvalidClose);
+                }
+            }
+        }
+    }
+}
+
+impl<C, T> Sender for Session<C, T>
+where
+    C: Sender + Receiver,
+    T: Fn() -> Duration,
+{
+    fn send(&mut self, frame_type: FrameType, frame_data: &[u8]) -> Result<(), Self::Error> {
+        self.state.send(frame_type)?;
+
+        self.connection
+            .send(frame_type, frame_data)
+            .map_err(SessionError::Io)
+    }
+}
+
+pub mod asynch {
+    use core::time::Duration;
+
+    use crate::ws::asynch::{CloseCode, ErrorType, FrameType, Receiver, Sender};
+
+    use super::{CloseState, State, Step, MAX_CONTROL_PAYLOAD_LEN};
+
+    pub use super::{CloseFrame, SessionError};
+
+    /// A WebSocket session over a frame [`Sender`] and [`Receiver`], handling the close handshake and keeping
+    /// the connection alive.
+    ///
+    /// See [`super::Session`]. Pings are only sent, and timeouts only detected, when [`Session::poll`] or
+    /// [`Receiver::recv`] is called.
+    ///
+    /// Since `recv` borrows the session until a frame arrives, it should be raced against a timer expiring at
+    /// [`Session::deadline`], after which `poll` is called and `recv` is called again with the same buffer.
+    /// Doing so is safe as long as the underlying receiver is cancel-safe, as [`super::super::WsConnection`]
+    /// is: a frame partially received when `recv` is cancelled is completed by the next call. Sending is not
+    /// cancel-safe though, so a `Pong` or `Close` frame which `recv` is sending in reply when cancelled may be
+    /// left incomplete, breaking the connection.
+    pub struct Session<C, T> {
+        connection: C,
+        state: State<T>,
+    }
+
+    impl<C, T> Session<C, T>
+    where
+        C: Sender + Receiver,
+        T: Fn() -> Duration,
+    {
+        pub fn new(
+            connection: C,
+            current_time: T,
+            ping_interval: Duration,
+            pong_timeout: Duration,
+        ) -> Self {
+            Self {
+                connection,
+                state: State::new(current_time, ping_interval, pong_timeout),
+            }
+        }
+
+        /// The close code received from the peer, if any.
+        pub fn close_code(&self) -> Option<CloseCode> {
+            self.state.close_code
+        }
+
+        /// Returns `true` once the `Close` frame of the peer is received.
+        pub fn is_closed(&self) -> bool {
+            self.state.close == CloseState::Closed
+        }
+
+        /// The time at which [`Session::poll`] should be called next, to send a ping or to detect a timeout, or
+        /// `None` once the close handshake started.
+        pub fn deadline(&self) -> Option<Duration> {
+            self.state.deadline()
+        }
+
+        pub fn release(self) -> C {
+            self.connection
+        }
+
+        /// Sends a ping if one is due, and fails with [`SessionError::Timeout`] if the peer did not answer the
+        /// pending one in time.
+        pub async fn poll(&mut self) -> Result<(), SessionError<C::Error>> {
+            if let Some(payload) = self.state.poll()? {
+                self.connection
+                    .send(FrameType::Ping, &payload)
+                    .await
+                    .map_err(SessionError::Io)?;
+            }
+
+            Ok(())
+        }
+
+        /// Starts the close handshake by sending a `Close` frame with `code` and `reason`.
+        pub async fn close(
+            &mut self,
+            code: CloseCode,
+            reason: &str,
+        ) -> Result<(), SessionError<C::Error>> {
+            let mut buf = [0; MAX_CONTROL_PAYLOAD_LEN];
+            let payload = CloseFrame { code, reason }
+                .serialize(&mut buf)
+                .ok_or(SessionError::ReasonTooLong)?;
+
+            self.send(FrameType::Close, payload).await
+        }
+
+        async fn send_close(
+            &mut self,
+            code: Option<CloseCode>,
+        ) -> Result<(), SessionError<C::Error>> {
+            let payload = code.map(|code| code.as_u16().to_be_bytes());
+
+            self.connection
+                .send(
+                    FrameType::Close,
+                    payload.as_ref().map_or(&[][..], |payload| &payload[..]),
+                )
+                .await
+                .map_err(SessionError::Io)
+        }
+    }
+
+    impl<C, T> Receiver for Session<C, T>
+    where
+        C: Sender + Receiver,
+        T: Fn() -> Duration,
+    {
+        async fn recv(
+            &mut self,
+            frame_data_buf: &mut [u8],
+        ) -> Result<(FrameType, usize), Self::Error> {
+            loop {
+                self.poll().await?;
+
+                let (frame_type, len) = self
+                    .connection
+                    .recv(frame_data_buf)
+                    .await
+                    .map_err(SessionError::Io)?;
+
+                match self.state.received(frame_type, &frame_data_buf[..len]) {
+                    Step::Deliver => return Ok((frame_type, len)),
+                    Step::Skip => (),
+                    Step::Pong => self
+                        .connection
+                        .send(FrameType::Pong, &frame_data_buf[..len])
+                        .await
+                        .map_err(SessionError::Io)?,
+                    Step::Close(code) => {
+                        self.send_close(code).await?;
+                        return Ok((frame_type, len));
+                    }
+                    Step::Reject(code) => {
+                        if code.is_some() {
+                            self.send_close(code).await?;
+                        }
+
+                        return Err(SessionError::InvalidClose);
+                    }
+                }
+            }
+        }
+    }
+
+    impl<C, T> Sender for Session<C, T>
+    where
+        C: Sender + Receiver,
+        T: Fn() -> Duration,
+    {
+        async fn send(
+            &mut self,
+            frame_type: FrameType,
+            frame_data: &[u8],
+        ) -> Result<(), Self::Error> {
+            self.state.send(frame_type)?;
+
+            self.connection
+                .send(frame_type, frame_data)
+                .await
+                .map_err(SessionError::Io)
+        }
+    }
+
+    impl<C, T> ErrorType for Session<C, T>
+    where
+        C: ErrorType,
+    {
+        type Error = SessionError<C::Error>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use core::pin::pin;
+    use core::task::Poll;
+    use core::time::Duration;
+
+    use crate::io::ErrorType;
+    use crate::utils::io::test::{block_on, poll_once, MockSocket};
+    use crate::utils::ws::WsConnection;
+    use crate::ws::{CloseCode, FrameType, Receiver, Sender};
+
+    use super::{Session, SessionError};
+
+    fn mask(key: &mut [u8]) {
+        key.fill(0);
+    }
+
+    fn session<'a, 'b, 't>(
+        socket: &'a mut MockSocket<'b>,
+        now: &'t Cell<u64>,
+    ) -> Session<WsConnection<&'a mut MockSocket<'b>>, impl Fn() -> Duration + 't> {
+        Session::new(
+            WsConnection::client(socket, mask as fn(&mut [u8])),
+            || Duration::from_secs(now.get()),
+            Duration::from_secs(10),
+            Duration::from_secs(5),
+        )
+    }
+
+    #[test]
+    fn pong() {
+        let mut socket = MockSocket::new(b"\x89\x03abc\x81\x02hi");
+        let now = Cell::new(0);
+        let mut session = session(&mut socket, &now);
+        let mut buf = [0; 16];
+
+        assert_eq!(session.recv(&mut buf).unwrap(), (FrameType::Text(false), 2));
+        assert_eq!(&buf[..2], b"hi");
+
+        assert_eq!(session.release().release().output, b"\x8a\x83\0\0\0\0abc");
+    }
+
+    #[test]
+    fn close_handshake() {
+        let mut socket = MockSocket::new(b"\x88\x05\x03\xe8bye");
+        let now = Cell::new(0);
+        let mut session = session(&mut socket, &now);
+        let mut buf = [0; 16];
+
+        assert_eq!(session.recv(&mut buf).unwrap(), (FrameType::Close, 5));
+        assert!(session.is_closed());
+        assert_eq!(session.close_code(), Some(CloseCode::NORMAL));
+        assert_eq!(session.deadline(), None);
+        assert!(matches!(
+            session.send(FrameType::Text(false), b"hi"),
+            Err(SessionError::Closed)
+        ));
+
+        assert_eq!(
+            session.release().release().output,
+            b"\x88\x82\0\0\0\0\x03\xe8"
+        );
+    }
+
+    #[test]
+    fn close_initiated() {
+        let mut socket = MockSocket::new(b"\x81\x02hi\x88\x02\x03\xe9");
+        let now = Cell::new(0);
+        let mut session = session(&mut socket, &now);
+        let mut buf = [0; 16];
+
+        session.close(CloseCode::NORMAL, "bye").unwrap();
+        assert_eq!(session.deadline(), None);
+
+        // Data frames are still received until the `Close` frame of the peer
+        assert_eq!(session.recv(&mut buf).unwrap(), (FrameType::Text(false), 2));
+        assert_eq!(session.recv(&mut buf).unwrap(), (FrameType::Close, 2));
+        assert_eq!(session.close_code(), Some(CloseCode::GOING_AWAY));
+
+        assert_eq!(
+            session.release().release().output,
+            b"\x88\x85\0\0\0\0\x03\xe8bye"
+        );
+    }
+
+    #[test]
+    fn invalid_close() {
+        let mut socket = MockSocket::new(b"\x88\x01\x03");
+        let now = Cell::new(0);
+        let mut session = session(&mut socket, &now);
+        let mut buf = [0; 16];
+
+        assert!(matches!(
+            session.recv(&mut buf),
+            Err(SessionError::InvalidClose)
+        ));
+
+        assert_eq!(
+            session.release().release().output,
+            b"\x88\x82\0\0\0\0\x03\xea"
+        );
+    }
+
+    #[test]
+    fn keepalive() {
+        let mut socket = MockSocket::new(b"\x8a\x04\0\0\0\x01\x81\x02hi");
+        let now = Cell::new(0);
+        let mut session = session(&mut socket, &now);
+        let mut buf = [0; 16];
+
+        assert_eq!(session.deadline(), Some(Duration::from_secs(10)));
+        session.poll().unwrap();
+
+        now.set(10);
+        session.poll().unwrap();
+        assert_eq!(session.deadline(), Some(Duration::from_secs(15)));
+
+        // The pong resets the keepalive, and is not returned
+        now.set(12);
+        assert_eq!(session.recv(&mut buf).unwrap(), (FrameType::Text(false), 2));
+        assert_eq!(session.deadline(), Some(Duration::from_secs(22)));
+
+        now.set(22);
+        session.poll().unwrap();
+
+        now.set(27);
+        assert!(matches!(session.poll(), Err(SessionError::Timeout)));
+
+        assert_eq!(
+            session.release().release().output,
+            b"\x89\x84\0\0\0\0\0\0\0\x01\x89\x84\0\0\0\0\0\0\0\x02"
+        );
+    }
+
+    /// A socket whose async reads are pending every other time.
+    struct Stalling<'a> {
+        socket: MockSocket<'a>,
+        stall: bool,
+    }
+
+    impl ErrorType for Stalling<'_> {
+        type Error = Infallible;
+    }
+
+    impl crate::io::asynch::Read for Stalling<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if self.stall {
+                self.stall = false;
+
+                let mut stalled = false;
+                core::future::poll_fn(|_| {
+                    if stalled {
+                        Poll::Ready(())
+                    } else {
+                        stalled = true;
+                        Poll::Pending
+                    }
+                })
+                .await;
+            }
+
+            self.stall = true;
+
+            crate::io::asynch::Read::read(&mut self.socket, buf).await
+        }
+    }
+
+    impl crate::io::asynch::Write for Stalling<'_> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            crate::io::asynch::Write::write(&mut self.socket, buf).await
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recv_cancelled_async() {
+        let mut socket = Stalling {
+            socket: MockSocket::chunked(b"\x81\x0bhello world", 3),
+            stall: true,
+        };
+        let now = Cell::new(0);
+        let mut session = super::asynch::Session::new(
+            WsConnection::client(&mut socket, mask),
+            || Duration::from_secs(now.get()),
+            Duration::from_secs(10),
+            Duration::from_secs(5),
+        );
+        let mut buf = [0; 16];
+
+        {
+            let mut recv = pin!(crate::ws::asynch::Receiver::recv(&mut session, &mut buf));
+
+            // The header and the first 6 bytes of the payload are received
+            for _ in 0..4 {
+                assert!(poll_once(recv.as_mut()).is_pending());
+            }
+        }
+
+        // A ping can be sent in between, when the deadline expires
+        now.set(10);
+        assert_eq!(session.deadline(), Some(Duration::from_secs(10)));
+        block_on(session.poll()).unwrap();
+
+        assert_eq!(
+            block_on(crate::ws::asynch::Receiver::recv(&mut session, &mut buf)).unwrap(),
+            (FrameType::Text(false), 11)
+        );
+        assert_eq!(&buf[..11], b"hello world");
+
+        let socket = session.release().release();
+        assert_eq!(socket.socket.input, b"");
+        assert_eq!(socket.socket.output, b"\x89\x84\0\0\0\0\0\0\0\x01");
+    }
+}
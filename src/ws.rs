@@ -51,6 +51,67 @@ impl FrameType {
     }
 }
 
+/// A WebSocket close code, as sent in the payload of a `Close` frame.
+///
+/// Only the codes which may be sent on the wire can be represented, i.e. not `1005`, `1006` or `1015`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CloseCode(u16);
+
+impl CloseCode {
+    /// `1000`, the purpose of the connection has been fulfilled.
+    pub const NORMAL: Self = Self(1000);
+    /// `1001`, the endpoint is going away, e.g. the server is shutting down.
+    pub const GOING_AWAY: Self = Self(1001);
+    /// `1002`, the endpoint received a frame violating the protocol.
+    pub const PROTOCOL_ERROR: Self = Self(1002);
+    /// `1003`, the endpoint cannot accept the type of data it received.
+    pub const UNSUPPORTED_DATA: Self = Self(1003);
+    /// `1007`, a message is inconsistent with its type, e.g. a text message which is not valid UTF-8.
+    pub const INVALID_PAYLOAD: Self = Self(1007);
+    /// `1008`, a message violates the policy of the endpoint, when no more specific code applies.
+    pub const POLICY_VIOLATION: Self = Self(1008);
+    /// `1009`, a message is too big for the endpoint to process.
+    pub const MESSAGE_TOO_BIG: Self = Self(1009);
+    /// `1010`, sent by a client when the server did not negotiate an extension it requires.
+    pub const MANDATORY_EXTENSION: Self = Self(1010);
+    /// `1011`, the server encountered an unexpected condition preventing it from fulfilling the request.
+    pub const INTERNAL_ERROR: Self = Self(1011);
+    /// `1012`, the server is restarting.
+    pub const SERVICE_RESTART: Self = Self(1012);
+    /// `1013`, the server is overloaded, and the client should reconnect later.
+    pub const TRY_AGAIN_LATER: Self = Self(1013);
+    /// `1014`, the server, acting as a gateway, received an invalid response from upstream.
+    pub const BAD_GATEWAY: Self = Self(1014);
+
+    /// Returns `None` if `code` is reserved or not allowed on the wire.
+    ///
+    /// Codes `3000` to `3999` are registered with IANA by libraries and frameworks, and codes `4000` to `4999`
+    /// are private to applications.
+    pub const fn from_u16(code: u16) -> Option<Self> {
+        match code {
+            1000..=1003 | 1007..=1014 | 3000..=4999 => Some(Self(code)),
+            _ => None,
+        }
+    }
+
+    pub const fn as_u16(&self) -> u16 {
+        self.0
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(code: CloseCode) -> Self {
+        code.0
+    }
+}
+
+impl PartialEq<u16> for CloseCode {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+
 pub trait Receiver: ErrorType {
     fn recv(&mut self, frame_data_buf: &mut [u8]) -> Result<(FrameType, usize), Self::Error>;
 }
@@ -118,7 +179,7 @@ pub mod server {
 }
 
 pub mod asynch {
-    pub use super::{ErrorType, Fragmented, FrameType};
+    pub use super::{CloseCode, ErrorType, Fragmented, FrameType};
 
     pub trait Receiver: ErrorType {
         async fn recv(